@@ -0,0 +1,121 @@
+//! Blocking API.
+//!
+//! These functions drive the processor futures on the current thread, so they can be used from
+//! non-async code without an async runtime.
+//!
+//! The remote document loader should not require a runtime either. For example, the loader
+//! built on the asynchronous `reqwest` client needs a tokio runtime, so it cannot be used here.
+
+use std::{io::BufRead, iter};
+
+use futures_executor::block_on;
+use serde_json::Value;
+
+use crate::{
+    error::Result,
+    expand::{expand_line, read_error},
+    options::JsonLdOptions,
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+/// Expands the given document, blocking the current thread.
+///
+/// See [`Processor::expand()`](../struct.Processor.html#method.expand).
+pub fn expand<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    options: &JsonLdOptions,
+) -> Result<Value> {
+    block_on(processor.expand(input, options))
+}
+
+/// Compacts the given document, blocking the current thread.
+///
+/// See [`Processor::compact()`](../struct.Processor.html#method.compact).
+pub fn compact<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    context: &Value,
+    options: &JsonLdOptions,
+) -> Result<Value> {
+    block_on(processor.compact(input, context, options))
+}
+
+/// Expands each document of the newline-delimited JSON-LD text read from the reader, blocking
+/// the current thread for each document.
+///
+/// The iterator yields the line number (starting from 1) and the result for each non-empty line,
+/// as the lines are read.
+/// A failure on a document does not stop processing of the following lines, but a failure on
+/// reading is yielded as the last item.
+///
+/// See [`Processor::expand_ndjson()`](../struct.Processor.html#method.expand_ndjson).
+pub fn expand_ndjson<'a, L: LoadRemoteDocument>(
+    processor: &'a Processor<L>,
+    mut reader: impl BufRead + 'a,
+    options: &'a JsonLdOptions,
+) -> impl Iterator<Item = (usize, Result<Value>)> + 'a {
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    let mut finished = false;
+    iter::from_fn(move || {
+        while !finished {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => finished = true,
+                Ok(_) => {
+                    line_number += 1;
+                    if let Some(result) = block_on(expand_line(processor, &line, options)) {
+                        return Some((line_number, result));
+                    }
+                }
+                Err(e) => {
+                    finished = true;
+                    return Some((line_number + 1, Err(read_error(e))));
+                }
+            }
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{self, BufReader, Read};
+
+    use iri_string::types::IriStr;
+
+    use crate::{error::ErrorCode, processor::ProcessorOptions, remote::StaticLoader};
+
+    /// Reader always failing.
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::Other, "broken pipe"))
+        }
+    }
+
+    /// A failure on reading should be yielded after the documents read so far.
+    #[test]
+    fn expand_ndjson_read_error() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let options = JsonLdOptions::new();
+        let reader = BufReader::new((&b"[]\n\n{}\n"[..]).chain(FailingReader));
+        let results = expand_ndjson(&processor, reader, &options)
+            .map(|(line, result)| (line, result.map_err(|e| e.code())))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            vec![
+                (1, Ok(Value::Array(Vec::new()))),
+                (3, Ok(Value::Array(Vec::new()))),
+                (4, Err(ErrorCode::LoadingDocumentFailed)),
+            ]
+        );
+    }
+}
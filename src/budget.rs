@@ -0,0 +1,187 @@
+//! Memory budget.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::Value;
+
+use crate::error::{ErrorCode, Result};
+
+/// Approximate size of a JSON value, excluding the heap data.
+const VALUE_SIZE: usize = std::mem::size_of::<Value>();
+
+/// Approximate overhead of an entry of a map, excluding the key and the value.
+const ENTRY_OVERHEAD: usize = 3 * std::mem::size_of::<usize>();
+
+/// Approximate accounting of memory allocated for values created during an operation.
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+    /// Maximum number of bytes.
+    limit: usize,
+    /// Number of bytes charged so far.
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    /// Creates a new `MemoryBudget`.
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Charges the given number of bytes, and returns an error if the budget is exceeded.
+    pub(crate) fn charge(&self, bytes: usize) -> Result<()> {
+        let used = self
+            .used
+            .fetch_add(bytes, Ordering::Relaxed)
+            .saturating_add(bytes);
+        if used > self.limit {
+            return Err(ErrorCode::MemoryBudgetExceeded
+                .and_message(format!("Memory budget of {} bytes is exceeded", self.limit)));
+        }
+        Ok(())
+    }
+
+    /// Releases the given number of bytes.
+    pub(crate) fn release(&self, bytes: usize) {
+        let mut used = self.used.load(Ordering::Relaxed);
+        loop {
+            match self.used.compare_exchange_weak(
+                used,
+                used.saturating_sub(bytes),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    /// Charges the size of the given newly created value.
+    ///
+    /// Nested arrays and maps (except arrays of scalar values in maps) are expected to be charged
+    /// separately when they are created, so only their headers are counted.
+    pub(crate) fn charge_value(&self, v: &Value) -> Result<()> {
+        self.charge(shallow_size(v))
+    }
+
+    /// Releases the size of the given value which is no longer used, including nested values.
+    pub(crate) fn release_value(&self, v: &Value) {
+        self.release(deep_size(v))
+    }
+}
+
+/// Returns the approximate size of the value, including nested arrays and maps.
+///
+/// This is consistent with `shallow_size()` of the value and the nested values charged
+/// separately.
+fn deep_size(v: &Value) -> usize {
+    let mut size = 0;
+    let mut stack = vec![v];
+    while let Some(v) = stack.pop() {
+        size += shallow_size(v);
+        match v {
+            Value::Array(items) => stack.extend(items.iter().filter(|v| is_container(v))),
+            Value::Object(map) => {
+                for value in map.values() {
+                    match value {
+                        // Items are counted as scalars by `entry_size()`.
+                        Value::Array(items) => {
+                            stack.extend(items.iter().filter(|v| is_container(v)))
+                        }
+                        Value::Object(_) => stack.push(value),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    size
+}
+
+/// Checks whether the value is an array or a map.
+fn is_container(v: &Value) -> bool {
+    match v {
+        Value::Array(_) | Value::Object(_) => true,
+        _ => false,
+    }
+}
+
+/// Returns the approximate size of the value, excluding nested arrays and maps.
+fn shallow_size(v: &Value) -> usize {
+    match v {
+        Value::Array(items) => VALUE_SIZE * (items.len() + 1),
+        Value::Object(map) => {
+            VALUE_SIZE
+                + map
+                    .iter()
+                    .map(|(key, value)| ENTRY_OVERHEAD + key.len() + entry_size(value))
+                    .sum::<usize>()
+        }
+        v => scalar_size(v),
+    }
+}
+
+/// Returns the approximate size of the value of a map entry.
+fn entry_size(v: &Value) -> usize {
+    match v {
+        Value::Array(items) => VALUE_SIZE + items.iter().map(scalar_size).sum::<usize>(),
+        v => scalar_size(v),
+    }
+}
+
+/// Returns the approximate size of the value, counting only the header of arrays and maps.
+fn scalar_size(v: &Value) -> usize {
+    match v {
+        Value::String(s) => VALUE_SIZE + s.len(),
+        _ => VALUE_SIZE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn charge_limit() {
+        let v = json!({"@id": "http://example.com/", "@type": ["http://example.com/T"]});
+        let size = shallow_size(&v);
+        assert!(size > "http://example.com/".len() + "http://example.com/T".len());
+
+        let budget = MemoryBudget::new(size * 2);
+        budget.charge_value(&v).expect("within the budget");
+        budget.charge_value(&v).expect("within the budget");
+        let err = budget.charge_value(&v).expect_err("budget exceeded");
+        assert_eq!(err.code(), ErrorCode::MemoryBudgetExceeded);
+    }
+
+    #[test]
+    fn release() {
+        let inner = json!({"@value": "x", "@language": "en"});
+        let v = json!({
+            "@id": "http://example.com/",
+            "http://example.com/p": [inner.clone(), [inner.clone()]],
+            "http://example.com/q": inner.clone(),
+        });
+        let budget = MemoryBudget::new(usize::max_value());
+        // Nested values are charged when they are created.
+        for _ in 0..3 {
+            budget.charge_value(&inner).expect("within the budget");
+        }
+        budget
+            .charge_value(&v["http://example.com/p"][1])
+            .expect("within the budget");
+        budget.charge_value(&v).expect("within the budget");
+        assert_eq!(budget.used.load(Ordering::Relaxed), deep_size(&v));
+
+        budget.release_value(&v);
+        assert_eq!(budget.used.load(Ordering::Relaxed), 0);
+        budget.release(42);
+        assert_eq!(budget.used.load(Ordering::Relaxed), 0);
+    }
+}
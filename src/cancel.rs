@@ -0,0 +1,244 @@
+//! Cooperative cancellation.
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use crate::hash::HashMap;
+
+/// Token to cancel processor operations.
+///
+/// Clones of a token share the state, so an operation can be cancelled from another task or
+/// thread by calling [`cancel()`](#method.cancel) on a clone.
+/// Cancelled operations fail with `ErrorCode::Cancelled`.
+#[derive(Default, Clone)]
+pub struct CancellationToken {
+    /// Shared state.
+    inner: Arc<Inner>,
+}
+
+/// Shared state of a cancellation token.
+#[derive(Default)]
+struct Inner {
+    /// Whether the token is cancelled.
+    cancelled: AtomicBool,
+    /// Wakers of the futures waiting for the token.
+    wakers: Mutex<Wakers>,
+}
+
+/// Wakers of the futures waiting for a cancellation token.
+#[derive(Default)]
+struct Wakers {
+    /// Key of the next slot.
+    next_key: u64,
+    /// Wakers of the pending futures, keyed by the slot of each future.
+    slots: HashMap<u64, Waker>,
+}
+
+impl CancellationToken {
+    /// Creates a new `CancellationToken`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the operations using the token.
+    ///
+    /// Pending loads of remote documents are aborted.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let slots = std::mem::replace(&mut self.lock_wakers().slots, HashMap::default());
+        for waker in slots.values() {
+            waker.wake_by_ref();
+        }
+    }
+
+    /// Checks whether the token is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Registers the waker to be woken on cancellation, and returns the key of the slot.
+    ///
+    /// If the slot is already allocated, the waker in the slot is replaced.
+    fn register(&self, key: Option<u64>, waker: &Waker) -> u64 {
+        let mut wakers = self.lock_wakers();
+        let key = key.unwrap_or_else(|| {
+            let key = wakers.next_key;
+            wakers.next_key += 1;
+            key
+        });
+        match wakers.slots.get(&key) {
+            Some(registered) if registered.will_wake(waker) => {}
+            _ => {
+                wakers.slots.insert(key, waker.clone());
+            }
+        }
+        key
+    }
+
+    /// Removes the waker registered to the slot.
+    fn deregister(&self, key: u64) {
+        self.lock_wakers().slots.remove(&key);
+    }
+
+    /// Locks the wakers.
+    fn lock_wakers(&self) -> std::sync::MutexGuard<'_, Wakers> {
+        self.inner.wakers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+/// Future which completes with `None` when the token is cancelled.
+pub(crate) struct Cancellable<'a, F> {
+    /// Future.
+    future: Pin<Box<F>>,
+    /// Cancellation token.
+    token: &'a CancellationToken,
+    /// Key of the waker slot registered to the token.
+    key: Option<u64>,
+}
+
+impl<'a, F: Future> Cancellable<'a, F> {
+    /// Creates a new `Cancellable`.
+    pub(crate) fn new(future: F, token: &'a CancellationToken) -> Self {
+        Self {
+            future: Box::pin(future),
+            token,
+            key: None,
+        }
+    }
+}
+
+impl<F> Cancellable<'_, F> {
+    /// Removes the waker from the token, if registered.
+    fn deregister(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.token.deregister(key);
+        }
+    }
+}
+
+impl<F: Future> Future for Cancellable<'_, F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.token.is_cancelled() {
+            self.deregister();
+            return Poll::Ready(None);
+        }
+        if let Poll::Ready(v) = self.future.as_mut().poll(cx) {
+            self.deregister();
+            return Poll::Ready(Some(v));
+        }
+        self.key = Some(self.token.register(self.key, cx.waker()));
+        // The token may be cancelled before the waker is registered.
+        if self.token.is_cancelled() {
+            self.deregister();
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    }
+}
+
+impl<F> Drop for Cancellable<'_, F> {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn cancel_clone() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token, clone);
+        assert_ne!(token, CancellationToken::new());
+    }
+
+    /// Future which never completes.
+    struct Never;
+
+    impl Future for Never {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+            Poll::Pending
+        }
+    }
+
+    /// Future which polls the inner future once, and returns whether it is ready.
+    struct PollOnce<F>(Pin<Box<F>>);
+
+    impl<F: Future> Future for PollOnce<F> {
+        type Output = bool;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            Poll::Ready(self.0.as_mut().poll(cx).is_ready())
+        }
+    }
+
+    /// Returns the number of wakers registered to the token.
+    fn num_wakers(token: &CancellationToken) -> usize {
+        token.lock_wakers().slots.len()
+    }
+
+    #[test]
+    fn wakers_removed() {
+        let token = CancellationToken::new();
+        for _ in 0..3 {
+            let mut cancellable = Box::pin(Cancellable::new(Never, &token));
+            for _ in 0..2 {
+                let ready = block_on(PollOnce(Box::pin(cancellable.as_mut())));
+                assert!(!ready);
+            }
+            assert_eq!(num_wakers(&token), 1);
+            drop(cancellable);
+            assert_eq!(num_wakers(&token), 0);
+        }
+
+        let mut cancellable = Box::pin(Cancellable::new(async {}, &token));
+        assert!(block_on(PollOnce(Box::pin(cancellable.as_mut()))));
+        assert_eq!(num_wakers(&token), 0);
+    }
+
+    #[test]
+    fn cancel_pending() {
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            canceller.cancel();
+        });
+        assert_eq!(block_on(Cancellable::new(Never, &token)), None);
+        assert_eq!(num_wakers(&token), 0);
+        handle.join().expect("the thread should not panic");
+    }
+}
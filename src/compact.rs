@@ -0,0 +1,847 @@
+//! Compaction.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#compaction-algorithms>.
+
+use std::{borrow::Cow, future::Future, pin::Pin};
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::{definition::ContainerItem, Context, Definition, InverseContext},
+    error::{ErrorCode, Result},
+    expand::expand,
+    json::{add_value, into_array, map_entries, to_ref_array},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+    syntax::{is_graph_object, is_list_object, is_simple_graph_object},
+};
+
+pub(crate) use self::stream::compact_to_writer;
+
+use self::{iri::CompactIriOptions, value::compact_value};
+
+mod iri;
+mod stream;
+mod value;
+
+/// Runs compaction algorithm for the given document, and returns the compacted document.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+pub(crate) async fn compact<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    context: &Value,
+) -> Result<Value> {
+    // Step 2
+    let expanded_input = expand(processor, input).await?;
+
+    compact_expanded(processor, &expanded_input, context, false).await
+}
+
+/// Runs compaction for the given expanded document, and returns the compacted document.
+///
+/// If `force_graph` is true, the compacted output always has an `@graph` entry unless it is empty.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+pub(crate) async fn compact_expanded<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    expanded_input: &Value,
+    context: &Value,
+    force_graph: bool,
+) -> Result<Value> {
+    let (active_context, context) = compaction_context(processor, context).await?;
+
+    compact_with_active_context(
+        processor,
+        expanded_input,
+        &active_context,
+        context,
+        force_graph,
+    )
+    .await
+}
+
+/// Processes the context given to the compaction, and returns the active context and the local
+/// context to be used as the `@context` entry of the result.
+///
+/// This is steps 3 to 5 of the compaction.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+async fn compaction_context<'a, L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    context: &'a Value,
+) -> Result<(Context, &'a Value)> {
+    // Step 3
+    let context_base = processor.options().document_iri();
+    // Step 4
+    let context = match context {
+        Value::Object(map) if map.contains_key("@context") => &map["@context"],
+        v => v,
+    };
+    // Step 5
+    let active_context = Context::with_base(context_base.to_owned())
+        .join_context_value(processor, context, context_base, false)
+        .await?;
+
+    Ok((active_context, context))
+}
+
+/// Runs compaction for the given expanded document using the processed context, and returns the
+/// compacted document.
+///
+/// `context` is the local context `active_context` is created from, and is used as the `@context`
+/// entry of the result.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+pub(crate) async fn compact_with_active_context<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    expanded_input: &Value,
+    active_context: &Context,
+    context: &Value,
+    force_graph: bool,
+) -> Result<Value> {
+    // Step 7
+    let compacted_output = compact_element(processor, active_context, None, expanded_input).await?;
+    // Step 8
+    let compacted_output = match compacted_output {
+        Value::Object(map) if force_graph && !map.is_empty() => {
+            Value::Array(vec![Value::Object(map)])
+        }
+        v => v,
+    };
+    let mut compacted_output = match compacted_output {
+        Value::Array(items) if items.is_empty() => JsonMap::new(),
+        Value::Array(items) => {
+            let graph = compact_graph_key(processor, active_context)?;
+            let mut map = JsonMap::new();
+            map.insert(graph, Value::Array(items));
+            map
+        }
+        Value::Object(map) => map,
+        v => {
+            return Err(ErrorCode::Uncategorized.and_message(format!(
+                "Compacted output should be a map or an array, but got {:?}",
+                v
+            )))
+        }
+    };
+    // Step 9
+    if !is_empty_context(context) {
+        compacted_output = with_context_first(context, compacted_output);
+    }
+
+    Ok(Value::Object(compacted_output))
+}
+
+/// Returns the key of the `@graph` entry of the compacted output.
+// Step 8
+fn compact_graph_key<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+) -> Result<String> {
+    let inverse_context = active_context.inverse_context();
+    CompactIriOptions::new(active_context, &inverse_context)
+        .vocab(true)
+        .compact(processor, "@graph")
+        .map(Cow::into_owned)
+}
+
+/// Returns the map with the `@context` entry put before the other entries.
+///
+/// This only makes a difference with `preserve_order` feature, where the entries are serialized
+/// in insertion order.
+// Step 9
+fn with_context_first(context: &Value, map: JsonMap<String, Value>) -> JsonMap<String, Value> {
+    let mut output = JsonMap::new();
+    output.insert("@context".to_owned(), context.clone());
+    output.extend(map);
+    output
+}
+
+/// Checks if the local context is empty, so that it is not added to the compacted output.
+// Step 9
+fn is_empty_context(context: &Value) -> bool {
+    match context {
+        Value::Null => true,
+        Value::Object(map) => map.is_empty(),
+        Value::Array(arr) => arr.is_empty(),
+        _ => false,
+    }
+}
+
+/// Runs compaction algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#compaction-algorithm>.
+///
+/// This is a wrapper for recursive call.
+pub(crate) fn compact_element<'a, L: LoadRemoteDocument>(
+    processor: &'a Processor<L>,
+    active_context: &'a Context,
+    active_property: Option<&'a str>,
+    element: &'a Value,
+) -> Pin<Box<dyn Future<Output = Result<Value>> + 'a + Send>> {
+    Box::pin(async move {
+        let compacted =
+            compact_element_impl(processor, active_context, active_property, element).await?;
+        processor.charge_memory(&compacted)?;
+        Ok(compacted)
+    })
+}
+
+/// Runs compaction algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#compaction-algorithm>.
+async fn compact_element_impl<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    active_property: Option<&str>,
+    element: &Value,
+) -> Result<Value> {
+    processor.options().check_cancelled()?;
+    // Step 1
+    let type_scoped_context = active_context;
+
+    let element = match element {
+        // Step 3
+        Value::Array(items) => {
+            return compact_array(processor, active_context, active_property, items).await
+        }
+        // Step 4
+        Value::Object(map) => map,
+        // Step 2
+        v => return Ok(v.clone()),
+    };
+
+    // Step 5
+    let mut active_context = Cow::Borrowed(active_context);
+    if active_context.has_previous_context()
+        && !element.contains_key("@value")
+        && !(element.len() == 1 && element.contains_key("@id"))
+    {
+        let previous_context = active_context
+            .previous_context()
+            .expect("Should never fail: the context has the previous context")
+            .clone();
+        active_context = Cow::Owned(previous_context);
+    }
+    // Step 6
+    if let Some(local_context) = active_property
+        .and_then(|prop| type_scoped_context.term_definition(prop))
+        .and_then(Definition::local_context)
+    {
+        active_context = Cow::Owned(
+            active_context
+                .join_local_context(processor, local_context, true, true)
+                .await?,
+        );
+    }
+    // Step 7
+    if element.contains_key("@value") || element.contains_key("@id") {
+        let inverse_context = active_context.inverse_context();
+        let result = compact_value(
+            processor,
+            &active_context,
+            &inverse_context,
+            active_property,
+            element,
+        )?;
+        let is_json_property = active_property
+            .and_then(|prop| active_context.term_definition(prop))
+            .and_then(Definition::ty)
+            == Some("@json");
+        match result {
+            Value::Object(_) | Value::Array(_) if !is_json_property => {}
+            v => return Ok(v),
+        }
+    }
+    // Step 8
+    if is_list_object_map(element)
+        && container_contains(&active_context, active_property, ContainerItem::List)
+    {
+        return compact_element(
+            processor,
+            &active_context,
+            active_property,
+            &element["@list"],
+        )
+        .await;
+    }
+    // Step 9
+    let inside_reverse = active_property == Some("@reverse");
+    // Step 10
+    let mut result = JsonMap::new();
+    // Step 11
+    let type_scoped_inverse = type_scoped_context.inverse_context();
+    if let Some(types) = element.get("@type") {
+        let mut compacted_types = Vec::new();
+        for ty in to_ref_array(types).iter().filter_map(Value::as_str) {
+            compacted_types.push(
+                CompactIriOptions::new(type_scoped_context, &type_scoped_inverse)
+                    .vocab(true)
+                    .compact(processor, ty)?
+                    .into_owned(),
+            );
+        }
+        compacted_types.sort();
+        // Step 11.1
+        for term in &compacted_types {
+            if let Some(local_context) = type_scoped_context
+                .term_definition(term)
+                .and_then(Definition::local_context)
+            {
+                active_context = Cow::Owned(
+                    active_context
+                        .join_local_context(processor, local_context, false, false)
+                        .await?,
+                );
+            }
+        }
+    }
+    let active_context: &Context = &active_context;
+    let inverse_context = active_context.inverse_context();
+    let compact_iri = CompactIriOptions::new(active_context, &inverse_context);
+    let alias = |keyword: &str| -> Result<String> {
+        Ok(compact_iri
+            .vocab(true)
+            .compact(processor, keyword)?
+            .into_owned())
+    };
+
+    // Step 12
+    for (expanded_property, expanded_value) in map_entries(element, processor.is_ordered()) {
+        match expanded_property.as_str() {
+            // Step 12.1
+            "@id" => {
+                // Step 12.1.1
+                let compacted_value = match expanded_value {
+                    Value::String(id) => {
+                        Value::String(compact_iri.compact(processor, id)?.into_owned())
+                    }
+                    v => v.clone(),
+                };
+                // Step 12.1.2, 12.1.3
+                result.insert(alias("@id")?, compacted_value);
+                continue;
+            }
+            // Step 12.2
+            "@type" => {
+                let compact_type =
+                    CompactIriOptions::new(type_scoped_context, &type_scoped_inverse).vocab(true);
+                // Step 12.2.1, 12.2.2
+                let compacted_value = match expanded_value {
+                    Value::String(ty) => {
+                        Value::String(compact_type.compact(processor, ty)?.into_owned())
+                    }
+                    Value::Array(types) => {
+                        let mut compacted = Vec::with_capacity(types.len());
+                        for ty in types {
+                            compacted.push(match ty {
+                                Value::String(ty) => {
+                                    Value::String(compact_type.compact(processor, ty)?.into_owned())
+                                }
+                                v => v.clone(),
+                            });
+                        }
+                        Value::Array(compacted)
+                    }
+                    v => v.clone(),
+                };
+                // Step 12.2.3
+                let alias = alias("@type")?;
+                // Step 12.2.4
+                let as_array = (!processor.is_processing_mode_1_0()
+                    && container_contains(
+                        active_context,
+                        Some(alias.as_str()),
+                        ContainerItem::Set,
+                    ))
+                    || !processor.is_compact_arrays();
+                // Step 12.2.5
+                add_value(&mut result, &alias, compacted_value, as_array);
+                // Step 12.2.6
+                continue;
+            }
+            // Step 12.3
+            "@reverse" => {
+                // Step 12.3.1
+                let compacted_value =
+                    compact_element(processor, active_context, Some("@reverse"), expanded_value)
+                        .await?;
+                let mut compacted_value = match compacted_value {
+                    Value::Object(map) => map,
+                    _ => JsonMap::new(),
+                };
+                // Step 12.3.2
+                let reverse_properties = compacted_value
+                    .keys()
+                    .filter(|property| {
+                        active_context
+                            .term_definition(property)
+                            .map_or(false, Definition::is_reverse)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for property in reverse_properties {
+                    let value = compacted_value
+                        .remove(&property)
+                        .expect("Should never fail: the key is taken from the map");
+                    // Step 12.3.2.1.1
+                    let as_array = container_contains(
+                        active_context,
+                        Some(property.as_str()),
+                        ContainerItem::Set,
+                    ) || !processor.is_compact_arrays();
+                    // Step 12.3.2.1.2, 12.3.2.1.3
+                    add_value(&mut result, &property, value, as_array);
+                }
+                // Step 12.3.3
+                if !compacted_value.is_empty() {
+                    // Step 12.3.3.1, 12.3.3.2
+                    result.insert(alias("@reverse")?, Value::Object(compacted_value));
+                }
+                // Step 12.3.4
+                continue;
+            }
+            // Step 12.4
+            "@preserve" => {
+                // Step 12.4.1
+                let compacted_value =
+                    compact_element(processor, active_context, active_property, expanded_value)
+                        .await?;
+                // Step 12.4.2
+                if !compacted_value.as_array().map_or(false, Vec::is_empty) {
+                    add_value(&mut result, "@preserve", compacted_value, false);
+                }
+                continue;
+            }
+            // Step 12.5
+            "@index"
+                if container_contains(active_context, active_property, ContainerItem::Index) =>
+            {
+                continue;
+            }
+            // Step 12.6
+            "@direction" | "@index" | "@language" | "@value" => {
+                // Step 12.6.1, 12.6.2
+                result.insert(alias(expanded_property)?, expanded_value.clone());
+                continue;
+            }
+            _ => {}
+        }
+
+        let expanded_items = to_ref_array(expanded_value);
+        // Step 12.7
+        if expanded_items.is_empty() {
+            // Step 12.7.1
+            let item_active_property = compact_iri
+                .value(expanded_value)
+                .vocab(true)
+                .reverse(inside_reverse)
+                .compact(processor, expanded_property)?;
+            // Step 12.7.2
+            let nest_result = nest_result(active_context, &item_active_property, &mut result)?;
+            // Step 12.7.3
+            add_value(
+                nest_result,
+                &item_active_property,
+                Value::Array(Vec::new()),
+                true,
+            );
+        }
+
+        // Step 12.8
+        for expanded_item in expanded_items {
+            compact_item(
+                processor,
+                active_context,
+                &inverse_context,
+                expanded_property,
+                expanded_item,
+                inside_reverse,
+                &mut result,
+            )
+            .await?;
+        }
+    }
+
+    // Step 13
+    Ok(Value::Object(result))
+}
+
+/// Compacts an array.
+// Step 3
+async fn compact_array<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    active_property: Option<&str>,
+    items: &[Value],
+) -> Result<Value> {
+    // Step 3.1
+    let mut result = Vec::with_capacity(items.len());
+    // Step 3.2
+    for item in items {
+        // Step 3.2.1
+        let compacted_item =
+            compact_element(processor, active_context, active_property, item).await?;
+        // Step 3.2.2
+        if !compacted_item.is_null() {
+            result.push(compacted_item);
+        }
+    }
+    // Step 3.3
+    if !processor.is_compact_arrays()
+        || result.len() != 1
+        || active_property == Some("@graph")
+        || active_property == Some("@set")
+        || container_contains(active_context, active_property, ContainerItem::List)
+        || container_contains(active_context, active_property, ContainerItem::Set)
+    {
+        return Ok(Value::Array(result));
+    }
+
+    // Step 3.4
+    Ok(result
+        .pop()
+        .expect("Should never fail: the array has exactly one item"))
+}
+
+/// Compacts an item of an expanded property, and adds the result to `result`.
+// Step 12.8
+async fn compact_item<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    inverse_context: &InverseContext,
+    expanded_property: &str,
+    expanded_item: &Value,
+    inside_reverse: bool,
+    result: &mut JsonMap<String, Value>,
+) -> Result<()> {
+    let compact_iri = CompactIriOptions::new(active_context, inverse_context);
+    let alias = |keyword: &str| -> Result<String> {
+        Ok(compact_iri
+            .vocab(true)
+            .compact(processor, keyword)?
+            .into_owned())
+    };
+
+    // Step 12.8.1
+    let item_active_property = compact_iri
+        .value(expanded_item)
+        .vocab(true)
+        .reverse(inside_reverse)
+        .compact(processor, expanded_property)?;
+    let item_active_property: &str = &item_active_property;
+    // Step 12.8.2
+    let result = nest_result(active_context, item_active_property, result)?;
+    // Step 12.8.3
+    let container =
+        |item: ContainerItem| container_contains(active_context, Some(item_active_property), item);
+    // Step 12.8.4
+    let as_array = container(ContainerItem::Set)
+        || item_active_property == "@graph"
+        || item_active_property == "@list"
+        || !processor.is_compact_arrays();
+    // Step 12.8.5
+    let item_is_list = is_list_object(expanded_item);
+    let item_is_graph = is_graph_object(expanded_item);
+    let inner = if item_is_list {
+        &expanded_item["@list"]
+    } else if item_is_graph {
+        &expanded_item["@graph"]
+    } else {
+        expanded_item
+    };
+    let mut compacted_item =
+        compact_element(processor, active_context, Some(item_active_property), inner).await?;
+
+    if item_is_list {
+        // Step 12.8.6
+        // Step 12.8.6.1
+        let compacted_item = Value::Array(into_array(compacted_item));
+        if !container(ContainerItem::List) {
+            // Step 12.8.6.2
+            // Step 12.8.6.2.1
+            let mut list = JsonMap::new();
+            list.insert(alias("@list")?, compacted_item);
+            // Step 12.8.6.2.2
+            if let Some(index) = expanded_item.get("@index") {
+                list.insert(alias("@index")?, index.clone());
+            }
+            // Step 12.8.6.2.3
+            add_value(result, item_active_property, Value::Object(list), as_array);
+        } else {
+            // Step 12.8.6.3
+            result.insert(item_active_property.to_owned(), compacted_item);
+        }
+    } else if item_is_graph {
+        // Step 12.8.7
+        let item_id = expanded_item.get("@id").and_then(Value::as_str);
+        let item_index = expanded_item.get("@index");
+        if container(ContainerItem::Graph) && container(ContainerItem::Id) {
+            // Step 12.8.7.1
+            // Step 12.8.7.1.2
+            let map_key = match item_id {
+                Some(id) => compact_iri.compact(processor, id)?.into_owned(),
+                None => alias("@none")?,
+            };
+            // Step 12.8.7.1.1, 12.8.7.1.3
+            let map_object = map_object_entry(result, item_active_property)?;
+            add_value(map_object, &map_key, compacted_item, as_array);
+        } else if container(ContainerItem::Graph)
+            && container(ContainerItem::Index)
+            && is_simple_graph_object(expanded_item)
+        {
+            // Step 12.8.7.2
+            // Step 12.8.7.2.2
+            let map_key = match item_index.and_then(Value::as_str) {
+                Some(index) => index.to_owned(),
+                None => alias("@none")?,
+            };
+            // Step 12.8.7.2.1, 12.8.7.2.3
+            let map_object = map_object_entry(result, item_active_property)?;
+            add_value(map_object, &map_key, compacted_item, as_array);
+        } else if container(ContainerItem::Graph) && is_simple_graph_object(expanded_item) {
+            // Step 12.8.7.3
+            // Step 12.8.7.3.1
+            compacted_item = match compacted_item {
+                Value::Array(items) if items.len() > 1 => {
+                    let mut included = JsonMap::new();
+                    included.insert(alias("@included")?, Value::Array(items));
+                    Value::Object(included)
+                }
+                v => v,
+            };
+            // Step 12.8.7.3.2
+            add_value(result, item_active_property, compacted_item, as_array);
+        } else {
+            // Step 12.8.7.4
+            // Step 12.8.7.4.1
+            let mut graph = JsonMap::new();
+            graph.insert(alias("@graph")?, compacted_item);
+            // Step 12.8.7.4.2
+            if let Some(id) = item_id {
+                graph.insert(
+                    alias("@id")?,
+                    Value::String(compact_iri.compact(processor, id)?.into_owned()),
+                );
+            }
+            // Step 12.8.7.4.3
+            if let Some(index) = item_index {
+                graph.insert(alias("@index")?, index.clone());
+            }
+            // Step 12.8.7.4.4
+            add_value(result, item_active_property, Value::Object(graph), as_array);
+        }
+    } else if !container(ContainerItem::Graph)
+        && (container(ContainerItem::Language)
+            || container(ContainerItem::Index)
+            || container(ContainerItem::Id)
+            || container(ContainerItem::Type))
+    {
+        // Step 12.8.8
+        // Step 12.8.8.2
+        let container_keyword = if container(ContainerItem::Language) {
+            "@language"
+        } else if container(ContainerItem::Index) {
+            "@index"
+        } else if container(ContainerItem::Id) {
+            "@id"
+        } else {
+            "@type"
+        };
+        let mut container_key = alias(container_keyword)?;
+        // Step 12.8.8.3
+        let index_key = active_context
+            .term_definition(item_active_property)
+            .and_then(Definition::index)
+            .unwrap_or("@index");
+        let mut map_key: Option<String> = None;
+        if container(ContainerItem::Language) && expanded_item.get("@value").is_some() {
+            // Step 12.8.8.4
+            compacted_item = expanded_item["@value"].clone();
+            map_key = expanded_item
+                .get("@language")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned);
+        } else if container(ContainerItem::Index) && index_key == "@index" {
+            // Step 12.8.8.5
+            map_key = expanded_item
+                .get("@index")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned);
+        } else if container(ContainerItem::Index) {
+            // Step 12.8.8.6
+            // Step 12.8.8.6.1
+            container_key = alias(index_key)?;
+            // Step 12.8.8.6.2, 12.8.8.6.3
+            if let Value::Object(item_map) = &mut compacted_item {
+                map_key = take_first_string(item_map, &container_key);
+            }
+        } else if container(ContainerItem::Id) {
+            // Step 12.8.8.7
+            if let Value::Object(item_map) = &mut compacted_item {
+                map_key = match item_map.remove(&container_key) {
+                    Some(Value::String(id)) => Some(id),
+                    Some(v) => {
+                        item_map.insert(container_key.clone(), v);
+                        None
+                    }
+                    None => None,
+                };
+            }
+        } else {
+            // Step 12.8.8.8
+            let mut id_only = None;
+            if let Value::Object(item_map) = &mut compacted_item {
+                // Step 12.8.8.8.1, 12.8.8.8.2
+                map_key = take_first_string(item_map, &container_key);
+                // Step 12.8.8.8.3
+                if item_map.len() == 1 {
+                    if let Some(id) = expanded_item.get("@id") {
+                        let key = item_map
+                            .keys()
+                            .next()
+                            .expect("Should never fail: the map has exactly one entry");
+                        if alias_expands_to_id(active_context, key) {
+                            let mut id_map = JsonMap::new();
+                            id_map.insert("@id".to_owned(), id.clone());
+                            id_only = Some(Value::Object(id_map));
+                        }
+                    }
+                }
+            }
+            if let Some(id_only) = id_only {
+                compacted_item = compact_element(
+                    processor,
+                    active_context,
+                    Some(item_active_property),
+                    &id_only,
+                )
+                .await?;
+            }
+        }
+        // Step 12.8.8.9
+        let map_key = match map_key {
+            Some(map_key) => map_key,
+            None => alias("@none")?,
+        };
+        // Step 12.8.8.1, 12.8.8.10
+        let map_object = map_object_entry(result, item_active_property)?;
+        add_value(map_object, &map_key, compacted_item, as_array);
+    } else {
+        // Step 12.8.9
+        // A JSON literal compacted to its raw value is a single value even if it is an array.
+        let is_json_literal = expanded_item.get("@type").and_then(Value::as_str) == Some("@json");
+        let compacted_item = if is_json_literal {
+            Value::Array(vec![compacted_item])
+        } else {
+            compacted_item
+        };
+        add_value(result, item_active_property, compacted_item, as_array);
+    }
+
+    Ok(())
+}
+
+/// Checks whether the given map is a list object.
+fn is_list_object_map(map: &JsonMap<String, Value>) -> bool {
+    map.contains_key("@list") && map.keys().all(|key| key == "@list" || key == "@index")
+}
+
+/// Checks whether the container mapping of the given property contains the given item.
+fn container_contains(
+    active_context: &Context,
+    property: Option<&str>,
+    item: ContainerItem,
+) -> bool {
+    property
+        .and_then(|prop| active_context.term_definition(prop))
+        .map_or(false, |def| def.container_contains(item))
+}
+
+/// Checks whether the given key (possibly an alias) expands to `@id`.
+fn alias_expands_to_id(active_context: &Context, key: &str) -> bool {
+    key == "@id"
+        || active_context
+            .term_definition(key)
+            .map_or(false, |def| def.iri() == "@id")
+}
+
+/// Returns the map object for the given key in the result, creating it if necessary.
+fn map_object_entry<'a>(
+    result: &'a mut JsonMap<String, Value>,
+    key: &str,
+) -> Result<&'a mut JsonMap<String, Value>> {
+    result
+        .entry(key)
+        .or_insert_with(|| Value::Object(JsonMap::new()))
+        .as_object_mut()
+        .ok_or_else(|| {
+            ErrorCode::Uncategorized
+                .and_message(format!("Expected a map for the container entry {:?}", key))
+        })
+}
+
+/// Returns the map where the values of the given property should be added.
+///
+/// This is the value of the nest term entry if the property has a nest value, and `result`
+/// itself otherwise.
+// Step 12.7.2, 12.8.2
+fn nest_result<'a>(
+    active_context: &Context,
+    item_active_property: &str,
+    result: &'a mut JsonMap<String, Value>,
+) -> Result<&'a mut JsonMap<String, Value>> {
+    let nest_term = match active_context
+        .term_definition(item_active_property)
+        .and_then(Definition::nest)
+    {
+        Some(v) => v,
+        None => return Ok(result),
+    };
+    // Step 12.8.2.1
+    if nest_term != "@nest"
+        && active_context
+            .term_definition(nest_term)
+            .map(Definition::iri)
+            != Some("@nest")
+    {
+        return Err(ErrorCode::InvalidNestValue.and_message(format!(
+            "Nest value {:?} of the term {:?} is not `@nest` or its alias",
+            nest_term, item_active_property
+        )));
+    }
+    // Step 12.8.2.2, 12.8.2.3
+    result
+        .entry(nest_term)
+        .or_insert_with(|| Value::Object(JsonMap::new()))
+        .as_object_mut()
+        .ok_or_else(|| {
+            ErrorCode::InvalidNestValue
+                .and_message(format!("Expected a map for the nest entry {:?}", nest_term))
+        })
+}
+
+/// Removes the first string value of the given entry and returns it.
+///
+/// The remaining values are kept in the entry.
+fn take_first_string(map: &mut JsonMap<String, Value>, key: &str) -> Option<String> {
+    let mut values = into_array(map.remove(key)?);
+    let first = match values.first() {
+        Some(Value::String(_)) => match values.remove(0) {
+            Value::String(s) => Some(s),
+            _ => unreachable!("Should never fail: the first value is a string"),
+        },
+        _ => None,
+    };
+    match values.len() {
+        0 => {}
+        1 => {
+            map.insert(key.to_owned(), values.pop().expect("Should never fail"));
+        }
+        _ => {
+            map.insert(key.to_owned(), Value::Array(values));
+        }
+    }
+    first
+}
@@ -0,0 +1,418 @@
+//! IRI compaction.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#iri-compaction>.
+
+use std::borrow::Cow;
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::{Context, InverseContext},
+    error::{ErrorCode, Result},
+    iri::{to_prefix_and_suffix, to_relative_iri},
+    json::{to_ref_array, Nullable},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+    syntax::{is_graph_object, is_list_object, is_value_object},
+};
+
+/// Options for IRI compaction algorithm.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CompactIriOptions<'a> {
+    /// Active context.
+    active_context: &'a Context,
+    /// Inverse context.
+    inverse_context: &'a InverseContext,
+    /// Value.
+    value: Option<&'a Value>,
+    /// Vocab.
+    vocab: bool,
+    /// Reverse.
+    reverse: bool,
+}
+
+impl<'a> CompactIriOptions<'a> {
+    /// Creates a new `CompactIriOptions`.
+    pub(crate) fn new(active_context: &'a Context, inverse_context: &'a InverseContext) -> Self {
+        Self {
+            active_context,
+            inverse_context,
+            value: None,
+            vocab: false,
+            reverse: false,
+        }
+    }
+
+    /// Sets the value.
+    pub(crate) fn value(self, value: &'a Value) -> Self {
+        Self {
+            value: Some(value),
+            ..self
+        }
+    }
+
+    /// Sets "vocab" flag.
+    pub(crate) fn vocab(self, vocab: bool) -> Self {
+        Self { vocab, ..self }
+    }
+
+    /// Sets "reverse" flag.
+    pub(crate) fn reverse(self, reverse: bool) -> Self {
+        Self { reverse, ..self }
+    }
+
+    /// Runs IRI compaction algorithm.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#iri-compaction>.
+    pub(crate) fn compact<'b, L: LoadRemoteDocument>(
+        self,
+        processor: &Processor<L>,
+        var: &'b str,
+    ) -> Result<Cow<'b, str>>
+    where
+        'a: 'b,
+    {
+        compact_iri(processor, self, var)
+    }
+}
+
+/// Runs IRI compaction algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#iri-compaction>.
+fn compact_iri<'a: 'b, 'b, L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    options: CompactIriOptions<'a>,
+    var: &'b str,
+) -> Result<Cow<'b, str>> {
+    let CompactIriOptions {
+        active_context,
+        inverse_context,
+        value,
+        vocab,
+        ..
+    } = options;
+
+    // Step 1: `var` is not null.
+    // Step 2: The inverse context is created by the caller.
+    // Step 3
+    if vocab && inverse_context.contains_iri(var) {
+        if let Some(term) = select_term_for_value(processor, options, var)? {
+            // Step 3.21
+            return Ok(Cow::Borrowed(term));
+        }
+    }
+    // Step 4
+    if vocab {
+        if let Nullable::Value(vocab_mapping) = active_context.vocab() {
+            // Step 4.1
+            if var.starts_with(vocab_mapping) && var.len() > vocab_mapping.len() {
+                let suffix = &var[vocab_mapping.len()..];
+                if active_context.raw_term_definition(suffix).is_none() {
+                    return Ok(Cow::Borrowed(suffix));
+                }
+            }
+        }
+    }
+    // Step 5
+    let mut compact_iri: Option<String> = None;
+    // Step 6
+    for (term, definition) in active_context.term_definitions() {
+        // Step 6.1
+        let definition = match definition {
+            Some(def) => def,
+            None => continue,
+        };
+        let prefix_iri = definition.iri();
+        if prefix_iri == var || !var.starts_with(prefix_iri) || !definition.is_prefix() {
+            continue;
+        }
+        // Step 6.2
+        let candidate = format!("{}:{}", term, &var[prefix_iri.len()..]);
+        // Step 6.3
+        let is_shorter = compact_iri.as_ref().map_or(true, |compact_iri| {
+            candidate.len() < compact_iri.len()
+                || (candidate.len() == compact_iri.len() && candidate < *compact_iri)
+        });
+        let is_usable = match active_context.raw_term_definition(&candidate) {
+            None => true,
+            Some(Nullable::Value(def)) => value.is_none() && def.iri() == var,
+            Some(Nullable::Null) => false,
+        };
+        if is_shorter && is_usable {
+            compact_iri = Some(candidate);
+        }
+    }
+    // Step 7
+    if let Some(compact_iri) = compact_iri {
+        return Ok(Cow::Owned(compact_iri));
+    }
+    // Step 8
+    if let Some((scheme, suffix)) = to_prefix_and_suffix(var) {
+        if !suffix.starts_with("//")
+            && active_context
+                .term_definition(scheme)
+                .map_or(false, |def| def.is_prefix())
+        {
+            return Err(ErrorCode::IriConfusedWithPrefix.and_message(format!(
+                "IRI {:?} can be confused with a compact IRI with prefix {:?}",
+                var, scheme
+            )));
+        }
+    }
+    // Step 9
+    // If `compactToRelative` is false, IRIs are kept absolute.
+    if !vocab && processor.is_compact_to_relative() {
+        if let Some(base) = processor.base(active_context) {
+            if let Some(relative) = to_relative_iri(base.as_str(), var) {
+                return Ok(Cow::Owned(relative));
+            }
+        }
+    }
+
+    // Step 10
+    Ok(Cow::Borrowed(var))
+}
+
+/// Selects a term for the given value, using the inverse context.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#iri-compaction>.
+// Step 3
+fn select_term_for_value<'a, L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    options: CompactIriOptions<'a>,
+    var: &str,
+) -> Result<Option<&'a str>> {
+    let CompactIriOptions {
+        active_context,
+        inverse_context,
+        value,
+        reverse,
+        ..
+    } = options;
+    let value_map: Option<&JsonMap<String, Value>> = value.and_then(Value::as_object);
+    let has_entry = |key: &str| value_map.map_or(false, |map| map.contains_key(key));
+    let entry_str = |key: &str| {
+        value_map
+            .and_then(|map| map.get(key))
+            .and_then(Value::as_str)
+    };
+
+    // Step 3.1
+    let default_language = active_context
+        .default_language()
+        .map_or_else(|| "@none".to_owned(), str::to_lowercase);
+    // Step 3.3
+    let mut containers: Vec<&str> = Vec::new();
+    // Step 3.4
+    let mut type_language = "@language";
+    let mut type_language_value: Option<String> = Some("@null".to_owned());
+    // Step 3.5
+    if has_entry("@index") && !value.map_or(false, is_graph_object) {
+        containers.push("@index");
+        containers.push("@index@set");
+    }
+    if reverse {
+        // Step 3.6
+        type_language = "@type";
+        type_language_value = Some("@reverse".to_owned());
+        containers.push("@set");
+    } else if value.map_or(false, is_list_object) {
+        // Step 3.7
+        // Step 3.7.1
+        if !has_entry("@index") {
+            containers.push("@list");
+        }
+        // Step 3.7.2
+        let list = value_map
+            .and_then(|map| map.get("@list"))
+            .map_or(&[][..], to_ref_array);
+        // Step 3.7.3
+        let mut common_type: Option<String> = None;
+        let mut common_language: Option<String> = None;
+        if list.is_empty() {
+            common_language = Some(default_language.clone());
+        }
+        // Step 3.7.4
+        for item in list {
+            // Step 3.7.4.1
+            let mut item_language = "@none".to_owned();
+            let mut item_type = "@none".to_owned();
+            let item_is_value = is_value_object(item);
+            // Step 3.7.4.2
+            if item_is_value {
+                if let Some(direction) = item.get("@direction").and_then(Value::as_str) {
+                    // Step 3.7.4.2.1
+                    let language = item.get("@language").and_then(Value::as_str).unwrap_or("");
+                    item_language = format!("{}_{}", language, direction).to_lowercase();
+                } else if let Some(language) = item.get("@language").and_then(Value::as_str) {
+                    // Step 3.7.4.2.2
+                    item_language = language.to_lowercase();
+                } else if let Some(ty) = item.get("@type").and_then(Value::as_str) {
+                    // Step 3.7.4.2.3
+                    item_type = ty.to_owned();
+                } else {
+                    // Step 3.7.4.2.4
+                    item_language = "@null".to_owned();
+                }
+            } else {
+                // Step 3.7.4.3
+                item_type = "@id".to_owned();
+            }
+            // Step 3.7.4.4, 3.7.4.5
+            match &common_language {
+                None => common_language = Some(item_language),
+                Some(lang) if *lang != item_language && item_is_value => {
+                    common_language = Some("@none".to_owned())
+                }
+                Some(_) => {}
+            }
+            // Step 3.7.4.6, 3.7.4.7
+            match &common_type {
+                None => common_type = Some(item_type),
+                Some(ty) if *ty != item_type => common_type = Some("@none".to_owned()),
+                Some(_) => {}
+            }
+            // Step 3.7.4.8
+            if common_language.as_ref().map(String::as_str) == Some("@none")
+                && common_type.as_ref().map(String::as_str) == Some("@none")
+            {
+                break;
+            }
+        }
+        // Step 3.7.5
+        let common_language = common_language.unwrap_or_else(|| "@none".to_owned());
+        // Step 3.7.6
+        let common_type = common_type.unwrap_or_else(|| "@none".to_owned());
+        // Step 3.7.7, 3.7.8
+        if common_type != "@none" {
+            type_language = "@type";
+            type_language_value = Some(common_type);
+        } else {
+            type_language_value = Some(common_language);
+        }
+    } else if value.map_or(false, is_graph_object) {
+        // Step 3.8
+        // Step 3.8.1
+        if has_entry("@index") {
+            containers.extend(&["@graph@index", "@graph@index@set"]);
+        }
+        // Step 3.8.2
+        if has_entry("@id") {
+            containers.extend(&["@graph@id", "@graph@id@set"]);
+        }
+        // Step 3.8.3
+        containers.extend(&["@graph", "@graph@set", "@set"]);
+        // Step 3.8.4
+        if !has_entry("@index") {
+            containers.extend(&["@graph@index", "@graph@index@set"]);
+        }
+        // Step 3.8.5
+        if !has_entry("@id") {
+            containers.extend(&["@graph@id", "@graph@id@set"]);
+        }
+        // Step 3.8.6
+        containers.extend(&["@index", "@index@set"]);
+        // Step 3.8.7
+        type_language = "@type";
+        type_language_value = Some("@id".to_owned());
+    } else {
+        // Step 3.9
+        if value.map_or(false, is_value_object) {
+            // Step 3.9.1
+            if let (Some(direction), false) = (entry_str("@direction"), has_entry("@index")) {
+                // Step 3.9.1.1
+                let language = entry_str("@language").unwrap_or("");
+                type_language_value = Some(format!("{}_{}", language, direction).to_lowercase());
+                containers.extend(&["@language", "@language@set"]);
+            } else if let (Some(language), false) = (entry_str("@language"), has_entry("@index")) {
+                // Step 3.9.1.2
+                type_language_value = Some(language.to_lowercase());
+                containers.extend(&["@language", "@language@set"]);
+            } else if let Some(ty) = entry_str("@type") {
+                // Step 3.9.1.3
+                type_language_value = Some(ty.to_owned());
+                type_language = "@type";
+            }
+        } else {
+            // Step 3.9.2
+            type_language = "@type";
+            type_language_value = Some("@id".to_owned());
+            containers.extend(&["@id", "@id@set", "@type", "@set@type"]);
+        }
+        // Step 3.9.3
+        containers.push("@set");
+    }
+    // Step 3.10
+    containers.push("@none");
+    if !processor.is_processing_mode_1_0() {
+        // Step 3.11
+        if !has_entry("@index") {
+            containers.extend(&["@index", "@index@set"]);
+        }
+        // Step 3.12
+        if value_map.map_or(false, |map| map.len() == 1 && map.contains_key("@value")) {
+            containers.extend(&["@language", "@language@set"]);
+        }
+    }
+    // Step 3.13
+    let type_language_value = type_language_value.unwrap_or_else(|| "@null".to_owned());
+    // Step 3.14
+    let mut preferred_values: Vec<Cow<'_, str>> = Vec::new();
+    // Step 3.15
+    if type_language_value == "@reverse" {
+        preferred_values.push(Cow::Borrowed("@reverse"));
+    }
+    let id = entry_str("@id");
+    match id {
+        // Step 3.16
+        Some(id) if type_language_value == "@id" || type_language_value == "@reverse" => {
+            // Step 3.16.1
+            let compacted_id = compact_iri(
+                processor,
+                CompactIriOptions::new(active_context, inverse_context).vocab(true),
+                id,
+            )?;
+            let is_vocab_term = active_context
+                .term_definition(&compacted_id)
+                .map_or(false, |def| def.iri() == id);
+            if is_vocab_term {
+                preferred_values.extend(vec![
+                    Cow::Borrowed("@vocab"),
+                    Cow::Borrowed("@id"),
+                    Cow::Borrowed("@none"),
+                ]);
+            } else {
+                // Step 3.16.2
+                preferred_values.extend(vec![
+                    Cow::Borrowed("@id"),
+                    Cow::Borrowed("@vocab"),
+                    Cow::Borrowed("@none"),
+                ]);
+            }
+        }
+        _ => {
+            // Step 3.17
+            let is_empty_list = value_map
+                .and_then(|map| map.get("@list"))
+                .and_then(Value::as_array)
+                .map_or(false, Vec::is_empty);
+            preferred_values.push(Cow::Owned(type_language_value));
+            preferred_values.push(Cow::Borrowed("@none"));
+            if is_empty_list {
+                type_language = "@any";
+            }
+        }
+    }
+    // Step 3.18
+    preferred_values.push(Cow::Borrowed("@any"));
+    // Step 3.19: Terms with only the matching direction are also candidates.
+    if type_language == "@language" {
+        let directions = preferred_values
+            .iter()
+            .filter_map(|v| v.find('_').map(|pos| v[pos..].to_owned()))
+            .collect::<Vec<_>>();
+        preferred_values.extend(directions.into_iter().map(Cow::Owned));
+    }
+    // Step 3.20
+    Ok(inverse_context.select_term(var, &containers, type_language, &preferred_values))
+}
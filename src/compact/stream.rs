@@ -0,0 +1,175 @@
+//! Compaction writing the results item by item.
+//!
+//! The compacted output is written as each top-level node is compacted, so the whole compacted
+//! document is never materialized. The expanded document the compaction starts from is still
+//! materialized as a whole.
+
+use std::io;
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    error::{ErrorCode, Result},
+    expand::{expand, write_error},
+    json::to_ref_array,
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+use super::{
+    compact_element, compact_graph_key, compaction_context, is_empty_context, with_context_first,
+};
+
+/// Runs compaction algorithm for the given document, and writes the compacted document.
+///
+/// The output is the same JSON as serializing the result of `compact()`.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+pub(crate) async fn compact_to_writer<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    context: &Value,
+    writer: &mut impl io::Write,
+) -> Result<()> {
+    // Step 2
+    let expanded_input = expand(processor, input).await?;
+    // Steps 3-5
+    let (active_context, context) = compaction_context(processor, context).await?;
+    let context = if is_empty_context(context) {
+        None
+    } else {
+        Some(context)
+    };
+
+    // Step 7
+    // The top-level items are compacted one at a time, as step 3 of the compaction algorithm
+    // does for the expanded document (which is always an array).
+    let mut output = GraphWriter::new(writer, context);
+    for item in to_ref_array(&expanded_input) {
+        let compacted_item = compact_element(processor, &active_context, None, item).await?;
+        if compacted_item.is_null() {
+            continue;
+        }
+        if output.is_started() || !processor.is_compact_arrays() || output.first.is_some() {
+            if !output.is_started() {
+                let graph = compact_graph_key(processor, &active_context)?;
+                output.start(graph)?;
+            }
+            output.write_item(&compacted_item)?;
+        } else {
+            output.first = Some(compacted_item);
+        }
+    }
+    output.finish()
+}
+
+/// Writer of the compacted output.
+struct GraphWriter<'a, W> {
+    /// Writer.
+    writer: &'a mut W,
+    /// Local context written as the `@context` entry.
+    context: Option<&'a Value>,
+    /// The only item compacted so far, which is written as the whole output if no other items
+    /// follow.
+    first: Option<Value>,
+    /// Whether the `@context` entry is written after the `@graph` entry, or `None` if the
+    /// `@graph` entry is not yet started.
+    context_last: Option<bool>,
+    /// Whether an item is written in the `@graph` entry.
+    has_items: bool,
+}
+
+impl<'a, W: io::Write> GraphWriter<'a, W> {
+    /// Creates a new `GraphWriter`.
+    fn new(writer: &'a mut W, context: Option<&'a Value>) -> Self {
+        Self {
+            writer,
+            context,
+            first: None,
+            context_last: None,
+            has_items: false,
+        }
+    }
+
+    /// Checks if the `@graph` entry is started.
+    fn is_started(&self) -> bool {
+        self.context_last.is_some()
+    }
+
+    /// Writes the start of the output with the `@graph` entry, and the pending item if any.
+    // Step 8
+    fn start(&mut self, graph: String) -> Result<()> {
+        // Entries are written in the order `serde_json::Map` would serialize them.
+        let mut probe = JsonMap::new();
+        probe.insert("@context".to_owned(), Value::Null);
+        probe.insert(graph.clone(), Value::Null);
+        let context_last = probe.keys().next() == Some(&graph);
+
+        self.write(b"{")?;
+        if let (Some(context), false) = (self.context, context_last) {
+            self.write_entry("@context", context)?;
+            self.write(b",")?;
+        }
+        serde_json::to_writer(&mut *self.writer, &graph).map_err(write_error)?;
+        self.write(b":[")?;
+        self.context_last = Some(context_last);
+        if let Some(first) = self.first.take() {
+            self.write_item(&first)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the item of the `@graph` entry.
+    fn write_item(&mut self, item: &Value) -> Result<()> {
+        if self.has_items {
+            self.write(b",")?;
+        }
+        self.has_items = true;
+        serde_json::to_writer(&mut *self.writer, item).map_err(write_error)
+    }
+
+    /// Writes the rest of the output.
+    fn finish(mut self) -> Result<()> {
+        match self.context_last {
+            Some(context_last) => {
+                self.write(b"]")?;
+                if let (Some(context), true) = (self.context, context_last) {
+                    self.write(b",")?;
+                    self.write_entry("@context", context)?;
+                }
+                self.write(b"}")
+            }
+            None => {
+                // Step 8
+                let output = match self.first.take() {
+                    None => JsonMap::new(),
+                    Some(Value::Object(map)) => map,
+                    Some(v) => {
+                        return Err(ErrorCode::Uncategorized.and_message(format!(
+                            "Compacted output should be a map or an array, but got {:?}",
+                            v
+                        )))
+                    }
+                };
+                // Step 9
+                let output = match self.context {
+                    Some(context) => with_context_first(context, output),
+                    None => output,
+                };
+                serde_json::to_writer(&mut *self.writer, &output).map_err(write_error)
+            }
+        }
+    }
+
+    /// Writes the entry of a map.
+    fn write_entry(&mut self, key: &str, value: &Value) -> Result<()> {
+        serde_json::to_writer(&mut *self.writer, key).map_err(write_error)?;
+        self.write(b":")?;
+        serde_json::to_writer(&mut *self.writer, value).map_err(write_error)
+    }
+
+    /// Writes the bytes.
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes).map_err(write_error)
+    }
+}
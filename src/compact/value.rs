@@ -0,0 +1,115 @@
+//! Value compaction.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#value-compaction>.
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    compact::iri::CompactIriOptions,
+    context::{definition::ContainerItem, Context, InverseContext},
+    error::Result,
+    json::Nullable,
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+/// Runs value compaction algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#value-compaction>.
+pub(crate) fn compact_value<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    inverse_context: &InverseContext,
+    active_property: Option<&str>,
+    value: &JsonMap<String, Value>,
+) -> Result<Value> {
+    let definition = active_property.and_then(|prop| active_context.term_definition(prop));
+    let type_mapping = definition.and_then(|def| def.ty());
+    let has_index_container =
+        definition.map_or(false, |def| def.container_contains(ContainerItem::Index));
+    // `@index` entry does not prevent compaction if the container mapping includes `@index`.
+    let index_compactable = !value.contains_key("@index") || has_index_container;
+    let compact_iri = CompactIriOptions::new(active_context, inverse_context);
+
+    // Step 3
+    let language = match definition.and_then(|def| def.language()) {
+        Some(Nullable::Value(lang)) => Some(lang),
+        Some(Nullable::Null) => None,
+        None => active_context.default_language(),
+    };
+    // Step 4
+    let direction = match definition.and_then(|def| def.direction()) {
+        Some(Nullable::Value(dir)) => Some(dir.as_str()),
+        Some(Nullable::Null) => None,
+        None => active_context
+            .default_base_direction()
+            .map(|dir| dir.as_str()),
+    };
+
+    // Step 5
+    if let Some(Value::String(id)) = value.get("@id") {
+        if value.keys().all(|key| key == "@id" || key == "@index") {
+            match type_mapping {
+                // Step 5.1
+                Some("@id") => {
+                    let id = compact_iri.compact(processor, id)?;
+                    return Ok(Value::String(id.into_owned()));
+                }
+                // Step 5.2
+                Some("@vocab") => {
+                    let id = compact_iri.vocab(true).compact(processor, id)?;
+                    return Ok(Value::String(id.into_owned()));
+                }
+                _ => {}
+            }
+        }
+    }
+    let value_type = value.get("@type").and_then(Value::as_str);
+    let result = value.get("@value");
+    if let Some(result) = result {
+        if value_type.is_some() && value_type == type_mapping {
+            // Step 6
+            if index_compactable {
+                return Ok(result.clone());
+            }
+        } else if type_mapping == Some("@none") || value_type.is_some() {
+            // Step 7: Leave value as is.
+        } else if !result.is_string() {
+            // Step 8
+            if index_compactable {
+                return Ok(result.clone());
+            }
+        } else {
+            // Step 9
+            let value_language = value.get("@language").and_then(Value::as_str);
+            let language_matches = match (value_language, language) {
+                (Some(value_language), Some(language)) => {
+                    value_language.to_lowercase() == language.to_lowercase()
+                }
+                (None, None) => true,
+                _ => false,
+            };
+            let direction_matches = value.get("@direction").and_then(Value::as_str) == direction;
+            if language_matches && direction_matches && index_compactable {
+                return Ok(result.clone());
+            }
+        }
+    }
+
+    // Step 10
+    let mut result = JsonMap::new();
+    for (key, v) in value {
+        let compacted_key = compact_iri.vocab(true).compact(processor, key)?;
+        let v = match (key.as_str(), v) {
+            // Step 10.1: Compact the type IRI.
+            ("@type", Value::String(ty)) => {
+                Value::String(compact_iri.vocab(true).compact(processor, ty)?.into_owned())
+            }
+            _ => v.clone(),
+        };
+        result.insert(compacted_key.into_owned(), v);
+    }
+
+    // Step 11
+    Ok(Value::Object(result))
+}
@@ -2,21 +2,42 @@
 //!
 //! See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#the-context>.
 
-use std::collections::HashMap;
+use std::sync::Arc;
 
 use iri_string::types::{IriStr, IriString};
 use serde_json::{Map as JsonMap, Value};
 
-use crate::{error::Result, json::Nullable, processor::Processor, remote::LoadRemoteDocument};
+use crate::{
+    error::Result, hash::HashMap, json::Nullable, options::JsonLdOptions, processor::Processor,
+    remote::LoadRemoteDocument,
+};
 
-pub(crate) use self::definition::Definition;
+pub use self::{
+    builder::{ContextBuilder, TermDefinitionBuilder},
+    compiled::CompiledContext,
+    definition::{
+        Container, ContainerItem, ContainerLoadError, Definition, Direction, DirectionLoadError,
+    },
+    diff::{ContextDiff, DefinitionAspect, TermChange},
+    lint::{ContextLint, LintKind},
+};
+pub(crate) use self::{
+    create_term_def::{DefinedTerms, OptionalParams as CreateTermDefOptionalParams},
+    inverse::InverseContext,
+    merge::ProcessedContextKey,
+};
 use self::{
-    create_term_def::{create_term_definition, OptionalParams as CreateTermDefOptionalParams},
-    merge::OptionalParams as MergeOptionalParams,
+    inverse::InverseContextCache,
+    merge::{OptionalParams as MergeOptionalParams, RemoteContexts},
 };
 
+mod builder;
+mod compiled;
 mod create_term_def;
-mod definition;
+pub(crate) mod definition;
+mod diff;
+mod inverse;
+mod lint;
 mod merge;
 
 /// JSON-LD context.
@@ -26,9 +47,14 @@ mod merge;
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Context {
     /// Term definitions.
-    term_definitions: HashMap<String, Nullable<Definition>>,
+    ///
+    /// This is shared between clones, and copied on the first modification.
+    term_definitions: Arc<HashMap<String, Nullable<Definition>>>,
     /// Base IRI.
     base: Nullable<IriString>,
+    /// Whether the base IRI is set explicitly (for example by `@base` entry of a local context),
+    /// rather than given as the base IRI of the document.
+    has_explicit_base: bool,
     /// Vocabulary mapping (optional).
     // TODO: This is an IRI.
     vocab: Nullable<String>,
@@ -37,7 +63,11 @@ pub struct Context {
     /// Default base direction (optional).
     default_base_direction: Option<definition::Direction>,
     /// Previous context (optional).
-    previous_context: Option<Box<Self>>,
+    previous_context: Option<Arc<Self>>,
+    /// Inverse context, created on demand.
+    ///
+    /// This should be cleared on every modification of the context.
+    inverse_context: InverseContextCache,
 }
 
 impl Context {
@@ -54,14 +84,54 @@ impl Context {
         }
     }
 
+    /// Returns the context with the given base IRI.
+    ///
+    /// `None` means the explicit `null`, that is, relative IRIs are not resolved.
+    pub fn with_base_iri(mut self, base: Option<IriString>) -> Self {
+        self.set_base(base.into());
+        self
+    }
+
+    /// Returns the context with the given vocabulary mapping.
+    pub fn with_vocab(mut self, vocab: Option<IriString>) -> Self {
+        self.set_vocab(vocab.map(|vocab| vocab.as_str().to_owned()));
+        self
+    }
+
+    /// Returns the context with the given default language.
+    pub fn with_default_language(mut self, lang: Option<String>) -> Self {
+        self.set_default_language(lang);
+        self
+    }
+
+    /// Returns the context with the given default base direction.
+    pub fn with_default_base_direction(mut self, dir: Option<Direction>) -> Self {
+        self.set_default_base_direction(dir);
+        self
+    }
+
+    /// Returns the base IRI.
+    ///
+    /// Returns `None` if the base IRI is explicitly set to `null`.
+    pub fn base_iri(&self) -> Option<&IriStr> {
+        self.base().into()
+    }
+
+    /// Returns the vocabulary mapping.
+    pub fn vocab_mapping(&self) -> Option<&str> {
+        self.vocab().into()
+    }
+
     /// Returns the base IRI.
     pub(crate) fn base(&self) -> Nullable<&IriStr> {
         self.base.as_ref().map(AsRef::as_ref)
     }
 
-    /// Sets the base IRI.
+    /// Sets the base IRI explicitly.
     pub(crate) fn set_base(&mut self, base: Nullable<IriString>) {
+        self.inverse_context.clear();
         self.base = base;
+        self.has_explicit_base = true;
     }
 
     /// Returns the vocabulary mapping.
@@ -71,19 +141,37 @@ impl Context {
 
     /// Sets the vocabulary mapping.
     pub(crate) fn set_vocab(&mut self, vocab: impl Into<Nullable<String>>) {
+        self.inverse_context.clear();
         self.vocab = vocab.into();
     }
 
+    /// Returns the default language.
+    pub fn default_language(&self) -> Option<&str> {
+        self.default_language.as_ref().map(AsRef::as_ref)
+    }
+
     /// Sets the default language.
     pub(crate) fn set_default_language(&mut self, lang: Option<String>) {
+        self.inverse_context.clear();
         self.default_language = lang;
     }
 
+    /// Returns the default base direction.
+    pub fn default_base_direction(&self) -> Option<Direction> {
+        self.default_base_direction
+    }
+
     /// Sets the default base direction.
-    pub(crate) fn set_default_base_direction(&mut self, dir: Option<definition::Direction>) {
+    pub(crate) fn set_default_base_direction(&mut self, dir: Option<Direction>) {
+        self.inverse_context.clear();
         self.default_base_direction = dir;
     }
 
+    /// Returns the previous context.
+    pub(crate) fn previous_context(&self) -> Option<&Self> {
+        self.previous_context.as_ref().map(AsRef::as_ref)
+    }
+
     /// Returns a raw term definition.
     ///
     /// This distinguishes absence and explicit `null`.
@@ -94,38 +182,124 @@ impl Context {
     /// Returns a flattened term definition.
     ///
     /// This returns `None` for both absent term and term set to explicit `null`.
-    pub(crate) fn term_definition(&self, term: &'_ str) -> Option<&Definition> {
+    pub fn term_definition(&self, term: &'_ str) -> Option<&Definition> {
         self.term_definitions
             .get(term)
             .and_then(|v| v.as_ref().into())
     }
 
+    /// Returns an iterator of terms and their term definitions.
+    ///
+    /// Terms explicitly set to `null` are also iterated, with `None` as definition.
+    /// The order is unspecified.
+    pub fn term_definitions(&self) -> impl Iterator<Item = (&str, Option<&Definition>)> {
+        self.term_definitions
+            .iter()
+            .map(|(term, def)| (term.as_str(), def.as_ref().into()))
+    }
+
     /// Removes the given term definition.
     ///
     /// This does nothing if the given term is not in the context.
     pub(crate) fn remove_term_definition(&mut self, term: &str) -> Option<Nullable<Definition>> {
-        self.term_definitions.remove(term)
+        // Avoid copying the shared term definitions if nothing is removed.
+        if !self.term_definitions.contains_key(term) {
+            return None;
+        }
+        self.inverse_context.clear();
+        Arc::make_mut(&mut self.term_definitions).remove(term)
     }
 
-    /// Runs create term definition algorithm.
-    ///
-    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#create-term-definition>.
-    pub(crate) async fn create_term_definition<L: LoadRemoteDocument>(
+    /// Sets the term definition.
+    pub(crate) fn insert_term_definition(
         &mut self,
-        processor: &Processor<L>,
-        local_context: ValueWithBase<'_, &JsonMap<String, Value>>,
-        term: &str,
-        defined: &mut HashMap<String, bool>,
-    ) -> Result<()> {
-        create_term_definition(
-            processor,
-            self,
-            local_context,
-            term,
-            defined,
-            CreateTermDefOptionalParams::new(),
-        )
-        .await
+        term: String,
+        definition: Nullable<Definition>,
+    ) {
+        self.inverse_context.clear();
+        Arc::make_mut(&mut self.term_definitions).insert(term, definition);
+    }
+
+    /// Returns the inverse context.
+    ///
+    /// The inverse context is created on the first call and cached until the context is modified.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#inverse-context-creation>.
+    pub(crate) fn inverse_context(&self) -> Arc<InverseContext> {
+        self.inverse_context.get_or_create(self)
+    }
+
+    /// Returns a local context (a value of `@context` entry) equivalent to the context.
+    ///
+    /// `@version` is set to `1.1` only if the context uses features not available in JSON-LD 1.0.
+    /// Term definitions are serialized as simple term definitions where possible.
+    /// The base IRI is serialized as `@base` only if it is set explicitly (by `@base` entry of a
+    /// local context, or by the builder), not if it is the base IRI of the document.
+    /// `@base` is ignored if the result is used as a remote context.
+    /// Terms are sorted, so that the result is deterministic.
+    /// The previous context and base URLs of scoped contexts are not preserved.
+    pub fn to_json(&self) -> Value {
+        let mut obj = JsonMap::new();
+        if self.requires_json_ld_11() {
+            obj.insert("@version".to_owned(), Value::from(1.1));
+        }
+        if self.has_explicit_base {
+            let base = match &self.base {
+                Nullable::Value(base) => Value::String(base.to_string()),
+                Nullable::Null => Value::Null,
+            };
+            obj.insert("@base".to_owned(), base);
+        }
+        if let Nullable::Value(vocab) = &self.vocab {
+            obj.insert("@vocab".to_owned(), Value::String(vocab.clone()));
+        }
+        if let Some(language) = &self.default_language {
+            obj.insert("@language".to_owned(), Value::String(language.clone()));
+        }
+        if let Some(direction) = self.default_base_direction {
+            obj.insert(
+                "@direction".to_owned(),
+                Value::String(direction.as_str().to_owned()),
+            );
+        }
+        // Sort the terms, so that the result does not depend on the order of the hash map.
+        let mut terms = self.term_definitions.iter().collect::<Vec<_>>();
+        terms.sort_unstable_by_key(|&(term, _)| term);
+        for (term, definition) in terms {
+            let definition = match definition {
+                Nullable::Value(definition) => definition.to_json(term),
+                Nullable::Null => Value::Null,
+            };
+            obj.insert(term.clone(), definition);
+        }
+        Value::Object(obj)
+    }
+
+    /// Checks whether the context uses features not available in JSON-LD 1.0.
+    fn requires_json_ld_11(&self) -> bool {
+        self.default_base_direction.is_some()
+            || self
+                .term_definitions
+                .iter()
+                .any(|(term, definition)| match definition {
+                    Nullable::Value(definition) => definition.requires_json_ld_11(term),
+                    Nullable::Null => false,
+                })
+    }
+
+    /// Compares term definitions with the newer context.
+    ///
+    /// This is useful to detect breaking changes of a published context.
+    pub fn diff(&self, new: &Context) -> ContextDiff {
+        ContextDiff::new(self, new)
+    }
+
+    /// Checks the local context for suspicious patterns, using `self` as the active context.
+    ///
+    /// The local context is not processed, and remote contexts in it are not dereferenced.
+    /// Use `Context::new()` as the active context to check a context standalone.
+    pub fn lint(&self, local_context: &Value) -> Vec<ContextLint> {
+        lint::lint(self, local_context)
     }
 
     /// Checks whether the context has the previous context.
@@ -141,6 +315,29 @@ impl Context {
             .any(Definition::is_protected)
     }
 
+    /// Processes the local context from scratch and returns the resulting active context.
+    ///
+    /// This receives a value associated to `@context` key, and the base IRI of the document
+    /// containing it.
+    /// If the options have the base IRI, it overrides the given base IRI.
+    ///
+    /// The result can be reused for multiple operations, and inspected independently of document
+    /// expansion.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#context-processing-algorithm>.
+    pub async fn process<L: LoadRemoteDocument>(
+        processor: &Processor<L>,
+        local_context: &Value,
+        base: &IriStr,
+        options: &JsonLdOptions,
+    ) -> Result<Self> {
+        let processor = processor.with_options(options);
+        let base = options.get_base().unwrap_or(base);
+        Self::with_base(base.to_owned())
+            .join_context_value(&processor, local_context, base, false)
+            .await
+    }
+
     /// Runs context processing algorithm and returns a new context.
     ///
     /// This receives a value associated to `@context` key.
@@ -164,6 +361,54 @@ impl Context {
         .await
     }
 
+    /// Runs context processing algorithm with the given flags and returns a new context.
+    ///
+    /// This is used to apply embedded, property-scoped, and type-scoped contexts.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#context-processing-algorithm>.
+    pub(crate) async fn join_local_context<L: LoadRemoteDocument>(
+        &self,
+        processor: &Processor<L>,
+        local_context: ValueWithBase<'_, &Value>,
+        override_protected: bool,
+        propagate: bool,
+    ) -> Result<Self> {
+        merge::join_value(
+            processor,
+            self,
+            local_context,
+            MergeOptionalParams::new()
+                .override_protected(override_protected)
+                .propagate(propagate),
+        )
+        .await
+    }
+
+    /// Runs context processing algorithm to validate a scoped context.
+    ///
+    /// Remote contexts in `remote_contexts` are not processed again, as they are being processed
+    /// already.
+    ///
+    /// See step 23.3 of <https://www.w3.org/TR/json-ld11-api/#create-term-definition>.
+    pub(crate) async fn validate_scoped_context<L: LoadRemoteDocument>(
+        &self,
+        processor: &Processor<L>,
+        local_context: ValueWithBase<'_, &Value>,
+        remote_contexts: &RemoteContexts,
+    ) -> Result<()> {
+        merge::join_value(
+            processor,
+            self,
+            local_context,
+            MergeOptionalParams::new()
+                .override_protected(true)
+                .remote_contexts(remote_contexts.clone())
+                .validate_scoped(false),
+        )
+        .await
+        .map(drop)
+    }
+
     /// Runs context processing algorithm and returns a new context.
     ///
     /// This receives a JSON value which contains `@context` entry.
@@ -211,14 +456,6 @@ impl<'a, T> ValueWithBase<'a, T> {
         Self { value, base }
     }
 
-    /// Applies the given function to the value, and returns the new value.
-    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> ValueWithBase<'a, U> {
-        ValueWithBase {
-            value: f(self.value),
-            base: self.base,
-        }
-    }
-
     /// Creates a new `ValueWithBase` with the same base IRI and the given new value.
     pub(crate) fn with_new_value<U>(&self, value: U) -> ValueWithBase<'a, U> {
         ValueWithBase {
@@ -232,11 +469,6 @@ impl<'a, T> ValueWithBase<'a, T> {
         &self.value
     }
 
-    /// Returns the base IRI of the document containing the value.
-    pub(crate) fn into_value(self) -> T {
-        self.value
-    }
-
     /// Returns the base IRI of the document containing the value.
     pub(crate) fn base(&self) -> &IriStr {
         self.base
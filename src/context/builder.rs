@@ -0,0 +1,325 @@
+//! Programmatic construction of contexts.
+
+use iri_string::types::IriString;
+use serde_json::Value;
+
+use crate::{
+    context::{
+        create_term_def::validate_container_items,
+        definition::{Container, ContainerItem, DefinitionBuilder, Direction},
+        Context, Definition,
+    },
+    error::{ErrorCode, Result},
+    iri::is_absolute_iri_ref,
+    json::Nullable,
+    syntax::has_form_of_keyword,
+};
+
+/// Builder of term definitions.
+///
+/// This constructs a term definition without creating and processing an expanded term definition
+/// as JSON.
+/// The IRI mapping is given as an absolute IRI, so no IRI expansion is required.
+///
+/// ```
+/// # use json_ld::{iri_string::types::IriStr, ContainerItem, TermDefinitionBuilder};
+/// # fn main() -> json_ld::Result<()> {
+/// let iri = IriStr::new("http://schema.org/name").expect("valid IRI");
+/// let name = TermDefinitionBuilder::new(iri.to_owned())
+///     .container(ContainerItem::Language)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermDefinitionBuilder {
+    /// Definition builder.
+    definition: DefinitionBuilder,
+}
+
+impl TermDefinitionBuilder {
+    /// Creates a new builder for a term mapped to the given IRI.
+    pub fn new(iri: IriString) -> Self {
+        let mut definition = DefinitionBuilder::new();
+        definition.set_iri(iri.as_str());
+        definition.set_reverse(false);
+        Self { definition }
+    }
+
+    /// Creates a new builder for a reverse property of the given IRI.
+    pub fn reverse(iri: IriString) -> Self {
+        let mut definition = DefinitionBuilder::new();
+        definition.set_iri(iri.as_str());
+        definition.set_reverse(true);
+        Self { definition }
+    }
+
+    /// Sets the type mapping.
+    ///
+    /// The value should be an absolute IRI, `@id`, `@json`, `@none`, or `@vocab`.
+    pub fn ty(mut self, ty: impl Into<String>) -> Self {
+        let ty: String = ty.into();
+        self.definition.set_ty(ty);
+        self
+    }
+
+    /// Sets the language mapping.
+    ///
+    /// `None` means the explicit `null`, that is, strings are not associated with any language.
+    pub fn language(mut self, language: Option<String>) -> Self {
+        self.definition
+            .set_language(Nullable::<String>::from(language).map(Into::into));
+        self
+    }
+
+    /// Sets the direction mapping.
+    ///
+    /// `None` means the explicit `null`, that is, strings have no base direction.
+    pub fn direction(mut self, direction: Option<Direction>) -> Self {
+        self.definition.set_direction(Nullable::from(direction));
+        self
+    }
+
+    /// Sets the container mapping.
+    pub fn container(mut self, container: impl Into<Container>) -> Self {
+        self.definition
+            .set_container(Nullable::Value(container.into()));
+        self
+    }
+
+    /// Sets the scoped context and its base URL.
+    pub fn scoped_context(mut self, context: Value, base_url: IriString) -> Self {
+        self.definition.set_local_context(context, base_url);
+        self
+    }
+
+    /// Sets the nest value.
+    pub fn nest(mut self, nest: impl Into<String>) -> Self {
+        let nest: String = nest.into();
+        self.definition.set_nest(nest);
+        self
+    }
+
+    /// Sets the prefix flag.
+    pub fn prefix(mut self, prefix: bool) -> Self {
+        self.definition.set_prefix(prefix);
+        self
+    }
+
+    /// Sets the index mapping.
+    pub fn index(mut self, index: impl Into<String>) -> Self {
+        let index: String = index.into();
+        self.definition.set_index(index);
+        self
+    }
+
+    /// Sets the "protected" flag.
+    pub fn protected(mut self, protected: bool) -> Self {
+        self.definition.set_protected(protected);
+        self
+    }
+
+    /// Validates and builds the term definition.
+    ///
+    /// The checks follow the create term definition algorithm, and the same error codes are
+    /// returned.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#create-term-definition>.
+    pub fn build(mut self) -> Result<Definition> {
+        let definition = &mut self.definition;
+        if let Some(ty) = definition.ty() {
+            // Step 13.2
+            match ty {
+                "@id" | "@json" | "@none" | "@vocab" => {}
+                ty if is_absolute_iri_ref(ty) => {}
+                ty => {
+                    return Err(ErrorCode::InvalidTypeMapping
+                        .and_message(format!("Unexpected type mapping {:?}", ty)))
+                }
+            }
+        }
+        if let Some(&container) = definition.container() {
+            if definition.is_reverse() {
+                // Step 14.5
+                match container.get_single_item() {
+                    Some(ContainerItem::Set) | Some(ContainerItem::Index) => {}
+                    _ => {
+                        return Err(ErrorCode::InvalidReverseProperty
+                            .and_message(format!("`@container` = {:?}", container)))
+                    }
+                }
+            } else {
+                // Step 21.1
+                validate_container_items(container)?;
+            }
+            // Step 21.4
+            if container.contains(ContainerItem::Type) {
+                match definition.ty() {
+                    None => definition.set_ty("@id"),
+                    Some("@id") | Some("@vocab") => {}
+                    Some(ty) => {
+                        return Err(ErrorCode::InvalidTypeMapping.and_message(format!(
+                            "Expected `@id` or `@vocab` for type container but got {:?}",
+                            ty
+                        )))
+                    }
+                }
+            }
+        }
+        if let Some(index) = definition.index() {
+            // Step 20
+            if !definition.container_contains(ContainerItem::Index) || has_form_of_keyword(index) {
+                return Err(ErrorCode::InvalidTermDefinition.and_message(format!(
+                    "Index mapping {:?} requires an index container and must not be a keyword",
+                    index
+                )));
+            }
+        }
+        if let Some(nest) = definition.nest() {
+            // Step 24.2
+            if nest != "@nest" && has_form_of_keyword(nest) {
+                return Err(ErrorCode::InvalidNestValue
+                    .and_message(format!("Unexpected nest value {:?}", nest)));
+            }
+        }
+        Ok(self.definition.build())
+    }
+}
+
+/// Builder of contexts.
+///
+/// This constructs an active context directly, without processing a local context as JSON.
+///
+/// ```
+/// # use json_ld::{iri_string::types::IriStr, ContextBuilder, TermDefinitionBuilder};
+/// # fn main() -> json_ld::Result<()> {
+/// let iri = |s: &str| IriStr::new(s).expect("valid IRI").to_owned();
+/// let name_definition = TermDefinitionBuilder::new(iri("http://schema.org/name")).build()?;
+/// let context = ContextBuilder::new()
+///     .vocab(iri("http://schema.org/"))
+///     .term("name", name_definition)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ContextBuilder {
+    /// Context under construction.
+    context: Context,
+    /// Terms, in order of addition.
+    terms: Vec<String>,
+}
+
+impl ContextBuilder {
+    /// Creates a new `ContextBuilder` for an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the base IRI.
+    pub fn base(mut self, base: IriString) -> Self {
+        self.context.set_base(Nullable::Value(base));
+        self
+    }
+
+    /// Sets the vocabulary mapping.
+    pub fn vocab(mut self, vocab: IriString) -> Self {
+        self.context.set_vocab(vocab.as_str().to_owned());
+        self
+    }
+
+    /// Sets the default language.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.context.set_default_language(Some(language.into()));
+        self
+    }
+
+    /// Sets the default base direction.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.context.set_default_base_direction(Some(direction));
+        self
+    }
+
+    /// Adds the term definition.
+    ///
+    /// A term added later replaces the earlier definition of the same term.
+    pub fn term(mut self, term: impl Into<String>, definition: Definition) -> Self {
+        self.add_term(term.into(), Nullable::Value(definition));
+        self
+    }
+
+    /// Adds the term explicitly defined as `null`.
+    ///
+    /// Such a term is not expanded, even if the vocabulary mapping is set.
+    pub fn null_term(mut self, term: impl Into<String>) -> Self {
+        self.add_term(term.into(), Nullable::Null);
+        self
+    }
+
+    /// Adds the term definition.
+    fn add_term(&mut self, term: String, definition: Nullable<Definition>) {
+        self.context
+            .insert_term_definition(term.clone(), definition);
+        self.terms.push(term);
+    }
+
+    /// Validates the terms and builds the context.
+    pub fn build(self) -> Result<Context> {
+        for term in &self.terms {
+            // Step 4 of create term definition algorithm.
+            if term.is_empty() {
+                return Err(ErrorCode::InvalidTermDefinition.and_message("Empty term"));
+            }
+            // Step 5 of create term definition algorithm.
+            if has_form_of_keyword(term) {
+                return Err(ErrorCode::KeywordRedefinition
+                    .and_message(format!("Term {:?} has the form of a keyword", term)));
+            }
+        }
+        Ok(self.context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use iri_string::types::IriStr;
+
+    /// Returns the IRI.
+    fn iri(s: &str) -> IriString {
+        IriStr::new(s).expect("valid IRI").to_owned()
+    }
+
+    #[test]
+    fn build_context() {
+        let tags = TermDefinitionBuilder::new(iri("http://example.com/tags"))
+            .container(ContainerItem::Type)
+            .build()
+            .expect("valid term definition");
+        assert_eq!(tags.ty(), Some("@id"));
+        let context = ContextBuilder::new()
+            .vocab(iri("http://example.com/vocab#"))
+            .term("tags", tags)
+            .null_term("ignored")
+            .build()
+            .expect("valid context");
+        assert_eq!(
+            context.term_definition("tags").map(Definition::iri),
+            Some("http://example.com/tags")
+        );
+        assert!(context.term_definition("ignored").is_none());
+
+        let invalid = TermDefinitionBuilder::reverse(iri("http://example.com/parent"))
+            .container(ContainerItem::List)
+            .build();
+        assert_eq!(
+            invalid.map_err(|e| e.code()).err(),
+            Some(ErrorCode::InvalidReverseProperty)
+        );
+        let keyword = TermDefinitionBuilder::new(iri("http://example.com/a"))
+            .build()
+            .expect("valid term definition");
+        assert!(ContextBuilder::new().term("@a", keyword).build().is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! Precompiled contexts.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::context::Context;
+
+/// Context processed in advance, to be reused for many documents.
+///
+/// This is created by [`Processor::compile_context`], and passed to
+/// [`Processor::expand_with_context`] or [`Processor::compact_with_context`], which skip context
+/// processing of the given context entirely.
+/// Cloning is cheap, as the processed context is shared.
+///
+/// [`Processor::compile_context`]: ../struct.Processor.html#method.compile_context
+/// [`Processor::expand_with_context`]: ../struct.Processor.html#method.expand_with_context
+/// [`Processor::compact_with_context`]: ../struct.Processor.html#method.compact_with_context
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledContext {
+    /// Active context.
+    context: Arc<Context>,
+    /// Local context the active context is created from.
+    local_context: Value,
+}
+
+impl CompiledContext {
+    /// Creates a new `CompiledContext`.
+    pub(crate) fn new(context: Context, local_context: Value) -> Self {
+        Self {
+            context: Arc::new(context),
+            local_context,
+        }
+    }
+
+    /// Creates a `CompiledContext` from the given active context.
+    ///
+    /// This allows contexts built or modified in code (for example with deployment defaults of
+    /// `@vocab` and `@language`) to be used for expansion and compaction.
+    /// The equivalent local context is created by [`Context::to_json`].
+    ///
+    /// [`Context::to_json`]: struct.Context.html#method.to_json
+    pub fn from_context(context: Context) -> Self {
+        let local_context = context.to_json();
+        Self::new(context, local_context)
+    }
+
+    /// Returns the active context.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Returns the local context (the value of `@context` entry) the context is compiled from.
+    pub fn local_context(&self) -> &Value {
+        &self.local_context
+    }
+}
@@ -1,21 +1,25 @@
 //! "Create term definition" algorithm.
 
-use std::{borrow::Cow, collections::HashMap, future::Future, pin::Pin};
+use std::{borrow::Cow, future::Future, pin::Pin};
 
-use anyhow::anyhow;
 use serde_json::{Map as JsonMap, Value};
 
 use crate::{
-    context::{definition::DefinitionBuilder, Context, ValueWithBase},
+    context::{
+        definition::DefinitionBuilder, merge::RemoteContexts, Context, Definition, ValueWithBase,
+    },
     error::{ErrorCode, Result},
     expand::iri::ExpandIriOptions,
+    hash::HashMap,
     iri::is_absolute_iri_ref,
     json::single_entry_map,
     processor::{Processor, ProcessorOptions},
     remote::LoadRemoteDocument,
     syntax::has_form_of_keyword,
+    warning::Warning,
 };
 
+pub(crate) use self::non_reverse::validate_container_items;
 use self::{non_reverse::run_for_non_reverse, reverse::run_for_reverse};
 
 mod non_reverse;
@@ -64,31 +68,155 @@ impl OptionalParams {
     }
 }
 
+/// Terms defined and being defined by create term definition algorithm.
+///
+/// This is `defined` of the algorithm. The term definitions which the term being defined depends
+/// on are not created recursively: the term requests one by `require()` and stops, and
+/// `create_term_definition()` creates the required term definition and then runs the algorithm
+/// for the term again. This keeps the stack usage constant however long the chain of dependent
+/// terms is.
+///
+/// The algorithm may return without defining the term (for example, if the IRI mapping has the
+/// form of a keyword), and such a term is also regarded as defined, so that it is neither
+/// requested again nor reported as a cycle by the context processing.
+#[derive(Default, Debug)]
+pub(crate) struct DefinedTerms {
+    /// Whether the algorithm has been run (`true`) or is running (`false`) for each term.
+    states: HashMap<String, bool>,
+    /// Term required by the term being defined, with the optional parameters to define it.
+    required: Option<(String, OptionalParams)>,
+}
+
+impl DefinedTerms {
+    /// Creates a new empty `DefinedTerms`.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the algorithm done for the term.
+    pub(crate) fn set_defined(&mut self, term: &str) {
+        self.states.insert(term.to_owned(), true);
+    }
+
+    /// Requests the term definition in the local context.
+    ///
+    /// Fails if the algorithm is not yet run for the term, so that the term being defined stops
+    /// and is retried after the required term. Fails with `cyclic IRI mapping` if the term is
+    /// being defined.
+    pub(crate) fn require(&mut self, term: &str, optional: OptionalParams) -> Result<()> {
+        // Step 1
+        match self.states.get(term) {
+            Some(true) => return Ok(()),
+            Some(false) => {
+                return Err(ErrorCode::CyclicIriMapping
+                    .and_message(format!("term = {:?}", term))
+                    .with_term(term))
+            }
+            None => {}
+        }
+        self.required = Some((term.to_owned(), optional));
+        Err(ErrorCode::Uncategorized.and_message(format!(
+            "The term definition of {:?} is required first",
+            term
+        )))
+    }
+}
+
+/// Term waiting for the term definitions it requires.
+struct PendingTerm {
+    /// Term.
+    term: String,
+    /// Optional parameters.
+    optional: OptionalParams,
+}
+
 /// Runs create term definition algorithm.
 ///
+/// The term definitions the term depends on are created first, using an explicit stack instead of
+/// recursion.
+///
 /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#create-term-definition>
+// This is still boxed, as the context processing for the scoped context (step 23) runs this.
 pub(crate) fn create_term_definition<'a, L: LoadRemoteDocument>(
     processor: &'a Processor<L>,
     active_context: &'a mut Context,
     local_context: ValueWithBase<'a, &'a JsonMap<String, Value>>,
     term: &'a str,
-    defined: &'a mut HashMap<String, bool>,
+    defined: &'a mut DefinedTerms,
+    remote_contexts: &'a RemoteContexts,
     optional: OptionalParams,
 ) -> Pin<Box<dyn Future<Output = Result<()>> + 'a + Send>> {
-    Box::pin(async move {
-        create_term_definition_impl(
-            processor,
-            active_context,
-            local_context,
-            term,
-            defined,
-            optional,
-        )
-        .await
-    })
+    instrument!(
+        Box::pin(async move {
+            // Step 1
+            match defined.states.get(term) {
+                // Term definition for `term` has already been created.
+                Some(true) => return Ok(()),
+                Some(false) => {
+                    return Err(ErrorCode::CyclicIriMapping
+                        .and_message(format!("term = {:?}", term))
+                        .with_term(term))
+                }
+                None => {}
+            }
+            // Terms being defined, each of which is waiting for the next one.
+            let mut pending = vec![PendingTerm {
+                term: term.to_owned(),
+                optional,
+            }];
+            while let Some(current) = pending.last_mut() {
+                let term = current.term.clone();
+                let optional = current.optional;
+                // Step 2
+                defined.states.insert(term.clone(), false);
+                // The term definition removed by step 6 is restored if the term is retried.
+                let previous_definition = active_context
+                    .raw_term_definition(&term)
+                    .map(|def| def.map(Definition::clone));
+                let result = create_term_definition_impl(
+                    processor,
+                    active_context,
+                    local_context,
+                    &term,
+                    defined,
+                    remote_contexts,
+                    optional,
+                )
+                .await;
+                match (result, defined.required.take()) {
+                    (Ok(()), _) => {
+                        defined.set_defined(&term);
+                        pending.pop();
+                    }
+                    (Err(_), Some((required, optional))) => {
+                        trace_event!(
+                            term = term.as_str(),
+                            required = required.as_str(),
+                            "Creating the required term definition first"
+                        );
+                        match previous_definition {
+                            Some(def) => active_context.insert_term_definition(term, def),
+                            None => {
+                                active_context.remove_term_definition(&term);
+                            }
+                        }
+                        pending.push(PendingTerm {
+                            term: required,
+                            optional,
+                        });
+                    }
+                    (Err(e), None) => return Err(e.with_term(&term)),
+                }
+            }
+
+            Ok(())
+        }),
+        "create_term_definition",
+        term = %term
+    )
 }
 
-/// Runs create term definition algorithm.
+/// Runs create term definition algorithm, except for steps 1 and 2.
 ///
 /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#create-term-definition>
 async fn create_term_definition_impl<L: LoadRemoteDocument>(
@@ -96,32 +224,10 @@ async fn create_term_definition_impl<L: LoadRemoteDocument>(
     active_context: &mut Context,
     local_context: ValueWithBase<'_, &JsonMap<String, Value>>,
     term: &str,
-    defined: &mut HashMap<String, bool>,
+    defined: &mut DefinedTerms,
+    remote_contexts: &RemoteContexts,
     optional: OptionalParams,
 ) -> Result<()> {
-    use std::collections::hash_map::Entry;
-
-    // Step 1, 2
-    match defined.entry(term.into()) {
-        Entry::Occupied(entry) => {
-            // Step 1
-            if *entry.get() {
-                // Term definition for `term` has already been created.
-                return Ok(());
-            } else {
-                return Err(ErrorCode::CyclicIriMapping.and_source(anyhow!("term = {:?}", term)));
-            }
-        }
-        Entry::Vacant(entry) => {
-            // Step 2
-            entry.insert(false);
-        }
-    }
-    debug_assert!(
-        defined.contains_key(term),
-        "`defined` should have an entry for `term` (= {:?})",
-        term
-    );
     // Step 3
     let value = local_context.value().get(term).unwrap_or_else(|| {
         panic!(
@@ -133,45 +239,53 @@ async fn create_term_definition_impl<L: LoadRemoteDocument>(
     // Step 4
     if term == "@type" {
         if processor.is_processing_mode_1_0() {
-            return Err(ErrorCode::KeywordRedefinition.and_source(anyhow!(
-                "`term` = \"@type\" and processing mode is `json-ld-1.0`"
-            )));
+            return Err(ErrorCode::KeywordRedefinition
+                .and_message("`term` = \"@type\" and processing mode is `json-ld-1.0`"));
         }
         let map = match value {
             Value::Object(map) => map,
             v => {
-                return Err(ErrorCode::KeywordRedefinition.and_source(anyhow!(
+                return Err(ErrorCode::KeywordRedefinition.and_message(format!(
                     "Expected an object for term `@type`, but got {:?}",
                     v
                 )))
             }
         };
-        // > At this point, value *MUST* be a map with only the entry `@container` and value
-        // > `@set` and optional entry `@protected`.
-        if map.get("@container").and_then(|v| v.as_str()) != Some("@set") {
-            return Err(ErrorCode::KeywordRedefinition.and_source(anyhow!(
-                "Expected the value `@set` for `@container` entry for term `@type`, but got {:?}",
-                map.get("@container")
-            )));
+        // > At this point, value *MUST* be a map with only either or both of the following
+        // > entries:
+        // >
+        // > * An entry for `@container` with value `@set`.
+        // > * An entry for `@protected`.
+        if let Some(container) = map.get("@container") {
+            if container.as_str() != Some("@set") {
+                return Err(ErrorCode::KeywordRedefinition.and_message(format!(
+                    "Expected the value `@set` for `@container` entry for term `@type`, \
+                     but got {:?}",
+                    container
+                )));
+            }
+        }
+        if map.is_empty() {
+            return Err(ErrorCode::KeywordRedefinition
+                .and_message("Expected `@container` or `@protected` entry for term `@type`"));
         }
         if let Some((k, v)) = map
             .iter()
             .find(|(k, _)| *k != "@container" && *k != "@protected")
         {
-            return Err(ErrorCode::KeywordRedefinition.and_source(anyhow!(
+            return Err(ErrorCode::KeywordRedefinition.and_message(format!(
                 "Unexpected entry for term `@type`: key={:?}, value={:?}",
-                k,
-                v
+                k, v
             )));
         }
     }
     // Step 5
-    if processor.is_keyword(term) {
+    if term != "@type" && processor.is_keyword(term) {
         // Keywords cannot be overridden.
-        return Err(ErrorCode::KeywordRedefinition.and_source(anyhow!("term = {:?}", term)));
+        return Err(ErrorCode::KeywordRedefinition.and_message(format!("term = {:?}", term)));
     }
-    if has_form_of_keyword(term) {
-        // TODO: Generate a warning.
+    if term != "@type" && has_form_of_keyword(term) {
+        processor.warn(Warning::KeywordLikeValue(term.to_owned()));
         return Ok(());
     }
     // Step 6
@@ -188,7 +302,7 @@ async fn create_term_definition_impl<L: LoadRemoteDocument>(
         // Step 9
         Value::Object(v) => (Cow::Borrowed(v), false),
         // Step 9
-        v => return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!("value = {:?}", v))),
+        v => return Err(ErrorCode::InvalidTermDefinition.and_message(format!("value = {:?}", v))),
     };
     // Step 10
     let mut definition = DefinitionBuilder::new();
@@ -226,6 +340,7 @@ async fn create_term_definition_impl<L: LoadRemoteDocument>(
             local_context,
             term,
             defined,
+            remote_contexts,
             optional,
             &value,
             definition,
@@ -248,9 +363,8 @@ fn process_protected(
         // Step 11
         Some(Value::Bool(true)) => {
             if processor.is_processing_mode_1_0() {
-                return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!(
-                    "`@protected` is `true` but processing mode is `json-ld-1.0`"
-                )));
+                return Err(ErrorCode::InvalidTermDefinition
+                    .and_message("`@protected` is `true` but processing mode is `json-ld-1.0`"));
             }
             definition.set_protected(true);
         }
@@ -258,7 +372,7 @@ fn process_protected(
         Some(Value::Bool(false)) => {}
         // Step 11
         Some(v) => {
-            return Err(ErrorCode::InvalidProtectedValue.and_source(anyhow!(
+            return Err(ErrorCode::InvalidProtectedValue.and_message(format!(
                 "Expected boolean or `null` as `@protected`, but got {:?}",
                 v,
             )))
@@ -278,7 +392,7 @@ async fn process_type<L: LoadRemoteDocument>(
     processor: &Processor<L>,
     active_context: &mut Context,
     local_context: ValueWithBase<'_, &JsonMap<String, Value>>,
-    defined: &mut HashMap<String, bool>,
+    defined: &mut DefinedTerms,
     value: &JsonMap<String, Value>,
     definition: &mut DefinitionBuilder,
 ) -> Result<()> {
@@ -293,27 +407,32 @@ async fn process_type<L: LoadRemoteDocument>(
                 .await?
                 .ok_or_else(|| {
                     ErrorCode::InvalidTypeMapping
-                        .and_source(anyhow!("@type ({:?}) is expanded to `null`", ty))
+                        .and_message(format!("@type ({:?}) is expanded to `null`", ty))
                 })?;
             // Step 13.3
             if (ty == "@json" || ty == "@none") && processor.is_processing_mode_1_0() {
-                return Err(ErrorCode::InvalidTypeMapping.and_source(anyhow!(
+                return Err(ErrorCode::InvalidTypeMapping.and_message(format!(
                     "@type = {:?} while processing mode is JSON-LD-1.0",
                     ty
                 )));
             }
             // Step 13.4, 13.5
-            if ty == "@id" || ty == "@vocab" || is_absolute_iri_ref(&ty) {
-                definition.set_ty(ty);
+            if ty == "@id"
+                || ty == "@json"
+                || ty == "@none"
+                || ty == "@vocab"
+                || is_absolute_iri_ref(&ty)
+            {
+                definition.set_ty(processor.intern_symbol(ty));
             } else {
                 return Err(
-                    ErrorCode::InvalidTypeMapping.and_source(anyhow!("expanded type = {:?}", ty))
+                    ErrorCode::InvalidTypeMapping.and_message(format!("expanded type = {:?}", ty))
                 );
             }
         }
         None => {}
         // Step 13.1
-        v => return Err(ErrorCode::InvalidTypeMapping.and_source(anyhow!("@type = {:?}", v))),
+        v => return Err(ErrorCode::InvalidTypeMapping.and_message(format!("@type = {:?}", v))),
     }
 
     Ok(())
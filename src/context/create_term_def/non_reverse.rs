@@ -1,14 +1,14 @@
 //! Part of create term definition algorithm, for non-reverse term definitions.
 
-use std::{collections::HashMap, convert::TryFrom};
+use std::convert::TryFrom;
 
-use anyhow::anyhow;
 use serde_json::{Map as JsonMap, Value};
 
 use crate::{
     context::{
-        create_term_def::{create_term_definition, OptionalParams},
+        create_term_def::{DefinedTerms, OptionalParams},
         definition::{Container, ContainerItem, Definition, DefinitionBuilder, Direction},
+        merge::RemoteContexts,
         Context, ValueWithBase,
     },
     error::{ErrorCode, Result},
@@ -20,7 +20,8 @@ use crate::{
     json::Nullable,
     processor::{Processor, ProcessorOptions},
     remote::LoadRemoteDocument,
-    syntax::has_form_of_keyword,
+    syntax::{has_form_of_keyword, is_well_formed_language},
+    warning::Warning,
 };
 
 /// Runs rest of the create term definition algorithm for the case `@reverse` exists.
@@ -33,7 +34,8 @@ pub(crate) async fn run_for_non_reverse<L: LoadRemoteDocument>(
     active_context: &mut Context,
     local_context: ValueWithBase<'_, &JsonMap<String, Value>>,
     term: &str,
-    defined: &mut HashMap<String, bool>,
+    defined: &mut DefinedTerms,
+    remote_contexts: &RemoteContexts,
     optional: OptionalParams,
     value: &JsonMap<String, Value>,
     mut definition: DefinitionBuilder,
@@ -61,47 +63,47 @@ pub(crate) async fn run_for_non_reverse<L: LoadRemoteDocument>(
     // Step 21
     process_container(processor, value, &mut definition).await?;
     // Step 22
-    process_index(processor.options(), value, &mut definition)?;
+    process_index(processor, value, &mut definition)?;
     // Step 23
     process_local_context(
         processor,
         active_context,
         local_context.with_new_value(value),
+        remote_contexts,
         &mut definition,
     )
     .await?;
     // Step 24
-    process_language(value, &mut definition)?;
+    process_language(processor, value, &mut definition)?;
     // Step 25
-    process_direction(value, &mut definition)?;
+    process_direction(processor.options(), value, &mut definition)?;
     // Step 26
-    process_nest(processor.options(), value, &mut definition)?;
+    process_nest(processor, value, &mut definition)?;
     // Step 27
     process_prefix(processor.options(), term, value, &mut definition)?;
     // Step 28
     for key in value.keys() {
         match key.as_str() {
-            "@id" | "@reverse" | "@container" | "@context" | "@language" | "@nest" | "@prefix"
-            | "@type" => {}
+            "@id" | "@reverse" | "@container" | "@context" | "@direction" | "@index"
+            | "@language" | "@nest" | "@prefix" | "@protected" | "@type" => {}
             v => {
                 return Err(ErrorCode::InvalidTermDefinition
-                    .and_source(anyhow!("Unexpected entry: key={:?}", v)))
+                    .and_message(format!("Unexpected entry: key={:?}", v)))
             }
         }
     }
     // Step 29
     let definition = build_term_definition(optional, definition, previous_definition)?;
     // Step 30
-    active_context
-        .term_definitions
-        .insert(term.to_owned(), Nullable::Value(definition));
-    defined.insert(term.to_owned(), true);
+    active_context.insert_term_definition(term.to_owned(), Nullable::Value(definition));
+    defined.set_defined(term);
 
     Ok(())
 }
 
 /// Processes the language mapping.
-fn process_language(
+fn process_language<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
     value: &JsonMap<String, Value>,
     definition: &mut DefinitionBuilder,
 ) -> Result<()> {
@@ -113,16 +115,20 @@ fn process_language(
                 Value::Null => Nullable::Null,
                 Value::String(s) => Nullable::Value(s.as_str()),
                 v => {
-                    return Err(ErrorCode::InvalidLanguageMapping.and_source(anyhow!(
+                    return Err(ErrorCode::InvalidLanguageMapping.and_message(format!(
                         "Expected string or null as `@language` value, but got {:?}",
                         v
                     )))
                 }
             };
-            // TODO: Issue a warning if `language` is not well-formed according to section 2.2.9 of BCP47.
+            if let Nullable::Value(language) = language {
+                if !is_well_formed_language(language) {
+                    processor.warn(Warning::MalformedLanguageTag(language.to_owned()));
+                }
+            }
             // Step 24.2
             // TODO: Processors MAY normalize language tags to lower case.
-            definition.set_language(language.map(ToOwned::to_owned));
+            definition.set_language(language.map(|lang| processor.intern(lang)));
         }
     }
 
@@ -145,7 +151,7 @@ async fn process_iri<L: LoadRemoteDocument>(
     active_context: &mut Context,
     local_context: ValueWithBase<'_, &JsonMap<String, Value>>,
     term: &str,
-    defined: &mut HashMap<String, bool>,
+    defined: &mut DefinedTerms,
     optional: OptionalParams,
     value: &JsonMap<String, Value>,
     definition: &mut DefinitionBuilder,
@@ -161,15 +167,14 @@ async fn process_iri<L: LoadRemoteDocument>(
                 //
                 // This seems essentially not changed from JSON-LD-API 1.0, so return from this
                 // function here.
-                active_context
-                    .term_definitions
-                    .insert(term.to_owned(), Nullable::Null);
+                active_context.insert_term_definition(term.to_owned(), Nullable::Null);
+                return Ok(ProcessIriStatus::Stop);
             }
             // Step 16.3-
             Value::String(id) => {
                 // Step 16.3
                 if !processor.is_keyword(id) && has_form_of_keyword(id) {
-                    // TODO: Generate warning.
+                    processor.warn(Warning::KeywordLikeValue(id.to_owned()));
                     return Ok(ProcessIriStatus::Stop);
                 }
                 // Step 16.4
@@ -179,35 +184,34 @@ async fn process_iri<L: LoadRemoteDocument>(
                     .await?
                     .ok_or_else(|| {
                         ErrorCode::InvalidIriMapping
-                            .and_source(anyhow!("@id ({:?}) is expanded to `null`", id))
+                            .and_message(format!("@id ({:?}) is expanded to `null`", id))
                     })?;
                 if !processor.is_keyword(&id) && !is_absolute_ref_or_blank_node_ident(&id) {
-                    return Err(ErrorCode::InvalidIriMapping.and_source(anyhow!(
+                    return Err(ErrorCode::InvalidIriMapping.and_message(format!(
                         "@id ({:?}) should be a keyword, \
                          an IRI (which is absolute), or a blank node identifier",
                         id
                     )));
                 } else if id == "@context" {
-                    return Err(ErrorCode::InvalidKeywordAlias
-                        .and_source(anyhow!("Invalid alias to `@context`")));
+                    return Err(
+                        ErrorCode::InvalidKeywordAlias.and_message("Invalid alias to `@context`")
+                    );
                 }
-                definition.set_iri(id);
+                definition.set_iri(processor.intern_symbol(id));
                 let id = definition.iri();
                 // Step 16.5
-                if (!term.is_empty() && term[1..(term.len() - 1)].contains(':'))
-                    || term.contains('/')
-                {
+                let has_inner_colon = term
+                    .char_indices()
+                    .any(|(i, c)| c == ':' && i != 0 && i != term.len() - 1);
+                if has_inner_colon || term.contains('/') {
                     let expanded =
                         ExpandIriOptions::mutable(active_context, local_context, defined)
                             .vocab(true)
                             .expand_str(processor, term)
                             .await?;
                     if expanded.as_ref().map(|s| &**s) != Some(id) {
-                        return Err(ErrorCode::InvalidIriMapping.and_source(anyhow!(
-                            "expanded={:?}, term={:?}",
-                            expanded,
-                            term
-                        )));
+                        return Err(ErrorCode::InvalidIriMapping
+                            .and_message(format!("expanded={:?}, term={:?}", expanded, term)));
                     }
                 }
                 // Step 16.6
@@ -222,7 +226,7 @@ async fn process_iri<L: LoadRemoteDocument>(
             // Step 16.2
             v => {
                 return Err(ErrorCode::InvalidIriMapping
-                    .and_source(anyhow!("Expected string as @id but got {:?}", v)))
+                    .and_message(format!("Expected string as @id but got {:?}", v)))
             }
         }
 
@@ -235,22 +239,14 @@ async fn process_iri<L: LoadRemoteDocument>(
             // Step 17.1
             if is_compact_iri(term) && local_context.value().contains_key(prefix) {
                 // TODO: Should optional params be default or same as callee?
-                create_term_definition(
-                    processor,
-                    active_context,
-                    local_context,
-                    prefix,
-                    defined,
-                    optional,
-                )
-                .await?;
+                defined.require(prefix, optional)?;
             }
             // Step 17.2
             if let Some(prefix_iri) = active_context.term_definition(prefix).map(Definition::iri) {
-                definition.set_iri(format!("{}{}", prefix_iri, suffix));
+                definition.set_iri(processor.intern_concat(prefix_iri, suffix));
             } else {
                 // Step 17.3
-                definition.set_iri(term);
+                definition.set_iri(processor.intern(term));
             }
         }
         // Step 18-20
@@ -264,31 +260,30 @@ async fn process_iri<L: LoadRemoteDocument>(
                     .expand_str(processor, term)
                     .await?
                     .ok_or_else(|| {
-                        ErrorCode::InvalidIriMapping.and_source(anyhow!(
+                        ErrorCode::InvalidIriMapping.and_message(format!(
                             "Expected an absolute IRI reference as resolved term, \
                              but got null: term={:?}",
                             term
                         ))
                     })?;
                 if !is_absolute_iri_ref(&resolved) {
-                    return Err(ErrorCode::InvalidIriMapping.and_source(anyhow!(
+                    return Err(ErrorCode::InvalidIriMapping.and_message(format!(
                         "Expected an absolute IRI reference as resolved term, \
                          but got {:?}: term={:?}",
-                        resolved,
-                        term
+                        resolved, term
                     )));
                 } else {
-                    definition.set_iri(resolved);
+                    definition.set_iri(processor.intern_symbol(resolved));
                 }
             } else if term == "@type" {
                 // Step 19
-                definition.set_iri("@type");
+                definition.set_iri(processor.intern("@type"));
             } else if let Nullable::Value(vocab) = active_context.vocab() {
                 // Step 20
-                definition.set_iri(format!("{}{}", vocab, term));
+                definition.set_iri(processor.intern_concat(vocab, term));
             } else {
                 // Step 20
-                return Err(ErrorCode::InvalidIriMapping.and_source(anyhow!(
+                return Err(ErrorCode::InvalidIriMapping.and_message(format!(
                     "term={:?}, active context has no vocab mapping",
                     term
                 )));
@@ -313,7 +308,7 @@ async fn process_container<L: LoadRemoteDocument>(
         // Step 21.2
         if processor.is_processing_mode_1_0() {
             if has_array_form {
-                return Err(ErrorCode::InvalidContainerMapping.and_source(anyhow!(
+                return Err(ErrorCode::InvalidContainerMapping.and_message(format!(
                     "Expected `@container` to be a string but got {:?}, \
                      with processing mode `json-ld-1.0`",
                     container_raw
@@ -323,7 +318,7 @@ async fn process_container<L: LoadRemoteDocument>(
                 Some(item @ ContainerItem::Graph)
                 | Some(item @ ContainerItem::Id)
                 | Some(item @ ContainerItem::Type) => {
-                    return Err(ErrorCode::InvalidContainerMapping.and_source(anyhow!(
+                    return Err(ErrorCode::InvalidContainerMapping.and_message(format!(
                         "Unexpected `@container` value {:?} with processing mode `json-ld-1.0`",
                         item
                     )))
@@ -338,17 +333,14 @@ async fn process_container<L: LoadRemoteDocument>(
             match definition.ty() {
                 None => {
                     // Step 21.4.1
-                    definition.set_ty("@id");
+                    definition.set_ty(processor.intern("@id"));
                 }
                 // Step 21.4.2
                 Some("@id") | Some("@vocab") => {}
                 Some(ty) => {
                     // Step 21.4.2
-                    return Err(ErrorCode::InvalidTypeMapping.and_source(anyhow!(
-                        "container = {:?}, type = {:?}",
-                        container,
-                        ty
-                    )));
+                    return Err(ErrorCode::InvalidTypeMapping
+                        .and_message(format!("container = {:?}, type = {:?}", container, ty)));
                 }
             }
         }
@@ -358,8 +350,8 @@ async fn process_container<L: LoadRemoteDocument>(
 }
 
 /// Processes the index mapping.
-fn process_index(
-    processor: &ProcessorOptions,
+fn process_index<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
     value: &JsonMap<String, Value>,
     definition: &mut DefinitionBuilder,
 ) -> Result<()> {
@@ -374,7 +366,7 @@ fn process_index(
             } else {
                 "not json-ld-1.0"
             };
-            return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!(
+            return Err(ErrorCode::InvalidTermDefinition.and_message(format!(
                 "`value` has `@index` entry, processing mode is {}, container = {:?}",
                 processing_mode,
                 definition.container()
@@ -383,11 +375,11 @@ fn process_index(
         // Step 22.2
         let index = index.as_str().ok_or_else(|| {
             ErrorCode::InvalidTermDefinition
-                .and_source(anyhow!("Invalid `@index` value {:?}", index))
+                .and_message(format!("Invalid `@index` value {:?}", index))
         })?;
         // TODO: Now `index` must be a string expanding to an absolute IRI. How to check that?
         // Step 22.3
-        definition.set_index(index);
+        definition.set_index(processor.intern(index));
     }
 
     Ok(())
@@ -398,24 +390,24 @@ async fn process_local_context<L: LoadRemoteDocument>(
     processor: &Processor<L>,
     active_context: &mut Context,
     value: ValueWithBase<'_, &JsonMap<String, Value>>,
+    remote_contexts: &RemoteContexts,
     definition: &mut DefinitionBuilder,
 ) -> Result<()> {
     // Step 23
     if let Some(context) = value.value().get("@context") {
         // Step 23.1
         if processor.is_processing_mode_1_0() {
-            return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!(
-                "`value` has `@context` entry but processing mode is json-ld-1.0"
-            )));
+            return Err(ErrorCode::InvalidTermDefinition
+                .and_message("`value` has `@context` entry but processing mode is json-ld-1.0"));
         }
         // Step 23.2: `context` is already the value associated with the `@context` entry.
-        // Step 23.3
-        let context: Context = active_context
-            .join_context_value(processor, context, value.base(), true)
+        // Step 23.3: The result is used only for validation.
+        active_context
+            .validate_scoped_context(processor, value.with_new_value(context), remote_contexts)
             .await
-            .map_err(|e| ErrorCode::InvalidScopedContext.and_source(e))?;
+            .map_err(|e| ErrorCode::InvalidScopedContext.and_cause(e))?;
         // Step 23.4
-        definition.set_local_context(context);
+        definition.set_local_context(context.clone(), value.base().to_owned());
     }
 
     Ok(())
@@ -423,15 +415,21 @@ async fn process_local_context<L: LoadRemoteDocument>(
 
 /// Processes the direction mapping.
 fn process_direction(
+    processor: &ProcessorOptions,
     value: &JsonMap<String, Value>,
     definition: &mut DefinitionBuilder,
 ) -> Result<()> {
     // Step 25
     if let Some(direction) = value.get("@direction") {
+        // `@direction` is not a keyword in JSON-LD 1.0.
+        if processor.is_processing_mode_1_0() {
+            return Err(ErrorCode::InvalidTermDefinition
+                .and_message("Found `@direction` but processing mode is `json-ld-1.0`"));
+        }
         if !value.contains_key("@type") {
             // Step 25.1
             let direction = Nullable::<Direction>::try_from(direction)
-                .map_err(|e| ErrorCode::InvalidBaseDirection.and_source(e))?;
+                .map_err(|e| ErrorCode::InvalidBaseDirection.and_value_error(e))?;
             // Step 25.2
             definition.set_direction(direction);
         }
@@ -441,8 +439,8 @@ fn process_direction(
 }
 
 /// Processes the nest value.
-fn process_nest(
-    processor: &ProcessorOptions,
+fn process_nest<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
     value: &JsonMap<String, Value>,
     definition: &mut DefinitionBuilder,
 ) -> Result<()> {
@@ -450,23 +448,22 @@ fn process_nest(
     if let Some(nest) = value.get("@nest") {
         // Step 26.1
         if processor.is_processing_mode_1_0() {
-            return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!(
-                "Found `@nest` but processing mode is `json-ld-1.0`"
-            )));
+            return Err(ErrorCode::InvalidTermDefinition
+                .and_message("Found `@nest` but processing mode is `json-ld-1.0`"));
         }
         // Step 26.2
         let nest = match nest {
             Value::String(s) => s.as_str(),
             v => {
                 return Err(ErrorCode::InvalidNestValue
-                    .and_source(anyhow!("Expected string but got {:?}", v)))
+                    .and_message(format!("Expected string but got {:?}", v)))
             }
         };
         if nest != "@nest" && processor.is_keyword(nest) {
             return Err(ErrorCode::InvalidNestValue
-                .and_source(anyhow!("Got a keyword {:?} other than `\"@nest\"`", nest)));
+                .and_message(format!("Got a keyword {:?} other than `\"@nest\"`", nest)));
         }
-        definition.set_nest(nest);
+        definition.set_nest(processor.intern(nest));
     }
 
     Ok(())
@@ -483,11 +480,10 @@ fn process_prefix(
     if let Some(prefix) = value.get("@prefix") {
         // Step 27.1
         if processor.is_processing_mode_1_0() {
-            return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!(
-                "Found `@prefix` but processing mode is `json-ld-1.0`"
-            )));
+            return Err(ErrorCode::InvalidTermDefinition
+                .and_message("Found `@prefix` but processing mode is `json-ld-1.0`"));
         } else if term.contains(':') || term.contains('/') {
-            return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!(
+            return Err(ErrorCode::InvalidTermDefinition.and_message(format!(
                 "Found `@prefix` but the term {:?} contains colon or slash",
                 term
             )));
@@ -497,13 +493,13 @@ fn process_prefix(
             Value::Bool(v) => *v,
             v => {
                 return Err(ErrorCode::InvalidPrefixValue
-                    .and_source(anyhow!("Expected boolean but got {:?}", v)))
+                    .and_message(format!("Expected boolean but got {:?}", v)))
             }
         };
         definition.set_prefix(prefix);
         // Step 27.3
         if prefix && processor.is_keyword(definition.iri()) {
-            return Err(ErrorCode::InvalidTermDefinition.and_source(anyhow!(
+            return Err(ErrorCode::InvalidTermDefinition.and_message(format!(
                 "`prefix` flag is set to `true` for a definition \
                  whose IRI mapping is a keyword {:?}",
                 definition.iri()
@@ -542,7 +538,13 @@ fn build_term_definition(
 // Step 21.
 async fn validate_container_non_reverse(container: &Value) -> Result<Container> {
     let container = Container::try_from(container)
-        .map_err(|e| ErrorCode::InvalidContainerMapping.and_source(e))?;
+        .map_err(|e| ErrorCode::InvalidContainerMapping.and_value_error(e))?;
+    validate_container_items(container)
+}
+
+/// Returns the container mapping, if its combination of items is valid for non-reverse terms.
+// Step 21.1.
+pub(crate) fn validate_container_items(container: Container) -> Result<Container> {
     if container.len() == 1 {
         // > either `@graph`, `@id`, `@index`, `@language`, `@list`, `@set`, `@type`,
         // > or an array containing exactly any one of those keywords
@@ -553,22 +555,17 @@ async fn validate_container_non_reverse(container: &Value) -> Result<Container>
         let mut has_graph = false;
         let mut has_id = false;
         let mut has_index = false;
+        let mut has_other = false;
         for item in container.iter() {
             match item {
                 ContainerItem::Graph => has_graph = true,
                 ContainerItem::Id => has_id = true,
                 ContainerItem::Index => has_index = true,
                 ContainerItem::Set => {}
-                v => {
-                    return Err(ErrorCode::InvalidContainerMapping.and_source(anyhow!(
-                        "Unexpected item {:?} in container {:?}",
-                        v,
-                        container
-                    )))
-                }
+                _ => has_other = true,
             }
         }
-        if has_graph && (has_id ^ has_index) {
+        if !has_other && has_graph && (has_id ^ has_index) {
             // > an array containing `@graph` and either `@id` or `@index` optionally including
             // > `@set`
             return Ok(container);
@@ -585,10 +582,9 @@ async fn validate_container_non_reverse(container: &Value) -> Result<Container>
                 | ContainerItem::Type
                 | ContainerItem::Language => {}
                 v => {
-                    return Err(ErrorCode::InvalidContainerMapping.and_source(anyhow!(
+                    return Err(ErrorCode::InvalidContainerMapping.and_message(format!(
                         "Unexpected item {:?} in container {:?}",
-                        v,
-                        container
+                        v, container
                     )))
                 }
             }
@@ -601,5 +597,5 @@ async fn validate_container_non_reverse(container: &Value) -> Result<Container>
     }
 
     Err(ErrorCode::InvalidContainerMapping
-        .and_source(anyhow!("Unexpected container {:?}", container)))
+        .and_message(format!("Unexpected container {:?}", container)))
 }
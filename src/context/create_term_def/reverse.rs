@@ -1,12 +1,12 @@
 //! Part of create term definition algorithm, for reverse term definitions.
 
-use std::{collections::HashMap, convert::TryFrom};
+use std::convert::TryFrom;
 
-use anyhow::anyhow;
 use serde_json::{Map as JsonMap, Value};
 
 use crate::{
     context::{
+        create_term_def::DefinedTerms,
         definition::{Container, ContainerItem, DefinitionBuilder},
         Context, ValueWithBase,
     },
@@ -17,6 +17,7 @@ use crate::{
     processor::Processor,
     remote::LoadRemoteDocument,
     syntax::has_form_of_keyword,
+    warning::Warning,
 };
 
 /// Runs rest of the create term definition algorithm for the case `@reverse` exists.
@@ -29,28 +30,26 @@ pub(crate) async fn run_for_reverse<L: LoadRemoteDocument>(
     active_context: &mut Context,
     local_context: ValueWithBase<'_, &JsonMap<String, Value>>,
     term: &str,
-    defined: &mut HashMap<String, bool>,
+    defined: &mut DefinedTerms,
     value: &JsonMap<String, Value>,
     reverse: &Value,
     mut definition: DefinitionBuilder,
 ) -> Result<()> {
     // Step 14.1
     if value.contains_key("@id") || value.contains_key("@nest") {
-        return Err(
-            ErrorCode::InvalidReverseProperty.and_source(anyhow!("Found `@id` or `@nest` entries"))
-        );
+        return Err(ErrorCode::InvalidReverseProperty.and_message("Found `@id` or `@nest` entries"));
     }
     // Step 14.2
     let reverse = match reverse {
         Value::String(s) => s,
         v => {
             return Err(ErrorCode::InvalidIriMapping
-                .and_source(anyhow!("Expected string as @reverse but got {:?}", v)))
+                .and_message(format!("Expected string as @reverse but got {:?}", v)))
         }
     };
     // Step 14.3
     if has_form_of_keyword(reverse) {
-        // FIXME: Generate a warning.
+        processor.warn(Warning::KeywordLikeValue(reverse.to_owned()));
         return Ok(());
     }
     // Step 14.4
@@ -60,12 +59,12 @@ pub(crate) async fn run_for_reverse<L: LoadRemoteDocument>(
         .await?
         .ok_or_else(|| {
             ErrorCode::InvalidIriMapping
-                .and_source(anyhow!("@reverse ({:?}) is expanded to `null`", reverse))
+                .and_message(format!("@reverse ({:?}) is expanded to `null`", reverse))
         })?;
     if is_absolute_ref_or_blank_node_ident(&reverse) {
-        definition.set_iri(reverse);
+        definition.set_iri(processor.intern_symbol(reverse));
     } else {
-        return Err(ErrorCode::InvalidIriMapping.and_source(anyhow!(
+        return Err(ErrorCode::InvalidIriMapping.and_message(format!(
             "Expanded @reverse value ({:?}) is neither an IRI nor blank node identifier",
             reverse
         )));
@@ -76,12 +75,8 @@ pub(crate) async fn run_for_reverse<L: LoadRemoteDocument>(
     definition.set_reverse(true);
     // Step 14.7
     let definition = definition.build();
-    active_context
-        .term_definitions
-        .insert(term.to_owned(), Nullable::Value(definition));
-    *defined
-        .get_mut(term)
-        .expect("Should never fail: inserted before") = true;
+    active_context.insert_term_definition(term.to_owned(), Nullable::Value(definition));
+    defined.set_defined(term);
 
     Ok(())
 }
@@ -94,7 +89,7 @@ fn process_conatiner(
     // Step 14.5
     if let Some(container) = value.get("@container") {
         let container = Nullable::<Container>::try_from(container)
-            .map_err(|e| ErrorCode::InvalidContainerMapping.and_source(e))?;
+            .map_err(|e| ErrorCode::InvalidContainerMapping.and_value_error(e))?;
         // > If _value_ contains an `@container` entry, set the container mapping of _definition_
         // > to an array containing its value; if its value is neither `@set`, nor `@index`, nor
         // > `null`, an `invalid reverse property` error has been detected (reverse properties only
@@ -107,7 +102,7 @@ fn process_conatiner(
                 Ok(())
             }
             _ => Err(ErrorCode::InvalidReverseProperty
-                .and_source(anyhow!("`@container` = {:?}", container))),
+                .and_message(format!("`@container` = {:?}", container))),
         }
     } else {
         Ok(())
@@ -1,44 +1,63 @@
 //! Term definition.
 
-use crate::{context::Context, json::Nullable};
+use std::sync::Arc;
 
-pub(crate) use self::{
-    builder::DefinitionBuilder,
-    container::{Container, ContainerItem},
-    direction::Direction,
+use iri_string::types::IriString;
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{context::ValueWithBase, iri::is_gen_delims_byte, json::Nullable};
+
+pub(crate) use self::builder::DefinitionBuilder;
+pub use self::{
+    container::{Container, ContainerItem, ContainerLoadError},
+    direction::{Direction, DirectionLoadError},
 };
 
 mod builder;
 mod container;
 mod direction;
 
+/// Scoped context and its base URL.
+///
+/// This is shared between clones of a term definition, since a scoped context can be large.
+#[derive(Debug, Clone, PartialEq)]
+struct ScopedContext {
+    /// Unprocessed local context.
+    context: Value,
+    /// Base URL of the local context.
+    base_url: IriString,
+}
+
 /// Term definition.
 ///
 /// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-term-definition> and
 /// <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#context-processing-algorithm>.
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) struct Definition {
+pub struct Definition {
     /// IRI mapping or reverse property.
     // This can be a non-IRI-reference (such as keywords), so use `String` here.
     // TODO: This is an IRI (including a blank node identifier) or a keyword.
-    iri: String,
+    iri: Arc<str>,
     /// Reverse property flag.
     reverse: bool,
     /// Type mapping (optional).
     // TODO: This is an IRI.
-    ty: Option<String>,
+    ty: Option<Arc<str>>,
     /// Lanugage mapping (optional).
-    language: Option<Nullable<String>>,
+    language: Option<Nullable<Arc<str>>>,
     /// Direction mapping (optional).
-    direction: Option<Direction>,
-    /// Context (optional).
-    context: Option<Context>,
+    direction: Option<Nullable<Direction>>,
+    /// Local context and its base URL (optional).
+    ///
+    /// This is an unprocessed value, because a scoped context should be processed against the
+    /// active context at the time it is used.
+    scoped_context: Option<Arc<ScopedContext>>,
     /// Nest value (optional).
-    nest: Option<String>,
+    nest: Option<Arc<str>>,
     /// Prefix flag (optoinal).
     prefix: Option<bool>,
     /// Index mapping (optional).
-    index: Option<String>,
+    index: Option<Arc<str>>,
     /// "Protected" flag (optional).
     protected: Option<bool>,
     /// Container mapping (optional).
@@ -47,22 +66,207 @@ pub(crate) struct Definition {
 
 impl Definition {
     /// Returns the IRI mapping.
-    pub(crate) fn iri(&self) -> &str {
+    pub fn iri(&self) -> &str {
+        &self.iri
+    }
+
+    /// Returns the IRI mapping as the interned string.
+    pub(crate) fn interned_iri(&self) -> &Arc<str> {
         &self.iri
     }
 
+    /// Returns whether the term is a reverse property.
+    pub fn is_reverse(&self) -> bool {
+        self.reverse
+    }
+
+    /// Returns the type mapping.
+    pub fn ty(&self) -> Option<&str> {
+        self.ty.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Returns the language mapping.
+    ///
+    /// This distinguishes absence and explicit `null`.
+    pub(crate) fn language(&self) -> Option<Nullable<&str>> {
+        self.language
+            .as_ref()
+            .map(|lang| lang.as_ref().map(|lang| &**lang))
+    }
+
+    /// Returns the language mapping.
+    ///
+    /// Returns `Some(None)` if the language mapping is explicitly set to `null`.
+    pub fn language_mapping(&self) -> Option<Option<&str>> {
+        self.language().map(Into::into)
+    }
+
+    /// Returns the direction mapping.
+    ///
+    /// This distinguishes absence and explicit `null`.
+    pub(crate) fn direction(&self) -> Option<Nullable<Direction>> {
+        self.direction
+    }
+
+    /// Returns the direction mapping.
+    ///
+    /// Returns `Some(None)` if the direction mapping is explicitly set to `null`.
+    pub fn direction_mapping(&self) -> Option<Option<Direction>> {
+        self.direction.map(Into::into)
+    }
+
+    /// Returns the unprocessed scoped context.
+    pub fn scoped_context(&self) -> Option<&Value> {
+        self.scoped_context.as_ref().map(|scoped| &scoped.context)
+    }
+
+    /// Returns the local context and its base URL.
+    pub(crate) fn local_context(&self) -> Option<ValueWithBase<'_, &Value>> {
+        self.scoped_context
+            .as_ref()
+            .map(|scoped| ValueWithBase::new(&scoped.context, &scoped.base_url))
+    }
+
+    /// Returns the nest value.
+    pub fn nest(&self) -> Option<&str> {
+        self.nest.as_ref().map(AsRef::as_ref)
+    }
+
     /// Returns the prefix flag.
-    pub(crate) fn is_prefix(&self) -> bool {
+    pub fn is_prefix(&self) -> bool {
         self.prefix.unwrap_or(false)
     }
 
+    /// Returns the index mapping.
+    pub fn index(&self) -> Option<&str> {
+        self.index.as_ref().map(AsRef::as_ref)
+    }
+
     /// Returns whether the definition is protected.
     ///
     /// Returns false if the value is not set.
-    pub(crate) fn is_protected(&self) -> bool {
+    pub fn is_protected(&self) -> bool {
         self.protected.unwrap_or(false)
     }
 
+    /// Returns the container mapping.
+    pub fn container(&self) -> Option<Container> {
+        self.container
+    }
+
+    /// Checks if the container mapping contains the given value.
+    ///
+    /// Returns `false` if the container mapping is not set.
+    pub(crate) fn container_contains(&self, v: ContainerItem) -> bool {
+        self.container
+            .map_or(false, |container| container.contains(v))
+    }
+
+    /// Checks whether the simple term definition (the IRI string) for the term is equivalent.
+    fn is_simple(&self, term: &str) -> bool {
+        // See step 16.6 of the create term definition algorithm.
+        let implicit_prefix = !term.contains(':')
+            && !term.contains('/')
+            && self.iri.bytes().last().map_or(false, is_gen_delims_byte);
+        let prefix = if implicit_prefix { Some(true) } else { None };
+        !self.reverse
+            && self.ty.is_none()
+            && self.language.is_none()
+            && self.direction.is_none()
+            && self.container.is_none()
+            && self.scoped_context.is_none()
+            && self.nest.is_none()
+            && self.index.is_none()
+            && self.protected.is_none()
+            && self.prefix == prefix
+    }
+
+    /// Checks whether the term definition for the term uses features not available in JSON-LD
+    /// 1.0.
+    pub(crate) fn requires_json_ld_11(&self, term: &str) -> bool {
+        let container_requires_11 =
+            self.container
+                .map_or(false, |container| match container.get_single_item() {
+                    Some(ContainerItem::Graph)
+                    | Some(ContainerItem::Id)
+                    | Some(ContainerItem::Type) => true,
+                    Some(_) => false,
+                    None => true,
+                });
+        term == "@type"
+            || self.protected.is_some()
+            || self.direction.is_some()
+            || self.scoped_context.is_some()
+            || self.nest.is_some()
+            || self.index.is_some()
+            || (self.prefix.is_some() && !self.is_simple(term))
+            || self.ty() == Some("@json")
+            || self.ty() == Some("@none")
+            || container_requires_11
+    }
+
+    /// Returns a term definition for the term equivalent to the term definition.
+    ///
+    /// A simple term definition is returned if it is equivalent, and an expanded term definition
+    /// otherwise.
+    /// The base URL of the scoped context is not preserved.
+    pub(crate) fn to_json(&self, term: &str) -> Value {
+        if term != "@type" && self.is_simple(term) {
+            return Value::String(self.iri.to_string());
+        }
+        let mut obj = JsonMap::new();
+        if term == "@type" {
+            // Only `@container` (with `@set`) and `@protected` are allowed for `@type`.
+            if self.container.is_some() {
+                obj.insert("@container".to_owned(), Value::String("@set".to_owned()));
+            }
+            if let Some(protected) = self.protected {
+                obj.insert("@protected".to_owned(), Value::Bool(protected));
+            }
+            return Value::Object(obj);
+        }
+        let iri_key = if self.reverse { "@reverse" } else { "@id" };
+        obj.insert(iri_key.to_owned(), Value::String(self.iri.to_string()));
+        if let Some(ty) = &self.ty {
+            obj.insert("@type".to_owned(), Value::String(ty.to_string()));
+        }
+        if let Some(language) = &self.language {
+            let language = language.as_ref().map(|lang| lang.to_string());
+            obj.insert("@language".to_owned(), language.into());
+        }
+        if let Some(direction) = self.direction {
+            let direction = direction.map(|dir| dir.as_str().to_owned());
+            obj.insert("@direction".to_owned(), direction.into());
+        }
+        if let Some(container) = self.container {
+            // The array form is not available in JSON-LD 1.0.
+            let container = match container.get_single_item() {
+                Some(item) => Value::String(item.as_str().to_owned()),
+                None => container
+                    .iter()
+                    .map(|item| Value::String(item.as_str().to_owned()))
+                    .collect(),
+            };
+            obj.insert("@container".to_owned(), container);
+        }
+        if let Some(context) = self.scoped_context() {
+            obj.insert("@context".to_owned(), context.clone());
+        }
+        if let Some(nest) = &self.nest {
+            obj.insert("@nest".to_owned(), Value::String(nest.to_string()));
+        }
+        if let Some(prefix) = self.prefix {
+            obj.insert("@prefix".to_owned(), Value::Bool(prefix));
+        }
+        if let Some(index) = &self.index {
+            obj.insert("@index".to_owned(), Value::String(index.to_string()));
+        }
+        if let Some(protected) = self.protected {
+            obj.insert("@protected".to_owned(), Value::Bool(protected));
+        }
+        Value::Object(obj)
+    }
+
     /// Compares the term definitions other than `@protected` flag).
     pub(crate) fn eq_other_than_protected(&self, other: &Self) -> bool {
         self.iri == other.iri
@@ -70,11 +274,10 @@ impl Definition {
             && self.ty == other.ty
             && self.language == other.language
             && self.direction == other.direction
-            && self.context == other.context
+            && self.scoped_context == other.scoped_context
             && self.nest == other.nest
             && self.prefix == other.prefix
             && self.index == other.index
-            && self.protected == other.protected
             && self.container == other.container
     }
 }
@@ -1,9 +1,14 @@
 //! Definition builder.
 
+use std::sync::Arc;
+
+use iri_string::types::IriString;
+use serde_json::Value;
+
 use crate::{
     context::{
-        definition::{Container, ContainerItem, Direction},
-        Context, Definition,
+        definition::{Container, ContainerItem, Direction, ScopedContext},
+        Definition,
     },
     json::Nullable,
 };
@@ -12,26 +17,28 @@ use crate::{
 #[derive(Default, Debug, Clone, PartialEq)]
 pub(crate) struct DefinitionBuilder {
     /// IRI mapping or reverse property.
-    // This can be a non-IRI-reference (such as keywords), so use `String` here.
-    iri: Option<String>,
+    // This can be a non-IRI-reference (such as keywords), so use a string here.
+    iri: Option<Arc<str>>,
     /// Reverse property flag.
     reverse: Option<bool>,
     /// Type mapping (optional).
-    ty: Option<String>,
+    ty: Option<Arc<str>>,
     /// Lanugage mapping (optional).
     ///
     /// This property distinguishes explicit `null`.
-    language: Option<Nullable<String>>,
+    language: Option<Nullable<Arc<str>>>,
     /// Direction mapping (optional).
-    direction: Option<Direction>,
-    /// Context (optional).
-    context: Option<Context>,
+    ///
+    /// This property distinguishes explicit `null`.
+    direction: Option<Nullable<Direction>>,
+    /// Local context and its base URL (optional).
+    scoped_context: Option<Arc<ScopedContext>>,
     /// Nest value (optional).
-    nest: Option<String>,
+    nest: Option<Arc<str>>,
     /// Prefix flag (optoinal).
     prefix: Option<bool>,
     /// Index mapping (optional).
-    index: Option<String>,
+    index: Option<Arc<str>>,
     /// "Protected" flag (optional).
     protected: Option<bool>,
     /// Container mapping (optional).
@@ -58,7 +65,7 @@ impl DefinitionBuilder {
             ty: self.ty,
             language: self.language,
             direction: self.direction,
-            context: self.context,
+            scoped_context: self.scoped_context,
             nest: self.nest,
             prefix: self.prefix,
             index: self.index,
@@ -68,7 +75,7 @@ impl DefinitionBuilder {
     }
 
     /// Sets the IRI mapping.
-    pub(crate) fn set_iri(&mut self, v: impl Into<String>) {
+    pub(crate) fn set_iri(&mut self, v: impl Into<Arc<str>>) {
         self.iri = Some(v.into());
     }
 
@@ -78,7 +85,7 @@ impl DefinitionBuilder {
     ///
     /// Panics if the IRI mapping is not set.
     pub(crate) fn iri(&self) -> &str {
-        self.iri.as_ref().expect("IRI mapping must be set").as_str()
+        self.iri.as_ref().expect("IRI mapping must be set")
     }
 
     /// Sets the reverse property flag.
@@ -86,8 +93,15 @@ impl DefinitionBuilder {
         self.reverse = Some(v);
     }
 
+    /// Returns the reverse property flag.
+    ///
+    /// Returns `false` if the flag is not set.
+    pub(crate) fn is_reverse(&self) -> bool {
+        self.reverse.unwrap_or(false)
+    }
+
     /// Sets the type mapping.
-    pub(crate) fn set_ty(&mut self, v: impl Into<String>) {
+    pub(crate) fn set_ty(&mut self, v: impl Into<Arc<str>>) {
         self.ty = Some(v.into());
     }
 
@@ -97,35 +111,48 @@ impl DefinitionBuilder {
     }
 
     /// Sets the language mapping.
-    pub(crate) fn set_language(&mut self, v: impl Into<Nullable<String>>) {
-        self.language = Some(v.into())
+    pub(crate) fn set_language(&mut self, v: Nullable<Arc<str>>) {
+        self.language = Some(v)
     }
 
     /// Sets the direction mapping.
     pub(crate) fn set_direction(&mut self, v: Nullable<Direction>) {
-        self.direction = v.into();
+        self.direction = Some(v);
     }
 
-    /// Sets the local context.
-    pub(crate) fn set_local_context(&mut self, v: Context) {
-        self.context = Some(v);
+    /// Sets the local context and its base URL.
+    pub(crate) fn set_local_context(&mut self, v: Value, base_url: IriString) {
+        self.scoped_context = Some(Arc::new(ScopedContext {
+            context: v,
+            base_url,
+        }));
     }
 
     /// Sets the nest value.
-    pub(crate) fn set_nest(&mut self, v: impl Into<String>) {
+    pub(crate) fn set_nest(&mut self, v: impl Into<Arc<str>>) {
         self.nest = Some(v.into())
     }
 
+    /// Returns the nest value.
+    pub(crate) fn nest(&self) -> Option<&str> {
+        self.nest.as_ref().map(AsRef::as_ref)
+    }
+
     /// Sets the prefix flag.
     pub(crate) fn set_prefix(&mut self, v: bool) {
         self.prefix = Some(v);
     }
 
     /// Sets the index mapping.
-    pub(crate) fn set_index(&mut self, v: impl Into<String>) {
+    pub(crate) fn set_index(&mut self, v: impl Into<Arc<str>>) {
         self.index = Some(v.into());
     }
 
+    /// Returns the index mapping.
+    pub(crate) fn index(&self) -> Option<&str> {
+        self.index.as_ref().map(AsRef::as_ref)
+    }
+
     /// Sets the "protected" flag.
     pub(crate) fn set_protected(&mut self, v: bool) {
         self.protected = Some(v);
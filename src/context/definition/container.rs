@@ -27,6 +27,19 @@ pub enum ContainerItem {
 }
 
 impl ContainerItem {
+    /// Returns the keyword string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Graph => "@graph",
+            Self::Id => "@id",
+            Self::Index => "@index",
+            Self::Language => "@language",
+            Self::List => "@list",
+            Self::Set => "@set",
+            Self::Type => "@type",
+        }
+    }
+
     /// Returns an integer with distinct single bit set.
     fn single_bit(self) -> u8 {
         let shift = match self {
@@ -132,12 +145,12 @@ impl Container {
     }
 
     /// Checks whether the container has the given item.
-    pub(crate) fn contains(self, v: ContainerItem) -> bool {
+    pub fn contains(self, v: ContainerItem) -> bool {
         (self.items & v.single_bit()) != 0
     }
 
-    /// Returns an iterator of profiles.
-    pub(crate) fn iter(self) -> impl Iterator<Item = ContainerItem> {
+    /// Returns an iterator of items.
+    pub fn iter(self) -> impl Iterator<Item = ContainerItem> {
         ContainerItem::variants().filter(move |v| self.contains(*v))
     }
 
@@ -145,6 +158,18 @@ impl Container {
     pub(crate) fn len(self) -> usize {
         self.items.count_ones() as usize
     }
+
+    /// Returns the concatenation of the items in lexicographical order.
+    ///
+    /// Returns `@none` if the container is empty.
+    /// This is used as a key of the inverse context.
+    pub(crate) fn to_key_string(self) -> String {
+        if self.len() == 0 {
+            return "@none".to_owned();
+        }
+        // `ContainerItem::variants()` is already sorted lexicographically.
+        self.iter().map(ContainerItem::as_str).collect()
+    }
 }
 
 impl fmt::Debug for Container {
@@ -16,6 +16,16 @@ pub enum Direction {
     Rtl,
 }
 
+impl Direction {
+    /// Returns the direction string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+        }
+    }
+}
+
 impl TryFrom<&Value> for Nullable<Direction> {
     type Error = DirectionLoadError;
 
@@ -0,0 +1,229 @@
+//! Differences between contexts.
+
+use std::collections::BTreeSet;
+
+use crate::context::{Context, Definition};
+
+/// Aspect of a term definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DefinitionAspect {
+    /// IRI mapping.
+    Iri,
+    /// Reverse property flag.
+    Reverse,
+    /// Type mapping.
+    Type,
+    /// Language mapping.
+    Language,
+    /// Direction mapping.
+    Direction,
+    /// Container mapping.
+    Container,
+    /// Scoped context.
+    ScopedContext,
+    /// Nest value.
+    Nest,
+    /// Prefix flag.
+    Prefix,
+    /// Index mapping.
+    Index,
+    /// Protected flag.
+    Protected,
+}
+
+impl DefinitionAspect {
+    /// Checks whether changing the aspect may change the meaning of existing documents.
+    pub fn is_breaking(self) -> bool {
+        match self {
+            Self::Iri
+            | Self::Reverse
+            | Self::Type
+            | Self::Language
+            | Self::Direction
+            | Self::Container
+            | Self::ScopedContext
+            | Self::Nest
+            | Self::Index => true,
+            Self::Prefix | Self::Protected => false,
+        }
+    }
+}
+
+/// Change of a term definition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TermChange {
+    /// The term is newly defined.
+    Added(String),
+    /// The term is no longer defined (or defined as `null`).
+    Removed(String),
+    /// The term definition is changed.
+    Changed {
+        /// Term.
+        term: String,
+        /// Changed aspects.
+        aspects: Vec<DefinitionAspect>,
+    },
+}
+
+impl TermChange {
+    /// Returns the term.
+    pub fn term(&self) -> &str {
+        match self {
+            Self::Added(term) | Self::Removed(term) | Self::Changed { term, .. } => term,
+        }
+    }
+
+    /// Checks whether the change may change the meaning of existing documents.
+    ///
+    /// Removed terms and terms with breaking aspects changed are considered breaking.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Self::Added(_) => false,
+            Self::Removed(_) => true,
+            Self::Changed { aspects, .. } => aspects.iter().any(|aspect| aspect.is_breaking()),
+        }
+    }
+}
+
+/// Differences of term definitions between two contexts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextDiff {
+    /// Changes sorted by terms.
+    changes: Vec<TermChange>,
+}
+
+impl ContextDiff {
+    /// Compares term definitions of the old and the new contexts.
+    pub(crate) fn new(old: &Context, new: &Context) -> Self {
+        let terms = old
+            .term_definitions()
+            .chain(new.term_definitions())
+            .map(|(term, _)| term)
+            .collect::<BTreeSet<_>>();
+        let changes = terms
+            .into_iter()
+            .filter_map(
+                |term| match (old.term_definition(term), new.term_definition(term)) {
+                    (None, None) => None,
+                    (None, Some(_)) => Some(TermChange::Added(term.to_owned())),
+                    (Some(_), None) => Some(TermChange::Removed(term.to_owned())),
+                    (Some(old), Some(new)) => {
+                        let aspects = changed_aspects(old, new);
+                        if aspects.is_empty() {
+                            None
+                        } else {
+                            Some(TermChange::Changed {
+                                term: term.to_owned(),
+                                aspects,
+                            })
+                        }
+                    }
+                },
+            )
+            .collect();
+        Self { changes }
+    }
+
+    /// Returns the changes sorted by terms.
+    pub fn changes(&self) -> &[TermChange] {
+        &self.changes
+    }
+
+    /// Checks whether the contexts have the same term definitions.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Checks whether any change may change the meaning of existing documents.
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(TermChange::is_breaking)
+    }
+}
+
+/// Returns the aspects which differ between the term definitions.
+fn changed_aspects(old: &Definition, new: &Definition) -> Vec<DefinitionAspect> {
+    let mut aspects = Vec::new();
+    let mut check = |aspect, is_changed: bool| {
+        if is_changed {
+            aspects.push(aspect);
+        }
+    };
+    check(DefinitionAspect::Iri, old.iri() != new.iri());
+    check(
+        DefinitionAspect::Reverse,
+        old.is_reverse() != new.is_reverse(),
+    );
+    check(DefinitionAspect::Type, old.ty() != new.ty());
+    check(
+        DefinitionAspect::Language,
+        old.language_mapping() != new.language_mapping(),
+    );
+    check(
+        DefinitionAspect::Direction,
+        old.direction_mapping() != new.direction_mapping(),
+    );
+    check(
+        DefinitionAspect::Container,
+        old.container() != new.container(),
+    );
+    check(
+        DefinitionAspect::ScopedContext,
+        old.scoped_context() != new.scoped_context(),
+    );
+    check(DefinitionAspect::Nest, old.nest() != new.nest());
+    check(DefinitionAspect::Prefix, old.is_prefix() != new.is_prefix());
+    check(DefinitionAspect::Index, old.index() != new.index());
+    check(
+        DefinitionAspect::Protected,
+        old.is_protected() != new.is_protected(),
+    );
+    aspects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{context::definition::DefinitionBuilder, json::Nullable};
+
+    /// Creates a term definition with the given IRI mapping and type mapping.
+    fn definition(iri: &str, ty: Option<&str>) -> Nullable<Definition> {
+        let mut builder = DefinitionBuilder::new();
+        builder.set_iri(iri);
+        builder.set_reverse(false);
+        if let Some(ty) = ty {
+            builder.set_ty(ty);
+        }
+        Nullable::Value(builder.build())
+    }
+
+    #[test]
+    fn diff() {
+        let mut old = Context::new();
+        old.insert_term_definition("a".to_owned(), definition("http://example.com/a", None));
+        old.insert_term_definition("b".to_owned(), definition("http://example.com/b", None));
+        old.insert_term_definition("c".to_owned(), definition("http://example.com/c", None));
+        let mut new = old.clone();
+        new.remove_term_definition("a");
+        new.insert_term_definition(
+            "b".to_owned(),
+            definition("http://example.com/b", Some("@id")),
+        );
+        new.insert_term_definition("d".to_owned(), definition("http://example.com/d", None));
+
+        let diff = ContextDiff::new(&old, &new);
+        assert_eq!(
+            diff.changes(),
+            &[
+                TermChange::Removed("a".to_owned()),
+                TermChange::Changed {
+                    term: "b".to_owned(),
+                    aspects: vec![DefinitionAspect::Type],
+                },
+                TermChange::Added("d".to_owned()),
+            ][..]
+        );
+        assert!(diff.is_breaking());
+        assert!(ContextDiff::new(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+//! Inverse context.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#inverse-context-creation>.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{context::Context, json::Nullable};
+
+/// Type/language map of an inverse context.
+///
+/// Each map is a map from a type or language to a term.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+struct TypeLanguageMap {
+    /// `@language` map.
+    language: HashMap<String, String>,
+    /// `@type` map.
+    ty: HashMap<String, String>,
+    /// `@any` map.
+    any: HashMap<String, String>,
+}
+
+impl TypeLanguageMap {
+    /// Creates a new `TypeLanguageMap` with `@any` entry mapped to the given term.
+    fn new(term: &str) -> Self {
+        let mut any = HashMap::new();
+        any.insert("@none".to_owned(), term.to_owned());
+        Self {
+            any,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the value map for the given type/language key.
+    fn value_map(&self, type_language: &str) -> Option<&HashMap<String, String>> {
+        match type_language {
+            "@language" => Some(&self.language),
+            "@type" => Some(&self.ty),
+            "@any" => Some(&self.any),
+            _ => None,
+        }
+    }
+}
+
+/// Inverse context.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#inverse-context-creation>.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InverseContext {
+    /// Map from IRI (variable) to the map from container key to type/language map.
+    entries: HashMap<String, HashMap<String, TypeLanguageMap>>,
+}
+
+impl InverseContext {
+    /// Creates an inverse context from the given active context.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#inverse-context-creation>.
+    pub(crate) fn new(active_context: &Context) -> Self {
+        // Step 1
+        let mut result = Self::default();
+        // Step 2
+        let default_language = active_context
+            .default_language()
+            .map_or_else(|| "@none".to_owned(), str::to_lowercase);
+        // Step 3
+        let mut terms = active_context.term_definitions().collect::<Vec<_>>();
+        terms.sort_by(|(l, _), (r, _)| l.len().cmp(&r.len()).then_with(|| l.cmp(r)));
+        for (term, definition) in terms {
+            // Step 3.1
+            let definition = match definition {
+                Some(def) => def,
+                None => continue,
+            };
+            // Step 3.2
+            let container = definition
+                .container()
+                .map_or_else(|| "@none".to_owned(), |c| c.to_key_string());
+            // Step 3.3
+            let var = definition.iri();
+            // Step 3.4, 3.5, 3.6, 3.7, 3.8
+            let type_language_map = result
+                .entries
+                .entry(var.to_owned())
+                .or_insert_with(HashMap::new)
+                .entry(container)
+                .or_insert_with(|| TypeLanguageMap::new(term));
+            let TypeLanguageMap { language, ty, .. } = type_language_map;
+
+            if definition.is_reverse() {
+                // Step 3.9
+                insert_if_absent(ty, "@reverse", term);
+            } else if definition.ty() == Some("@none") {
+                // Step 3.10
+                insert_if_absent(language, "@any", term);
+                insert_if_absent(ty, "@any", term);
+            } else if let Some(type_mapping) = definition.ty() {
+                // Step 3.11
+                insert_if_absent(ty, type_mapping, term);
+            } else if let (Some(language_mapping), Some(direction_mapping)) =
+                (definition.language(), definition.direction())
+            {
+                // Step 3.12
+                let lang_dir = match (language_mapping, direction_mapping) {
+                    (Nullable::Value(lang), Nullable::Value(dir)) => {
+                        format!("{}_{}", lang, dir.as_str()).to_lowercase()
+                    }
+                    (Nullable::Value(lang), Nullable::Null) => lang.to_lowercase(),
+                    (Nullable::Null, Nullable::Value(dir)) => format!("_{}", dir.as_str()),
+                    (Nullable::Null, Nullable::Null) => "@null".to_owned(),
+                };
+                insert_if_absent(language, &lang_dir, term);
+            } else if let Some(language_mapping) = definition.language() {
+                // Step 3.13
+                let language_mapping = match language_mapping {
+                    Nullable::Value(lang) => lang.to_lowercase(),
+                    Nullable::Null => "@null".to_owned(),
+                };
+                insert_if_absent(language, &language_mapping, term);
+            } else if let Some(direction_mapping) = definition.direction() {
+                // Step 3.14
+                let direction_mapping = match direction_mapping {
+                    Nullable::Value(dir) => format!("_{}", dir.as_str()),
+                    Nullable::Null => "@none".to_owned(),
+                };
+                insert_if_absent(language, &direction_mapping, term);
+            } else if let Some(default_direction) = active_context.default_base_direction() {
+                // Step 3.15
+                let lang_dir =
+                    format!("{}_{}", default_language, default_direction.as_str()).to_lowercase();
+                insert_if_absent(language, &lang_dir, term);
+                insert_if_absent(language, "@none", term);
+                insert_if_absent(ty, "@none", term);
+            } else {
+                // Step 3.16
+                insert_if_absent(language, &default_language, term);
+                insert_if_absent(language, "@none", term);
+                insert_if_absent(ty, "@none", term);
+            }
+        }
+
+        // Step 4
+        result
+    }
+
+    /// Runs term selection algorithm.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#term-selection>.
+    pub(crate) fn select_term<'a, C: AsRef<str>, P: AsRef<str>>(
+        &'a self,
+        var: &str,
+        containers: &[C],
+        type_language: &str,
+        preferred_values: &[P],
+    ) -> Option<&'a str> {
+        // Step 1: Not necessary because the inverse context is created by the caller.
+        // Step 2
+        let container_map = self.entries.get(var)?;
+        // Step 3
+        for container in containers {
+            // Step 3.1
+            let type_language_map = match container_map.get(container.as_ref()) {
+                Some(v) => v,
+                None => continue,
+            };
+            // Step 3.2, 3.3
+            let value_map = match type_language_map.value_map(type_language) {
+                Some(v) => v,
+                None => continue,
+            };
+            // Step 3.4
+            for item in preferred_values {
+                // Step 3.4.1, 3.4.2
+                if let Some(term) = value_map.get(item.as_ref()) {
+                    return Some(term);
+                }
+            }
+        }
+
+        // Step 4
+        None
+    }
+
+    /// Checks whether the given IRI is in the inverse context.
+    pub(crate) fn contains_iri(&self, var: &str) -> bool {
+        self.entries.contains_key(var)
+    }
+}
+
+/// Lazily created inverse context of a context.
+///
+/// Clones share nothing but copy the inverse context created so far, and the cache is ignored on
+/// comparison.
+#[derive(Default)]
+pub(super) struct InverseContextCache {
+    /// Inverse context, if already created.
+    inverse: Mutex<Option<Arc<InverseContext>>>,
+}
+
+impl InverseContextCache {
+    /// Returns the cached inverse context, or creates it from the given active context.
+    ///
+    /// The active context should be the context owning the cache.
+    pub(super) fn get_or_create(&self, active_context: &Context) -> Arc<InverseContext> {
+        let mut inverse = self.inverse.lock().unwrap_or_else(|e| e.into_inner());
+        inverse
+            .get_or_insert_with(|| Arc::new(InverseContext::new(active_context)))
+            .clone()
+    }
+
+    /// Discards the cached inverse context.
+    pub(super) fn clear(&mut self) {
+        *self.inverse.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Returns the cached inverse context.
+    fn cached(&self) -> Option<Arc<InverseContext>> {
+        self.inverse
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl Clone for InverseContextCache {
+    fn clone(&self) -> Self {
+        Self {
+            inverse: Mutex::new(self.cached()),
+        }
+    }
+}
+
+impl PartialEq for InverseContextCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for InverseContextCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InverseContextCache")
+            .field("is_cached", &self.cached().is_some())
+            .finish()
+    }
+}
+
+/// Inserts the term to the map if the key is absent.
+fn insert_if_absent(map: &mut HashMap<String, String>, key: &str, term: &str) {
+    if !map.contains_key(key) {
+        map.insert(key.to_owned(), term.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::context::definition::DefinitionBuilder;
+
+    #[test]
+    fn cache_invalidation() {
+        let mut context = Context::new();
+        let empty = context.inverse_context();
+        assert!(Arc::ptr_eq(&empty, &context.inverse_context()));
+        assert!(Arc::ptr_eq(&empty, &context.clone().inverse_context()));
+
+        let mut builder = DefinitionBuilder::new();
+        builder.set_iri("http://example.com/a");
+        builder.set_reverse(false);
+        context.insert_term_definition("a".to_owned(), Nullable::Value(builder.build()));
+        let inverse = context.inverse_context();
+        assert!(!Arc::ptr_eq(&empty, &inverse));
+        assert!(inverse.contains_iri("http://example.com/a"));
+    }
+}
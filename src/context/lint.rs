@@ -0,0 +1,290 @@
+//! Context linter.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::Context,
+    json::{escape_pointer_token, to_ref_array},
+    syntax::has_form_of_keyword,
+};
+
+/// Suspicious pattern found in a context definition.
+///
+/// These are not errors, and processors accept such contexts, but they are likely to be mistakes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// A term has the form of a keyword (`"@"1*ALPHA`) but is not a keyword.
+    ///
+    /// Such term definitions are ignored by processors.
+    KeywordLikeTerm(String),
+    /// Multiple prefixes are defined for the same IRI.
+    ///
+    /// Compaction chooses only one of them.
+    PrefixCollision {
+        /// IRI.
+        iri: String,
+        /// Terms used as prefixes of the IRI.
+        prefixes: Vec<String>,
+    },
+    /// A term redefines a protected term of the active context with another IRI.
+    ///
+    /// Processors reject such contexts unless overriding protected terms is allowed.
+    ProtectedTermOverride(String),
+    /// A prefix is defined but not used by the other term definitions in the context.
+    UnusedPrefix(String),
+    /// A term has the form of an absolute or compact IRI, but is mapped to another IRI.
+    ///
+    /// Documents using the IRI as a property get the different meaning.
+    ShadowedIri {
+        /// Term.
+        term: String,
+        /// IRI mapped to the term.
+        iri: String,
+    },
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::KeywordLikeTerm(term) => write!(
+                f,
+                "Term {:?} has the form of a keyword and is ignored",
+                term
+            ),
+            LintKind::PrefixCollision { iri, prefixes } => write!(
+                f,
+                "Multiple prefixes {:?} are defined for <{}>",
+                prefixes, iri
+            ),
+            LintKind::ProtectedTermOverride(term) => {
+                write!(f, "Term {:?} redefines a protected term", term)
+            }
+            LintKind::UnusedPrefix(term) => write!(f, "Prefix {:?} is not used", term),
+            LintKind::ShadowedIri { term, iri } => write!(
+                f,
+                "Term {:?} has the form of an IRI but is mapped to <{}>",
+                term, iri
+            ),
+        }
+    }
+}
+
+/// Lint diagnostic for a context definition.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContextLint {
+    /// Kind of the diagnostic.
+    kind: LintKind,
+    /// JSON pointer to the relevant value in the local context.
+    path: String,
+}
+
+impl ContextLint {
+    /// Creates a new `ContextLint`.
+    fn new(kind: LintKind, path: String) -> Self {
+        Self { kind, path }
+    }
+
+    /// Returns the kind of the diagnostic.
+    pub fn kind(&self) -> &LintKind {
+        &self.kind
+    }
+
+    /// Returns the JSON pointer to the relevant value in the local context.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for ContextLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {:?})", self.kind, self.path)
+    }
+}
+
+/// Keys of context definitions which are keywords.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#context-definitions>.
+const CONTEXT_KEYWORDS: &[&str] = &[
+    "@base",
+    "@direction",
+    "@import",
+    "@language",
+    "@propagate",
+    "@protected",
+    "@type",
+    "@version",
+    "@vocab",
+];
+
+/// Checks the local context for suspicious patterns.
+///
+/// The local context is a value of `@context` entry, and context definitions (objects) in it are
+/// checked.
+/// Remote contexts are not dereferenced.
+pub(crate) fn lint(active_context: &Context, local_context: &Value) -> Vec<ContextLint> {
+    let mut lints = Vec::new();
+    let is_array = local_context.is_array();
+    for (index, context) in to_ref_array(local_context).iter().enumerate() {
+        if let Value::Object(definition) = context {
+            let path = if is_array {
+                format!("/{}", index)
+            } else {
+                String::new()
+            };
+            lint_definition(active_context, definition, &path, &mut lints);
+        }
+    }
+    lints
+}
+
+/// Checks the context definition for suspicious patterns.
+fn lint_definition(
+    active_context: &Context,
+    definition: &JsonMap<String, Value>,
+    path: &str,
+    lints: &mut Vec<ContextLint>,
+) {
+    let term_path = |term: &str| format!("{}/{}", path, escape_pointer_token(term));
+    let terms = definition
+        .iter()
+        .filter(|(term, _)| !CONTEXT_KEYWORDS.contains(&term.as_str()));
+    // Prefixes keyed by IRIs.
+    let mut prefixes: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (term, value) in terms {
+        if has_form_of_keyword(term) {
+            lints.push(ContextLint::new(
+                LintKind::KeywordLikeTerm(term.clone()),
+                term_path(term),
+            ));
+            continue;
+        }
+        let iri = match term_iri(value) {
+            Some(iri) => iri,
+            None => continue,
+        };
+        if let Some(protected) = active_context.term_definition(term) {
+            if protected.is_protected() && protected.iri() != iri {
+                lints.push(ContextLint::new(
+                    LintKind::ProtectedTermOverride(term.clone()),
+                    term_path(term),
+                ));
+            }
+        }
+        if term.contains(':') && !term.starts_with("_:") && iri != term.as_str() {
+            lints.push(ContextLint::new(
+                LintKind::ShadowedIri {
+                    term: term.clone(),
+                    iri: iri.to_owned(),
+                },
+                term_path(term),
+            ));
+        }
+        if is_prefix_definition(term, value, iri) {
+            prefixes.entry(iri).or_insert_with(Vec::new).push(term);
+        }
+    }
+
+    for (iri, terms) in &prefixes {
+        if terms.len() > 1 {
+            lints.push(ContextLint::new(
+                LintKind::PrefixCollision {
+                    iri: (*iri).to_owned(),
+                    prefixes: terms.iter().map(|&term| term.to_owned()).collect(),
+                },
+                term_path(terms[1]),
+            ));
+        }
+    }
+
+    let used_prefixes = definition
+        .values()
+        .flat_map(referenced_iris)
+        .filter_map(|iri| {
+            let pos = iri.find(':')?;
+            Some(&iri[..pos])
+        })
+        .collect::<Vec<_>>();
+    for term in prefixes.values().flatten() {
+        if !used_prefixes.contains(term) {
+            lints.push(ContextLint::new(
+                LintKind::UnusedPrefix((*term).to_owned()),
+                term_path(term),
+            ));
+        }
+    }
+}
+
+/// Returns the IRI given by the term definition value.
+fn term_iri(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(iri) => Some(iri.as_str()),
+        Value::Object(obj) => obj
+            .get("@id")
+            .or_else(|| obj.get("@reverse"))
+            .and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// Checks whether the term is defined as a prefix.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#create-term-definition> (step
+/// 14.2.3).
+fn is_prefix_definition(term: &str, value: &Value, iri: &str) -> bool {
+    if term.contains(':') || term.contains('/') {
+        return false;
+    }
+    if let Some(prefix) = value.get("@prefix").and_then(Value::as_bool) {
+        return prefix;
+    }
+    value.is_string() && iri.chars().last().map_or(false, |c| ":/?#[]@".contains(c))
+}
+
+/// Returns IRIs (possibly compact IRIs) referenced by the term definition value.
+fn referenced_iris(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(iri) => vec![iri.as_str()],
+        Value::Object(obj) => ["@id", "@reverse", "@type"]
+            .iter()
+            .filter_map(|key| obj.get(*key).and_then(Value::as_str))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn lint_context() {
+        let local_context = json!({
+            "@vocab": "http://example.com/vocab#",
+            "@foo": "http://example.com/foo",
+            "ex": "http://example.com/",
+            "ex2": "http://example.com/",
+            "unused": "http://unused.example.com/",
+            "name": "ex:name",
+            "ex:age": "http://example.com/other",
+        });
+        let lints = lint(&Context::new(), &local_context)
+            .into_iter()
+            .map(|lint| lint.kind().clone())
+            .collect::<Vec<_>>();
+        assert!(lints.contains(&LintKind::KeywordLikeTerm("@foo".to_owned())));
+        assert!(lints.contains(&LintKind::PrefixCollision {
+            iri: "http://example.com/".to_owned(),
+            prefixes: vec!["ex".to_owned(), "ex2".to_owned()],
+        }));
+        assert!(lints.contains(&LintKind::UnusedPrefix("unused".to_owned())));
+        assert!(!lints.contains(&LintKind::UnusedPrefix("ex".to_owned())));
+        assert!(lints.contains(&LintKind::ShadowedIri {
+            term: "ex:age".to_owned(),
+            iri: "http://example.com/other".to_owned(),
+        }));
+    }
+}
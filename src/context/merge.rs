@@ -1,22 +1,17 @@
 //! Context processing algorithm.
 
-use std::{
-    collections::{HashMap, HashSet},
-    future::Future,
-    pin::Pin,
-    sync::Arc,
-};
+use std::sync::Arc;
 
-use anyhow::anyhow;
 use iri_string::types::{IriReferenceStr, IriStr, IriString};
 use serde_json::Value;
 
 use crate::{
     context::{Context, ValueWithBase},
     error::{ErrorCode, Result},
+    hash::HashSet,
     json::to_ref_array,
     processor::Processor,
-    remote::{LoadDocumentOptions, LoadRemoteDocument, Profile, RemoteDocument},
+    remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument},
 };
 
 use self::ctx_def::process_context_definition;
@@ -27,11 +22,13 @@ mod ctx_def;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OptionalParams {
     /// Remote contexts.
-    remote_contexts: HashSet<IriString>,
+    remote_contexts: RemoteContexts,
     /// "Override protected" flag.
     override_protected: bool,
     /// "Propagate" flag.
     propagate: bool,
+    /// "Validate scoped context" flag.
+    validate_scoped: bool,
 }
 
 impl OptionalParams {
@@ -47,6 +44,27 @@ impl OptionalParams {
             ..self
         }
     }
+
+    /// Sets the "propagate" flag.
+    pub(crate) fn propagate(self, propagate: bool) -> Self {
+        Self { propagate, ..self }
+    }
+
+    /// Sets the remote contexts.
+    pub(crate) fn remote_contexts(self, remote_contexts: RemoteContexts) -> Self {
+        Self {
+            remote_contexts,
+            ..self
+        }
+    }
+
+    /// Sets the "validate scoped context" flag.
+    pub(crate) fn validate_scoped(self, validate_scoped: bool) -> Self {
+        Self {
+            validate_scoped,
+            ..self
+        }
+    }
 }
 
 impl Default for OptionalParams {
@@ -55,10 +73,85 @@ impl Default for OptionalParams {
             remote_contexts: Default::default(),
             override_protected: false,
             propagate: true,
+            validate_scoped: true,
         }
     }
 }
 
+/// Remote contexts dereferenced so far, to detect context overflow.
+///
+/// Remote contexts dereferenced while processing a local context are visible only from the local
+/// context and the contexts nested in it, so they are removed when the processing of the local
+/// context finishes.
+/// This avoids copying the set for each nested context.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RemoteContexts {
+    /// Remote contexts in order of dereference.
+    iris: Vec<IriString>,
+    /// Set of the remote contexts.
+    set: HashSet<IriString>,
+}
+
+impl RemoteContexts {
+    /// Returns the number of remote contexts.
+    fn len(&self) -> usize {
+        self.iris.len()
+    }
+
+    /// Checks whether the remote context is dereferenced.
+    fn contains(&self, iri: &IriStr) -> bool {
+        self.set.contains(iri)
+    }
+
+    /// Checks whether no remote contexts are dereferenced.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.iris.is_empty()
+    }
+
+    /// Adds the remote context.
+    fn insert(&mut self, iri: IriString) {
+        if self.set.insert(iri.clone()) {
+            self.iris.push(iri);
+        }
+    }
+
+    /// Removes remote contexts added after the number of remote contexts was `len`.
+    fn truncate(&mut self, len: usize) {
+        while self.iris.len() > len {
+            if let Some(iri) = self.iris.pop() {
+                self.set.remove(&iri);
+            }
+        }
+    }
+}
+
+/// Key of a processed remote context cached by the processor.
+///
+/// The cached context is used only if the active context is also the same.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ProcessedContextKey {
+    /// IRI of the remote context.
+    iri: IriString,
+    /// Base IRI to process the remote context.
+    base: IriString,
+    /// "Override protected" flag.
+    override_protected: bool,
+    /// "Propagate" flag.
+    propagate: bool,
+    /// Options to load the remote context.
+    load_options: LoadDocumentOptions,
+    /// Whether the processing mode is `json-ld-1.0`.
+    is_processing_mode_1_0: bool,
+    /// Number of remote contexts dereferenced so far, and the limit.
+    ///
+    /// These are necessary to detect context overflow in nested remote contexts.
+    remote_contexts_budget: (usize, Option<usize>),
+    /// Remote contexts including this context.
+    ///
+    /// This is necessary to detect recursive inclusion in nested remote contexts.
+    inclusion_chain: Vec<IriString>,
+}
+
 /// Runs context processing algorithm and returns a new context.
 ///
 /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#context-processing-algorithm>.
@@ -74,112 +167,229 @@ pub(crate) async fn join_value<L: LoadRemoteDocument>(
         remote_contexts,
         override_protected,
         propagate,
+        validate_scoped,
     } = optional;
+    let override_protected = override_protected || processor.options().is_override_protected();
 
-    join_value_impl(
-        processor,
-        active_context,
-        local_context,
-        remote_contexts,
-        override_protected,
-        propagate,
-        &mut Default::default(),
+    instrument!(
+        Box::pin(join_value_impl(
+            processor,
+            active_context,
+            local_context,
+            remote_contexts,
+            override_protected,
+            propagate,
+            validate_scoped,
+            Vec::new(),
+        )),
+        "process_context"
     )
     .await
 }
 
-/// Runs context processing algorithm and returns a new context.
-///
-/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#context-processing-algorithm>.
+/// Local context processed by `join_value_impl`.
+#[derive(Clone)]
+enum LocalContext<'a> {
+    /// Local context given by the caller.
+    Given(ValueWithBase<'a, &'a Value>),
+    /// `@context` entry of a remote context document, and the base IRI to process it.
+    Remote(Arc<RemoteDocument>, IriString),
+}
+
+impl LocalContext<'_> {
+    /// Returns the local context value and its base IRI.
+    fn value_with_base(&self) -> ValueWithBase<'_, &Value> {
+        match self {
+            LocalContext::Given(v) => *v,
+            LocalContext::Remote(doc, base) => ValueWithBase::new(
+                doc.document()
+                    .get("@context")
+                    .expect("Should never fail: checked when the remote context is loaded"),
+                base,
+            ),
+        }
+    }
+}
+
+/// Local context being processed, with the state of context processing algorithm for it.
 ///
-/// This is a wrapper for recursive call.
-fn join_value_impl_recursive<'a, L: LoadRemoteDocument>(
-    processor: &'a Processor<L>,
-    active_context: &'a Context,
-    local_context: ValueWithBase<'a, &'a Value>,
-    remote_contexts: HashSet<IriString>,
-    override_protected: bool,
+/// A remote context is processed as a new frame on top of the frame referring to it, instead of a
+/// recursive call.
+struct Frame<'a> {
+    /// Local context.
+    local_context: LocalContext<'a>,
+    /// Index of the next context to process in the local context.
+    next: usize,
+    /// Active context the local context is processed against.
+    active_context: Context,
+    /// Number of remote contexts when the processing of the local context started.
+    remote_contexts_len: usize,
+    /// "Propagate" flag.
     propagate: bool,
-    remote_contexts_cache: &'a mut HashMap<IriString, Arc<RemoteDocument>>,
-) -> Pin<Box<dyn Future<Output = Result<Context>> + 'a + Send>> {
-    Box::pin(async move {
-        join_value_impl(
-            processor,
-            active_context,
+    /// Remote contexts including this local context.
+    inclusion_chain: Vec<IriString>,
+    /// Key to cache the result, if the local context is a remote context.
+    cache_key: Option<ProcessedContextKey>,
+}
+
+impl<'a> Frame<'a> {
+    /// Creates a new frame, and updates the result for the frame.
+    ///
+    /// `result` should be the active context for the local context.
+    // Step 1-3
+    fn start<L: LoadRemoteDocument>(
+        processor: &Processor<L>,
+        local_context: LocalContext<'a>,
+        remote_contexts_len: usize,
+        propagate: bool,
+        inclusion_chain: Vec<IriString>,
+        cache_key: Option<ProcessedContextKey>,
+        result: &mut Context,
+    ) -> Result<Self> {
+        // Step 1
+        let active_context = result.clone();
+        // Step 2
+        // > If _local context_ is an object containing the member `@propagate`, its value MUST be
+        // > boolean `true` or `false`, set _propagate_ to that value.
+        let propagate = match local_context.value_with_base().value().get("@propagate") {
+            None => propagate,
+            Some(_) if processor.is_processing_mode_1_0() => {
+                return Err(ErrorCode::InvalidContextEntry
+                    .and_message("Found `@propagate` while processing mode is `json-ld-1.0`"))
+            }
+            Some(Value::Bool(v)) => *v,
+            Some(v) => {
+                return Err(ErrorCode::InvalidPropagateValue
+                    .and_message(format!("Expected boolean as `@propagate` but got {:?}", v)))
+            }
+        };
+        // Step 3
+        if !propagate && !result.has_previous_context() {
+            result.previous_context = Some(Arc::new(active_context.clone()));
+        }
+
+        Ok(Self {
             local_context,
-            remote_contexts,
-            override_protected,
+            next: 0,
+            active_context,
+            remote_contexts_len,
             propagate,
-            remote_contexts_cache,
-        )
-        .await
-    })
+            inclusion_chain,
+            cache_key,
+        })
+    }
 }
 
 /// Runs context processing algorithm and returns a new context.
 ///
 /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#context-processing-algorithm>.
+///
+/// Nested remote contexts are processed iteratively with an explicit stack of local contexts.
+#[allow(clippy::too_many_arguments)] // TODO: FIXME
 async fn join_value_impl<L: LoadRemoteDocument>(
     processor: &Processor<L>,
     active_context: &Context,
     local_context: ValueWithBase<'_, &Value>,
-    mut remote_contexts: HashSet<IriString>,
+    mut remote_contexts: RemoteContexts,
     override_protected: bool,
     propagate: bool,
-    remote_contexts_cache: &mut HashMap<IriString, Arc<RemoteDocument>>,
+    validate_scoped: bool,
+    inclusion_chain: Vec<IriString>,
 ) -> Result<Context> {
     // Step 1
     let mut result = active_context.clone();
-    // Step 2
-    // NOTE: Spec says as below, but I have no idea what to do if the value of the `@propagate`
-    // entry is not a boolean.
-    //
-    // > If _local context_ is an object containing the member `@propagate`, its value MUST be
-    // > boolean `true` or `false`, set _propagate_ to that value.
-    let propagate = local_context
-        .value()
-        .get("@propagate")
-        .and_then(Value::as_bool)
-        .unwrap_or(propagate);
-    // Step 3
-    if !propagate && result.has_previous_context() {
-        result.previous_context = Some(Box::new(active_context.clone()));
-    }
-    // Step 4
-    let local_context = local_context.map(to_ref_array);
-    // Step 5
-    for context in local_context.into_value() {
+    let mut stack = vec![Frame::start(
+        processor,
+        LocalContext::Given(local_context),
+        remote_contexts.len(),
+        propagate,
+        inclusion_chain,
+        None,
+        &mut result,
+    )?];
+    while let Some(frame) = stack.last_mut() {
+        processor.options().check_cancelled()?;
+        let local_context = frame.local_context.clone();
+        // Step 4
+        let local_context = local_context.value_with_base();
+        let context = match to_ref_array(local_context.value()).get(frame.next) {
+            Some(v) => v,
+            None => {
+                // Step 6
+                let frame = stack
+                    .pop()
+                    .expect("Should never fail: the stack has the frame");
+                remote_contexts.truncate(frame.remote_contexts_len);
+                if let Some(key) = frame.cache_key {
+                    processor.cache_processed_context(key, frame.active_context, result.clone());
+                }
+                continue;
+            }
+        };
+        frame.next += 1;
         // Step 5.1-
         match context {
             // Step 5.1
             Value::Null => {
                 // Step 5.1.1, 5.1.2
-                result =
-                    process_single_null(active_context, override_protected, propagate, result)?;
+                result = process_single_null(
+                    &frame.active_context,
+                    override_protected,
+                    frame.propagate,
+                    result,
+                )?;
             }
             // Step 5.2
             Value::String(context) => {
-                // Step 5.2.1-5.2.6
-                result = process_single_string(
+                // Step 5.2.1-5.2.5
+                let (document, key) = match load_single_string(
                     processor,
                     &mut remote_contexts,
                     override_protected,
-                    propagate,
-                    remote_contexts_cache,
-                    result,
+                    frame.propagate,
+                    validate_scoped,
+                    &frame.inclusion_chain,
+                    &result,
                     local_context.with_new_value(context),
                 )
-                .await?;
-                // Step 5.2.7: Continue with the next _context_.
-                // No need of explicit `continue` here.
+                .await?
+                {
+                    RemoteContext::Processed(processed) => {
+                        result = processed;
+                        // Step 5.2.7: Continue with the next _context_.
+                        continue;
+                    }
+                    RemoteContext::Loaded(document, key) => (document, key),
+                    RemoteContext::Skipped => continue,
+                };
+                // Step 5.2.6
+                trace_event!(iri = %key.iri, "Processing the remote context");
+                let propagate = frame.propagate;
+                let inclusion_chain = key
+                    .inclusion_chain
+                    .iter()
+                    .cloned()
+                    .chain(Some(key.iri.clone()))
+                    .collect();
+                let nested = Frame::start(
+                    processor,
+                    LocalContext::Remote(document, key.base.clone()),
+                    remote_contexts.len(),
+                    propagate,
+                    inclusion_chain,
+                    // The result of the validation skips the remote contexts already
+                    // dereferenced, so it should not be reused.
+                    Some(key).filter(|_| validate_scoped),
+                    &mut result,
+                )?;
+                stack.push(nested);
             }
             // Step 5.4-5.13
             Value::Object(context) => {
                 result = process_context_definition(
                     processor,
-                    active_context,
-                    &mut remote_contexts,
-                    propagate,
+                    &remote_contexts,
+                    frame.propagate,
                     result,
                     local_context.with_new_value(context),
                 )
@@ -188,7 +398,7 @@ async fn join_value_impl<L: LoadRemoteDocument>(
             // Step 5.3
             v => {
                 return Err(
-                    ErrorCode::InvalidLocalContext.and_source(anyhow!("local context = {:?}", v))
+                    ErrorCode::InvalidLocalContext.and_message(format!("local context = {:?}", v))
                 )
             }
         }
@@ -214,38 +424,68 @@ fn process_single_null(
     // > in _result_ to the previous value of _result_ if propagate is `false`.
     let previous_context = std::mem::replace(&mut result, Context::new());
     if !propagate {
-        result.previous_context = Some(Box::new(previous_context));
+        result.previous_context = Some(Arc::new(previous_context));
     }
 
     Ok(result)
 }
 
-/// Processes single context which is a string.
+/// Remote context dereferenced by `load_single_string`.
+enum RemoteContext {
+    /// The remote context is already processed against the active context.
+    Processed(Context),
+    /// The remote context document is loaded, and should be processed.
+    Loaded(Arc<RemoteDocument>, ProcessedContextKey),
+    /// The remote context is already dereferenced while validating a scoped context.
+    Skipped,
+}
+
+/// Dereferences single context which is a string.
+///
+/// The remote context is not processed here, but a cached result is returned if available.
 #[allow(clippy::too_many_arguments)] // TODO: FIXME
-async fn process_single_string<L: LoadRemoteDocument>(
+async fn load_single_string<L: LoadRemoteDocument>(
     processor: &Processor<L>,
-    remote_contexts: &mut HashSet<IriString>,
+    remote_contexts: &mut RemoteContexts,
     override_protected: bool,
     propagate: bool,
-    remote_contexts_cache: &mut HashMap<IriString, Arc<RemoteDocument>>,
-    mut result: Context,
+    validate_scoped: bool,
+    inclusion_chain: &[IriString],
+    result: &Context,
     context: ValueWithBase<'_, &str>,
-) -> Result<Context> {
-    use std::collections::hash_map::Entry;
-
+) -> Result<RemoteContext> {
     // Step 5.2.1
     let context = {
         let base: &IriStr = context.base();
-        let context: &IriReferenceStr = IriReferenceStr::new(context.value()).map_err(|e| {
-            ErrorCode::Uncategorized
-                .and_source(e)
-                .context(format!("Expected IRI reference, but got {:?}", context))
-        })?;
+        let context: &IriReferenceStr = IriReferenceStr::new(context.value())
+            .map_err(|e| ErrorCode::Uncategorized.and_iri_error(*context.value(), e))?;
         context.resolve_against(base.to_absolute())
     };
+    // Validating a scoped context does not process remote contexts already dereferenced, so that
+    // a scoped context can refer to the remote context defining it.
+    // See step 5.2.2 of <https://www.w3.org/TR/json-ld11-api/#context-processing-algorithm>:
+    //
+    // > If _validate scoped context_ is `false`, and _remote contexts_ already includes
+    // > _context_ do not process _context_ further and continue to any next _context_ in
+    // > _local context_.
+    if !validate_scoped && remote_contexts.contains(&context) {
+        return Ok(RemoteContext::Skipped);
+    }
+    // Remote contexts including each other would be dereferenced until the context overflow,
+    // so detect them early to report the cycle.
+    if let Some(pos) = inclusion_chain.iter().position(|iri| *iri == context) {
+        let chain = inclusion_chain[pos..]
+            .iter()
+            .chain(Some(&context))
+            .map(|iri| format!("<{}>", iri))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(ErrorCode::RecursiveContextInclusion
+            .and_message(format!("Remote contexts include each other: {}", chain)));
+    }
     // Step 5.2.2
-    if !processor.is_remote_context_limit_exceeded(remote_contexts.len()) {
-        return Err(ErrorCode::ContextOverflow.and_source(anyhow!(
+    if processor.is_remote_context_limit_exceeded(remote_contexts.len()) {
+        return Err(ErrorCode::ContextOverflow.and_message(format!(
             "Current number of remote contexts = {:?}",
             remote_contexts.len()
         )));
@@ -255,38 +495,82 @@ async fn process_single_string<L: LoadRemoteDocument>(
     // > If _context_ was previously dereferenced, then the processor MUST NOT do a
     // > further dereference, and _context_ is set to the previously established
     // > internal representation.
-    let remote_doc: Arc<RemoteDocument> = match remote_contexts_cache.entry(context.clone()) {
-        // Step 5.2.3
-        Entry::Occupied(entry) => entry.into_mut().clone(),
-        // Step 5.2.4, 5.2.5
-        Entry::Vacant(entry) => {
-            let mut load_opts = LoadDocumentOptions::new();
-            load_opts.set_profile(Profile::Context);
-            load_opts.set_request_profile(Profile::Context);
-            let doc = processor
-                .loader()
-                .load(&context, load_opts)
-                .await
-                .map_err(|e| ErrorCode::LoadingRemoteContextFailed.and_source(e))?;
-            entry.insert(doc).clone()
-        }
-    };
+    let load_opts = processor.context_load_options();
+    let remote_doc: Arc<RemoteDocument> =
+        match processor.cached_remote_context(&context, &load_opts) {
+            // Step 5.2.3
+            Some(doc) => {
+                trace_event!(iri = %context, "Using the cached remote context");
+                doc
+            }
+            // Step 5.2.4, 5.2.5
+            None => {
+                let doc = processor
+                    .cancellable(instrument!(
+                        processor.loader().load(&context, load_opts.clone()),
+                        "load_remote_context",
+                        iri = %context
+                    ))
+                    .await?
+                    .map_err(|e| {
+                        ErrorCode::LoadingRemoteContextFailed.and_load_error(context.as_str(), e)
+                    })?;
+                processor
+                    .check_document_type(context.as_str(), &doc, &load_opts)
+                    .map_err(|e| ErrorCode::LoadingRemoteContextFailed.and_cause(e))?;
+                processor.check_document_nodes(context.as_str(), &doc)?;
+                processor.cache_remote_context(context.clone(), load_opts.clone(), doc.clone());
+                doc
+            }
+        };
     // Step 5.2.5
     let context_iri = context;
-    let context = remote_doc.document().get("@context").ok_or_else(|| {
-        ErrorCode::InvalidRemoteContext.and_source(anyhow!("doc = {:?}", remote_doc))
-    })?;
+    if remote_doc.document().get("@context").is_none() {
+        return Err(ErrorCode::InvalidRemoteContext.and_message(format!("doc = {:?}", remote_doc)));
+    }
     // Step 5.2.6
-    result = join_value_impl_recursive(
-        processor,
-        &result,
-        ValueWithBase::new(context, &context_iri),
-        remote_contexts.clone(),
+    // > passing ... the `documentUrl` of _context document_ for _base URL_ ...
+    // The document URL differs from the requested IRI if the loader followed redirects.
+    let base = IriStr::new(remote_doc.get_document_url()).unwrap_or(&context_iri);
+    // Processing the same remote context against the same active context gives the same result,
+    // so the result is cached to avoid processing well-known contexts repeatedly.
+    let key = ProcessedContextKey {
+        iri: context_iri.clone(),
+        base: base.to_owned(),
         override_protected,
         propagate,
-        remote_contexts_cache,
-    )
-    .await?;
+        load_options: load_opts,
+        is_processing_mode_1_0: processor.is_processing_mode_1_0(),
+        remote_contexts_budget: (
+            remote_contexts.len(),
+            processor.options().allowed_max_remote_context(),
+        ),
+        inclusion_chain: inclusion_chain.to_vec(),
+    };
+    if let Some(processed) = processor.cached_processed_context(&key, result) {
+        trace_event!(iri = %context_iri, "Using the cached processed context");
+        return Ok(RemoteContext::Processed(processed));
+    }
 
-    Ok(result)
+    Ok(RemoteContext::Loaded(remote_doc, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_contexts_truncate() {
+        let iri = |s: &str| IriStr::new(s).expect("valid IRI").to_owned();
+        let mut remote_contexts = RemoteContexts::default();
+        remote_contexts.insert(iri("http://example.com/a"));
+        let len = remote_contexts.len();
+        remote_contexts.insert(iri("http://example.com/b"));
+        remote_contexts.insert(iri("http://example.com/a"));
+        assert_eq!(remote_contexts.len(), 2);
+        remote_contexts.truncate(len);
+        assert_eq!(remote_contexts.len(), 1);
+        remote_contexts.insert(iri("http://example.com/b"));
+        assert_eq!(remote_contexts.len(), 2);
+    }
 }
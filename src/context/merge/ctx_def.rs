@@ -1,34 +1,30 @@
 //! Processing function for a context definition.
 
-use std::{
-    borrow::Cow,
-    collections::{HashMap, HashSet},
-    convert::TryInto,
-    sync::Arc,
-};
+use std::{borrow::Cow, convert::TryInto, sync::Arc};
 
-use anyhow::anyhow;
 use iri_string::types::{IriReferenceStr, IriStr, IriString, RelativeIriStr};
 use serde_json::{Map as JsonMap, Value};
 
 use crate::{
     context::{
-        create_term_def::{create_term_definition, OptionalParams},
+        create_term_def::{create_term_definition, DefinedTerms, OptionalParams},
         definition::Direction,
+        merge::RemoteContexts,
         Context, ValueWithBase,
     },
     error::{ErrorCode, Result},
     expand::iri::ExpandIriOptions,
     json::Nullable,
     processor::{Processor, ProcessorOptions},
-    remote::{LoadDocumentOptions, LoadRemoteDocument, Profile, RemoteDocument},
+    remote::{LoadRemoteDocument, RemoteDocument},
+    syntax::is_well_formed_language,
+    warning::Warning,
 };
 
 /// Processes single context which is a map.
 pub(crate) async fn process_context_definition<L: LoadRemoteDocument>(
     processor: &Processor<L>,
-    active_context: &Context,
-    remote_contexts: &mut HashSet<IriString>,
+    remote_contexts: &RemoteContexts,
     propagate: bool,
     mut result: Context,
     context: ValueWithBase<'_, &JsonMap<String, Value>>,
@@ -39,7 +35,7 @@ pub(crate) async fn process_context_definition<L: LoadRemoteDocument>(
     // Step 5.6
     let context: ValueWithBase<'_, Cow<'_, _>> = {
         let new_context: Cow<JsonMap<String, Value>> =
-            process_ctxdef_import(processor, active_context, context.value()).await?;
+            process_ctxdef_import(processor, context.value(), context.base()).await?;
         context.with_new_value(new_context)
     };
     let context: ValueWithBase<'_, &JsonMap<_, _>> = context.with_new_value(context.value());
@@ -48,21 +44,21 @@ pub(crate) async fn process_context_definition<L: LoadRemoteDocument>(
     // Step 5.8
     process_ctxdef_vocab(processor, &mut result, context.value()).await?;
     // Step 5.9.
-    process_ctxdef_language(&mut result, context.value())?;
+    process_ctxdef_language(processor, &mut result, context.value())?;
     // Step 5.10.
     process_ctxdef_direction(processor.options(), &mut result, context.value())?;
     // Step 5.11.
     // Note that this does only error handling.
     process_ctxdef_propagate(processor.options(), context.value())?;
     // Step 5.12.
-    let mut defined = HashMap::new();
+    let mut defined = DefinedTerms::new();
     // Step 5.13.
     let protected = match context.value().get("@protected") {
         None => None,
         Some(Value::Bool(v)) => Some(*v),
         Some(v) => {
             return Err(ErrorCode::Uncategorized
-                .and_source(anyhow!("Expected boolean as `@protected`, but got {:?}", v)))
+                .and_message(format!("Expected boolean as `@protected`, but got {:?}", v)))
         }
     };
     let options = OptionalParams::new()
@@ -74,7 +70,16 @@ pub(crate) async fn process_context_definition<L: LoadRemoteDocument>(
             | "@version" | "@vocab" => continue,
             _ => {}
         }
-        create_term_definition(processor, &mut result, context, key, &mut defined, options).await?;
+        create_term_definition(
+            processor,
+            &mut result,
+            context,
+            key,
+            &mut defined,
+            remote_contexts,
+            options,
+        )
+        .await?;
     }
 
     Ok(result)
@@ -88,17 +93,18 @@ fn process_ctxdef_version(
     // Step 5.5
     if let Some(version) = context.get("@version") {
         // Step 5.5.1
-        let is_1_1 = version.as_f64().map_or(false, |v| v >= 1.09 && v <= 1.11);
+        // Only the number `1.1` is allowed. Strings such as `"1.1"` and other numbers are
+        // rejected.
+        let is_1_1 = *version == Value::from(1.1);
         if !is_1_1 {
             return Err(
-                ErrorCode::InvalidVersionValue.and_source(anyhow!("`@version` = {:?}", version))
+                ErrorCode::InvalidVersionValue.and_message(format!("`@version` = {:?}", version))
             );
         }
         // Step 5.5.2
         if processor.is_processing_mode_1_0() {
-            return Err(ErrorCode::ProcessingModeConflict.and_source(anyhow!(
-                "Got `@version` = 1.1, but processing mode is `json-ld-1.0`"
-            )));
+            return Err(ErrorCode::ProcessingModeConflict
+                .and_message("Got `@version` = 1.1, but processing mode is `json-ld-1.0`"));
         }
     }
 
@@ -108,8 +114,8 @@ fn process_ctxdef_version(
 /// Processes `@import` entry of the context definition.
 async fn process_ctxdef_import<'a, L: LoadRemoteDocument>(
     processor: &Processor<L>,
-    active_context: &Context,
     context: &'a JsonMap<String, Value>,
+    base_url: &IriStr,
 ) -> Result<Cow<'a, JsonMap<String, Value>>> {
     // Step 5.6
     let import = match context.get("@import") {
@@ -119,66 +125,65 @@ async fn process_ctxdef_import<'a, L: LoadRemoteDocument>(
 
     // Step 5.6.1
     if processor.is_processing_mode_1_0() {
-        return Err(ErrorCode::InvalidContextEntry.and_source(anyhow!(
-            "Found `@import` but processing mode is `json-ld-1.0`"
-        )));
+        return Err(ErrorCode::InvalidContextEntry
+            .and_message("Found `@import` but processing mode is `json-ld-1.0`"));
     }
     // Step 5.6.2
     let import = import.as_str().ok_or_else(|| {
-        ErrorCode::InvalidImportValue.and_source(anyhow!("Expected string but got {:?}", import))
+        ErrorCode::InvalidImportValue.and_message(format!("Expected string but got {:?}", import))
     })?;
     // Step 5.6.3
     let import = {
-        let base = match processor.base(&active_context) {
-            Some(v) => v,
-            None => unimplemented!("FIXME: What to do if no base IRI available?"),
-        };
-        let import = IriReferenceStr::new(import).map_err(|e| {
-            ErrorCode::Uncategorized.and_source(e).context(format!(
-                "Cannot resolve `@import` IRI ({:?}) because it is not an IRI reference",
-                import
-            ))
-        })?;
-        import.resolve_against(base.to_absolute())
+        let import = IriReferenceStr::new(import)
+            .map_err(|e| ErrorCode::InvalidImportValue.and_iri_error(import, e))?;
+        import.resolve_against(base_url.to_absolute())
     };
     // Step 5.6.4, 5.6.5
     // NOTE: The spec does not say this should be cached (but also does not say this should not
     // be cached...
     let remote_doc: Arc<RemoteDocument> = {
-        let mut load_opts = LoadDocumentOptions::new();
-        load_opts.set_profile(Profile::Context);
-        load_opts.set_request_profile(Profile::Context);
+        let load_opts = processor.context_load_options();
+        let doc = processor
+            .cancellable(instrument!(
+                processor.loader().load(&import, load_opts.clone()),
+                "load_imported_context",
+                iri = %import
+            ))
+            .await?
+            .map_err(|e| {
+                ErrorCode::LoadingRemoteContextFailed
+                    .and_load_error(import.as_str(), e)
+                    .context("Failed to dereference `@import`")
+            })?;
         processor
-            .loader()
-            .load(&import, load_opts)
-            .await
+            .check_document_type(import.as_str(), &doc, &load_opts)
             .map_err(|e| {
                 ErrorCode::LoadingRemoteContextFailed
-                    .and_source(e)
+                    .and_cause(e)
                     .context("Failed to dereference `@import`")
-            })?
+            })?;
+        processor.check_document_nodes(import.as_str(), &doc)?;
+        doc
     };
     // Step 5.6.6
     let import_context = match remote_doc.document().get("@context") {
         Some(Value::Object(map)) => map,
         Some(v) => {
-            return Err(ErrorCode::InvalidRemoteContext.and_source(anyhow!(
+            return Err(ErrorCode::InvalidRemoteContext.and_message(format!(
                 "Expected a map as `@context` entry in remote doc \
                  specified by `@import`, but got {:?}",
                 v
             )))
         }
         None => {
-            return Err(ErrorCode::InvalidRemoteContext.and_source(anyhow!(
-                "`@context` entry not found in remote doc specified by `@import`"
-            )))
+            return Err(ErrorCode::InvalidRemoteContext
+                .and_message("`@context` entry not found in remote doc specified by `@import`"))
         }
     };
     // Step 5.6.7
     if import_context.contains_key("@import") {
-        return Err(ErrorCode::InvalidContextEntry.and_source(anyhow!(
-            "`@import` entry found in the remote doc specified by `@import`"
-        )));
+        return Err(ErrorCode::InvalidContextEntry
+            .and_message("`@import` entry found in the remote doc specified by `@import`"));
     };
     // Step 5.6.8
     if import_context.is_empty() {
@@ -202,7 +207,7 @@ async fn process_ctxdef_import<'a, L: LoadRemoteDocument>(
 
 /// Processes `@base` entry of the context definition.
 fn process_ctxdef_base(
-    remote_contexts: &HashSet<IriString>,
+    remote_contexts: &RemoteContexts,
     result: &mut Context,
     context: &JsonMap<String, Value>,
 ) -> Result<()> {
@@ -237,7 +242,7 @@ fn process_ctxdef_base_impl(result: &Context, value: &Value) -> Result<Nullable<
                     return Ok(Nullable::Value(resolved.to_owned()));
                 } else {
                     // Step 5.7.5
-                    return Err(ErrorCode::InvalidBaseIri.and_source(anyhow!(
+                    return Err(ErrorCode::InvalidBaseIri.and_message(format!(
                         "Got a relative IRI reference {:?} as `@base`, \
                          but base IRI of `result` is not available",
                         value
@@ -245,13 +250,13 @@ fn process_ctxdef_base_impl(result: &Context, value: &Value) -> Result<Nullable<
                 }
             }
             // Step 5.7.5
-            Err(ErrorCode::InvalidBaseIri.and_source(anyhow!(
+            Err(ErrorCode::InvalidBaseIri.and_message(format!(
                 "Value of `@base` ({:?}) is not an IRI reference",
                 value
             )))
         }
         // Step 5.7.5
-        v => Err(ErrorCode::InvalidBaseIri.and_source(anyhow!(
+        v => Err(ErrorCode::InvalidBaseIri.and_message(format!(
             "Expected `null` or a string as `@base`, but got {:?}",
             v
         ))),
@@ -279,7 +284,7 @@ async fn process_ctxdef_vocab<L: LoadRemoteDocument>(
             // Step 5.8.3
             v => {
                 return Err(ErrorCode::InvalidVocabMapping
-                    .and_source(anyhow!("Expected string as `@vocab`, but got {:?}", v)))
+                    .and_message(format!("Expected string as `@vocab`, but got {:?}", v)))
             }
         };
         // Step 5.8.3
@@ -289,10 +294,10 @@ async fn process_ctxdef_vocab<L: LoadRemoteDocument>(
                 .document_relative(true)
                 .expand_str(processor, value)
                 .await?
-                .map(Cow::into_owned);
+                .map(String::from);
             result.set_vocab(expanded);
         } else {
-            return Err(ErrorCode::InvalidVocabMapping.and_source(anyhow!(
+            return Err(ErrorCode::InvalidVocabMapping.and_message(format!(
                 "Expected blank node identifier or an IRI, but got {:?}",
                 value
             )));
@@ -303,7 +308,11 @@ async fn process_ctxdef_vocab<L: LoadRemoteDocument>(
 }
 
 /// Processes `@language` entry of the context definition.
-fn process_ctxdef_language(result: &mut Context, context: &JsonMap<String, Value>) -> Result<()> {
+fn process_ctxdef_language<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    result: &mut Context,
+    context: &JsonMap<String, Value>,
+) -> Result<()> {
     // Step 5.9.
     if let Some(value) = context.get("@language") {
         // Step 5.9.1: Initialize _value_ to the value associated with the `@language` entry.
@@ -313,13 +322,15 @@ fn process_ctxdef_language(result: &mut Context, context: &JsonMap<String, Value
             Value::Null => result.set_default_language(None),
             // Step 5.9.3
             Value::String(value) => {
-                // TODO: Emit a warning if the value is not a well-formed language tag.
+                if !is_well_formed_language(value) {
+                    processor.warn(Warning::MalformedLanguageTag(value.to_owned()));
+                }
                 // NOTE: The spec says "Processors MAY normalize language tags to lower case".
                 result.set_default_language(Some(value.into()));
             }
             // Step 5.9.3
             v => {
-                return Err(ErrorCode::InvalidDefaultLanguage.and_source(anyhow!(
+                return Err(ErrorCode::InvalidDefaultLanguage.and_message(format!(
                     "Expected `null` or string as `@language`, but got {:?}",
                     v
                 )))
@@ -340,15 +351,14 @@ fn process_ctxdef_direction(
     if let Some(value) = context.get("@direction") {
         // Step 5.10.1
         if processor.is_processing_mode_1_0() {
-            return Err(ErrorCode::InvalidContextEntry.and_source(anyhow!(
-                "Found `@direction` while processing mode is `json-ld-1.0`"
-            )));
+            return Err(ErrorCode::InvalidContextEntry
+                .and_message("Found `@direction` while processing mode is `json-ld-1.0`"));
         }
         // Step 5.10.2: Initialize _value_ to the value associated with the `@direction` entry.
         // Step 5.10.3, 5.10.4
         let value: Nullable<Direction> = value
             .try_into()
-            .map_err(|e| ErrorCode::InvalidBaseDirection.and_source(e))?;
+            .map_err(|e| ErrorCode::InvalidBaseDirection.and_value_error(e))?;
         result.set_default_base_direction(value.into());
     }
 
@@ -364,16 +374,15 @@ fn process_ctxdef_propagate(
     context: &JsonMap<String, Value>,
 ) -> Result<()> {
     // Step 5.11.
-    if let Some(value) = context.get("@direction") {
+    if let Some(value) = context.get("@propagate") {
         // Step 5.11.1
         if processor.is_processing_mode_1_0() {
-            return Err(ErrorCode::InvalidContextEntry.and_source(anyhow!(
-                "Found `@propagate` while processing mode is `json-ld-1.0`"
-            )));
+            return Err(ErrorCode::InvalidContextEntry
+                .and_message("Found `@propagate` while processing mode is `json-ld-1.0`"));
         }
         // Step 5.11.2
         if !value.is_boolean() {
-            return Err(ErrorCode::InvalidPropagateValue.and_source(anyhow!(
+            return Err(ErrorCode::InvalidPropagateValue.and_message(format!(
                 "Expected boolean as `@propagate` but got {:?}",
                 value
             )));
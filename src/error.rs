@@ -2,7 +2,13 @@
 
 use std::fmt;
 
-use thiserror;
+use iri_string::validate::Error as IriError;
+
+use crate::{
+    context::{ContainerLoadError, DirectionLoadError},
+    json::{escape_pointer_token, locate_pointer},
+    remote::{IntegrityMismatch, MultipleContextLinkHeaders, SizeLimitExceeded},
+};
 
 /// JSON-LD processing result.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -52,6 +58,14 @@ pub enum ErrorCode {
     ///
     /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonlderrorcode-invalid-default-language>.
     InvalidDefaultLanguage,
+    /// Invalid `@embed` value.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#dom-jsonldframingerrorcode-invalid-@embed-value>.
+    InvalidEmbedValue,
+    /// Invalid frame.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#dom-jsonldframingerrorcode-invalid-frame>.
+    InvalidFrame,
     /// Invalid `@id` value.
     ///
     /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonlderrorcode-invalid-@id-value>.
@@ -208,6 +222,42 @@ pub enum ErrorCode {
     ///
     /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonlderrorcode-protected-term-redefinition>.
     ProtectedTermRedefinition,
+    /// Recursive context inclusion.
+    ///
+    /// This is specified in JSON-LD 1.0, and reported when remote contexts include each other
+    /// cyclically.
+    ///
+    /// See <https://www.w3.org/TR/2014/REC-json-ld-api-20140116/#idl-def-JsonLdErrorCode.recursive-context-inclusion>.
+    RecursiveContextInclusion,
+    /// Document too large (not specified in the spec).
+    ///
+    /// The loaded remote document exceeds the size limit or the node count limit.
+    DocumentTooLarge,
+    /// Memory budget exceeded (not specified in the spec).
+    ///
+    /// The values created during the operation exceed the memory budget.
+    MemoryBudgetExceeded,
+    /// Integrity check failed (not specified in the spec).
+    ///
+    /// The loaded remote document does not match the pinned hash.
+    IntegrityCheckFailed,
+    /// Nesting too deep (not specified in the spec).
+    ///
+    /// The input document exceeds the nesting depth limit.
+    NestingTooDeep,
+    /// Canonicalization limit exceeded (not specified in the spec).
+    ///
+    /// The RDF dataset needs more steps of the hash N-degree quads algorithm than allowed to be
+    /// canonicalized.
+    CanonicalizationLimitExceeded,
+    /// Safe mode violation (not specified in the spec).
+    ///
+    /// The `safe` flag is set, and some data is about to be dropped silently.
+    SafeModeViolation,
+    /// Cancelled (not specified in the spec).
+    ///
+    /// The cancellation token is cancelled, or the deadline has passed.
+    Cancelled,
     /// Uncategorized errors (not specified in the spec).
     ///
     /// This may include spec ambiguity and internal processor error.
@@ -228,6 +278,8 @@ impl ErrorCode {
             Self::InvalidContextEntry => "invalid context entry",
             Self::InvalidContextNullification => "invalid context nullification",
             Self::InvalidDefaultLanguage => "invalid default language",
+            Self::InvalidEmbedValue => "invalid @embed value",
+            Self::InvalidFrame => "invalid frame",
             Self::InvalidIdValue => "invalid @id value",
             Self::InvalidImportValue => "invalid @import value",
             Self::InvalidIncludedValue => "invalid @included value",
@@ -267,34 +319,76 @@ impl ErrorCode {
             Self::MultipleContextLinkHeaders => "multiple context link header",
             Self::ProcessingModeConflict => "processing mode conflict",
             Self::ProtectedTermRedefinition => "protected term redefinition",
+            Self::RecursiveContextInclusion => "recursive context inclusion",
+            Self::DocumentTooLarge => "document too large",
+            Self::MemoryBudgetExceeded => "memory budget exceeded",
+            Self::IntegrityCheckFailed => "integrity check failed",
+            Self::NestingTooDeep => "nesting too deep",
+            Self::CanonicalizationLimitExceeded => "canonicalization limit exceeded",
+            Self::SafeModeViolation => "safe mode violation",
+            Self::Cancelled => "cancelled",
             Self::Uncategorized => "uncategorized error",
         }
     }
 
-    /// Creates an `Error` from the error code and the given source error.
-    pub(crate) fn and_source<E>(self, source: E) -> Error
-    where
-        E: Into<anyhow::Error>,
-    {
-        Error {
+    /// Creates an `Error` from the error code and the given message.
+    pub(crate) fn and_message(self, message: impl Into<String>) -> Error {
+        Error::Processing {
             code: self,
-            source: Some(source.into()),
+            term: None,
+            message: Some(message.into()),
         }
     }
 
-    /*
-    /// Creates an `Error` from the error code and the given source error generator.
-    pub(crate) fn with_source<E, F>(self, f: F) -> Error
+    /// Creates an `Error` from the error code and the given IRI validation error.
+    pub(crate) fn and_iri_error(self, term: impl Into<String>, source: IriError) -> Error {
+        Error::InvalidIri {
+            code: self,
+            term: term.into(),
+            source,
+        }
+    }
+
+    /// Creates an `Error` from the error code and the given keyword value error.
+    pub(crate) fn and_value_error(self, source: impl Into<KeywordValueError>) -> Error {
+        Error::InvalidKeywordValue {
+            code: self,
+            source: source.into(),
+        }
+    }
+
+    /// Creates an `Error` from the error code and the given remote document loader error.
+    ///
+    /// If the loader error is caused by `SizeLimitExceeded`, `MultipleContextLinkHeaders`, or
+    /// `IntegrityMismatch`, the corresponding error code is used instead.
+    pub(crate) fn and_load_error<E>(self, iri: impl Into<String>, source: E) -> Error
     where
-        E: Into<anyhow::Error>,
-        F: FnOnce() -> E,
+        E: std::error::Error + Send + Sync + 'static,
     {
-        Error {
+        let code = if has_source::<SizeLimitExceeded>(&source) {
+            ErrorCode::DocumentTooLarge
+        } else if has_source::<MultipleContextLinkHeaders>(&source) {
+            ErrorCode::MultipleContextLinkHeaders
+        } else if has_source::<IntegrityMismatch>(&source) {
+            ErrorCode::IntegrityCheckFailed
+        } else {
+            self
+        };
+        Error::Load {
+            code,
+            iri: iri.into(),
+            source: Box::new(source),
+        }
+    }
+
+    /// Creates an `Error` from the error code and the given error which caused it.
+    pub(crate) fn and_cause(self, source: Error) -> Error {
+        Error::Nested {
             code: self,
-            source: Some(f().into()),
+            context: None,
+            source: Box::new(source),
         }
     }
-    */
 }
 
 impl fmt::Display for ErrorCode {
@@ -306,19 +400,212 @@ impl fmt::Display for ErrorCode {
 impl std::error::Error for ErrorCode {}
 
 /// JSON-LD processing error.
-#[derive(Debug, thiserror::Error)]
-pub struct Error {
-    /// Error code.
-    code: ErrorCode,
-    /// Details of the error (if available).
-    #[source]
-    source: Option<anyhow::Error>,
+///
+/// Every variant has the error code defined by the spec, which is available by
+/// [`Error::code()`](#method.code).
+///
+/// Variants may be added in future releases, so matching on this should have a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error detected by the processing algorithms.
+    Processing {
+        /// Error code.
+        code: ErrorCode,
+        /// Term or IRI which caused the error (if available).
+        term: Option<String>,
+        /// Details of the error (if available).
+        message: Option<String>,
+    },
+    /// A string which is expected to be an IRI or an IRI reference is invalid.
+    InvalidIri {
+        /// Error code.
+        code: ErrorCode,
+        /// The invalid string.
+        term: String,
+        /// Validation error.
+        source: IriError,
+    },
+    /// The value of a keyword (such as `@container` or `@direction`) is invalid.
+    InvalidKeywordValue {
+        /// Error code.
+        code: ErrorCode,
+        /// Error on loading the value.
+        source: KeywordValueError,
+    },
+    /// Loading a remote document failed.
+    Load {
+        /// Error code.
+        code: ErrorCode,
+        /// IRI of the remote document.
+        iri: String,
+        /// Error returned by the remote document loader.
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+    /// Error caused by another JSON-LD processing error.
+    Nested {
+        /// Error code.
+        code: ErrorCode,
+        /// Additional context (if available).
+        context: Option<String>,
+        /// The error which caused this error.
+        source: Box<Error>,
+    },
+    /// Error at the specific location of the input document.
+    AtPath {
+        /// JSON pointer to the location.
+        ///
+        /// See <https://tools.ietf.org/html/rfc6901>.
+        path: String,
+        /// Position of the location in the source text (if available).
+        position: Option<Position>,
+        /// The error.
+        source: Box<Error>,
+    },
 }
 
 impl Error {
     /// Returns the error code.
     pub fn code(&self) -> ErrorCode {
-        self.code
+        match self {
+            Error::Processing { code, .. }
+            | Error::InvalidIri { code, .. }
+            | Error::InvalidKeywordValue { code, .. }
+            | Error::Load { code, .. }
+            | Error::Nested { code, .. } => *code,
+            Error::AtPath { source, .. } => source.code(),
+        }
+    }
+
+    /// Returns the term or IRI which caused the error, if available.
+    pub fn term(&self) -> Option<&str> {
+        match self {
+            Error::Processing { term, .. } => term.as_ref().map(String::as_str),
+            Error::InvalidIri { term, .. } => Some(term),
+            Error::InvalidKeywordValue { .. } => None,
+            Error::Load { iri, .. } => Some(iri),
+            Error::Nested { source, .. } | Error::AtPath { source, .. } => source.term(),
+        }
+    }
+
+    /// Returns the JSON pointer to the location in the input document where the error occurred,
+    /// if available.
+    ///
+    /// For example, `/@graph/3/author/@id` is returned when the `@id` entry of the `author`
+    /// entry of the fourth item of the top-level `@graph` entry is invalid.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::AtPath { path, .. } => Some(path),
+            Error::Nested { source, .. } => source.path(),
+            _ => None,
+        }
+    }
+
+    /// Returns the position in the source text where the error occurred, if available.
+    ///
+    /// This is available only when the error is located by [`locate()`](#method.locate).
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Error::AtPath { position, .. } => *position,
+            Error::Nested { source, .. } => source.position(),
+            _ => None,
+        }
+    }
+
+    /// Locates the error in the given JSON source text of the input document.
+    ///
+    /// If the error has the path and it is found in the source text, the position of the value
+    /// is available by [`position()`](#method.position) after this.
+    pub fn locate(self, source_text: &str) -> Self {
+        match self {
+            Error::AtPath { path, source, .. } => Error::AtPath {
+                position: locate_pointer(source_text, &path),
+                path,
+                source,
+            },
+            Error::Nested {
+                code,
+                context,
+                source,
+            } => Error::Nested {
+                code,
+                context,
+                source: Box::new(source.locate(source_text)),
+            },
+            err => err,
+        }
+    }
+
+    /// Prepends the map key to the location of the error.
+    pub(crate) fn at_key(self, key: &str) -> Self {
+        self.at_segment(&escape_pointer_token(key))
+    }
+
+    /// Prepends the array index to the location of the error.
+    pub(crate) fn at_index(self, index: usize) -> Self {
+        self.at_segment(&index.to_string())
+    }
+
+    /// Prepends the escaped reference token to the location of the error.
+    ///
+    /// If a nested error already has the location, the token is prepended to it, so that
+    /// [`path()`](#method.path) returns the full path.
+    fn at_segment(self, segment: &str) -> Self {
+        match self {
+            Error::AtPath { path, source, .. } => Error::AtPath {
+                path: format!("/{}{}", segment, path),
+                position: None,
+                source,
+            },
+            Error::Nested {
+                code,
+                context,
+                source,
+            } if source.path().is_some() => Error::Nested {
+                code,
+                context,
+                source: Box::new(source.at_segment(segment)),
+            },
+            err => Error::AtPath {
+                path: format!("/{}", segment),
+                position: None,
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// Sets the term which caused the error, if the error does not have one yet.
+    pub(crate) fn with_term(self, term: &str) -> Self {
+        match self {
+            Error::Processing {
+                code,
+                term: None,
+                message,
+            } => Error::Processing {
+                code,
+                term: Some(term.to_owned()),
+                message,
+            },
+            Error::Nested {
+                code,
+                context,
+                source,
+            } => Error::Nested {
+                code,
+                context,
+                source: Box::new(source.with_term(term)),
+            },
+            Error::AtPath {
+                path,
+                position,
+                source,
+            } => Error::AtPath {
+                path,
+                position,
+                source: Box::new(source.with_term(term)),
+            },
+            err => err,
+        }
     }
 
     /// Wraps the error with the additional context.
@@ -326,31 +613,129 @@ impl Error {
     where
         C: fmt::Display + Send + Sync + 'static,
     {
-        let source = match self.source {
-            Some(source) => source.context(context),
-            None => anyhow::anyhow!("{}", context),
-        };
-
-        Self {
-            code: self.code,
-            source: Some(source),
+        Error::Nested {
+            code: self.code(),
+            context: Some(context.to_string()),
+            source: Box::new(self),
         }
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(self.code.message())?;
-        if let Some(source) = self.source.as_ref() {
-            write!(f, ": {}", source)?;
+        if let Error::Nested {
+            code,
+            context: Some(context),
+            source,
+        } = self
+        {
+            if *code == source.code() {
+                // Avoid printing the same error code twice.
+                return write!(f, "{}: {}", context, source);
+            }
+        }
+        if let Error::AtPath {
+            path,
+            position,
+            source,
+        } = self
+        {
+            return match position {
+                Some(position) => write!(f, "{} (at {:?}, {})", source, path, position),
+                None => write!(f, "{} (at {:?})", source, path),
+            };
+        }
+        f.write_str(self.code().message())?;
+        match self {
+            Error::Processing { term, message, .. } => {
+                if let Some(term) = term {
+                    write!(f, " (term {:?})", term)?;
+                }
+                if let Some(message) = message {
+                    write!(f, ": {}", message)?;
+                }
+            }
+            Error::InvalidIri { term, source, .. } => write!(f, ": {:?}: {}", term, source)?,
+            Error::InvalidKeywordValue { source, .. } => write!(f, ": {}", source)?,
+            Error::Load { iri, source, .. } => write!(f, ": <{}>: {}", iri, source)?,
+            Error::Nested {
+                context, source, ..
+            } => {
+                if let Some(context) = context {
+                    write!(f, ": {}", context)?;
+                }
+                write!(f, ": {}", source)?;
+            }
+            // Already handled above.
+            Error::AtPath { .. } => {}
         }
         Ok(())
     }
 }
 
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Processing { .. } => None,
+            Error::InvalidIri { source, .. } => Some(source),
+            Error::InvalidKeywordValue { source, .. } => Some(source),
+            Error::Load { source, .. } => Some(&**source),
+            Error::Nested { source, .. } | Error::AtPath { source, .. } => Some(&**source),
+        }
+    }
+}
+
 impl From<ErrorCode> for Error {
     fn from(code: ErrorCode) -> Self {
-        Self { code, source: None }
+        Error::Processing {
+            code,
+            term: None,
+            message: None,
+        }
+    }
+}
+
+/// Error on loading the value of a keyword.
+#[derive(Debug, Clone, thiserror::Error)]
+#[non_exhaustive]
+pub enum KeywordValueError {
+    /// Invalid `@container` value.
+    #[error(transparent)]
+    Container(#[from] ContainerLoadError),
+    /// Invalid `@direction` value.
+    #[error(transparent)]
+    Direction(#[from] DirectionLoadError),
+}
+
+/// Position in a source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// Line number (1-based).
+    line: usize,
+    /// Column number in characters (1-based).
+    column: usize,
+}
+
+impl Position {
+    /// Creates a new `Position`.
+    pub(crate) fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// Returns the line number (1-based).
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the column number in characters (1-based).
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
     }
 }
 
@@ -383,3 +768,58 @@ impl<T> ResultExt<T> for Result<T> {
         self.map_err(|err| err.context(f()))
     }
 }
+
+/// Checks whether the error is or is caused by an error of the type `T`.
+fn has_source<T: std::error::Error + 'static>(e: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(e);
+    while let Some(e) = current {
+        if e.is::<T>() {
+            return true;
+        }
+        current = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path() {
+        let err = ErrorCode::InvalidIriMapping
+            .and_message("test")
+            .at_key("@id")
+            .at_key("a/b~c")
+            .at_index(3)
+            .at_key("@graph");
+        assert_eq!(err.code(), ErrorCode::InvalidIriMapping);
+        assert_eq!(err.path(), Some("/@graph/3/a~1b~0c/@id"));
+        assert_eq!(Error::from(ErrorCode::InvalidIriMapping).path(), None);
+    }
+
+    #[test]
+    fn nested_path() {
+        let err = ErrorCode::InvalidIriMapping
+            .and_message("test")
+            .at_key("@id")
+            .context("Failed to expand the node")
+            .at_index(0)
+            .at_key("@graph");
+        assert_eq!(err.path(), Some("/@graph/0/@id"));
+        let err = Error::from(ErrorCode::InvalidIriMapping)
+            .context("Failed to expand the node")
+            .at_key("@id");
+        assert_eq!(err.path(), Some("/@id"));
+    }
+
+    #[test]
+    fn load_error_code() {
+        let err = ErrorCode::LoadingDocumentFailed
+            .and_load_error("https://example.com/", MultipleContextLinkHeaders);
+        assert_eq!(err.code(), ErrorCode::MultipleContextLinkHeaders);
+        let err = ErrorCode::LoadingRemoteContextFailed
+            .and_load_error("https://example.com/", SizeLimitExceeded::new(42));
+        assert_eq!(err.code(), ErrorCode::DocumentTooLarge);
+    }
+}
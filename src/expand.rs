@@ -2,4 +2,672 @@
 //!
 //! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithms>.
 
+use std::{borrow::Cow, future::Future, pin::Pin, slice};
+
+use iri_string::types::IriStr;
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::{definition::ContainerItem, Context, Definition, ValueWithBase},
+    error::{ErrorCode, Result},
+    intern::Symbol,
+    iri::is_absolute_iri_ref,
+    join::try_join_buffered,
+    json::{single_entry_map, to_ref_array},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+#[cfg(feature = "futures-io")]
+pub use self::ndjson::NdjsonExpansion;
+#[cfg(feature = "blocking")]
+pub(crate) use self::ndjson::{expand_line, read_error};
+#[cfg(feature = "futures-io")]
+pub(crate) use self::stream::expand_reader_events;
+pub use self::{report::DroppedTerm, stream::ExpansionEvent};
+pub(crate) use self::{
+    report::DroppedTermCollector,
+    stream::{expand_events, expand_to_writer, write_error},
+};
+
+use self::{entries::expand_entries, iri::ExpandIriOptions, value::expand_value};
+
+mod entries;
 pub(crate) mod iri;
+#[cfg(any(feature = "blocking", feature = "futures-io"))]
+mod ndjson;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod report;
+mod stream;
+mod value;
+
+/// Options for expansion algorithm.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ExpandOptions {
+    /// "From map" flag.
+    from_map: bool,
+    /// "Frame expansion" flag.
+    frame_expansion: bool,
+    /// Nesting depth of the element being expanded.
+    depth: usize,
+}
+
+impl ExpandOptions {
+    /// Creates a new default `ExpandOptions`.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the "from map" flag.
+    pub(crate) fn from_map(self, from_map: bool) -> Self {
+        Self { from_map, ..self }
+    }
+
+    /// Sets the "frame expansion" flag.
+    pub(crate) fn frame_expansion(self, frame_expansion: bool) -> Self {
+        Self {
+            frame_expansion,
+            ..self
+        }
+    }
+
+    /// Enters a nested element, and checks the nesting depth does not exceed `max`.
+    pub(crate) fn nested(self, max: usize) -> Result<Self> {
+        let depth = self.depth + 1;
+        if depth > max {
+            return Err(ErrorCode::NestingTooDeep.and_message(format!(
+                "Nesting depth of the document exceeds the limit {}",
+                max
+            )));
+        }
+        Ok(Self { depth, ..self })
+    }
+}
+
+/// Runs expansion algorithm for the given document, and returns the expanded document.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+pub(crate) async fn expand<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+) -> Result<Value> {
+    expand_with_options(processor, input, ExpandOptions::new()).await
+}
+
+/// Runs expansion algorithm for the given document with the given options, and returns the
+/// expanded document.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+pub(crate) async fn expand_with_options<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    options: ExpandOptions,
+) -> Result<Value> {
+    expand_with_context_url(processor, input, None, options).await
+}
+
+/// Runs expansion algorithm for the given remote document, applying the context URL given by the
+/// remote document.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+pub(crate) async fn expand_with_context_url<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    context_url: Option<&str>,
+    options: ExpandOptions,
+) -> Result<Value> {
+    let active_context = initial_context(processor, context_url, options).await?;
+    expand_with_active_context(processor, input, &active_context, options).await
+}
+
+/// Creates the initial active context for expansion.
+///
+/// `expandContext` option and the context URL given by the remote document are applied.
+pub(crate) async fn initial_context<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    context_url: Option<&str>,
+    options: ExpandOptions,
+) -> Result<Context> {
+    let base_url = processor.options().document_iri();
+    let mut active_context = Context::with_base(base_url.to_owned());
+    // Frames are expanded without `expandContext`.
+    if !options.frame_expansion {
+        if let Some(expand_context) = processor.options().expand_context() {
+            let local_context = expand_context.get("@context").unwrap_or(expand_context);
+            active_context = active_context
+                .join_context_value(processor, local_context, base_url, false)
+                .await?;
+        }
+    }
+    // > If _remote document_ has a `contextUrl`, update _active context_ using the Context
+    // > Processing algorithm, passing _active context_, _remote document_'s `contextUrl` as
+    // > _local context_, and _remote document_'s `documentUrl` as _base URL_.
+    if let Some(context_url) = context_url {
+        let local_context = Value::String(context_url.to_owned());
+        active_context = active_context
+            .join_context_value(processor, &local_context, base_url, false)
+            .await?;
+    }
+
+    Ok(active_context)
+}
+
+/// Runs expansion algorithm for the given document, using the given context as the initial active
+/// context.
+///
+/// `expandContext` option is not applied.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+pub(crate) async fn expand_with_active_context<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    active_context: &Context,
+    options: ExpandOptions,
+) -> Result<Value> {
+    let base_url = processor.options().document_iri();
+    let concurrency = processor.options().load_concurrency();
+    let expanded = match input {
+        #[cfg(feature = "parallel")]
+        Value::Array(items) if processor.is_parallel_expansion() && items.len() > 1 => {
+            // Step 5.2.1
+            let expanded =
+                parallel::expand_items_in_parallel(processor, active_context, items, options)
+                    .await?;
+            concat_expanded_items(expanded)
+        }
+        Value::Array(items) if concurrency > 1 => {
+            expand_top_level_array(processor, active_context, items, base_url, options).await?
+        }
+        input => expand_element(processor, active_context, None, input, base_url, options).await?,
+    };
+
+    // If expanded output is a map that contains only an `@graph` entry, set expanded output that
+    // value.
+    let expanded = match expanded {
+        Value::Object(mut map) if map.len() == 1 && map.contains_key("@graph") => map
+            .remove("@graph")
+            .expect("Should never fail: the map has an `@graph` entry"),
+        v => v,
+    };
+    // If expanded output is `null`, set it to an empty array.
+    // If expanded output is not an array, set it to an array containing only expanded output.
+    Ok(match expanded {
+        Value::Null => Value::Array(Vec::new()),
+        v @ Value::Array(_) => v,
+        v => Value::Array(vec![v]),
+    })
+}
+
+/// Runs expansion algorithm for the top-level array, expanding the items concurrently.
+///
+/// This is equivalent to step 5 of the expansion algorithm without active property, and up to
+/// the configured number of items are in flight at a time, so that their remote contexts are
+/// loaded concurrently.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+async fn expand_top_level_array<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    items: &[Value],
+    base_url: &IriStr,
+    options: ExpandOptions,
+) -> Result<Value> {
+    let concurrency = processor.options().load_concurrency();
+    // Step 5.2.1
+    let expansions = ItemExpansions {
+        processor,
+        active_context,
+        items: items.iter(),
+        base_url,
+        options,
+    };
+    let expanded = try_join_buffered(expansions, concurrency)
+        .await
+        .map_err(|(index, e)| e.at_index(index))?;
+    Ok(concat_expanded_items(expanded))
+}
+
+/// Iterator over the expansions of the items of the top-level array.
+///
+/// This is a named type rather than `Map` with a closure, since a closure taking a reference is
+/// generic over its lifetime, and the futures holding it would not be `Send`.
+struct ItemExpansions<'a, L> {
+    /// Processor.
+    processor: &'a Processor<L>,
+    /// Active context.
+    active_context: &'a Context,
+    /// Items not yet started.
+    items: slice::Iter<'a, Value>,
+    /// Base URL.
+    base_url: &'a IriStr,
+    /// Expansion options.
+    options: ExpandOptions,
+}
+
+impl<'a, L: LoadRemoteDocument> Iterator for ItemExpansions<'a, L> {
+    type Item = Pin<Box<dyn Future<Output = Result<Value>> + 'a + Send>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.items.next()?;
+        Some(expand_element(
+            self.processor,
+            self.active_context,
+            None,
+            item,
+            self.base_url,
+            self.options,
+        ))
+    }
+}
+
+/// Concatenates the expanded items of the top-level array.
+///
+/// This is step 5.1 to 5.3 of the expansion algorithm without active property, after each item
+/// is expanded.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+fn concat_expanded_items(expanded: Vec<Value>) -> Value {
+    // Step 5.1
+    let mut result = Vec::new();
+    // Step 5.2
+    for expanded in expanded {
+        // Step 5.2.3
+        match expanded {
+            Value::Array(expanded) => result.extend(expanded),
+            Value::Null => {}
+            expanded => result.push(expanded),
+        }
+    }
+    // Step 5.3
+    Value::Array(result)
+}
+
+/// Runs expansion algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+///
+/// This is a wrapper for recursive call.
+pub(crate) fn expand_element<'a, L: LoadRemoteDocument>(
+    processor: &'a Processor<L>,
+    active_context: &'a Context,
+    active_property: Option<&'a str>,
+    element: &'a Value,
+    base_url: &'a IriStr,
+    options: ExpandOptions,
+) -> Pin<Box<dyn Future<Output = Result<Value>> + 'a + Send>> {
+    instrument!(
+        Box::pin(async move {
+            let expanded = expand_element_impl(
+                processor,
+                active_context,
+                active_property,
+                element,
+                base_url,
+                options,
+            )
+            .await?;
+            processor.charge_memory(&expanded)?;
+            Ok(expanded)
+        }),
+        "expand_element",
+        active_property = ?active_property
+    )
+}
+
+/// Runs expansion algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+async fn expand_element_impl<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    active_property: Option<&str>,
+    element: &Value,
+    base_url: &IriStr,
+    options: ExpandOptions,
+) -> Result<Value> {
+    processor.options().check_cancelled()?;
+    // Only arrays and maps are nesting levels.
+    let options = if element.is_array() || element.is_object() {
+        options.nested(processor.options().max_nesting_depth())?
+    } else {
+        options
+    };
+    // Step 1
+    if element.is_null() {
+        return Ok(Value::Null);
+    }
+    // Step 3
+    let active_property_def = active_property.and_then(|prop| active_context.term_definition(prop));
+    let property_scoped_context = active_property_def.and_then(Definition::local_context);
+
+    match element {
+        // Step 5
+        Value::Array(items) => {
+            let is_list_container = active_property_def
+                .map_or(false, |def| def.container_contains(ContainerItem::List));
+            // Step 5.1
+            let mut result = Vec::new();
+            // Step 5.2
+            for (index, item) in items.iter().enumerate() {
+                // Step 5.2.1
+                let expanded = expand_element(
+                    processor,
+                    active_context,
+                    active_property,
+                    item,
+                    base_url,
+                    options,
+                )
+                .await
+                .map_err(|e| e.at_index(index))?;
+                // Step 5.2.2
+                let expanded = if is_list_container && expanded.is_array() {
+                    Value::Object(single_entry_map("@list", expanded))
+                } else {
+                    expanded
+                };
+                // Step 5.2.3
+                match expanded {
+                    Value::Array(expanded) => result.extend(expanded),
+                    Value::Null => {}
+                    expanded => result.push(expanded),
+                }
+            }
+            // Step 5.3
+            Ok(Value::Array(result))
+        }
+        // Step 6-
+        Value::Object(element) => {
+            expand_map(
+                processor,
+                active_context,
+                active_property,
+                element,
+                base_url,
+                options,
+                property_scoped_context,
+            )
+            .await
+        }
+        // Step 4
+        scalar => {
+            // Step 4.1
+            let active_property = match active_property {
+                None | Some("@graph") => return Ok(Value::Null),
+                Some(v) => v,
+            };
+            // Step 4.2
+            let active_context = match property_scoped_context {
+                Some(local_context) => Cow::Owned(
+                    active_context
+                        .join_local_context(processor, local_context, false, true)
+                        .await?,
+                ),
+                None => Cow::Borrowed(active_context),
+            };
+            // Step 4.3
+            expand_value(processor, &active_context, active_property, scalar).await
+        }
+    }
+}
+
+/// Runs expansion algorithm for a map.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+// Step 6-
+async fn expand_map<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    active_property: Option<&str>,
+    element: &JsonMap<String, Value>,
+    base_url: &IriStr,
+    options: ExpandOptions,
+    property_scoped_context: Option<ValueWithBase<'_, &Value>>,
+) -> Result<Value> {
+    // Step 7
+    let active_context = match active_context.previous_context() {
+        Some(previous_context) if !options.from_map => {
+            if has_value_or_only_id(processor, active_context, element).await? {
+                active_context
+            } else {
+                previous_context
+            }
+        }
+        _ => active_context,
+    };
+    // Step 8
+    let active_context = match property_scoped_context {
+        Some(local_context) => Cow::Owned(
+            active_context
+                .join_local_context(processor, local_context, true, true)
+                .await?,
+        ),
+        None => Cow::Borrowed(active_context),
+    };
+    // Step 9
+    let active_context = match element.get("@context") {
+        Some(local_context) => Cow::Owned(
+            active_context
+                .join_local_context(
+                    processor,
+                    ValueWithBase::new(local_context, base_url),
+                    false,
+                    true,
+                )
+                .await
+                .map_err(|e| e.at_key("@context"))?,
+        ),
+        None => active_context,
+    };
+    // Step 10
+    let type_scoped_context: &Context = &active_context;
+    // Step 11
+    let mut type_entries = Vec::new();
+    for (key, value) in element {
+        if expand_iri_vocab(processor, type_scoped_context, key)
+            .await?
+            .as_ref()
+            .map(|s| &**s)
+            == Some("@type")
+        {
+            type_entries.push((key.as_str(), value));
+        }
+    }
+    type_entries.sort_by_key(|(key, _)| *key);
+    let mut active_context: Cow<'_, Context> = Cow::Borrowed(type_scoped_context);
+    for (_, value) in &type_entries {
+        // Step 11.1, 11.2
+        let mut terms = to_ref_array(value)
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>();
+        terms.sort();
+        for term in terms {
+            if let Some(local_context) = type_scoped_context
+                .term_definition(term)
+                .and_then(Definition::local_context)
+            {
+                active_context = Cow::Owned(
+                    active_context
+                        .join_local_context(processor, local_context, false, false)
+                        .await?,
+                );
+            }
+        }
+    }
+    // The "from map" flag only affects step 7 of this call.
+    let options = options.from_map(false);
+    // Step 12
+    let mut result = JsonMap::new();
+    let input_type = match type_entries
+        .first()
+        .and_then(|(_, value)| to_ref_array(value).last())
+        .and_then(Value::as_str)
+    {
+        Some(ty) => expand_iri_vocab(processor, &active_context, ty)
+            .await?
+            .map(String::from),
+        None => None,
+    };
+    // Step 13, 14
+    expand_entries(
+        processor,
+        &active_context,
+        type_scoped_context,
+        active_property,
+        element,
+        base_url,
+        input_type.as_ref().map(String::as_str),
+        options,
+        &mut result,
+    )
+    .await?;
+
+    // Step 15-20
+    finalize_map(active_property, result, options)
+}
+
+/// Checks whether the map contains an entry expanding to `@value`, or consists of a single entry
+/// expanding to `@id`.
+async fn has_value_or_only_id<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    element: &JsonMap<String, Value>,
+) -> Result<bool> {
+    for key in element.keys() {
+        match expand_iri_vocab(processor, active_context, key)
+            .await?
+            .as_ref()
+            .map(|s| &**s)
+        {
+            Some("@value") => return Ok(true),
+            Some("@id") if element.len() == 1 => return Ok(true),
+            _ => {}
+        }
+    }
+
+    Ok(false)
+}
+
+/// Runs the rest of expansion algorithm for a map, after entries are expanded.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+// Step 15-20
+fn finalize_map(
+    active_property: Option<&str>,
+    mut result: JsonMap<String, Value>,
+    options: ExpandOptions,
+) -> Result<Value> {
+    if let Some(value) = result.get("@value") {
+        // Step 15
+        // Step 15.1
+        if let Some(key) = result.keys().find(|key| match key.as_str() {
+            "@direction" | "@index" | "@language" | "@type" | "@value" => false,
+            _ => true,
+        }) {
+            return Err(ErrorCode::InvalidValueObject
+                .and_message(format!("Unexpected entry {:?} in the value object", key)));
+        }
+        if result.contains_key("@type")
+            && (result.contains_key("@language") || result.contains_key("@direction"))
+        {
+            return Err(ErrorCode::InvalidValueObject.and_message(
+                "The value object has both `@type` and `@language` (or `@direction`)",
+            ));
+        }
+        // Frames may have value patterns, which are not valid value objects.
+        if options.frame_expansion {
+            return Ok(Value::Object(result));
+        }
+        // Step 15.2: The `@value` entry of a JSON literal may contain any value.
+        if result.get("@type").and_then(Value::as_str) != Some("@json") {
+            // Step 15.3
+            if value.is_null() || value.as_array().map_or(false, Vec::is_empty) {
+                return Ok(Value::Null);
+            }
+            // Step 15.4
+            if !value.is_string() && result.contains_key("@language") {
+                return Err(ErrorCode::InvalidLanguageTaggedValue.and_message(format!(
+                    "Expected string as language-tagged `@value`, but got {:?}",
+                    value
+                )));
+            }
+            // Step 15.5
+            if let Some(ty) = result.get("@type") {
+                if !ty.as_str().map_or(false, is_absolute_iri_ref) {
+                    return Err(ErrorCode::InvalidTypedValue
+                        .and_message(format!("Expected an IRI as `@type`, but got {:?}", ty)));
+                }
+            }
+        }
+    } else if let Some(ty) = result.get_mut("@type") {
+        // Step 16
+        if !ty.is_array() {
+            let ty_value = std::mem::replace(ty, Value::Null);
+            *ty = Value::Array(vec![ty_value]);
+        }
+    } else if result.contains_key("@set") || result.contains_key("@list") {
+        // Step 17
+        // Step 17.1
+        let allowed_len = if result.contains_key("@index") { 2 } else { 1 };
+        if result.len() > allowed_len {
+            return Err(ErrorCode::InvalidSetOrListObject.and_message(format!(
+                "Unexpected entries in set or list object: keys={:?}",
+                result.keys().collect::<Vec<_>>()
+            )));
+        }
+        // Step 17.2
+        if let Some(set) = result.remove("@set") {
+            if options.frame_expansion {
+                return Ok(set);
+            }
+            return Ok(drop_free_floating(active_property, set));
+        }
+    }
+
+    // Step 18
+    if result.len() == 1 && result.contains_key("@language") {
+        return Ok(Value::Null);
+    }
+
+    // Step 19, 20
+    // Frames may consist of empty maps and patterns, which should not be dropped.
+    if options.frame_expansion {
+        return Ok(Value::Object(result));
+    }
+    Ok(drop_free_floating(active_property, Value::Object(result)))
+}
+
+/// Drops free-floating values.
+// Step 19
+fn drop_free_floating(active_property: Option<&str>, result: Value) -> Value {
+    match active_property {
+        None | Some("@graph") => {}
+        _ => return result,
+    }
+    if let Value::Object(map) = &result {
+        // Step 19.1
+        if map.is_empty() || map.contains_key("@value") || map.contains_key("@list") {
+            return Value::Null;
+        }
+        // Step 19.2
+        if map.len() == 1 && map.contains_key("@id") {
+            return Value::Null;
+        }
+    }
+
+    result
+}
+
+/// Runs IRI expansion with `vocab` flag set.
+async fn expand_iri_vocab<'a, L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &'a Context,
+    value: &'a str,
+) -> Result<Option<Symbol<'a>>> {
+    ExpandIriOptions::constant(active_context)
+        .vocab(true)
+        .expand_str(processor, value)
+        .await
+}
@@ -0,0 +1,228 @@
+//! Expansion of map entries.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+
+use std::{future::Future, pin::Pin};
+
+use iri_string::types::IriStr;
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::Context,
+    error::{Error, ErrorCode, Result},
+    expand::{expand_iri_vocab, ExpandOptions},
+    json::{map_entries, to_ref_array},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+    syntax::is_framing_keyword,
+};
+
+use self::{
+    keyword::{expand_framing_keyword_entry, expand_keyword_entry},
+    property::expand_property_entry,
+};
+
+mod keyword;
+mod property;
+
+/// Expands the entries of the given map into `result`.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+///
+/// This is a wrapper for recursive call.
+// Step 13, 14
+#[allow(clippy::too_many_arguments)] // TODO: FIXME
+pub(crate) fn expand_entries<'a, L: LoadRemoteDocument>(
+    processor: &'a Processor<L>,
+    active_context: &'a Context,
+    type_scoped_context: &'a Context,
+    active_property: Option<&'a str>,
+    element: &'a JsonMap<String, Value>,
+    base_url: &'a IriStr,
+    input_type: Option<&'a str>,
+    options: ExpandOptions,
+    result: &'a mut JsonMap<String, Value>,
+) -> Pin<Box<dyn Future<Output = Result<()>> + 'a + Send>> {
+    Box::pin(async move {
+        expand_entries_impl(
+            processor,
+            active_context,
+            type_scoped_context,
+            active_property,
+            element,
+            base_url,
+            input_type,
+            options,
+            result,
+        )
+        .await
+    })
+}
+
+/// Expands the entries of the given map into `result`.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+// Step 13, 14
+#[allow(clippy::too_many_arguments)] // TODO: FIXME
+async fn expand_entries_impl<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    type_scoped_context: &Context,
+    active_property: Option<&str>,
+    element: &JsonMap<String, Value>,
+    base_url: &IriStr,
+    input_type: Option<&str>,
+    options: ExpandOptions,
+    result: &mut JsonMap<String, Value>,
+) -> Result<()> {
+    // Step 12: Nesting keys.
+    let mut nests = Vec::new();
+    // Step 13
+    for (key, value) in map_entries(element, processor.is_ordered()) {
+        // Step 13.1
+        if key == "@context" {
+            continue;
+        }
+        // Framing keywords are only allowed in frames.
+        if options.frame_expansion && is_framing_keyword(key) {
+            expand_framing_keyword_entry(
+                processor,
+                active_context,
+                active_property,
+                key,
+                value,
+                base_url,
+                options,
+                result,
+            )
+            .await
+            .map_err(|e| e.at_key(key))?;
+            continue;
+        }
+        // Step 13.2
+        let expanded_property = match expand_iri_vocab(processor, active_context, key)
+            .await
+            .map_err(|e| e.at_key(key))?
+        {
+            Some(v) => v,
+            None => {
+                if processor.is_safe() {
+                    return Err(ErrorCode::SafeModeViolation
+                        .and_message(format!("Property {:?} is dropped: expanded to null", key))
+                        .at_key(key));
+                }
+                processor.report_dropped_term(element, key, active_context);
+                continue;
+            }
+        };
+        // Step 13.3
+        let is_keyword = processor.is_keyword(&expanded_property);
+        if !is_keyword && !expanded_property.contains(':') {
+            if processor.is_safe() {
+                return Err(ErrorCode::SafeModeViolation
+                    .and_message(format!(
+                        "Property {:?} is dropped: not expanded to an IRI or a keyword",
+                        key
+                    ))
+                    .at_key(key));
+            }
+            processor.report_dropped_term(element, key, active_context);
+            continue;
+        }
+        if is_keyword {
+            // Step 13.4
+            expand_keyword_entry(
+                processor,
+                active_context,
+                type_scoped_context,
+                active_property,
+                key,
+                &expanded_property,
+                value,
+                base_url,
+                input_type,
+                options,
+                result,
+                &mut nests,
+            )
+            .await
+            .map_err(|e| e.at_key(key))?;
+        } else {
+            // Step 13.5-13.14
+            expand_property_entry(
+                processor,
+                active_context,
+                key,
+                &expanded_property,
+                value,
+                base_url,
+                options,
+                result,
+            )
+            .await
+            .map_err(|e| e.at_key(key))?;
+        }
+    }
+
+    // Step 14
+    for nesting_key in nests {
+        // Step 14.1
+        let nested_values = to_ref_array(&element[nesting_key]);
+        let locate = |e: Error, index: usize| {
+            let e = if element[nesting_key].is_array() {
+                e.at_index(index)
+            } else {
+                e
+            };
+            e.at_key(nesting_key)
+        };
+        // Step 14.2
+        for (index, nested_value) in nested_values.iter().enumerate() {
+            // Step 14.2.1
+            let nested_value = match nested_value {
+                Value::Object(map) => map,
+                v => {
+                    return Err(locate(
+                        ErrorCode::InvalidNestValue.and_message(format!(
+                            "Expected a map as nested value, but got {:?}",
+                            v
+                        )),
+                        index,
+                    ))
+                }
+            };
+            for nested_key in nested_value.keys() {
+                if expand_iri_vocab(processor, active_context, nested_key)
+                    .await?
+                    .as_ref()
+                    .map(|s| &**s)
+                    == Some("@value")
+                {
+                    return Err(locate(
+                        ErrorCode::InvalidNestValue.and_message(format!(
+                            "Nested value has a key {:?} expanding to `@value`",
+                            nested_key
+                        )),
+                        index,
+                    ));
+                }
+            }
+            // Step 14.2.2
+            expand_entries(
+                processor,
+                active_context,
+                type_scoped_context,
+                active_property,
+                nested_value,
+                base_url,
+                input_type,
+                options.nested(processor.options().max_nesting_depth())?,
+                result,
+            )
+            .await
+            .map_err(|e| locate(e, index))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,487 @@
+//! Expansion of entries with keyword keys.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+
+use iri_string::types::IriStr;
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::{definition::Direction, Context},
+    error::{ErrorCode, Result},
+    expand::{expand_element, iri::ExpandIriOptions, ExpandOptions},
+    json::{add_value, into_array, into_nonnull_array, single_entry_map},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+    syntax::{is_list_object, is_node_object, is_value_object},
+};
+
+/// Expands an entry whose key expands to a keyword, and stores the result to `result`.
+///
+/// Nesting keys are not processed here, but pushed to `nests`.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+// Step 13.4
+#[allow(clippy::too_many_arguments)] // TODO: FIXME
+pub(super) async fn expand_keyword_entry<'a, L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    type_scoped_context: &Context,
+    active_property: Option<&str>,
+    key: &'a str,
+    expanded_property: &str,
+    value: &Value,
+    base_url: &IriStr,
+    input_type: Option<&str>,
+    options: ExpandOptions,
+    result: &mut JsonMap<String, Value>,
+    nests: &mut Vec<&'a str>,
+) -> Result<()> {
+    // Step 13.4.1
+    if active_property == Some("@reverse") {
+        return Err(ErrorCode::InvalidReversePropertyMap.and_message(format!(
+            "Keyword {:?} found in a reverse property map",
+            expanded_property
+        )));
+    }
+    // Step 13.4.2
+    if result.contains_key(expanded_property)
+        && (processor.is_processing_mode_1_0()
+            || (expanded_property != "@included" && expanded_property != "@type"))
+    {
+        return Err(ErrorCode::CollidingKeywords.and_message(format!(
+            "Keyword {:?} is already expanded",
+            expanded_property
+        )));
+    }
+
+    let expanded_value = match expanded_property {
+        // Step 13.4.3
+        "@id" => match value {
+            // Step 13.4.3.1: Frames may have an empty map or an array of strings as `@id`.
+            v if options.frame_expansion => {
+                let ids = frame_pattern(v, Value::is_string).ok_or_else(|| {
+                    ErrorCode::InvalidIdValue.and_message(format!(
+                        "Expected string, empty map, or array of strings as `@id` value in \
+                         the frame, but got {:?}",
+                        v
+                    ))
+                })?;
+                let mut expanded = Vec::with_capacity(ids.len());
+                for id in ids {
+                    match id {
+                        Value::String(id) => expanded.push(
+                            ExpandIriOptions::constant(active_context)
+                                .document_relative(true)
+                                .expand_to_json(processor, &id)
+                                .await?,
+                        ),
+                        v => expanded.push(v),
+                    }
+                }
+                Value::Array(expanded)
+            }
+            // Step 13.4.3.2
+            Value::String(id) => {
+                ExpandIriOptions::constant(active_context)
+                    .document_relative(true)
+                    .expand_to_json(processor, id)
+                    .await?
+            }
+            // Step 13.4.3.1
+            v => {
+                return Err(ErrorCode::InvalidIdValue
+                    .and_message(format!("Expected string as `@id` value, but got {:?}", v)))
+            }
+        },
+        // Step 13.4.4
+        "@type" => {
+            // Step 13.4.4.1, 13.4.4.4
+            let expanded_value = match value {
+                Value::String(ty) => expand_type(processor, type_scoped_context, ty).await?,
+                Value::Array(types) => {
+                    let mut expanded = Vec::with_capacity(types.len());
+                    for ty in types {
+                        let ty = ty.as_str().ok_or_else(|| {
+                            ErrorCode::InvalidTypeValue.and_message(format!(
+                                "Expected string as `@type` value, but got {:?}",
+                                ty
+                            ))
+                        })?;
+                        expanded.push(expand_type(processor, type_scoped_context, ty).await?);
+                    }
+                    Value::Array(expanded)
+                }
+                // Step 13.4.4.2
+                Value::Object(map) if options.frame_expansion && map.is_empty() => value.clone(),
+                // Step 13.4.4.3
+                Value::Object(map) if options.frame_expansion && is_default_object(map) => {
+                    match &map["@default"] {
+                        Value::String(ty) => Value::Object(single_entry_map(
+                            "@default",
+                            expand_type(processor, type_scoped_context, ty).await?,
+                        )),
+                        v => {
+                            return Err(ErrorCode::InvalidTypeValue.and_message(format!(
+                                "Expected string as `@default` of `@type`, but got {:?}",
+                                v
+                            )))
+                        }
+                    }
+                }
+                v => {
+                    return Err(ErrorCode::InvalidTypeValue.and_message(format!(
+                        "Expected string or array of strings as `@type` value, but got {:?}",
+                        v
+                    )))
+                }
+            };
+            // Step 13.4.4.5
+            match result.remove("@type") {
+                Some(existing) => {
+                    let mut types = into_array(existing);
+                    types.extend(into_array(expanded_value));
+                    Value::Array(types)
+                }
+                // Frames always have an array as `@type` value, so that the matching can
+                // distinguish a wildcard (`[{}]`) and match-none (`[]`) patterns uniformly.
+                None if options.frame_expansion => Value::Array(into_array(expanded_value)),
+                None => expanded_value,
+            }
+        }
+        // Step 13.4.5
+        "@graph" => {
+            let expanded = expand_element(
+                processor,
+                active_context,
+                Some("@graph"),
+                value,
+                base_url,
+                options,
+            )
+            .await?;
+            Value::Array(into_nonnull_array(expanded))
+        }
+        // Step 13.4.6
+        "@included" => {
+            // Step 13.4.6.1
+            if processor.is_processing_mode_1_0() {
+                return Ok(());
+            }
+            // Step 13.4.6.2
+            let expanded = expand_element(
+                processor,
+                active_context,
+                active_property,
+                value,
+                base_url,
+                options,
+            )
+            .await?;
+            let mut expanded = into_nonnull_array(expanded);
+            // Step 13.4.6.3
+            if let Some(item) = expanded.iter().find(|item| !is_node_object(item)) {
+                return Err(ErrorCode::InvalidIncludedValue.and_message(format!(
+                    "Expected node objects as `@included` value, but got {:?}",
+                    item
+                )));
+            }
+            // Step 13.4.6.4
+            if let Some(Value::Array(included)) = result.remove("@included") {
+                expanded.splice(0..0, included);
+            }
+            Value::Array(expanded)
+        }
+        // Step 13.4.7
+        "@value" => match value {
+            // Step 13.4.7.1
+            v if input_type == Some("@json") => {
+                if processor.is_processing_mode_1_0() {
+                    return Err(ErrorCode::InvalidValueObjectValue
+                        .and_message("Found JSON literal but processing mode is `json-ld-1.0`"));
+                }
+                v.clone()
+            }
+            // Step 13.4.7.2: Frames may have an empty map or an array of scalars as `@value`.
+            v if options.frame_expansion => {
+                let values =
+                    frame_pattern(v, |v| !v.is_array() && !v.is_object()).ok_or_else(|| {
+                        ErrorCode::InvalidValueObjectValue.and_message(format!(
+                            "Expected scalar, empty map, or array of scalars as `@value` \
+                             value in the frame, but got {:?}",
+                            v
+                        ))
+                    })?;
+                Value::Array(values)
+            }
+            // Step 13.4.7.2
+            Value::Array(_) | Value::Object(_) => {
+                return Err(ErrorCode::InvalidValueObjectValue.and_message(format!(
+                    "Expected scalar or null as `@value` value, but got {:?}",
+                    value
+                )))
+            }
+            // Step 13.4.7.4
+            Value::Null => {
+                result.insert("@value".to_owned(), Value::Null);
+                return Ok(());
+            }
+            // Step 13.4.7.3
+            v => v.clone(),
+        },
+        // Step 13.4.8
+        "@language" => match value {
+            // Step 13.4.8.1: Frames may have an empty map or an array of strings as
+            // `@language`.
+            v if options.frame_expansion => {
+                let languages = frame_pattern(v, Value::is_string).ok_or_else(|| {
+                    ErrorCode::InvalidLanguageTaggedString.and_message(format!(
+                        "Expected string, empty map, or array of strings as `@language` \
+                         value in the frame, but got {:?}",
+                        v
+                    ))
+                })?;
+                Value::Array(languages)
+            }
+            // Step 13.4.8.2
+            Value::String(_) => value.clone(),
+            // Step 13.4.8.1
+            v => {
+                return Err(ErrorCode::InvalidLanguageTaggedString.and_message(format!(
+                    "Expected string as `@language` value, but got {:?}",
+                    v
+                )))
+            }
+        },
+        // Step 13.4.9
+        "@direction" => {
+            // Step 13.4.9.1
+            if processor.is_processing_mode_1_0() {
+                return Ok(());
+            }
+            // Step 13.4.9.2: Frames may have an empty map or an array of directions as
+            // `@direction`.
+            if options.frame_expansion {
+                let directions = frame_pattern(value, is_direction).ok_or_else(|| {
+                    ErrorCode::InvalidBaseDirection.and_message(format!(
+                        "Expected `\"ltr\"`, `\"rtl\"`, empty map, or array of them as \
+                         `@direction` value in the frame, but got {:?}",
+                        value
+                    ))
+                })?;
+                Value::Array(directions)
+            } else if is_direction(value) {
+                // Step 13.4.9.3
+                value.clone()
+            } else {
+                // Step 13.4.9.2
+                return Err(ErrorCode::InvalidBaseDirection.and_message(format!(
+                    "Expected `\"ltr\"` or `\"rtl\"` as `@direction` value, but got {:?}",
+                    value
+                )));
+            }
+        }
+        // Step 13.4.10
+        "@index" => match value {
+            // Step 13.4.10.2
+            Value::String(_) => value.clone(),
+            // Step 13.4.10.1
+            v => {
+                return Err(ErrorCode::InvalidIndexValue.and_message(format!(
+                    "Expected string as `@index` value, but got {:?}",
+                    v
+                )))
+            }
+        },
+        // Step 13.4.11
+        "@list" => {
+            // Step 13.4.11.1
+            match active_property {
+                None | Some("@graph") => return Ok(()),
+                Some(_) => {}
+            }
+            // Step 13.4.11.2
+            let expanded = expand_element(
+                processor,
+                active_context,
+                active_property,
+                value,
+                base_url,
+                options,
+            )
+            .await?;
+            Value::Array(into_nonnull_array(expanded))
+        }
+        // Step 13.4.12
+        "@set" => {
+            expand_element(
+                processor,
+                active_context,
+                active_property,
+                value,
+                base_url,
+                options,
+            )
+            .await?
+        }
+        // Step 13.4.13
+        "@reverse" => {
+            return expand_reverse(processor, active_context, value, base_url, options, result)
+                .await;
+        }
+        // Step 13.4.14
+        "@nest" => {
+            nests.push(key);
+            return Ok(());
+        }
+        // Other keywords are not supported here.
+        _ => return Ok(()),
+    };
+
+    // Step 13.4.16
+    result.insert(expanded_property.to_owned(), expanded_value);
+
+    Ok(())
+}
+
+/// Expands an entry whose key is a framing keyword, and stores the result to `result`.
+///
+/// `@default` value is expanded as a value of the active property, and the other values are
+/// stored as arrays.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#framing-keywords>.
+#[allow(clippy::too_many_arguments)] // TODO: FIXME
+pub(super) async fn expand_framing_keyword_entry<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    active_property: Option<&str>,
+    key: &str,
+    value: &Value,
+    base_url: &IriStr,
+    options: ExpandOptions,
+    result: &mut JsonMap<String, Value>,
+) -> Result<()> {
+    let expanded_value = match (key, value) {
+        ("@default", Value::String(s)) if s == "@null" => Value::Array(vec![value.clone()]),
+        ("@default", value) => {
+            let expanded = expand_element(
+                processor,
+                active_context,
+                active_property,
+                value,
+                base_url,
+                options,
+            )
+            .await?;
+            Value::Array(into_nonnull_array(expanded))
+        }
+        (_, value) => Value::Array(into_array(value.clone())),
+    };
+    result.insert(key.to_owned(), expanded_value);
+
+    Ok(())
+}
+
+/// Returns the items of the given frame pattern.
+///
+/// A frame pattern is an empty map, a valid item, or an array of valid items.
+/// Returns `None` if the value is not a valid pattern.
+fn frame_pattern(value: &Value, is_valid_item: impl Fn(&Value) -> bool) -> Option<Vec<Value>> {
+    match value {
+        Value::Object(map) if map.is_empty() => Some(vec![value.clone()]),
+        Value::Array(items) if items.iter().all(|item| is_valid_item(item)) => Some(items.clone()),
+        v if is_valid_item(v) => Some(vec![v.clone()]),
+        _ => None,
+    }
+}
+
+/// Checks whether the given value is a valid base direction.
+fn is_direction(value: &Value) -> bool {
+    value
+        .as_str()
+        .map_or(false, |s| s.parse::<Direction>().is_ok())
+}
+
+/// Checks whether the given map is a default object.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-default-object>.
+fn is_default_object(map: &JsonMap<String, Value>) -> bool {
+    map.len() == 1 && map.contains_key("@default")
+}
+
+/// Expands a type IRI.
+async fn expand_type<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    type_scoped_context: &Context,
+    ty: &str,
+) -> Result<Value> {
+    ExpandIriOptions::constant(type_scoped_context)
+        .vocab(true)
+        .document_relative(true)
+        .expand_to_json(processor, ty)
+        .await
+}
+
+/// Expands `@reverse` entry, and stores the result to `result`.
+// Step 13.4.13
+async fn expand_reverse<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    value: &Value,
+    base_url: &IriStr,
+    options: ExpandOptions,
+    result: &mut JsonMap<String, Value>,
+) -> Result<()> {
+    // Step 13.4.13.1
+    if !value.is_object() {
+        return Err(ErrorCode::InvalidReverseValue.and_message(format!(
+            "Expected map as `@reverse` value, but got {:?}",
+            value
+        )));
+    }
+    // Step 13.4.13.2
+    let mut expanded = match expand_element(
+        processor,
+        active_context,
+        Some("@reverse"),
+        value,
+        base_url,
+        options,
+    )
+    .await?
+    {
+        Value::Object(map) => map,
+        _ => return Ok(()),
+    };
+    // Step 13.4.13.3
+    if let Some(Value::Object(double_reversed)) = expanded.remove("@reverse") {
+        for (property, item) in double_reversed {
+            // Step 13.4.13.3.1, 13.4.13.3.2
+            add_value(result, &property, item, true);
+        }
+    }
+    // Step 13.4.13.4
+    if !expanded.is_empty() {
+        // Step 13.4.13.4.1, 13.4.13.4.2
+        let reverse_map = result
+            .entry("@reverse")
+            .or_insert_with(|| Value::Object(JsonMap::new()))
+            .as_object_mut()
+            .expect("Should never fail: `@reverse` entry of the result is always a map");
+        // Step 13.4.13.4.3
+        for (property, items) in expanded {
+            for item in into_array(items) {
+                // Step 13.4.13.4.3.1.1
+                if is_value_object(&item) || is_list_object(&item) {
+                    return Err(ErrorCode::InvalidReversePropertyValue.and_message(format!(
+                        "Value object or list object found as a reverse property value: {:?}",
+                        item
+                    )));
+                }
+                // Step 13.4.13.4.3.1.2, 13.4.13.4.3.1.3
+                add_value(reverse_map, &property, item, true);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,335 @@
+//! Expansion of entries with non-keyword keys.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+
+use std::borrow::Cow;
+
+use iri_string::types::IriStr;
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::{definition::ContainerItem, Context, Definition},
+    error::{ErrorCode, Result},
+    expand::{
+        expand_element, expand_iri_vocab, iri::ExpandIriOptions, value::expand_value, ExpandOptions,
+    },
+    json::{
+        add_value, into_array, into_nonnull_array, map_entries, single_entry_map, to_ref_array,
+        Nullable,
+    },
+    processor::Processor,
+    remote::LoadRemoteDocument,
+    syntax::{is_graph_object, is_list_object, is_value_object},
+};
+
+/// Expands an entry whose key expands to an IRI, and stores the result to `result`.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+// Step 13.5-13.14
+#[allow(clippy::too_many_arguments)] // TODO: FIXME
+pub(super) async fn expand_property_entry<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    key: &str,
+    expanded_property: &str,
+    value: &Value,
+    base_url: &IriStr,
+    options: ExpandOptions,
+    result: &mut JsonMap<String, Value>,
+) -> Result<()> {
+    let definition = active_context.term_definition(key);
+    // Step 13.5
+    let container_contains =
+        |item: ContainerItem| definition.map_or(false, |def| def.container_contains(item));
+
+    let expanded_value = match value {
+        // Step 13.6
+        _ if definition.and_then(Definition::ty) == Some("@json") => {
+            let mut json_literal = single_entry_map("@value", value.clone());
+            json_literal.insert("@type".to_owned(), Value::String("@json".to_owned()));
+            Value::Object(json_literal)
+        }
+        // Step 13.7
+        Value::Object(map) if container_contains(ContainerItem::Language) => {
+            expand_language_map(processor, active_context, definition, map).await?
+        }
+        // Step 13.8
+        Value::Object(map)
+            if container_contains(ContainerItem::Index)
+                || container_contains(ContainerItem::Type)
+                || container_contains(ContainerItem::Id) =>
+        {
+            let definition =
+                definition.expect("Should never fail: the term has a container mapping");
+            expand_index_map(
+                processor,
+                active_context,
+                key,
+                definition,
+                map,
+                base_url,
+                options,
+            )
+            .await?
+        }
+        // Step 13.9
+        _ => {
+            expand_element(
+                processor,
+                active_context,
+                Some(key),
+                value,
+                base_url,
+                options,
+            )
+            .await?
+        }
+    };
+    // Step 13.10
+    if expanded_value.is_null() {
+        return Ok(());
+    }
+    // Step 13.11
+    let expanded_value =
+        if container_contains(ContainerItem::List) && !is_list_object(&expanded_value) {
+            Value::Object(single_entry_map(
+                "@list",
+                Value::Array(into_array(expanded_value)),
+            ))
+        } else {
+            expanded_value
+        };
+    // Step 13.12
+    let expanded_value = if container_contains(ContainerItem::Graph)
+        && !container_contains(ContainerItem::Id)
+        && !container_contains(ContainerItem::Index)
+    {
+        Value::Array(
+            into_array(expanded_value)
+                .into_iter()
+                .map(|ev| Value::Object(single_entry_map("@graph", Value::Array(into_array(ev)))))
+                .collect(),
+        )
+    } else {
+        expanded_value
+    };
+
+    if definition.map_or(false, Definition::is_reverse) {
+        // Step 13.13
+        // Step 13.13.1, 13.13.2
+        let reverse_map = result
+            .entry("@reverse")
+            .or_insert_with(|| Value::Object(JsonMap::new()))
+            .as_object_mut()
+            .expect("Should never fail: `@reverse` entry of the result is always a map");
+        // Step 13.13.3, 13.13.4
+        for item in into_array(expanded_value) {
+            // Step 13.13.4.1
+            if is_value_object(&item) || is_list_object(&item) {
+                return Err(ErrorCode::InvalidReversePropertyValue.and_message(format!(
+                    "Value object or list object found as a reverse property value: {:?}",
+                    item
+                )));
+            }
+            // Step 13.13.4.2, 13.13.4.3
+            add_value(reverse_map, expanded_property, item, true);
+        }
+    } else {
+        // Step 13.14
+        add_value(result, expanded_property, expanded_value, true);
+    }
+
+    Ok(())
+}
+
+/// Expands a language map.
+// Step 13.7
+async fn expand_language_map<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    definition: Option<&Definition>,
+    value: &JsonMap<String, Value>,
+) -> Result<Value> {
+    // Step 13.7.1
+    let mut expanded_value = Vec::new();
+    // Step 13.7.2, 13.7.3
+    let direction = match definition.and_then(Definition::direction) {
+        Some(Nullable::Value(dir)) => Some(dir),
+        Some(Nullable::Null) => None,
+        None => active_context.default_base_direction(),
+    };
+    // Step 13.7.4
+    for (language, language_value) in map_entries(value, processor.is_ordered()) {
+        let language_is_none = language == "@none"
+            || expand_iri_vocab(processor, active_context, language)
+                .await?
+                .as_ref()
+                .map(|s| &**s)
+                == Some("@none");
+        // Step 13.7.4.1, 13.7.4.2
+        for item in to_ref_array(language_value) {
+            match item {
+                // Step 13.7.4.2.1
+                Value::Null => continue,
+                // Step 13.7.4.2.3
+                Value::String(_) => {
+                    let mut v = single_entry_map("@value", item.clone());
+                    // Step 13.7.4.2.4
+                    if !language_is_none {
+                        v.insert("@language".to_owned(), Value::String(language.clone()));
+                    }
+                    // Step 13.7.4.2.5
+                    if let Some(direction) = direction {
+                        v.insert(
+                            "@direction".to_owned(),
+                            Value::String(direction.as_str().to_owned()),
+                        );
+                    }
+                    // Step 13.7.4.2.6
+                    expanded_value.push(Value::Object(v));
+                }
+                // Step 13.7.4.2.2
+                v => {
+                    return Err(ErrorCode::InvalidLanguageMapValue.and_message(format!(
+                        "Expected string or null as a language map value, but got {:?}",
+                        v
+                    )))
+                }
+            }
+        }
+    }
+
+    Ok(Value::Array(expanded_value))
+}
+
+/// Expands an index map, a type map, or an id map.
+// Step 13.8
+async fn expand_index_map<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    key: &str,
+    definition: &Definition,
+    value: &JsonMap<String, Value>,
+    base_url: &IriStr,
+    options: ExpandOptions,
+) -> Result<Value> {
+    let container_contains = |item: ContainerItem| definition.container_contains(item);
+    // Step 13.8.1
+    let mut expanded_value = Vec::new();
+    // Step 13.8.2
+    let index_key = definition.index().unwrap_or("@index");
+    // Step 13.8.3
+    for (index, index_value) in map_entries(value, processor.is_ordered()) {
+        let map_context: Cow<'_, Context> = {
+            // Step 13.8.3.1
+            let base_context = if container_contains(ContainerItem::Id)
+                || container_contains(ContainerItem::Type)
+            {
+                active_context.previous_context().unwrap_or(active_context)
+            } else {
+                active_context
+            };
+            match base_context
+                .term_definition(index)
+                .and_then(Definition::local_context)
+            {
+                // Step 13.8.3.2
+                Some(local_context) if container_contains(ContainerItem::Type) => Cow::Owned(
+                    base_context
+                        .join_local_context(processor, local_context, false, true)
+                        .await?,
+                ),
+                // Step 13.8.3.3
+                _ => Cow::Borrowed(active_context),
+            }
+        };
+        // Step 13.8.3.4
+        let expanded_index = expand_iri_vocab(processor, active_context, index).await?;
+        let expanded_index_is_none = expanded_index.as_ref().map(|s| &**s) == Some("@none");
+        // Step 13.8.3.5, 13.8.3.6
+        let index_value = expand_element(
+            processor,
+            &map_context,
+            Some(key),
+            index_value,
+            base_url,
+            options.from_map(true),
+        )
+        .await
+        .map_err(|e| e.at_key(index))?;
+        // Step 13.8.3.7
+        for mut item in into_nonnull_array(index_value) {
+            // Step 13.8.3.7.1
+            if container_contains(ContainerItem::Graph) && !is_graph_object(&item) {
+                item = Value::Object(single_entry_map("@graph", Value::Array(into_array(item))));
+            }
+            if let Value::Object(item_map) = &mut item {
+                if expanded_index_is_none {
+                    // Nothing to add.
+                } else if container_contains(ContainerItem::Index) && index_key != "@index" {
+                    // Step 13.8.3.7.2
+                    // Step 13.8.3.7.2.5
+                    if item_map.contains_key("@value") {
+                        return Err(ErrorCode::InvalidValueObject.and_message(format!(
+                            "Value object cannot have a property-valued index {:?}",
+                            index_key
+                        )));
+                    }
+                    // Step 13.8.3.7.2.1
+                    let re_expanded_index = expand_value(
+                        processor,
+                        active_context,
+                        index_key,
+                        &Value::String(index.clone()),
+                    )
+                    .await?;
+                    // Step 13.8.3.7.2.2
+                    let expanded_index_key: String =
+                        expand_iri_vocab(processor, active_context, index_key)
+                            .await?
+                            .ok_or_else(|| {
+                                ErrorCode::InvalidTermDefinition.and_message(format!(
+                                    "Index key {:?} is expanded to `null`",
+                                    index_key
+                                ))
+                            })?
+                            .into();
+                    // Step 13.8.3.7.2.3
+                    let mut index_property_values = vec![re_expanded_index];
+                    if let Some(existing) = item_map.remove(&expanded_index_key) {
+                        index_property_values.extend(into_array(existing));
+                    }
+                    // Step 13.8.3.7.2.4
+                    item_map.insert(expanded_index_key, Value::Array(index_property_values));
+                } else if container_contains(ContainerItem::Index) {
+                    // Step 13.8.3.7.3
+                    if !item_map.contains_key("@index") {
+                        item_map.insert("@index".to_owned(), Value::String(index.clone()));
+                    }
+                } else if container_contains(ContainerItem::Id) {
+                    // Step 13.8.3.7.4
+                    if !item_map.contains_key("@id") {
+                        let id = ExpandIriOptions::constant(active_context)
+                            .document_relative(true)
+                            .expand_to_json(processor, index)
+                            .await?;
+                        item_map.insert("@id".to_owned(), id);
+                    }
+                } else if container_contains(ContainerItem::Type) {
+                    // Step 13.8.3.7.5
+                    let mut types = vec![expanded_index
+                        .as_ref()
+                        .map_or(Value::Null, |s| Value::String(s.clone().into()))];
+                    if let Some(existing) = item_map.remove("@type") {
+                        types.extend(into_array(existing));
+                    }
+                    item_map.insert("@type".to_owned(), Value::Array(types));
+                }
+            }
+            // Step 13.8.3.7.6
+            expanded_value.push(item);
+        }
+    }
+
+    Ok(Value::Array(expanded_value))
+}
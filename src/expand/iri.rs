@@ -2,19 +2,19 @@
 //!
 //! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#iri-expansion>.
 
-use std::{borrow::Cow, collections::HashMap};
-
 use iri_string::types::IriReferenceStr;
 use serde_json::{Map as JsonMap, Value};
 
 use crate::{
-    context::{Context, Definition, ValueWithBase},
+    context::{Context, CreateTermDefOptionalParams, DefinedTerms, Definition, ValueWithBase},
     error::{ErrorCode, Result},
+    intern::Symbol,
     iri::{is_absolute_iri_ref, to_prefix_and_suffix},
     json::Nullable,
     processor::Processor,
     remote::LoadRemoteDocument,
     syntax::has_form_of_keyword,
+    warning::Warning,
 };
 
 /// Context for IRI expansion.
@@ -32,7 +32,7 @@ enum ExpandIriContext<'a> {
         /// Local (currently loading) context.
         local_context: ValueWithBase<'a, &'a JsonMap<String, Value>>,
         /// Terms defined and being defined.
-        defined: &'a mut HashMap<String, bool>,
+        defined: &'a mut DefinedTerms,
     },
 }
 
@@ -46,7 +46,7 @@ impl<'a> ExpandIriContext<'a> {
     fn mutable(
         active_context: &'a mut Context,
         local_context: ValueWithBase<'a, &'a JsonMap<String, Value>>,
-        defined: &'a mut HashMap<String, bool>,
+        defined: &'a mut DefinedTerms,
     ) -> Self {
         ExpandIriContext::Mutable {
             active_context,
@@ -69,7 +69,6 @@ pub(crate) struct ExpandIriOptions<'a> {
 
 impl<'a> ExpandIriOptions<'a> {
     /// Creates a new `ExpandIriOptions` with the given immutable context.
-    #[allow(dead_code)]
     pub(crate) fn constant(active_context: &'a Context) -> Self {
         Self {
             context: ExpandIriContext::constant(active_context),
@@ -79,11 +78,10 @@ impl<'a> ExpandIriOptions<'a> {
     }
 
     /// Creates a new `ExpandIriOptions` with the given mutable context.
-    #[allow(dead_code)]
     pub(crate) fn mutable(
         active_context: &'a mut Context,
         local_context: ValueWithBase<'a, &'a JsonMap<String, Value>>,
-        defined: &'a mut HashMap<String, bool>,
+        defined: &'a mut DefinedTerms,
     ) -> Self {
         Self {
             context: ExpandIriContext::mutable(active_context, local_context, defined),
@@ -93,7 +91,6 @@ impl<'a> ExpandIriOptions<'a> {
     }
 
     /// Sets "document relative" flag.
-    #[allow(dead_code)]
     pub(crate) fn document_relative(self, document_relative: bool) -> Self {
         Self {
             document_relative,
@@ -102,7 +99,6 @@ impl<'a> ExpandIriOptions<'a> {
     }
 
     /// Sets "vocab" flag.
-    #[allow(dead_code)]
     pub(crate) fn vocab(self, vocab: bool) -> Self {
         Self { vocab, ..self }
     }
@@ -171,22 +167,19 @@ impl<'a> ExpandIriOptions<'a> {
         }
     }
 
-    /// Runs "create term definition" algorithm if necessary.
-    async fn create_term_definition<L: LoadRemoteDocument>(
-        &mut self,
-        processor: &Processor<L>,
-        value: &str,
-    ) -> Result<()> {
+    /// Requests "create term definition" algorithm if necessary.
+    ///
+    /// This fails if the term definition is not yet created, and then the caller runs the
+    /// algorithm for the term (see `DefinedTerms`).
+    fn require_term_definition(&mut self, value: &str) -> Result<()> {
         if let ExpandIriContext::Mutable {
-            active_context,
             local_context,
             defined,
+            ..
         } = &mut self.context
         {
-            if local_context.value().contains_key(value) && defined.get(value) != Some(&true) {
-                active_context
-                    .create_term_definition(processor, *local_context, value, defined)
-                    .await?;
+            if local_context.value().contains_key(value) {
+                defined.require(value, CreateTermDefOptionalParams::new())?;
             }
         }
 
@@ -208,14 +201,13 @@ impl<'a> ExpandIriOptions<'a> {
         self,
         processor: &Processor<L>,
         value: &'a str,
-    ) -> Result<Option<Cow<'a, str>>> {
+    ) -> Result<Option<Symbol<'a>>> {
         expand_str(self, processor, value).await
     }
 
     /// Runs IRI expansion algorithm for string value and returns JSON value.
     ///
     /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#iri-expansion>.
-    #[allow(dead_code)]
     pub(crate) async fn expand_to_json<L: LoadRemoteDocument>(
         self,
         processor: &Processor<L>,
@@ -235,27 +227,27 @@ async fn expand_str<'a, L: LoadRemoteDocument>(
     mut options: ExpandIriOptions<'a>,
     processor: &Processor<L>,
     value: &'a str,
-) -> Result<Option<Cow<'a, str>>> {
+) -> Result<Option<Symbol<'a>>> {
     // Step 1
     if processor.is_keyword(value) {
-        return Ok(Some(Cow::Borrowed(value)));
+        return Ok(Some(Symbol::Borrowed(value)));
     }
     // Step 2
     if has_form_of_keyword(value) {
-        // TODO: Generate a warning.
+        processor.warn(Warning::KeywordLikeValue(value.to_owned()));
         return Ok(None);
     }
     // Step 3
-    options.create_term_definition(processor, value).await?;
+    options.require_term_definition(value)?;
     // Step 4
     if let Some(keyword) = options
         .active_context()
         .term_definition(value)
-        .map(|def| def.iri())
+        .map(Definition::interned_iri)
         .filter(|iri| processor.is_keyword(iri))
     {
         // Return a keyword.
-        return Ok(Some(Cow::Owned(keyword.to_owned())));
+        return Ok(Some(Symbol::Interned(keyword.clone())));
     }
     // Step 5
     if options.vocab {
@@ -265,7 +257,7 @@ async fn expand_str<'a, L: LoadRemoteDocument>(
         options = match options.into_raw_term_definition(value) {
             Ok(def) => match def {
                 Nullable::Null => return Ok(None),
-                Nullable::Value(def) => return Ok(Some(Cow::Borrowed(def.iri()))),
+                Nullable::Value(def) => return Ok(Some(Symbol::Borrowed(def.iri()))),
             },
             Err(options) => options,
         };
@@ -276,10 +268,10 @@ async fn expand_str<'a, L: LoadRemoteDocument>(
         // Step 6.2: `value` is either an IRI, a compact IRI, or a blank node identifier.
         if prefix == "_" || suffix.starts_with("//") {
             // `value` is already an IRI or a blank node identifier.
-            return Ok(Some(Cow::Borrowed(value)));
+            return Ok(Some(Symbol::Borrowed(value)));
         }
         // Step 6.3
-        options.create_term_definition(processor, prefix).await?;
+        options.require_term_definition(prefix)?;
         // Step 6.4
         // NOTE: Treat prefix as not defined if it is mapped to `null`.
         if let Some(prefix_def) = options
@@ -287,18 +279,25 @@ async fn expand_str<'a, L: LoadRemoteDocument>(
             .term_definition(prefix)
             .filter(|def| def.is_prefix())
         {
-            return Ok(Some(Cow::Owned(format!("{}{}", prefix_def.iri(), suffix))));
+            let iri = if options.vocab {
+                Symbol::Interned(processor.intern_concat(prefix_def.iri(), suffix))
+            } else {
+                Symbol::Owned(format!("{}{}", prefix_def.iri(), suffix))
+            };
+            return Ok(Some(iri));
         }
         // Step 6.5
         if is_absolute_iri_ref(value) {
             // `value` is already an IRI.
-            return Ok(Some(Cow::Borrowed(value)));
+            return Ok(Some(Symbol::Borrowed(value)));
         }
     }
     // Step 7
     if options.vocab {
         if let Nullable::Value(vocab) = options.active_context().vocab() {
-            return Ok(Some(Cow::Owned(format!("{}{}", vocab, value))));
+            return Ok(Some(Symbol::Interned(
+                processor.intern_concat(vocab, value),
+            )));
         }
     }
     // Step 8
@@ -307,25 +306,16 @@ async fn expand_str<'a, L: LoadRemoteDocument>(
         // See <https://github.com/w3c/json-ld-api/issues/180#issuecomment-547177451>.
         let base = match options.active_context().base() {
             Nullable::Value(base) => base,
-            Nullable::Null => {
-                // Not sure what to do when the base is explicitly nullified.
-                return Err(ErrorCode::Uncategorized.and_source(anyhow::anyhow!(
-                    "`document_relative` is true but base IRI from the active context is `null`",
-                )));
-            }
+            // If the base is explicitly nullified, relative IRI references are kept as is.
+            Nullable::Null => return Ok(Some(Symbol::Borrowed(value))),
         };
-        let value: &IriReferenceStr = IriReferenceStr::new(value).map_err(|e| {
-            ErrorCode::Uncategorized.and_source(anyhow::anyhow!(
-                "Attempt to resolve {:?} as IRI, but it is not actually valid IRI: {}",
-                value,
-                e
-            ))
-        })?;
-        return Ok(Some(Cow::Owned(
+        let value: &IriReferenceStr = IriReferenceStr::new(value)
+            .map_err(|e| ErrorCode::Uncategorized.and_iri_error(value, e))?;
+        return Ok(Some(Symbol::Owned(
             value.resolve_against(base.to_absolute()).into(),
         )));
     }
 
     // Step 9
-    Ok(Some(Cow::Borrowed(value)))
+    Ok(Some(Symbol::Borrowed(value)))
 }
@@ -0,0 +1,163 @@
+//! Expansion of newline-delimited JSON-LD documents.
+
+#[cfg(feature = "futures-io")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::{io, str};
+
+#[cfg(feature = "futures-io")]
+use futures_io::AsyncBufRead;
+use serde_json::Value;
+
+use crate::{
+    error::{Error, ErrorCode, Result},
+    options::JsonLdOptions,
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+/// Expansion of the newline-delimited JSON-LD documents read from a reader.
+///
+/// This is created by [`Processor::expand_ndjson`], and yields the result for each document as
+/// soon as its line is read.
+///
+/// [`Processor::expand_ndjson`]: struct.Processor.html#method.expand_ndjson
+#[cfg(feature = "futures-io")]
+pub struct NdjsonExpansion<'a, L, R> {
+    /// Processor.
+    processor: &'a Processor<L>,
+    /// Options.
+    options: &'a JsonLdOptions,
+    /// Reader.
+    reader: R,
+    /// Buffer for the current line.
+    line: Vec<u8>,
+    /// Number of the lines read so far.
+    line_number: usize,
+    /// Whether the reader is exhausted or failed.
+    finished: bool,
+}
+
+#[cfg(feature = "futures-io")]
+impl<'a, L: LoadRemoteDocument, R: AsyncBufRead + Unpin> NdjsonExpansion<'a, L, R> {
+    /// Creates a new `NdjsonExpansion`.
+    pub(crate) fn new(processor: &'a Processor<L>, reader: R, options: &'a JsonLdOptions) -> Self {
+        Self {
+            processor,
+            options,
+            reader,
+            line: Vec::new(),
+            line_number: 0,
+            finished: false,
+        }
+    }
+
+    /// Reads the next non-empty line and expands the document in it.
+    ///
+    /// Returns the line number (starting from 1) and the result, or `None` at the end of the
+    /// input.
+    /// A failure on a document does not stop processing of the following lines, but a failure on
+    /// reading is returned as the last result.
+    pub async fn next(&mut self) -> Option<(usize, Result<Value>)> {
+        while !self.finished {
+            self.line.clear();
+            let read = ReadLine::new(&mut self.reader, &mut self.line).await;
+            match read {
+                Ok(0) => self.finished = true,
+                Ok(_) => {
+                    self.line_number += 1;
+                    if let Some(result) =
+                        expand_line(self.processor, &self.line, self.options).await
+                    {
+                        return Some((self.line_number, result));
+                    }
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some((self.line_number + 1, Err(read_error(e))));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Future to read a line (including the line feed) from the reader.
+#[cfg(feature = "futures-io")]
+struct ReadLine<'a, R> {
+    /// Reader.
+    reader: &'a mut R,
+    /// Buffer to append the line to.
+    buf: &'a mut Vec<u8>,
+    /// Number of the bytes read so far.
+    read: usize,
+}
+
+#[cfg(feature = "futures-io")]
+impl<'a, R: AsyncBufRead + Unpin> ReadLine<'a, R> {
+    /// Creates a new `ReadLine`.
+    fn new(reader: &'a mut R, buf: &'a mut Vec<u8>) -> Self {
+        Self {
+            reader,
+            buf,
+            read: 0,
+        }
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R: AsyncBufRead + Unpin> Future for ReadLine<'_, R> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            let available = match Pin::new(&mut *this.reader).poll_fill_buf(cx) {
+                Poll::Ready(Ok(available)) => available,
+                Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+            let (done, used) = match available.iter().position(|&b| b == b'\n') {
+                Some(pos) => (true, pos + 1),
+                None => (available.is_empty(), available.len()),
+            };
+            this.buf.extend_from_slice(&available[..used]);
+            Pin::new(&mut *this.reader).consume(used);
+            this.read += used;
+            if done {
+                return Poll::Ready(Ok(this.read));
+            }
+        }
+    }
+}
+
+/// Parses and expands the document in the line of newline-delimited JSON-LD text.
+///
+/// Returns `None` if the line is blank.
+pub(crate) async fn expand_line<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    line: &[u8],
+    options: &JsonLdOptions,
+) -> Option<Result<Value>> {
+    let line = match str::from_utf8(line) {
+        Ok(line) => line,
+        Err(e) => {
+            return Some(Err(
+                ErrorCode::LoadingDocumentFailed.and_message(format!("Invalid UTF-8: {}", e))
+            ))
+        }
+    };
+    if line.trim().is_empty() {
+        return None;
+    }
+    Some(processor.expand_str(line, options).await)
+}
+
+/// Returns an error for a failure on reading the input.
+pub(crate) fn read_error(e: io::Error) -> Error {
+    ErrorCode::LoadingDocumentFailed.and_message(format!("Failed to read the document: {}", e))
+}
@@ -0,0 +1,359 @@
+//! Parallel expansion of the items of the top-level array.
+//!
+//! Items are expanded on worker threads, while remote documents requested by the workers are
+//! loaded on the caller's task. This lets the expansion use multiple CPU cores, and still works
+//! with loaders which require the caller's async runtime.
+
+use std::{
+    error,
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context as TaskContext, Poll, Waker},
+    thread,
+};
+
+use async_trait::async_trait;
+use futures_executor::block_on;
+use iri_string::types::{IriStr, IriString};
+use serde_json::Value;
+
+use crate::{
+    context::Context,
+    error::{ErrorCode, Result},
+    processor::Processor,
+    remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument},
+};
+
+use super::{expand_element, ExpandOptions};
+
+/// Result of a load forwarded to the caller's task.
+type LoadResult<E> = std::result::Result<Arc<RemoteDocument>, ForwardedLoadError<E>>;
+
+/// Future of a load running on the caller's task.
+type LoadFuture<'a, E> =
+    Pin<Box<dyn Future<Output = std::result::Result<Arc<RemoteDocument>, E>> + Send + 'a>>;
+
+/// Error on loading a remote document requested by a worker.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ForwardedLoadError<E: error::Error + 'static> {
+    /// The loader failed.
+    #[error("{0}")]
+    Load(#[source] E),
+    /// The expansion is aborted before the load completes.
+    #[error("The expansion is aborted")]
+    Aborted,
+}
+
+/// Locks the mutex.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // The states are always consistent, so they can be used even if a worker panicked.
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// State shared by the caller's task and the workers.
+struct Shared<E: error::Error + 'static> {
+    /// Index of the next item to expand.
+    next_index: usize,
+    /// Results of the items, or `None` if not yet expanded.
+    results: Vec<Option<Result<Value>>>,
+    /// Number of running workers.
+    running_workers: usize,
+    /// Whether an item failed, so that no more items are started.
+    has_failed: bool,
+    /// Whether the caller's future is dropped, so that no more loads are served.
+    is_dropped: bool,
+    /// Loads requested by the workers and not yet started.
+    load_requests: Vec<LoadRequest<E>>,
+    /// Waker of the caller's task.
+    waker: Option<Waker>,
+}
+
+impl<E: error::Error + 'static> Shared<E> {
+    /// Wakes the caller's task.
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Load requested by a worker.
+struct LoadRequest<E: error::Error + 'static> {
+    /// IRI of the remote document.
+    iri: IriString,
+    /// Load options.
+    options: LoadDocumentOptions,
+    /// Slot for the result.
+    reply: Arc<Mutex<Reply<E>>>,
+}
+
+/// Slot for the result of a forwarded load.
+struct Reply<E: error::Error + 'static> {
+    /// Result of the load, or `None` if not yet available.
+    result: Option<LoadResult<E>>,
+    /// Waker of the worker waiting for the result.
+    waker: Option<Waker>,
+}
+
+/// Sets the result of the forwarded load, and wakes the worker waiting for it.
+fn resolve<E: error::Error + 'static>(reply: &Mutex<Reply<E>>, result: LoadResult<E>) {
+    let mut reply = lock(reply);
+    reply.result = Some(result);
+    if let Some(waker) = reply.waker.take() {
+        waker.wake();
+    }
+}
+
+/// Future of the result of a forwarded load.
+struct ReplyFuture<E: error::Error + 'static>(Arc<Mutex<Reply<E>>>);
+
+impl<E: error::Error + 'static> Future for ReplyFuture<E> {
+    type Output = LoadResult<E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let mut reply = lock(&self.0);
+        match reply.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                reply.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Loader of the workers, which forwards the loads to the caller's task.
+struct ForwardingLoader<E: error::Error + 'static> {
+    /// Shared state.
+    shared: Arc<Mutex<Shared<E>>>,
+}
+
+#[async_trait]
+impl<E: error::Error + Send + Sync + 'static> LoadRemoteDocument for ForwardingLoader<E> {
+    type Error = ForwardedLoadError<E>;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> std::result::Result<Arc<RemoteDocument>, Self::Error> {
+        let reply = Arc::new(Mutex::new(Reply {
+            result: None,
+            waker: None,
+        }));
+        {
+            let mut shared = lock(&self.shared);
+            if shared.is_dropped {
+                return Err(ForwardedLoadError::Aborted);
+            }
+            shared.load_requests.push(LoadRequest {
+                iri: iri.to_owned(),
+                options,
+                reply: reply.clone(),
+            });
+            shared.wake();
+        }
+        ReplyFuture(reply).await
+    }
+}
+
+/// Worker expanding the items one by one.
+struct Worker<E: error::Error + Send + Sync + 'static> {
+    /// Processor forwarding loads to the caller's task.
+    processor: Arc<Processor<ForwardingLoader<E>>>,
+    /// Active context.
+    active_context: Arc<Context>,
+    /// Items of the top-level array.
+    items: Arc<Vec<Value>>,
+    /// Expansion options.
+    options: ExpandOptions,
+    /// Shared state.
+    shared: Arc<Mutex<Shared<E>>>,
+}
+
+impl<E: error::Error + Send + Sync + 'static> Worker<E> {
+    /// Expands the items until no items remain or an item fails.
+    fn run(&self) {
+        let base_url = self.processor.options().document_iri();
+        loop {
+            let index = {
+                let mut shared = lock(&self.shared);
+                if shared.has_failed || shared.is_dropped || shared.next_index >= self.items.len() {
+                    return;
+                }
+                shared.next_index += 1;
+                shared.next_index - 1
+            };
+            // Step 5.2.1
+            let result = block_on(expand_element(
+                &self.processor,
+                &self.active_context,
+                None,
+                &self.items[index],
+                base_url,
+                self.options,
+            ));
+            let mut shared = lock(&self.shared);
+            shared.has_failed |= result.is_err();
+            shared.results[index] = Some(result);
+        }
+    }
+}
+
+impl<E: error::Error + Send + Sync + 'static> Drop for Worker<E> {
+    fn drop(&mut self) {
+        // This runs even if the worker panicked or could not be spawned.
+        let mut shared = lock(&self.shared);
+        shared.running_workers -= 1;
+        shared.wake();
+    }
+}
+
+/// Future which serves the loads requested by the workers until all the workers exit.
+struct ParallelExpansion<'a, L: LoadRemoteDocument> {
+    /// Remote document loader.
+    loader: &'a L,
+    /// Shared state.
+    shared: Arc<Mutex<Shared<L::Error>>>,
+    /// Loads in flight, with the slots for their results.
+    loads: Vec<(LoadFuture<'a, L::Error>, Arc<Mutex<Reply<L::Error>>>)>,
+}
+
+impl<L: LoadRemoteDocument> Future for ParallelExpansion<'_, L> {
+    type Output = Result<Vec<Value>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            let requests = {
+                let mut shared = lock(&this.shared);
+                if shared.running_workers == 0 {
+                    let results = mem::replace(&mut shared.results, Vec::new());
+                    return Poll::Ready(collect_results(results));
+                }
+                shared.waker = Some(cx.waker().clone());
+                mem::replace(&mut shared.load_requests, Vec::new())
+            };
+            let mut has_progress = !requests.is_empty();
+            for LoadRequest {
+                iri,
+                options,
+                reply,
+            } in requests
+            {
+                let loader = this.loader;
+                let load: LoadFuture<'_, L::Error> =
+                    Box::pin(async move { loader.load(&iri, options).await });
+                this.loads.push((load, reply));
+            }
+            let mut i = 0;
+            while i < this.loads.len() {
+                match this.loads[i].0.as_mut().poll(cx) {
+                    Poll::Ready(result) => {
+                        let (_, reply) = this.loads.swap_remove(i);
+                        resolve(&reply, result.map_err(ForwardedLoadError::Load));
+                        has_progress = true;
+                    }
+                    Poll::Pending => i += 1,
+                }
+            }
+            if !has_progress {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<L: LoadRemoteDocument> Drop for ParallelExpansion<'_, L> {
+    fn drop(&mut self) {
+        // Fail the loads the workers are waiting for, so that they stop.
+        let requests = {
+            let mut shared = lock(&self.shared);
+            shared.is_dropped = true;
+            mem::replace(&mut shared.load_requests, Vec::new())
+        };
+        for request in requests {
+            resolve(&request.reply, Err(ForwardedLoadError::Aborted));
+        }
+        for (_, reply) in self.loads.drain(..) {
+            resolve(&reply, Err(ForwardedLoadError::Aborted));
+        }
+    }
+}
+
+/// Returns the expanded items in order, or the error of the first failed item.
+fn collect_results(results: Vec<Option<Result<Value>>>) -> Result<Vec<Value>> {
+    // Items are started in order, so the items before the failed one have all been started.
+    results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| {
+            result
+                .unwrap_or_else(|| {
+                    Err(ErrorCode::Uncategorized.and_message("The expansion worker panicked"))
+                })
+                .map_err(|e| e.at_index(index))
+        })
+        .collect()
+}
+
+/// Expands the items of the top-level array on up to the configured number of worker threads,
+/// and returns the expanded items in order.
+///
+/// This is step 5.2.1 of the expansion algorithm without active property for each item.
+/// The items and the active context are copied for the workers.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+pub(super) async fn expand_items_in_parallel<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    items: &[Value],
+    options: ExpandOptions,
+) -> Result<Vec<Value>> {
+    let shared = Arc::new(Mutex::new(Shared {
+        next_index: 0,
+        results: items.iter().map(|_| None).collect(),
+        running_workers: 0,
+        has_failed: false,
+        is_dropped: false,
+        load_requests: Vec::new(),
+        waker: None,
+    }));
+    {
+        let worker_processor = Arc::new(processor.with_loader(ForwardingLoader {
+            shared: shared.clone(),
+        }));
+        let active_context = Arc::new(active_context.clone());
+        let items = Arc::new(items.to_vec());
+        let workers = processor.options().expansion_workers().min(items.len());
+        for i in 0..workers {
+            lock(&shared).running_workers += 1;
+            let worker = Worker {
+                processor: worker_processor.clone(),
+                active_context: active_context.clone(),
+                items: items.clone(),
+                options,
+                shared: shared.clone(),
+            };
+            // The worker is dropped and not counted if the thread cannot be spawned.
+            let spawned = thread::Builder::new()
+                .name("json-ld-expand".to_owned())
+                .spawn(move || worker.run());
+            if let Err(e) = spawned {
+                if i == 0 {
+                    return Err(ErrorCode::Uncategorized
+                        .and_message(format!("Failed to spawn the expansion worker: {}", e)));
+                }
+                break;
+            }
+        }
+    }
+    ParallelExpansion {
+        loader: processor.loader(),
+        shared,
+        loads: Vec::new(),
+    }
+    .await
+}
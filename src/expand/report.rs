@@ -0,0 +1,130 @@
+//! Report of keys dropped during expansion.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{context::Context, json::escape_pointer_token};
+
+/// Key dropped during expansion because it did not expand to an absolute IRI or a keyword.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroppedTerm {
+    /// The dropped key.
+    key: String,
+    /// JSON pointer to the dropped entry in the input document.
+    path: Option<String>,
+    /// Active context used to expand the key.
+    active_context: Context,
+}
+
+impl DroppedTerm {
+    /// Returns the dropped key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the JSON pointer to the dropped entry in the input document.
+    ///
+    /// Returns `None` if the entry is not in the input document, for example when it comes from
+    /// a remote document.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_ref().map(String::as_str)
+    }
+
+    /// Returns the active context which was in effect when the key was expanded.
+    pub fn active_context(&self) -> &Context {
+        &self.active_context
+    }
+}
+
+/// Collector of keys dropped during expansion.
+#[derive(Default, Debug)]
+pub(crate) struct DroppedTermCollector {
+    /// Address of the map, the dropped key, and the active context.
+    entries: Mutex<Vec<(usize, String, Context)>>,
+}
+
+impl DroppedTermCollector {
+    /// Creates a new empty `DroppedTermCollector`.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the key of the map dropped during expansion.
+    pub(crate) fn record(&self, map: &JsonMap<String, Value>, key: &str, active_context: &Context) {
+        self.entries
+            .lock()
+            .expect("Should never fail: the lock is never poisoned")
+            .push((map_address(map), key.to_owned(), active_context.clone()));
+    }
+
+    /// Returns the report of the dropped keys.
+    ///
+    /// The input document should be the one passed to the expansion algorithm, so that the paths
+    /// to the dropped entries are resolved.
+    pub(crate) fn report(&self, input: &Value) -> Vec<DroppedTerm> {
+        let mut paths = HashMap::new();
+        collect_map_paths(input, String::new(), &mut paths);
+        self.entries
+            .lock()
+            .expect("Should never fail: the lock is never poisoned")
+            .iter()
+            .map(|(address, key, active_context)| DroppedTerm {
+                key: key.clone(),
+                path: paths
+                    .get(address)
+                    .map(|path| format!("{}/{}", path, escape_pointer_token(key))),
+                active_context: active_context.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Returns the address of the map, which identifies the map in the input document.
+fn map_address(map: &JsonMap<String, Value>) -> usize {
+    map as *const JsonMap<String, Value> as usize
+}
+
+/// Collects the JSON pointers to the maps in the given value.
+fn collect_map_paths(v: &Value, path: String, paths: &mut HashMap<usize, String>) {
+    match v {
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_map_paths(item, format!("{}/{}", path, index), paths);
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                collect_map_paths(
+                    value,
+                    format!("{}/{}", path, escape_pointer_token(key)),
+                    paths,
+                );
+            }
+            paths.insert(map_address(map), path);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn paths() {
+        let input = json!({"@graph": [{"a/b": {"x": 1}}]});
+        let inner = input["@graph"][0]["a/b"]
+            .as_object()
+            .expect("should be a map");
+        let collector = DroppedTermCollector::new();
+        collector.record(inner, "x", &Context::default());
+        collector.record(&JsonMap::new(), "y", &Context::default());
+        let report = collector.report(&input);
+        assert_eq!(report[0].key(), "x");
+        assert_eq!(report[0].path(), Some("/@graph/0/a~1b/x"));
+        assert_eq!(report[1].path(), None);
+    }
+}
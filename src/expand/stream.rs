@@ -0,0 +1,501 @@
+//! Expansion emitting the results item by item.
+//!
+//! The document is split at the top-level items (the items of the top-level array, or of the
+//! `@graph` entry of the top-level map), and each item is expanded and emitted before the next
+//! item is expanded. Memory for the whole expanded document is not required, but each item is
+//! expanded as a whole before its results are emitted.
+
+use std::{borrow::Cow, fmt, io};
+#[cfg(feature = "futures-io")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "futures-io")]
+use futures_io::AsyncRead;
+use serde_json::{Map as JsonMap, Value};
+
+#[cfg(feature = "futures-io")]
+use crate::json::ArraySplitter;
+use crate::{
+    context::{Context as ActiveContext, ValueWithBase},
+    error::{Error, ErrorCode, Result},
+    expand::{expand_element, initial_context, ExpandOptions},
+    json::{map_entries, to_ref_array},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+/// Event emitted during the streaming expansion.
+///
+/// Events are emitted in the order of the expanded document.
+/// A node object starts with `NodeStart` and ends with `NodeEnd`, and the values of each
+/// property are emitted between `PropertyStart` and `PropertyEnd`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpansionEvent<'a> {
+    /// Start of a node object, with its `@id` if available.
+    NodeStart(Option<&'a str>),
+    /// End of a node object.
+    NodeEnd,
+    /// Type of the current node object.
+    Type(&'a str),
+    /// Index of the current node object.
+    Index(&'a str),
+    /// Start of the values of the property.
+    ///
+    /// The property is an IRI, or a keyword such as `@graph` and `@included`.
+    PropertyStart(&'a str),
+    /// End of the values of the property.
+    PropertyEnd,
+    /// Start of the reverse properties of the current node object.
+    ReverseStart,
+    /// End of the reverse properties of the current node object.
+    ReverseEnd,
+    /// Value object.
+    Value(&'a JsonMap<String, Value>),
+    /// Start of a list.
+    ListStart,
+    /// End of a list.
+    ListEnd,
+}
+
+/// Runs expansion algorithm for the given document, and emits the expanded document as events.
+///
+/// Top-level items are expanded and emitted one at a time, so the whole expanded document is
+/// never materialized.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+pub(crate) async fn expand_events<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    handler: &mut impl FnMut(ExpansionEvent<'_>),
+) -> Result<()> {
+    let ordered = processor.is_ordered();
+    expand_items(processor, input, &mut |item| {
+        emit_element(item, ordered, handler);
+        Ok(())
+    })
+    .await
+}
+
+/// Runs expansion algorithm for the given document, and writes the expanded document.
+///
+/// Top-level items are expanded and written one at a time, so the whole expanded document is
+/// never materialized.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+pub(crate) async fn expand_to_writer<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    writer: &mut impl io::Write,
+) -> Result<()> {
+    writer.write_all(b"[").map_err(write_error)?;
+    let mut is_first = true;
+    expand_items(processor, input, &mut |item| {
+        if !is_first {
+            writer.write_all(b",").map_err(write_error)?;
+        }
+        is_first = false;
+        serde_json::to_writer(&mut *writer, item).map_err(write_error)
+    })
+    .await?;
+    writer.write_all(b"]").map_err(write_error)
+}
+
+/// Returns an error for a failure on writing the result.
+pub(crate) fn write_error(e: impl fmt::Display) -> Error {
+    ErrorCode::Uncategorized.and_message(format!("Failed to write the result: {}", e))
+}
+
+/// Runs expansion algorithm for the given document, and passes the top-level items of the
+/// expanded document to the sink.
+async fn expand_items<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    sink: &mut impl FnMut(&Value) -> Result<()>,
+) -> Result<()> {
+    let active_context = initial_context(processor, None, ExpandOptions::new()).await?;
+    match input {
+        Value::Array(items) => {
+            for item in items {
+                expand_array_item(processor, &active_context, item, sink).await?;
+            }
+            Ok(())
+        }
+        input => expand_document(processor, &active_context, input, sink).await,
+    }
+}
+
+/// Runs expansion algorithm for the document read from the reader, and emits the expanded
+/// document as events.
+///
+/// If the document is an array, each element is parsed and expanded one at a time, so neither
+/// the whole document nor the whole expanded document is materialized.
+/// Other documents are buffered and parsed as a whole (see `expand_reader_items()`).
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+#[cfg(feature = "futures-io")]
+pub(crate) async fn expand_reader_events<L: LoadRemoteDocument, R: AsyncRead + Unpin>(
+    processor: &Processor<L>,
+    reader: R,
+    handler: &mut impl FnMut(ExpansionEvent<'_>),
+) -> Result<()> {
+    let ordered = processor.is_ordered();
+    expand_reader_items(processor, reader, &mut |item| {
+        emit_element(item, ordered, handler);
+        Ok(())
+    })
+    .await
+}
+
+/// Runs expansion algorithm for the document read from the reader, and passes the top-level
+/// items of the expanded document to the sink.
+///
+/// Only the top-level array is split from the source text. Other documents (including a map
+/// with `@graph` entry, whose other entries may follow the `@graph` entry in the source) are
+/// buffered until the end of the input and parsed as a whole, and then passed to
+/// `expand_document()`.
+#[cfg(feature = "futures-io")]
+async fn expand_reader_items<L: LoadRemoteDocument, R: AsyncRead + Unpin>(
+    processor: &Processor<L>,
+    mut reader: R,
+    sink: &mut impl FnMut(&Value) -> Result<()>,
+) -> Result<()> {
+    /// Size of the read buffer.
+    const BUF_SIZE: usize = 8 * 1024;
+
+    let active_context = initial_context(processor, None, ExpandOptions::new()).await?;
+    let mut splitter = ArraySplitter::new();
+    let mut elements = Vec::new();
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let len = ReadChunk::new(&mut reader, &mut buf).await.map_err(|e| {
+            ErrorCode::LoadingDocumentFailed
+                .and_message(format!("Failed to read the document: {}", e))
+        })?;
+        if len == 0 {
+            break;
+        }
+        splitter.feed(&buf[..len], &mut elements)?;
+        for element in elements.drain(..) {
+            let item = parse_slice(&element)?;
+            expand_array_item(processor, &active_context, &item, sink).await?;
+        }
+    }
+    match splitter.finish()? {
+        Some(document) => {
+            let input = parse_slice(&document)?;
+            expand_document(processor, &active_context, &input, sink).await
+        }
+        None => Ok(()),
+    }
+}
+
+/// Parses the JSON text.
+#[cfg(feature = "futures-io")]
+fn parse_slice(source: &[u8]) -> Result<Value> {
+    serde_json::from_slice(source)
+        .map_err(|e| ErrorCode::LoadingDocumentFailed.and_message(format!("Invalid JSON: {}", e)))
+}
+
+/// Future to read a chunk from the reader.
+#[cfg(feature = "futures-io")]
+struct ReadChunk<'a, R> {
+    /// Reader.
+    reader: &'a mut R,
+    /// Buffer.
+    buf: &'a mut [u8],
+}
+
+#[cfg(feature = "futures-io")]
+impl<'a, R: AsyncRead + Unpin> ReadChunk<'a, R> {
+    /// Creates a new `ReadChunk`.
+    fn new(reader: &'a mut R, buf: &'a mut [u8]) -> Self {
+        Self { reader, buf }
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R: AsyncRead + Unpin> Future for ReadChunk<'_, R> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        Pin::new(&mut *this.reader).poll_read(cx, this.buf)
+    }
+}
+
+/// Expands the element of the top-level array, and passes the results to the sink.
+async fn expand_array_item<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &ActiveContext,
+    item: &Value,
+    sink: &mut impl FnMut(&Value) -> Result<()>,
+) -> Result<()> {
+    let base_url = processor.options().document_iri();
+    let expanded = expand_element(
+        processor,
+        active_context,
+        None,
+        item,
+        base_url,
+        ExpandOptions::new(),
+    )
+    .await?;
+    pass_items(&expanded, sink)
+}
+
+/// Expands the whole document other than an array, and passes the results to the sink.
+///
+/// If the document is a map with only `@context` and `@graph` entries, the items of `@graph`
+/// are expanded and passed one at a time.
+async fn expand_document<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &ActiveContext,
+    input: &Value,
+    sink: &mut impl FnMut(&Value) -> Result<()>,
+) -> Result<()> {
+    if let Value::Object(map) = input {
+        // A previous context would be restored for the map (step 7 of the expansion algorithm),
+        // so leave such a document to the expansion algorithm.
+        if active_context.previous_context().is_none() {
+            if let Some(graph) = graph_entry(map) {
+                return expand_graph_items(processor, active_context, map, graph, sink).await;
+            }
+        }
+    }
+    let base_url = processor.options().document_iri();
+    let expanded = expand_element(
+        processor,
+        active_context,
+        None,
+        input,
+        base_url,
+        ExpandOptions::new(),
+    )
+    .await?;
+    // If expanded output is a map that contains only an `@graph` entry, set expanded output that
+    // value.
+    match &expanded {
+        Value::Object(map) if map.len() == 1 && map.contains_key("@graph") => {
+            pass_items(&map["@graph"], sink)
+        }
+        expanded => pass_items(expanded, sink),
+    }
+}
+
+/// Returns the `@graph` entry of the map, if the map has no entries other than `@context` and
+/// `@graph`.
+///
+/// The expanded document of such a map is the expanded items of `@graph`.
+fn graph_entry(map: &JsonMap<String, Value>) -> Option<&Value> {
+    if map.keys().all(|key| key == "@context" || key == "@graph") {
+        map.get("@graph")
+    } else {
+        None
+    }
+}
+
+/// Expands the items of the `@graph` entry of the top-level map one at a time, and passes the
+/// results to the sink.
+///
+/// This is equivalent to the expansion algorithm for the map with only `@context` and `@graph`
+/// entries.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#expansion-algorithm>.
+async fn expand_graph_items<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &ActiveContext,
+    map: &JsonMap<String, Value>,
+    graph: &Value,
+    sink: &mut impl FnMut(&Value) -> Result<()>,
+) -> Result<()> {
+    let base_url = processor.options().document_iri();
+    let max_depth = processor.options().max_nesting_depth();
+    // The top-level map is the first nesting level.
+    let mut options = ExpandOptions::new().nested(max_depth)?;
+    // Step 9
+    let active_context = match map.get("@context") {
+        Some(local_context) => Cow::Owned(
+            active_context
+                .join_local_context(
+                    processor,
+                    ValueWithBase::new(local_context, base_url),
+                    false,
+                    true,
+                )
+                .await
+                .map_err(|e| e.at_key("@context"))?,
+        ),
+        None => Cow::Borrowed(active_context),
+    };
+    let items = match graph {
+        Value::Array(items) => {
+            options = options.nested(max_depth).map_err(|e| e.at_key("@graph"))?;
+            items.as_slice()
+        }
+        graph => std::slice::from_ref(graph),
+    };
+    // Step 13.4.8, for each item.
+    for (index, item) in items.iter().enumerate() {
+        let expanded = expand_element(
+            processor,
+            &active_context,
+            Some("@graph"),
+            item,
+            base_url,
+            options,
+        )
+        .await
+        .map_err(|e| {
+            let e = if graph.is_array() {
+                e.at_index(index)
+            } else {
+                e
+            };
+            e.at_key("@graph")
+        })?;
+        pass_items(&expanded, sink)?;
+    }
+    Ok(())
+}
+
+/// Passes the items of the expanded element to the sink.
+///
+/// `null` is skipped, and arrays are passed item by item.
+fn pass_items(expanded: &Value, sink: &mut impl FnMut(&Value) -> Result<()>) -> Result<()> {
+    match expanded {
+        Value::Null => Ok(()),
+        Value::Array(items) => {
+            for item in items {
+                sink(item)?;
+            }
+            Ok(())
+        }
+        v => sink(v),
+    }
+}
+
+/// Emits the events for the expanded element.
+fn emit_element<'a>(
+    element: &'a Value,
+    ordered: bool,
+    handler: &mut impl FnMut(ExpansionEvent<'a>),
+) {
+    match element {
+        Value::Array(items) => {
+            for item in items {
+                emit_element(item, ordered, handler);
+            }
+        }
+        Value::Object(map) if map.contains_key("@value") => handler(ExpansionEvent::Value(map)),
+        Value::Object(map) if map.contains_key("@list") => {
+            handler(ExpansionEvent::ListStart);
+            for item in to_ref_array(&map["@list"]) {
+                emit_element(item, ordered, handler);
+            }
+            handler(ExpansionEvent::ListEnd);
+        }
+        Value::Object(map) => emit_node(map, ordered, handler),
+        // Other values do not appear in the expanded form.
+        _ => {}
+    }
+}
+
+/// Emits the events for the expanded node object.
+fn emit_node<'a>(
+    node: &'a JsonMap<String, Value>,
+    ordered: bool,
+    handler: &mut impl FnMut(ExpansionEvent<'a>),
+) {
+    handler(ExpansionEvent::NodeStart(
+        node.get("@id").and_then(Value::as_str),
+    ));
+    if let Some(types) = node.get("@type") {
+        for ty in to_ref_array(types).iter().filter_map(Value::as_str) {
+            handler(ExpansionEvent::Type(ty));
+        }
+    }
+    if let Some(index) = node.get("@index").and_then(Value::as_str) {
+        handler(ExpansionEvent::Index(index));
+    }
+    for (key, value) in map_entries(node, ordered) {
+        match key.as_str() {
+            "@id" | "@type" | "@index" => {}
+            "@reverse" => {
+                handler(ExpansionEvent::ReverseStart);
+                if let Value::Object(reverse_map) = value {
+                    emit_properties(reverse_map, ordered, handler);
+                }
+                handler(ExpansionEvent::ReverseEnd);
+            }
+            _ => emit_property(key, value, ordered, handler),
+        }
+    }
+    handler(ExpansionEvent::NodeEnd);
+}
+
+/// Emits the events for the properties.
+fn emit_properties<'a>(
+    map: &'a JsonMap<String, Value>,
+    ordered: bool,
+    handler: &mut impl FnMut(ExpansionEvent<'a>),
+) {
+    for (property, value) in map_entries(map, ordered) {
+        emit_property(property, value, ordered, handler);
+    }
+}
+
+/// Emits the events for the property and its values.
+fn emit_property<'a>(
+    property: &'a str,
+    value: &'a Value,
+    ordered: bool,
+    handler: &mut impl FnMut(ExpansionEvent<'a>),
+) {
+    handler(ExpansionEvent::PropertyStart(property));
+    emit_element(value, ordered, handler);
+    handler(ExpansionEvent::PropertyEnd);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn emit_expanded_node() {
+        let expanded = json!([{
+            "@id": "http://example.com/a",
+            "@type": ["http://example.com/T"],
+            "http://example.com/p": [
+                {"@value": "v"},
+                {"@list": [{"@id": "http://example.com/b"}]}
+            ]
+        }]);
+        let mut events = Vec::new();
+        emit_element(&expanded, true, &mut |event| events.push(event));
+        assert_eq!(
+            events,
+            vec![
+                ExpansionEvent::NodeStart(Some("http://example.com/a")),
+                ExpansionEvent::Type("http://example.com/T"),
+                ExpansionEvent::PropertyStart("http://example.com/p"),
+                ExpansionEvent::Value(
+                    expanded[0]["http://example.com/p"][0]
+                        .as_object()
+                        .expect("value object")
+                ),
+                ExpansionEvent::ListStart,
+                ExpansionEvent::NodeStart(Some("http://example.com/b")),
+                ExpansionEvent::NodeEnd,
+                ExpansionEvent::ListEnd,
+                ExpansionEvent::PropertyEnd,
+                ExpansionEvent::NodeEnd,
+            ]
+        );
+    }
+}
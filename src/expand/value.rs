@@ -0,0 +1,90 @@
+//! Value expansion.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#value-expansion>.
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    context::Context,
+    error::Result,
+    expand::iri::ExpandIriOptions,
+    json::{single_entry_map, Nullable},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+/// Runs value expansion algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#value-expansion>.
+pub(crate) async fn expand_value<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    active_context: &Context,
+    active_property: &str,
+    value: &Value,
+) -> Result<Value> {
+    let definition = active_context.term_definition(active_property);
+    let type_mapping = definition.and_then(|def| def.ty());
+
+    if let Value::String(s) = value {
+        match type_mapping {
+            // Step 1
+            Some("@id") => {
+                let id = ExpandIriOptions::constant(active_context)
+                    .document_relative(true)
+                    .expand_to_json(processor, s)
+                    .await?;
+                return Ok(Value::Object(single_entry_map("@id", id)));
+            }
+            // Step 2
+            Some("@vocab") => {
+                let id = ExpandIriOptions::constant(active_context)
+                    .vocab(true)
+                    .document_relative(true)
+                    .expand_to_json(processor, s)
+                    .await?;
+                return Ok(Value::Object(single_entry_map("@id", id)));
+            }
+            _ => {}
+        }
+    }
+
+    // Step 3
+    let mut result = JsonMap::new();
+    result.insert("@value".to_owned(), value.clone());
+    match type_mapping {
+        // Step 4
+        Some(ty) if ty != "@id" && ty != "@vocab" && ty != "@none" => {
+            result.insert("@type".to_owned(), Value::String(ty.to_owned()));
+        }
+        // Step 5
+        _ if value.is_string() => {
+            // Step 5.1
+            let language = match definition.and_then(|def| def.language()) {
+                Some(Nullable::Value(lang)) => Some(lang),
+                Some(Nullable::Null) => None,
+                None => active_context.default_language(),
+            };
+            // Step 5.2
+            let direction = match definition.and_then(|def| def.direction()) {
+                Some(Nullable::Value(dir)) => Some(dir),
+                Some(Nullable::Null) => None,
+                None => active_context.default_base_direction(),
+            };
+            // Step 5.3
+            if let Some(language) = language {
+                result.insert("@language".to_owned(), Value::String(language.to_owned()));
+            }
+            // Step 5.4
+            if let Some(direction) = direction {
+                result.insert(
+                    "@direction".to_owned(),
+                    Value::String(direction.as_str().to_owned()),
+                );
+            }
+        }
+        _ => {}
+    }
+
+    // Step 6
+    Ok(Value::Object(result))
+}
@@ -0,0 +1,87 @@
+//! Flattening.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#flattening-algorithm>.
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    compact::compact_expanded,
+    error::Result,
+    expand::expand,
+    json::single_entry_map,
+    node_map::{generate_node_map, BlankNodeIdGenerator, Graph, NodeMap},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+};
+
+/// Runs flattening algorithm for the given document, and returns the flattened document.
+///
+/// If the context is given, the flattened document is compacted using it.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-flatten>.
+pub(crate) async fn flatten<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    context: Option<&Value>,
+) -> Result<Value> {
+    // Step 2
+    let expanded_input = expand(processor, input).await?;
+    // Step 3
+    let mut generator = BlankNodeIdGenerator::new();
+    // Step 4
+    let flattened_output = flatten_expanded(&expanded_input, &mut generator)?;
+    processor.release_memory(&expanded_input);
+    drop(expanded_input);
+    // Step 5
+    match context {
+        Some(context) if !context.is_null() => {
+            compact_expanded(processor, &flattened_output, context, true).await
+        }
+        _ => Ok(flattened_output),
+    }
+}
+
+/// Runs flattening algorithm for the given expanded element.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#flattening-algorithm>.
+pub(crate) fn flatten_expanded(
+    element: &Value,
+    generator: &mut BlankNodeIdGenerator,
+) -> Result<Value> {
+    // Step 1
+    let mut node_map = NodeMap::new();
+    node_map.insert("@default".to_owned(), Graph::new());
+    // Step 2
+    generate_node_map(element, &mut node_map, generator)?;
+    // Step 3
+    let mut default_graph = node_map
+        .remove("@default")
+        .expect("Should never fail: the default graph is inserted at step 1");
+    // Step 4
+    for (graph_name, graph) in node_map {
+        // Step 4.1, 4.2
+        let entry = default_graph
+            .entry(graph_name.clone())
+            .or_insert_with(|| single_entry_map("@id", graph_name));
+        // Step 4.3, 4.4
+        entry.insert("@graph".to_owned(), Value::Array(graph_nodes(graph)));
+    }
+    // Step 5, 6, 7
+    Ok(Value::Array(graph_nodes(default_graph)))
+}
+
+/// Returns the nodes in the graph, except for ones only with `@id` entry.
+// Step 4.4, 6
+fn graph_nodes(graph: Graph) -> Vec<Value> {
+    graph
+        .into_iter()
+        .map(|(_, node)| node)
+        .filter(|node| !is_id_only(node))
+        .map(Value::Object)
+        .collect()
+}
+
+/// Checks whether the given node has only `@id` entry.
+fn is_id_only(node: &JsonMap<String, Value>) -> bool {
+    node.len() == 1 && node.contains_key("@id")
+}
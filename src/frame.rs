@@ -0,0 +1,833 @@
+//! Framing.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#framing-algorithm>.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    budget::MemoryBudget,
+    compact::compact_expanded,
+    context::Context,
+    error::{ErrorCode, Result},
+    expand::{expand, expand_with_options, iri::ExpandIriOptions, ExpandOptions},
+    iri::is_absolute_iri_ref,
+    json::{add_value, into_array, map_entries, single_entry_map, to_ref_array},
+    node_map::{generate_node_map, merge_node_maps, BlankNodeIdGenerator, Graph, NodeMap},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+    syntax::is_blank_node_identifier,
+};
+
+/// Value of `@embed` flag.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#dom-jsonldembed>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Embed {
+    /// `@always`.
+    Always,
+    /// `@once`.
+    Once,
+    /// `@never`.
+    Never,
+}
+
+impl Embed {
+    /// Creates an `Embed` from the `@embed` value.
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Bool(true) => Ok(Embed::Once),
+            Value::Bool(false) => Ok(Embed::Never),
+            Value::String(s) if s == "@always" => Ok(Embed::Always),
+            Value::String(s) if s == "@once" => Ok(Embed::Once),
+            Value::String(s) if s == "@never" => Ok(Embed::Never),
+            v => Err(ErrorCode::InvalidEmbedValue
+                .and_message(format!("Unexpected `@embed` value: {:?}", v))),
+        }
+    }
+
+    /// Returns the `@embed` value.
+    fn to_value(self) -> Value {
+        match self {
+            Embed::Always => Value::String("@always".to_owned()),
+            Embed::Once => Value::String("@once".to_owned()),
+            Embed::Never => Value::String("@never".to_owned()),
+        }
+    }
+}
+
+/// Framing flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameFlags {
+    /// `@embed` flag.
+    embed: Embed,
+    /// `@explicit` flag.
+    explicit: bool,
+    /// `@omitDefault` flag.
+    omit_default: bool,
+    /// `@requireAll` flag.
+    require_all: bool,
+}
+
+impl Default for FrameFlags {
+    fn default() -> Self {
+        Self {
+            embed: Embed::Once,
+            explicit: false,
+            omit_default: false,
+            require_all: false,
+        }
+    }
+}
+
+impl FrameFlags {
+    /// Returns the flags specified by the frame, falling back to `self`.
+    fn for_frame(self, frame: &JsonMap<String, Value>) -> Result<Self> {
+        let embed = match frame_flag(frame, "@embed") {
+            Some(embed) => Embed::from_value(embed)?,
+            None => self.embed,
+        };
+        Ok(Self {
+            embed,
+            explicit: bool_frame_flag(frame, "@explicit", self.explicit)?,
+            omit_default: bool_frame_flag(frame, "@omitDefault", self.omit_default)?,
+            require_all: bool_frame_flag(frame, "@requireAll", self.require_all)?,
+        })
+    }
+
+    /// Returns the implicit frame carrying the flags.
+    fn implicit_frame(self) -> Value {
+        let mut frame = JsonMap::new();
+        frame.insert(
+            "@embed".to_owned(),
+            Value::Array(vec![self.embed.to_value()]),
+        );
+        frame.insert(
+            "@explicit".to_owned(),
+            Value::Array(vec![Value::Bool(self.explicit)]),
+        );
+        frame.insert(
+            "@requireAll".to_owned(),
+            Value::Array(vec![Value::Bool(self.require_all)]),
+        );
+        Value::Object(frame)
+    }
+}
+
+/// Framing state.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#framing-state>.
+struct FramingState<'a> {
+    /// Default flags.
+    defaults: FrameFlags,
+    /// Graph map.
+    graph_map: &'a NodeMap,
+    /// Identifiers of embedded nodes, indexed by graph names.
+    unique_embeds: HashMap<String, HashSet<String>>,
+    /// Pairs of the graph name and the node identifier on the current embedding path.
+    subject_stack: Vec<(String, String)>,
+    /// Identifiers of the nodes referencing each node, indexed by the graph name and the
+    /// property.
+    references: HashMap<(String, String), HashMap<&'a str, Vec<&'a str>>>,
+    /// Memory budget of the operation.
+    memory_budget: Option<&'a MemoryBudget>,
+}
+
+impl<'a> FramingState<'a> {
+    /// Creates a new `FramingState`.
+    fn new(graph_map: &'a NodeMap, memory_budget: Option<&'a MemoryBudget>) -> Self {
+        Self {
+            defaults: FrameFlags::default(),
+            graph_map,
+            unique_embeds: HashMap::new(),
+            subject_stack: Vec::new(),
+            references: HashMap::new(),
+            memory_budget,
+        }
+    }
+
+    /// Returns the identifiers of the nodes in the graph referencing the node by the property.
+    ///
+    /// The references are indexed on the first use for each graph and property, so that
+    /// framing reverse properties does not scan the whole graph for each node.
+    fn referencing_subjects(&mut self, graph: &str, property: &str, id: &str) -> Vec<&'a str> {
+        let graph_map = self.graph_map;
+        let references = self
+            .references
+            .entry((graph.to_owned(), property.to_owned()))
+            .or_insert_with(|| {
+                let mut references = HashMap::<_, Vec<_>>::new();
+                for (subject_id, subject) in graph_map.get(graph).into_iter().flatten() {
+                    for value in subject.get(property).map_or(&[][..], to_ref_array) {
+                        if let Some(referenced) = node_reference_id(value) {
+                            let subjects = references.entry(referenced).or_default();
+                            if subjects.last() != Some(&subject_id.as_str()) {
+                                subjects.push(subject_id.as_str());
+                            }
+                        }
+                    }
+                }
+                references
+            });
+        references.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Checks whether the node is already embedded in the current path.
+    fn creates_circular_reference(&self, graph: &str, id: &str) -> bool {
+        self.subject_stack
+            .iter()
+            .rev()
+            .any(|(stack_graph, stack_id)| stack_graph == graph && stack_id == id)
+    }
+}
+
+/// Runs framing algorithm for the given document, and returns the framed document.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#dom-jsonldprocessor-frame>.
+pub(crate) async fn frame<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    frame: &Value,
+) -> Result<Value> {
+    // Step 2
+    let expanded_input = expand(processor, input).await?;
+    // Step 3
+    let expanded_frame =
+        expand_with_options(processor, frame, ExpandOptions::new().frame_expansion(true)).await?;
+    // Step 4
+    let context = frame.get("@context").unwrap_or(&Value::Null);
+    // Step 5
+    let base_url = processor.options().document_iri();
+    let active_context = Context::with_base(base_url.to_owned())
+        .join_context_value(processor, context, base_url, false)
+        .await?;
+    // Step 6
+    let mut frame_default = false;
+    if let Value::Object(frame) = frame {
+        for key in frame.keys() {
+            if ExpandIriOptions::constant(&active_context)
+                .vocab(true)
+                .expand_str(processor, key)
+                .await?
+                .as_ref()
+                .map(|s| &**s)
+                == Some("@graph")
+            {
+                frame_default = true;
+            }
+        }
+    }
+    // Step 7
+    let mut generator = BlankNodeIdGenerator::new();
+    let mut node_map = NodeMap::new();
+    node_map.insert("@default".to_owned(), Graph::new());
+    generate_node_map(&expanded_input, &mut node_map, &mut generator)?;
+    processor.release_memory(&expanded_input);
+    drop(expanded_input);
+    let graph_name = if frame_default {
+        "@default"
+    } else {
+        let merged = merge_node_maps(&node_map);
+        node_map.insert("@merged".to_owned(), merged);
+        "@merged"
+    };
+    let subjects = node_map[graph_name].keys().cloned().collect::<Vec<_>>();
+    let mut state = FramingState::new(&node_map, processor.memory_budget());
+    let mut framed = Value::Array(frame_subjects(
+        &mut state,
+        graph_name,
+        false,
+        &subjects,
+        &expanded_frame,
+    )?);
+    // Step 8
+    let mut counts = HashMap::new();
+    count_blank_nodes(&framed, &mut counts);
+    prune_blank_node_identifiers(&mut framed, &counts);
+    // Step 9
+    let omit_graph = !processor.is_processing_mode_1_0();
+    let compacted = compact_expanded(processor, &framed, context, !omit_graph).await?;
+    // Step 10
+    Ok(cleanup_compacted(compacted))
+}
+
+/// Frames the given subjects, and returns the output node objects.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#framing-algorithm>.
+fn frame_subjects(
+    state: &mut FramingState<'_>,
+    graph: &str,
+    embedded: bool,
+    subjects: &[String],
+    frame: &Value,
+) -> Result<Vec<Value>> {
+    let graph_map = state.graph_map;
+    // Step 1
+    let frame = validate_frame(frame)?;
+    // Step 2
+    let flags = state.defaults.for_frame(frame)?;
+    let nodes = match graph_map.get(graph) {
+        Some(nodes) => nodes,
+        None => return Ok(Vec::new()),
+    };
+    // Step 3
+    let mut matched = Vec::new();
+    for id in subjects {
+        if let Some(node) = nodes.get(id) {
+            if filter_subject(nodes, node, frame, flags)? {
+                matched.push((id.as_str(), node));
+            }
+        }
+    }
+    matched.sort_by_key(|(id, _)| *id);
+    matched.dedup_by_key(|(id, _)| *id);
+
+    let mut outputs = Vec::new();
+    // Step 4
+    for (id, node) in matched {
+        // Step 4.1
+        let mut output = single_entry_map("@id", id);
+        let is_embedded_elsewhere = state
+            .unique_embeds
+            .get(graph)
+            .map_or(false, |embeds| embeds.contains(id));
+        // Step 4.2: Skip the node already included in another node object.
+        if !embedded && is_embedded_elsewhere {
+            continue;
+        }
+        // Step 4.3, 4.4
+        if embedded
+            && (flags.embed == Embed::Never
+                || state.creates_circular_reference(graph, id)
+                || (flags.embed == Embed::Once && is_embedded_elsewhere))
+        {
+            outputs.push(Value::Object(output));
+            continue;
+        }
+        // Step 4.5
+        state
+            .unique_embeds
+            .entry(graph.to_owned())
+            .or_insert_with(HashSet::new)
+            .insert(id.to_owned());
+        state.subject_stack.push((graph.to_owned(), id.to_owned()));
+        // Step 4.6
+        if let Some(named_graph) = graph_map.get(id) {
+            let (recurse, subframe) = match frame.get("@graph") {
+                None => (graph != "@merged", Value::Object(JsonMap::new())),
+                Some(subframe) => (
+                    id != "@merged" && id != "@default",
+                    match to_ref_array(subframe).first() {
+                        Some(subframe) if subframe.is_object() => subframe.clone(),
+                        _ => Value::Object(JsonMap::new()),
+                    },
+                ),
+            };
+            if recurse {
+                let graph_subjects = named_graph.keys().cloned().collect::<Vec<_>>();
+                let framed = frame_subjects(state, id, false, &graph_subjects, &subframe)?;
+                add_value(&mut output, "@graph", Value::Array(framed), true);
+            }
+        }
+        // Step 4.7
+        if let Some(included_frame) = frame.get("@included") {
+            let framed = frame_subjects(state, graph, false, subjects, included_frame)?;
+            add_value(&mut output, "@included", Value::Array(framed), true);
+        }
+        // Step 4.8
+        for (property, values) in map_entries(node, true) {
+            // Step 4.8.1
+            if property.starts_with('@') {
+                output.insert(property.clone(), values.clone());
+                continue;
+            }
+            // Step 4.8.2
+            if flags.explicit && !frame.contains_key(property) {
+                continue;
+            }
+            // Step 4.8.3
+            let implicit_frame;
+            let subframe = match frame.get(property) {
+                Some(subframe) => subframe,
+                None => {
+                    implicit_frame = flags.implicit_frame();
+                    &implicit_frame
+                }
+            };
+            for value in to_ref_array(values) {
+                if let Some(list) = value.get("@list") {
+                    // Step 4.8.3.1
+                    let implicit_list_frame;
+                    let list_frame = match first_map(subframe).and_then(|f| f.get("@list")) {
+                        Some(list_frame) => list_frame,
+                        None => {
+                            implicit_list_frame = flags.implicit_frame();
+                            &implicit_list_frame
+                        }
+                    };
+                    let mut list_output = Vec::new();
+                    for item in to_ref_array(list) {
+                        match node_reference_id(item) {
+                            Some(item_id) => list_output.extend(frame_subjects(
+                                state,
+                                graph,
+                                true,
+                                &[item_id.to_owned()],
+                                list_frame,
+                            )?),
+                            None => list_output.push(item.clone()),
+                        }
+                    }
+                    let list = single_entry_map("@list", Value::Array(list_output));
+                    add_value(&mut output, property, Value::Object(list), true);
+                } else if let Some(value_id) = node_reference_id(value) {
+                    // Step 4.8.3.2
+                    let framed =
+                        frame_subjects(state, graph, true, &[value_id.to_owned()], subframe)?;
+                    add_value(&mut output, property, Value::Array(framed), true);
+                } else if value_match(first_map(subframe), value) {
+                    // Step 4.8.3.3
+                    add_value(&mut output, property, value.clone(), true);
+                }
+            }
+        }
+        // Step 4.9
+        for (property, property_frame) in frame {
+            if property == "@type" {
+                match to_ref_array(property_frame).first() {
+                    Some(Value::Object(ty)) if ty.contains_key("@default") => {}
+                    _ => continue,
+                }
+            } else if property.starts_with('@') {
+                continue;
+            }
+            // Step 4.9.1
+            let next_frame = first_map(property_frame);
+            let omit_default = match next_frame {
+                Some(next_frame) => {
+                    bool_frame_flag(next_frame, "@omitDefault", flags.omit_default)?
+                }
+                None => flags.omit_default,
+            };
+            // Step 4.9.2
+            if !omit_default && !output.contains_key(property) {
+                let preserve = next_frame
+                    .and_then(|next_frame| next_frame.get("@default"))
+                    .cloned()
+                    .unwrap_or_else(|| Value::String("@null".to_owned()));
+                let preserve = single_entry_map("@preserve", Value::Array(into_array(preserve)));
+                output.insert(
+                    property.clone(),
+                    Value::Array(vec![Value::Object(preserve)]),
+                );
+            }
+        }
+        // Step 4.10
+        if let Some(Value::Object(reverse_frame)) = frame.get("@reverse") {
+            for (reverse_property, subframe) in reverse_frame {
+                for subject_id in state.referencing_subjects(graph, reverse_property, id) {
+                    let framed =
+                        frame_subjects(state, graph, true, &[subject_id.to_owned()], subframe)?;
+                    let reverse = output
+                        .entry("@reverse")
+                        .or_insert_with(|| Value::Object(JsonMap::new()))
+                        .as_object_mut()
+                        .expect("Should never fail: `@reverse` entry of the output is a map");
+                    add_value(reverse, reverse_property, Value::Array(framed), true);
+                }
+            }
+        }
+        // Step 4.11
+        let output = Value::Object(output);
+        if let Some(budget) = state.memory_budget {
+            budget.charge_value(&output)?;
+        }
+        outputs.push(output);
+        state.subject_stack.pop();
+    }
+
+    Ok(outputs)
+}
+
+/// Checks whether the node matches the frame.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#frame-matching>.
+fn filter_subject(
+    nodes: &Graph,
+    node: &JsonMap<String, Value>,
+    frame: &JsonMap<String, Value>,
+    flags: FrameFlags,
+) -> Result<bool> {
+    let mut wildcard = true;
+    let mut matches_some = false;
+    for (key, frame_values) in frame {
+        let frame_values = to_ref_array(frame_values);
+        let node_values = node.get(key).map_or(&[][..], to_ref_array);
+        let matches_this = match key.as_str() {
+            "@id" => {
+                let matches_this = match frame_values.first() {
+                    None => true,
+                    Some(Value::Object(id)) if id.is_empty() => true,
+                    Some(_) => node_values
+                        .first()
+                        .map_or(false, |id| frame_values.contains(id)),
+                };
+                if !flags.require_all {
+                    return Ok(matches_this);
+                }
+                matches_this
+            }
+            "@type" => {
+                wildcard = false;
+                if frame_values.is_empty() {
+                    if !node_values.is_empty() {
+                        return Ok(false);
+                    }
+                    true
+                } else if frame_values.len() == 1 && is_empty_map(&frame_values[0]) {
+                    !node_values.is_empty()
+                } else {
+                    let matches_this = frame_values.iter().any(|ty| {
+                        ty.as_object()
+                            .map_or(false, |ty| ty.contains_key("@default"))
+                            || node_values.contains(ty)
+                    });
+                    if !flags.require_all {
+                        return Ok(matches_this);
+                    }
+                    matches_this
+                }
+            }
+            key if key.starts_with('@') => continue,
+            _ => {
+                let property_frame = match frame_values.first() {
+                    Some(property_frame) => Some(validate_frame(property_frame)?),
+                    None => None,
+                };
+                let has_default =
+                    property_frame.map_or(false, |frame| frame.contains_key("@default"));
+                wildcard = false;
+                // Allow match if the node has no value for the property and the frame has a
+                // default value.
+                if node_values.is_empty() && has_default {
+                    continue;
+                }
+                // Match none if the frame value is empty.
+                if !node_values.is_empty() && frame_values.is_empty() {
+                    return Ok(false);
+                }
+                match property_frame {
+                    None => true,
+                    Some(property_frame) => {
+                        if let Some(list_frame) = property_frame.get("@list") {
+                            let node_list = node_values.first().and_then(|v| v.get("@list"));
+                            match (to_ref_array(list_frame).first(), node_list) {
+                                (Some(Value::Object(pattern)), Some(node_list)) => {
+                                    if pattern.contains_key("@value") {
+                                        to_ref_array(node_list)
+                                            .iter()
+                                            .any(|v| value_match(Some(pattern), v))
+                                    } else {
+                                        let mut matches_this = false;
+                                        for v in to_ref_array(node_list) {
+                                            if node_match(nodes, pattern, v, flags)? {
+                                                matches_this = true;
+                                                break;
+                                            }
+                                        }
+                                        matches_this
+                                    }
+                                }
+                                _ => false,
+                            }
+                        } else if property_frame.contains_key("@value") {
+                            node_values
+                                .iter()
+                                .any(|v| value_match(Some(property_frame), v))
+                        } else if property_frame.len() == 1 && property_frame.contains_key("@id") {
+                            let mut matches_this = false;
+                            for v in node_values {
+                                if node_match(nodes, property_frame, v, flags)? {
+                                    matches_this = true;
+                                    break;
+                                }
+                            }
+                            matches_this
+                        } else {
+                            !node_values.is_empty()
+                        }
+                    }
+                }
+            }
+        };
+        // All non-defaulted values must match if `@requireAll` is set.
+        if !matches_this && flags.require_all {
+            return Ok(false);
+        }
+        matches_some = matches_some || matches_this;
+    }
+
+    Ok(wildcard || matches_some)
+}
+
+/// Checks whether the node referenced by the value matches the frame.
+fn node_match(
+    nodes: &Graph,
+    pattern: &JsonMap<String, Value>,
+    value: &Value,
+    flags: FrameFlags,
+) -> Result<bool> {
+    match value
+        .get("@id")
+        .and_then(Value::as_str)
+        .and_then(|id| nodes.get(id))
+    {
+        Some(node) => filter_subject(nodes, node, pattern, flags),
+        None => Ok(false),
+    }
+}
+
+/// Checks whether the value object matches the value pattern.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#value-pattern-matching>.
+fn value_match(pattern: Option<&JsonMap<String, Value>>, value: &Value) -> bool {
+    let (v2, t2, l2) = (
+        pattern_values(pattern, "@value"),
+        pattern_values(pattern, "@type"),
+        pattern_values(pattern, "@language"),
+    );
+    // Step 1
+    if v2.is_empty() && t2.is_empty() && l2.is_empty() {
+        return true;
+    }
+    let (v1, t1, l1) = (
+        value.get("@value"),
+        value.get("@type"),
+        value.get("@language"),
+    );
+    // Step 2
+    let is_wildcard = |values: &[Value]| values.first().map_or(false, is_empty_map);
+    if !(v1.map_or(false, |v1| v2.contains(v1)) || is_wildcard(v2)) {
+        return false;
+    }
+    if !((t1.is_none() && t2.is_empty())
+        || t1.map_or(false, |t1| t2.contains(t1))
+        || (t1.is_some() && is_wildcard(t2)))
+    {
+        return false;
+    }
+    let language_matches = |l1: &Value| match l1.as_str() {
+        Some(l1) => l2
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|l2| l2.eq_ignore_ascii_case(l1)),
+        None => false,
+    };
+    (l1.is_none() && l2.is_empty())
+        || l1.map_or(false, language_matches)
+        || (l1.is_some() && is_wildcard(l2))
+}
+
+/// Returns the values of the pattern entry.
+fn pattern_values<'a>(pattern: Option<&'a JsonMap<String, Value>>, key: &str) -> &'a [Value] {
+    pattern
+        .and_then(|pattern| pattern.get(key))
+        .map_or(&[][..], to_ref_array)
+}
+
+/// Validates the frame, and returns the frame as a map.
+fn validate_frame(frame: &Value) -> Result<&JsonMap<String, Value>> {
+    let frame = match frame {
+        Value::Array(items) if items.len() == 1 => &items[0],
+        Value::Array(items) => {
+            return Err(ErrorCode::InvalidFrame.and_message(format!(
+                "Expected single frame, but got {} frames",
+                items.len()
+            )))
+        }
+        v => v,
+    };
+    let frame = frame.as_object().ok_or_else(|| {
+        ErrorCode::InvalidFrame.and_message(format!("Expected map as a frame, but got {:?}", frame))
+    })?;
+    for key in &["@id", "@type"] {
+        for value in frame.get(*key).map_or(&[][..], to_ref_array) {
+            match value {
+                Value::Object(_) => {}
+                Value::String(s) if is_absolute_iri_ref(s) && !is_blank_node_identifier(s) => {}
+                v => {
+                    return Err(ErrorCode::InvalidFrame.and_message(format!(
+                        "Expected an IRI or a map as `{}` in the frame, but got {:?}",
+                        key, v
+                    )))
+                }
+            }
+        }
+    }
+
+    Ok(frame)
+}
+
+/// Returns the framing flag value.
+fn frame_flag<'a>(frame: &'a JsonMap<String, Value>, flag: &str) -> Option<&'a Value> {
+    let value = to_ref_array(frame.get(flag)?).first()?;
+    Some(value.get("@value").unwrap_or(value))
+}
+
+/// Returns the boolean framing flag value.
+fn bool_frame_flag(frame: &JsonMap<String, Value>, flag: &str, default: bool) -> Result<bool> {
+    match frame_flag(frame, flag) {
+        None => Ok(default),
+        Some(Value::Bool(v)) => Ok(*v),
+        Some(v) => Err(ErrorCode::InvalidFrame.and_message(format!(
+            "Expected boolean as `{}` value, but got {:?}",
+            flag, v
+        ))),
+    }
+}
+
+/// Returns the first item of the value if it is a map.
+fn first_map(value: &Value) -> Option<&JsonMap<String, Value>> {
+    to_ref_array(value).first().and_then(Value::as_object)
+}
+
+/// Returns the identifier if the value is a node reference.
+fn node_reference_id(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(map) if map.len() == 1 => map.get("@id").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// Checks whether the value is an empty map.
+fn is_empty_map(value: &Value) -> bool {
+    value.as_object().map_or(false, JsonMap::is_empty)
+}
+
+/// Counts occurrences of blank node identifiers used as node identifiers or types.
+fn count_blank_nodes(value: &Value, counts: &mut HashMap<String, usize>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                count_blank_nodes(item, counts);
+            }
+        }
+        Value::Object(map) if !map.contains_key("@value") => {
+            let ids = map.get("@id").into_iter();
+            let types = map.get("@type").map_or(&[][..], to_ref_array).iter();
+            for id in ids.chain(types).filter_map(Value::as_str) {
+                if is_blank_node_identifier(id) {
+                    *counts.entry(id.to_owned()).or_insert(0) += 1;
+                }
+            }
+            for (key, value) in map {
+                if key != "@id" && key != "@type" {
+                    count_blank_nodes(value, counts);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes `@id` of blank nodes which appear only once.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#dom-jsonldoptions-pruneblanknodeidentifiers>.
+fn prune_blank_node_identifiers(value: &mut Value, counts: &HashMap<String, usize>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                prune_blank_node_identifiers(item, counts);
+            }
+        }
+        Value::Object(map) if !map.contains_key("@value") => {
+            let is_used_once = map
+                .get("@id")
+                .and_then(Value::as_str)
+                .map_or(false, |id| counts.get(id) == Some(&1));
+            if is_used_once {
+                map.remove("@id");
+            }
+            for (key, value) in map.iter_mut() {
+                if key != "@type" {
+                    prune_blank_node_identifiers(value, counts);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces `@preserve` entries with their values, and `@null` with `null`.
+///
+/// `null` values in arrays are removed.
+fn cleanup_compacted(value: Value) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(cleanup_compacted)
+                .filter(|item| !item.is_null())
+                .collect(),
+        ),
+        Value::Object(mut map) => {
+            if let Some(preserved) = map.remove("@preserve") {
+                return cleanup_compacted(preserved);
+            }
+            Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| match key.as_str() {
+                        "@context" => (key, value),
+                        _ => (key, cleanup_compacted(value)),
+                    })
+                    .collect(),
+            )
+        }
+        Value::String(s) if s == "@null" => Value::Null,
+        v => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn value_pattern() {
+        let value = json!({"@value": "foo", "@language": "en-US"});
+        let pattern = |v: Value| v.as_object().cloned().expect("map");
+        assert!(value_match(None, &value));
+        assert!(value_match(
+            Some(&pattern(json!({"@value": [{}], "@language": ["en-us"]}))),
+            &value
+        ));
+        assert!(!value_match(
+            Some(&pattern(json!({"@value": ["foo"], "@type": [{}]}))),
+            &value
+        ));
+        assert!(!value_match(
+            Some(&pattern(json!({"@value": ["bar"]}))),
+            &value
+        ));
+    }
+
+    #[test]
+    fn cleanup() {
+        let compacted = json!({
+            "@context": {"ex": "http://example.com/"},
+            "ex:a": {"@preserve": "@null"},
+            "ex:b": [{"@preserve": "foo"}, "@null"],
+        });
+        assert_eq!(
+            cleanup_compacted(compacted),
+            json!({
+                "@context": {"ex": "http://example.com/"},
+                "ex:a": null,
+                "ex:b": ["foo"],
+            })
+        );
+    }
+}
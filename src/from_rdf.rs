@@ -0,0 +1,634 @@
+//! Serialization of RDF as JSON-LD.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#serialize-rdf-as-json-ld-algorithm>.
+
+use std::{borrow::Borrow, collections::HashMap};
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    error::{ErrorCode, Result},
+    json::single_entry_map,
+    node_map::{array_entry, push_unique, Graph, NodeMap},
+    options::RdfDirection,
+    processor::Processor,
+    rdf::{
+        GraphName, Literal, Object, Quad, I18N_BASE, RDF_DIRECTION, RDF_FIRST, RDF_JSON,
+        RDF_LANGUAGE, RDF_LIST, RDF_NIL, RDF_REST, RDF_TYPE, RDF_VALUE, XSD_BOOLEAN, XSD_DOUBLE,
+        XSD_INTEGER, XSD_STRING,
+    },
+    remote::LoadRemoteDocument,
+    syntax::is_blank_node_identifier,
+};
+
+/// Options for serialization of RDF as JSON-LD.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FromRdfOptions {
+    /// `useNativeTypes` flag.
+    use_native_types: bool,
+    /// `useRdfType` flag.
+    use_rdf_type: bool,
+    /// Method of representing `@direction` in RDF.
+    rdf_direction: Option<RdfDirection>,
+}
+
+impl FromRdfOptions {
+    /// Creates a new default `FromRdfOptions`.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `useNativeTypes` flag.
+    pub(crate) fn use_native_types(self, use_native_types: bool) -> Self {
+        Self {
+            use_native_types,
+            ..self
+        }
+    }
+
+    /// Sets the `useRdfType` flag.
+    pub(crate) fn use_rdf_type(self, use_rdf_type: bool) -> Self {
+        Self {
+            use_rdf_type,
+            ..self
+        }
+    }
+
+    /// Sets the method of representing `@direction` in RDF.
+    pub(crate) fn rdf_direction(self, rdf_direction: Option<RdfDirection>) -> Self {
+        Self {
+            rdf_direction,
+            ..self
+        }
+    }
+}
+
+/// Usage of a node as an object.
+#[derive(Debug, Clone)]
+struct Usage {
+    /// Subject node identifier.
+    subject: String,
+    /// Property.
+    property: String,
+    /// Value referencing the node.
+    value: Value,
+}
+
+/// Runs serialization of RDF as JSON-LD for the given dataset, and returns the expanded
+/// document.
+///
+/// The dataset can be any iterator of quads, but the whole graph map is built in memory before
+/// the document is returned.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-fromrdf>.
+pub(crate) fn from_rdf<L: LoadRemoteDocument, Q: Borrow<Quad>>(
+    processor: &Processor<L>,
+    dataset: impl IntoIterator<Item = Q>,
+    options: FromRdfOptions,
+) -> Result<Value> {
+    // Step 1, 2
+    let mut graph_map = NodeMap::new();
+    graph_map.insert("@default".to_owned(), Graph::new());
+    // Step 3
+    let mut referenced_once: HashMap<String, Option<Usage>> = HashMap::new();
+    // Usages of `rdf:nil` in each graph.
+    let mut nil_usages: HashMap<String, Vec<Usage>> = HashMap::new();
+
+    // Step 5
+    for quad in dataset {
+        let quad = quad.borrow();
+        // Step 5.1
+        let name = quad.graph().map_or("@default", GraphName::as_str);
+        // Step 5.2
+        graph_map.entry(name.to_owned()).or_insert_with(Graph::new);
+        // Step 5.4
+        if name != "@default" {
+            graph_map
+                .get_mut("@default")
+                .expect("Should never fail: the default graph is inserted at step 2")
+                .entry(name.to_owned())
+                .or_insert_with(|| single_entry_map("@id", name));
+        }
+        // Step 5.5
+        let node_map = graph_map
+            .get_mut(name)
+            .expect("Should never fail: the graph is inserted at step 5.2");
+
+        // Step 5.6
+        let subject = quad.subject().as_str();
+        let predicate = quad.predicate().as_str();
+        let object = quad.object();
+        let object_id = term_to_id(object);
+        // Step 5.6.1
+        node_map
+            .entry(subject.to_owned())
+            .or_insert_with(|| single_entry_map("@id", subject));
+        // Step 5.6.4
+        if let Some(object_id) = object_id {
+            node_map
+                .entry(object_id.to_owned())
+                .or_insert_with(|| single_entry_map("@id", object_id));
+        }
+        // Step 5.6.2
+        let node = node_map
+            .get_mut(subject)
+            .expect("Should never fail: the node is inserted at step 5.6.1");
+        // Step 5.6.5
+        if predicate == RDF_TYPE && !options.use_rdf_type {
+            if let Some(object_id) = object_id {
+                push_unique(
+                    array_entry(node, "@type"),
+                    Value::String(object_id.to_owned()),
+                );
+                continue;
+            }
+        }
+        // Step 5.6.6
+        let value = rdf_to_object(processor, object, options)?;
+        // Step 5.6.7, 5.6.8
+        push_unique(array_entry(node, predicate), value.clone());
+        let usage = Usage {
+            subject: subject.to_owned(),
+            property: predicate.to_owned(),
+            value,
+        };
+        match object {
+            // Step 5.6.9
+            Object::Iri(iri) if iri == RDF_NIL => nil_usages
+                .entry(name.to_owned())
+                .or_insert_with(Vec::new)
+                .push(usage),
+            // Step 5.6.10, 5.6.11
+            Object::BlankNode(id) => {
+                referenced_once
+                    .entry(id.clone())
+                    .and_modify(|usage| *usage = None)
+                    .or_insert_with(|| Some(usage));
+            }
+            _ => {}
+        }
+    }
+
+    // Step 6
+    for (name, graph) in &mut graph_map {
+        // Step 6.2, 6.3, 6.4
+        for usage in nil_usages.remove(name).unwrap_or_default() {
+            convert_list(graph, &referenced_once, usage);
+        }
+        if options.rdf_direction == Some(RdfDirection::CompoundLiteral) {
+            convert_compound_literals(graph, &referenced_once);
+        }
+    }
+
+    // Step 7
+    let mut result = Vec::new();
+    let default_graph = graph_map
+        .remove("@default")
+        .expect("Should never fail: the default graph is inserted at step 2");
+    // Step 8
+    for (subject, mut node) in default_graph {
+        // Step 8.1
+        if let Some(graph) = graph_map.remove(&subject) {
+            // Step 8.1.1, 8.1.2
+            let nodes = graph
+                .into_iter()
+                .map(|(_, node)| node)
+                .filter(|node| !is_id_only(node))
+                .map(Value::Object)
+                .collect();
+            node.insert("@graph".to_owned(), Value::Array(nodes));
+        }
+        // Step 8.2
+        if !is_id_only(&node) {
+            result.push(Value::Object(node));
+        }
+    }
+
+    // Step 9
+    Ok(Value::Array(result))
+}
+
+/// Converts the list nodes ending with the given usage of `rdf:nil` into a list object.
+// Step 6.4
+fn convert_list(graph: &mut Graph, referenced_once: &HashMap<String, Option<Usage>>, usage: Usage) {
+    // Step 6.4.1
+    let Usage {
+        mut subject,
+        mut property,
+        mut value,
+    } = usage;
+    // Step 6.4.2
+    let mut list = Vec::new();
+    let mut list_nodes = Vec::new();
+    // Step 6.4.3
+    while property == RDF_REST {
+        let node = match graph.get(&subject) {
+            Some(node) => node,
+            None => break,
+        };
+        let node_usage = match referenced_once.get(&subject) {
+            Some(Some(node_usage)) if is_well_formed_list_node(&subject, node) => node_usage,
+            _ => break,
+        };
+        // Step 6.4.3.1
+        list.push(node[RDF_FIRST][0].clone());
+        // Step 6.4.3.2
+        list_nodes.push(subject.clone());
+        // Step 6.4.3.3, 6.4.3.4
+        subject = node_usage.subject.clone();
+        property = node_usage.property.clone();
+        value = node_usage.value.clone();
+        // Step 6.4.3.5
+        if !is_blank_node_identifier(&subject) {
+            break;
+        }
+    }
+    // Step 6.4.5
+    list.reverse();
+    // Step 6.4.4, 6.4.6
+    if let Some(values) = graph
+        .get_mut(&subject)
+        .and_then(|node| node.get_mut(&property))
+        .and_then(Value::as_array_mut)
+    {
+        if let Some(head) = values.iter_mut().find(|v| **v == value) {
+            *head = Value::Object(single_entry_map("@list", Value::Array(list)));
+        }
+    }
+    // Step 6.4.7
+    for node_id in list_nodes {
+        graph.remove(&node_id);
+    }
+}
+
+/// Converts the compound literal nodes referenced only once into value objects with base
+/// directions.
+///
+/// This is used when `rdfDirection` is `compound-literal`.
+fn convert_compound_literals(graph: &mut Graph, referenced_once: &HashMap<String, Option<Usage>>) {
+    let literal_nodes = graph
+        .iter()
+        .filter(|(id, node)| is_compound_literal_node(id, node))
+        .map(|(id, _)| id.clone())
+        .collect::<Vec<_>>();
+    for id in literal_nodes {
+        let usage = match referenced_once.get(&id) {
+            Some(Some(usage)) => usage,
+            _ => continue,
+        };
+        let node = &graph[&id];
+        let mut literal = JsonMap::new();
+        for (property, key) in &[
+            (RDF_VALUE, "@value"),
+            (RDF_LANGUAGE, "@language"),
+            (RDF_DIRECTION, "@direction"),
+        ] {
+            if let Some(value) = node
+                .get(*property)
+                .and_then(|values| values[0].get("@value"))
+            {
+                literal.insert((*key).to_owned(), value.clone());
+            }
+        }
+        let replaced = match graph
+            .get_mut(&usage.subject)
+            .and_then(|node| node.get_mut(&usage.property))
+            .and_then(Value::as_array_mut)
+            .and_then(|values| values.iter_mut().find(|v| **v == usage.value))
+        {
+            Some(value) => {
+                *value = Value::Object(literal);
+                true
+            }
+            None => false,
+        };
+        if replaced {
+            graph.remove(&id);
+        }
+    }
+}
+
+/// Checks whether the node is a compound literal node with `rdf:value`, `rdf:direction`, and
+/// optional `rdf:language`, each of which has a single string value.
+fn is_compound_literal_node(id: &str, node: &JsonMap<String, Value>) -> bool {
+    if !is_blank_node_identifier(id) {
+        return false;
+    }
+    let has_single_string = |key: &str| {
+        node.get(key)
+            .and_then(Value::as_array)
+            .filter(|values| values.len() == 1)
+            .and_then(|values| values[0].get("@value"))
+            .map_or(false, Value::is_string)
+    };
+    if !has_single_string(RDF_VALUE) || !has_single_string(RDF_DIRECTION) {
+        return false;
+    }
+    node.iter().all(|(key, _)| match key.as_str() {
+        "@id" | RDF_VALUE | RDF_DIRECTION => true,
+        RDF_LANGUAGE => has_single_string(RDF_LANGUAGE),
+        _ => false,
+    })
+}
+
+/// Checks whether the node is a well-formed list node.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dfn-well-formed-list-node>.
+fn is_well_formed_list_node(id: &str, node: &JsonMap<String, Value>) -> bool {
+    if !is_blank_node_identifier(id) {
+        return false;
+    }
+    let has_single_value = |key: &str| {
+        node.get(key)
+            .and_then(Value::as_array)
+            .map_or(false, |values| values.len() == 1)
+    };
+    if !has_single_value(RDF_FIRST) || !has_single_value(RDF_REST) {
+        return false;
+    }
+    node.iter().all(|(key, value)| match key.as_str() {
+        "@id" => true,
+        "@type" => *value == Value::Array(vec![Value::String(RDF_LIST.to_owned())]),
+        key => key == RDF_FIRST || key == RDF_REST,
+    })
+}
+
+/// Converts the RDF term into a JSON-LD object.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#rdf-to-object-conversion>.
+fn rdf_to_object<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    object: &Object,
+    options: FromRdfOptions,
+) -> Result<Value> {
+    let literal = match object {
+        // Step 1
+        Object::Iri(id) | Object::BlankNode(id) => {
+            return Ok(Value::Object(single_entry_map("@id", id.as_str())))
+        }
+        Object::Literal(literal) => literal,
+    };
+    // Step 2.1
+    let mut result = JsonMap::new();
+    // Step 2.6
+    if !processor.is_processing_mode_1_0()
+        && options.rdf_direction == Some(RdfDirection::I18nDatatype)
+    {
+        if let Some((language, direction)) = parse_i18n_datatype(literal.datatype()) {
+            result.insert(
+                "@value".to_owned(),
+                Value::String(literal.value().to_owned()),
+            );
+            if !language.is_empty() {
+                result.insert("@language".to_owned(), Value::String(language.to_owned()));
+            }
+            result.insert("@direction".to_owned(), Value::String(direction.to_owned()));
+            return Ok(Value::Object(result));
+        }
+    }
+    // Step 2.2, 2.3, 2.4-2.8
+    let (converted_value, ty) = convert_literal(processor, literal, options)?;
+    if let Some(language) = literal.language() {
+        // Step 2.7
+        result.insert("@language".to_owned(), Value::String(language.to_owned()));
+    }
+    // Step 2.9
+    result.insert("@value".to_owned(), converted_value);
+    // Step 2.10
+    if let Some(ty) = ty {
+        result.insert("@type".to_owned(), Value::String(ty.to_owned()));
+    }
+
+    // Step 2.11
+    Ok(Value::Object(result))
+}
+
+/// Splits the `i18n` datatype IRI into the language and the base direction.
+///
+/// The language is empty if the datatype has no language part.
+fn parse_i18n_datatype(datatype: &str) -> Option<(&str, &str)> {
+    if !datatype.starts_with(I18N_BASE) {
+        return None;
+    }
+    let fragment = &datatype[I18N_BASE.len()..];
+    let underscore = fragment.find('_')?;
+    Some((&fragment[..underscore], &fragment[(underscore + 1)..]))
+}
+
+/// Converts the literal into a JSON value and its type.
+// Step 2.2-2.8
+fn convert_literal<'a, L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    literal: &'a Literal,
+    options: FromRdfOptions,
+) -> Result<(Value, Option<&'a str>)> {
+    let value = literal.value();
+    let datatype = literal.datatype();
+    let string = || Value::String(value.to_owned());
+    // Step 2.4
+    if options.use_native_types {
+        if let Some(native) = to_native_value(value, datatype) {
+            return Ok((native, None));
+        }
+    }
+    // Step 2.5
+    if !processor.is_processing_mode_1_0() && datatype == RDF_JSON {
+        let json = serde_json::from_str(value).map_err(|e| {
+            ErrorCode::InvalidJsonLiteral
+                .and_message(format!("Invalid JSON literal {:?}: {}", value, e))
+        })?;
+        return Ok((json, Some("@json")));
+    }
+    // Step 2.7
+    if literal.language().is_some() {
+        return Ok((string(), None));
+    }
+    // Step 2.8
+    if datatype == XSD_STRING {
+        Ok((string(), None))
+    } else {
+        Ok((string(), Some(datatype)))
+    }
+}
+
+/// Converts the lexical form of `xsd:string`, `xsd:boolean`, `xsd:integer`, or `xsd:double`
+/// into a native JSON value.
+///
+/// Returns `None` for other datatypes and invalid lexical forms, so that the literal is kept as
+/// a typed value.
+// Step 2.4
+fn to_native_value(value: &str, datatype: &str) -> Option<Value> {
+    match datatype {
+        // Step 2.4.1
+        XSD_STRING => Some(Value::String(value.to_owned())),
+        // Step 2.4.2
+        XSD_BOOLEAN => match value {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        // Step 2.4.3
+        XSD_INTEGER | XSD_DOUBLE => parse_numeric(value, datatype == XSD_INTEGER),
+        _ => None,
+    }
+}
+
+/// Parses the lexical form of `xsd:integer` or `xsd:double`.
+///
+/// Returns `None` if the value is not a valid lexical form.
+fn parse_numeric(value: &str, is_integer: bool) -> Option<Value> {
+    let digits = value.trim_start_matches(|c| c == '+' || c == '-');
+    if !digits.bytes().any(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if is_integer {
+        if !digits.bytes().all(|b| b.is_ascii_digit()) || value.len() - digits.len() > 1 {
+            return None;
+        }
+        return value
+            .parse::<i64>()
+            .ok()
+            .map(Into::into)
+            .or_else(|| value.parse::<u64>().ok().map(Into::into));
+    }
+    if !digits
+        .bytes()
+        .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+    {
+        return None;
+    }
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(Value::Number)
+}
+
+/// Returns the node identifier of the IRI or blank node.
+fn term_to_id(term: &Object) -> Option<&str> {
+    match term {
+        Object::Iri(id) | Object::BlankNode(id) => Some(id),
+        Object::Literal(_) => None,
+    }
+}
+
+/// Checks whether the given node has only `@id` entry.
+fn is_id_only(node: &JsonMap<String, Value>) -> bool {
+    node.len() == 1 && node.contains_key("@id")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{io, sync::Arc};
+
+    use iri_string::types::IriStr;
+    use serde_json::json;
+
+    use crate::{
+        processor::ProcessorOptions,
+        rdf::{Predicate, Subject},
+        remote::{LoadDocumentOptions, RemoteDocument},
+    };
+
+    /// Loader which loads nothing.
+    struct NoLoader;
+
+    #[async_trait::async_trait]
+    impl LoadRemoteDocument for NoLoader {
+        type Error = io::Error;
+
+        async fn load(
+            &self,
+            _iri: &IriStr,
+            _options: LoadDocumentOptions,
+        ) -> std::result::Result<Arc<RemoteDocument>, Self::Error> {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no remote documents",
+            ))
+        }
+    }
+
+    /// Creates a processor for tests.
+    fn processor() -> Processor<NoLoader> {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        ProcessorOptions::with_base(base.to_owned()).build(NoLoader)
+    }
+
+    #[test]
+    fn numeric_lexical_form() {
+        assert_eq!(parse_numeric("42", true), Some(Value::from(42)));
+        assert_eq!(parse_numeric("-042", true), Some(Value::from(-42)));
+        assert_eq!(parse_numeric("4.2", true), None);
+        assert_eq!(parse_numeric("4.2E1", false), Some(Value::from(42.0)));
+        assert_eq!(parse_numeric("NaN", false), None);
+        assert_eq!(parse_numeric("+-1", true), None);
+    }
+
+    #[test]
+    fn native_types() {
+        assert_eq!(
+            to_native_value("true", XSD_BOOLEAN),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(to_native_value("1", XSD_BOOLEAN), None);
+        assert_eq!(to_native_value("7", XSD_INTEGER), Some(Value::from(7)));
+        assert_eq!(to_native_value("1.5E0", XSD_DOUBLE), Some(Value::from(1.5)));
+        assert_eq!(to_native_value("foo", XSD_STRING), Some(Value::from("foo")));
+        assert_eq!(
+            to_native_value("2020-01-01", "http://www.w3.org/2001/XMLSchema#date"),
+            None
+        );
+    }
+
+    #[test]
+    fn rdf_type() {
+        let dataset = vec![Quad::new(
+            Subject::Iri("http://example.com/s".to_owned()),
+            Predicate::Iri(RDF_TYPE.to_owned()),
+            Object::Iri("http://example.com/T".to_owned()),
+            None,
+        )];
+        let processor = processor();
+        assert_eq!(
+            from_rdf(&processor, &dataset, FromRdfOptions::new()).expect("valid dataset"),
+            json!([{"@id": "http://example.com/s", "@type": ["http://example.com/T"]}])
+        );
+        assert_eq!(
+            from_rdf(
+                &processor,
+                &dataset,
+                FromRdfOptions::new().use_rdf_type(true)
+            )
+            .expect("valid dataset"),
+            json!([{
+                "@id": "http://example.com/s",
+                RDF_TYPE: [{"@id": "http://example.com/T"}],
+            }])
+        );
+    }
+
+    #[test]
+    fn i18n_datatype() {
+        let dataset = vec![Quad::new(
+            Subject::Iri("http://example.com/s".to_owned()),
+            Predicate::Iri("http://example.com/p".to_owned()),
+            Object::Literal(Literal::new("foo", format!("{}en-us_rtl", I18N_BASE), None)),
+            None,
+        )];
+        let options = FromRdfOptions::new().rdf_direction(Some(RdfDirection::I18nDatatype));
+        assert_eq!(
+            from_rdf(&processor(), &dataset, options).expect("valid dataset"),
+            json!([{
+                "@id": "http://example.com/s",
+                "http://example.com/p": [{
+                    "@value": "foo",
+                    "@language": "en-us",
+                    "@direction": "rtl",
+                }],
+            }])
+        );
+    }
+}
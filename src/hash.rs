@@ -0,0 +1,122 @@
+//! Hash maps and sets used by the processor.
+//!
+//! With `fxhash` feature, maps keyed by terms and IRIs use FxHash instead of SipHash.
+//! FxHash is considerably faster for short keys, but it is not resistant to hash flooding, so
+//! enable the feature only when the contexts to be processed are trusted.
+
+/// Hasher builder for maps and sets used by the processor.
+#[cfg(feature = "fxhash")]
+pub(crate) type BuildHasher = fxhash::FxBuildHasher;
+
+/// Hasher builder for maps and sets used by the processor.
+#[cfg(not(feature = "fxhash"))]
+pub(crate) type BuildHasher = std::collections::hash_map::RandomState;
+
+/// Hash map with the processor hasher.
+pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasher>;
+
+/// Hash set with the processor hasher.
+pub(crate) type HashSet<T> = std::collections::HashSet<T, BuildHasher>;
+
+/// Hash map with the maximum number of entries.
+///
+/// When the map is full, the least recently used entry is evicted on insertion.
+#[derive(Debug, Clone)]
+pub(crate) struct LruMap<K, V> {
+    /// Entries with the logical time of the last use.
+    entries: HashMap<K, (V, u64)>,
+    /// Maximum number of entries.
+    max_entries: usize,
+    /// Logical clock, incremented on every use.
+    clock: u64,
+}
+
+impl<K: Eq + std::hash::Hash + Clone, V> LruMap<K, V> {
+    /// Creates a new empty `LruMap`.
+    pub(crate) fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::default(),
+            max_entries,
+            clock: 0,
+        }
+    }
+
+    /// Returns the value for the key, and marks it as recently used.
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        let now = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        entry.1 = now;
+        Some(&entry.0)
+    }
+
+    /// Returns the value for the key, inserting the value created by `f` if not found.
+    pub(crate) fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> &mut V {
+        let now = self.tick();
+        if !self.entries.contains_key(&key) {
+            self.evict_if_full();
+        }
+        let entry = self.entries.entry(key).or_insert_with(|| (f(), now));
+        entry.1 = now;
+        &mut entry.0
+    }
+
+    /// Inserts the value for the key.
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        let now = self.tick();
+        if !self.entries.contains_key(&key) {
+            self.evict_if_full();
+        }
+        self.entries.insert(key, (value, now));
+    }
+
+    /// Removes all entries.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Advances the logical clock and returns the new time.
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Evicts the least recently used entry if the map is full.
+    fn evict_if_full(&mut self) {
+        if self.entries.len() < self.max_entries {
+            return;
+        }
+        let lru_key = self
+            .entries
+            .iter()
+            .min_by_key(|(_, (_, used_at))| *used_at)
+            .map(|(key, _)| key.clone());
+        if let Some(lru_key) = lru_key {
+            self.entries.remove(&lru_key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_eviction() {
+        let mut map = LruMap::new(2);
+        map.insert("a", 1);
+        map.insert("b", 2);
+        assert_eq!(map.get(&"a"), Some(&1));
+        // `b` is the least recently used.
+        map.insert("c", 3);
+        assert_eq!(map.get(&"b"), None);
+        assert_eq!(map.get(&"a"), Some(&1));
+        assert_eq!(map.get(&"c"), Some(&3));
+        // Updating the existing entry does not evict others.
+        *map.get_or_insert_with("c", || 0) += 1;
+        assert_eq!(map.get(&"a"), Some(&1));
+        assert_eq!(map.get(&"c"), Some(&4));
+        map.get_or_insert_with("d", || 5);
+        assert_eq!(map.get(&"a"), None);
+        assert_eq!(map.entries.len(), 2);
+    }
+}
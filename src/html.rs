@@ -0,0 +1,257 @@
+//! Extraction of JSON-LD script elements from HTML documents.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#process-html>.
+
+use serde_json::Value;
+
+use crate::error::{ErrorCode, Result};
+
+/// Media type of JSON-LD script elements.
+const JSON_LD_MEDIA_TYPE: &str = "application/ld+json";
+
+/// Extracts JSON-LD content from the script elements of the given HTML document.
+///
+/// * If `fragment` is given, the content of the JSON-LD script element with that `id` is
+///   returned.
+/// * Otherwise, if `extract_all_scripts` is `true`, the contents of all JSON-LD script elements
+///   are returned in an array. Arrays in script elements are flattened into the resulting array.
+/// * Otherwise, the content of the first JSON-LD script element is returned.
+///
+/// `extract_all_scripts` would usually be taken from
+/// `LoadDocumentOptions::should_extract_all_scripts()` or
+/// `JsonLdOptions::is_extract_all_scripts()`.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#process-html>.
+pub fn extract_json_ld(
+    html: &str,
+    fragment: Option<&str>,
+    extract_all_scripts: bool,
+) -> Result<Value> {
+    let mut scripts = ScriptElements::new(html);
+    if let Some(fragment) = fragment {
+        let script = scripts
+            .find(|script| script.id() == Some(fragment))
+            .ok_or_else(|| {
+                ErrorCode::LoadingDocumentFailed
+                    .and_message(format!("No script element with id {:?} found", fragment))
+            })?;
+        if !script.is_json_ld() {
+            return Err(ErrorCode::LoadingDocumentFailed.and_message(format!(
+                "Script element with id {:?} is not a JSON-LD script element",
+                fragment
+            )));
+        }
+        return script.parse_content();
+    }
+
+    let mut scripts = scripts.filter(ScriptElement::is_json_ld);
+    if !extract_all_scripts {
+        let script = scripts.next().ok_or_else(|| {
+            ErrorCode::LoadingDocumentFailed.and_message("No JSON-LD script element found")
+        })?;
+        return script.parse_content();
+    }
+    let mut result = Vec::new();
+    for script in scripts {
+        match script.parse_content()? {
+            Value::Array(items) => result.extend(items),
+            v => result.push(v),
+        }
+    }
+    Ok(Value::Array(result))
+}
+
+/// Script element.
+#[derive(Debug, Clone, Copy)]
+struct ScriptElement<'a> {
+    /// Raw source of the attributes.
+    attributes: &'a str,
+    /// Raw text content.
+    content: &'a str,
+}
+
+impl<'a> ScriptElement<'a> {
+    /// Returns the value of the given attribute.
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        let mut rest = self.attributes;
+        loop {
+            rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+            if rest.is_empty() {
+                return None;
+            }
+            let name_len = rest
+                .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '/')
+                .unwrap_or_else(|| rest.len());
+            let attr_name = &rest[..name_len];
+            rest = rest[name_len..].trim_start();
+            let value = if rest.starts_with('=') {
+                rest = rest[1..].trim_start();
+                let (value, value_len) = match rest.chars().next() {
+                    Some(quote @ '"') | Some(quote @ '\'') => {
+                        let end = rest[1..].find(quote).map_or(rest.len(), |pos| pos + 1);
+                        (&rest[1..end], (end + 1).min(rest.len()))
+                    }
+                    _ => {
+                        let end = rest
+                            .find(|c: char| c.is_ascii_whitespace())
+                            .unwrap_or_else(|| rest.len());
+                        (&rest[..end], end)
+                    }
+                };
+                rest = &rest[value_len..];
+                value
+            } else {
+                ""
+            };
+            if attr_name.eq_ignore_ascii_case(name) {
+                return Some(value);
+            }
+        }
+    }
+
+    /// Returns the `id` attribute.
+    fn id(&self) -> Option<&'a str> {
+        self.attribute("id")
+    }
+
+    /// Checks whether the element is a JSON-LD script element.
+    fn is_json_ld(&self) -> bool {
+        self.attribute("type").map_or(false, |ty| {
+            let essence = ty.split(';').next().unwrap_or("").trim();
+            essence.eq_ignore_ascii_case(JSON_LD_MEDIA_TYPE)
+        })
+    }
+
+    /// Parses the content as JSON.
+    fn parse_content(&self) -> Result<Value> {
+        serde_json::from_str(self.content).map_err(|e| {
+            ErrorCode::InvalidScriptElement
+                .and_message(format!("Invalid JSON in the script element: {}", e))
+        })
+    }
+}
+
+/// Iterator of script elements in an HTML document.
+#[derive(Debug, Clone)]
+struct ScriptElements<'a> {
+    /// Rest of the document.
+    rest: &'a str,
+}
+
+impl<'a> ScriptElements<'a> {
+    /// Creates a new `ScriptElements`.
+    fn new(html: &'a str) -> Self {
+        Self { rest: html }
+    }
+}
+
+impl<'a> Iterator for ScriptElements<'a> {
+    type Item = ScriptElement<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.rest.find('<')?;
+            self.rest = &self.rest[start..];
+            // Skip comments.
+            if self.rest.starts_with("<!--") {
+                let end = self.rest.find("-->").map_or(self.rest.len(), |pos| pos + 3);
+                self.rest = &self.rest[end..];
+                continue;
+            }
+            if !starts_with_tag(self.rest, "<script") {
+                self.rest = &self.rest[1..];
+                continue;
+            }
+            let tag_end = self.rest.find('>').unwrap_or_else(|| self.rest.len());
+            let attributes = &self.rest["<script".len()..tag_end];
+            self.rest = &self.rest[(tag_end + 1).min(self.rest.len())..];
+            let content_end =
+                find_ignore_ascii_case(self.rest, "</script").unwrap_or_else(|| self.rest.len());
+            let content = &self.rest[..content_end];
+            self.rest = &self.rest[content_end..];
+            return Some(ScriptElement {
+                attributes,
+                content,
+            });
+        }
+    }
+}
+
+/// Checks whether the string starts with the given start tag (such as `<script`).
+fn starts_with_tag(s: &str, tag: &str) -> bool {
+    s.get(..tag.len())
+        .map_or(false, |prefix| prefix.eq_ignore_ascii_case(tag))
+        && s[tag.len()..]
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_whitespace() || c == '>' || c == '/')
+}
+
+/// Returns the position of the given ASCII pattern, ignoring ASCII case.
+fn find_ignore_ascii_case(s: &str, pat: &str) -> Option<usize> {
+    s.char_indices().map(|(pos, _)| pos).find(|&pos| {
+        s.get(pos..(pos + pat.len()))
+            .map_or(false, |sub| sub.eq_ignore_ascii_case(pat))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    /// HTML document with JSON-LD script elements.
+    const HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<!-- <script type="application/ld+json">{"commented": true}</script> -->
+<script>var x = 1;</script>
+<script type="application/ld+json" id="first">{"@id": "http://example.com/a"}</script>
+<SCRIPT TYPE='application/ld+json;profile=http://www.w3.org/ns/json-ld#context' id=second>
+[{"@id": "http://example.com/b"}, {"@id": "http://example.com/c"}]
+</SCRIPT>
+<script type="text/plain" id="third">{}</script>
+</head>
+</html>"#;
+
+    #[test]
+    fn first_script() {
+        assert_eq!(
+            extract_json_ld(HTML, None, false).expect("valid HTML"),
+            json!({"@id": "http://example.com/a"})
+        );
+    }
+
+    #[test]
+    fn all_scripts() {
+        assert_eq!(
+            extract_json_ld(HTML, None, true).expect("valid HTML"),
+            json!([
+                {"@id": "http://example.com/a"},
+                {"@id": "http://example.com/b"},
+                {"@id": "http://example.com/c"},
+            ])
+        );
+    }
+
+    #[test]
+    fn fragment() {
+        assert_eq!(
+            extract_json_ld(HTML, Some("second"), false).expect("valid HTML"),
+            json!([{"@id": "http://example.com/b"}, {"@id": "http://example.com/c"}])
+        );
+        assert_eq!(
+            extract_json_ld(HTML, Some("third"), false)
+                .expect_err("not a JSON-LD script element")
+                .code(),
+            ErrorCode::LoadingDocumentFailed
+        );
+        assert_eq!(
+            extract_json_ld(HTML, Some("missing"), false)
+                .expect_err("no such element")
+                .code(),
+            ErrorCode::LoadingDocumentFailed
+        );
+    }
+}
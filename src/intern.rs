@@ -0,0 +1,169 @@
+//! String interning.
+
+use std::{
+    fmt,
+    hash::{BuildHasher as _, Hash, Hasher},
+    ops, str,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::hash::{BuildHasher, HashSet};
+
+/// Maximum number of strings kept by an interner.
+///
+/// Strings are not interned after the limit is reached, so that processing many unrelated
+/// contexts does not grow the table unboundedly.
+const MAX_SYMBOLS: usize = 1 << 16;
+
+/// Number of shards of an interner.
+///
+/// Strings are distributed to the shards by their hashes, so that threads interning different
+/// strings (such as parallel expansion workers) rarely wait for each other.
+const SHARDS: usize = 16;
+
+/// Maximum length of the concatenations which are looked up without allocation.
+const MAX_STACK_CONCAT_LEN: usize = 256;
+
+/// Symbol table of the IRIs, terms, and keywords used during processing.
+///
+/// Term definitions created repeatedly (for example by scoped contexts applied to each node of a
+/// large document) and the vocabulary IRIs and keywords expanded for each entry of a large
+/// document share the interned strings instead of allocating them each time.
+///
+/// The results (such as expanded documents) are `serde_json::Value`s which own their strings,
+/// so the strings are copied once when they are put into the results.
+#[derive(Default, Debug)]
+pub(crate) struct Interner {
+    /// Hasher to choose the shard.
+    hasher: BuildHasher,
+    /// Interned strings, sharded by their hashes.
+    shards: [Mutex<HashSet<Arc<str>>>; SHARDS],
+}
+
+impl Interner {
+    /// Locks the shard for the given string.
+    fn shard(&self, s: &str) -> MutexGuard<'_, HashSet<Arc<str>>> {
+        let mut hasher = self.hasher.build_hasher();
+        s.hash(&mut hasher);
+        self.shards[hasher.finish() as usize % SHARDS]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the shared string equal to the given string.
+    pub(crate) fn intern(&self, s: &str) -> Arc<str> {
+        let mut symbols = self.shard(s);
+        if let Some(symbol) = symbols.get(s) {
+            return symbol.clone();
+        }
+        let symbol: Arc<str> = Arc::from(s);
+        if symbols.len() < MAX_SYMBOLS / SHARDS {
+            symbols.insert(symbol.clone());
+        }
+        symbol
+    }
+
+    /// Returns the shared string equal to the concatenation of the given strings.
+    ///
+    /// This does not allocate if the concatenation is short and already interned.
+    pub(crate) fn intern_concat(&self, prefix: &str, suffix: &str) -> Arc<str> {
+        let len = prefix.len() + suffix.len();
+        if len > MAX_STACK_CONCAT_LEN {
+            return self.intern(&format!("{}{}", prefix, suffix));
+        }
+        let mut buf = [0; MAX_STACK_CONCAT_LEN];
+        buf[..prefix.len()].copy_from_slice(prefix.as_bytes());
+        buf[prefix.len()..len].copy_from_slice(suffix.as_bytes());
+        let s = str::from_utf8(&buf[..len])
+            .expect("Should never fail: a concatenation of strings is a valid string");
+        self.intern(s)
+    }
+
+    /// Removes all the interned strings.
+    pub(crate) fn clear(&self) {
+        for shard in &self.shards {
+            shard.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+}
+
+/// String borrowed from the input, owned, or shared with the symbol table.
+///
+/// This is a `Cow<'a, str>` which can also hold an interned string.
+#[derive(Clone)]
+pub(crate) enum Symbol<'a> {
+    /// Borrowed string.
+    Borrowed(&'a str),
+    /// Owned string, which is not worth interning.
+    Owned(String),
+    /// Interned string.
+    Interned(Arc<str>),
+}
+
+impl ops::Deref for Symbol<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Symbol::Borrowed(s) => s,
+            Symbol::Owned(s) => s,
+            Symbol::Interned(s) => s,
+        }
+    }
+}
+
+impl fmt::Debug for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl PartialEq<str> for Symbol<'_> {
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialEq<&str> for Symbol<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+impl From<Symbol<'_>> for String {
+    fn from(s: Symbol<'_>) -> Self {
+        match s {
+            Symbol::Borrowed(s) => s.to_owned(),
+            Symbol::Owned(s) => s,
+            Symbol::Interned(s) => String::from(&*s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern() {
+        let interner = Interner::default();
+        let a = interner.intern("http://example.com/a");
+        assert!(Arc::ptr_eq(&a, &interner.intern("http://example.com/a")));
+        assert!(!Arc::ptr_eq(&a, &interner.intern("http://example.com/b")));
+        assert!(Arc::ptr_eq(
+            &a,
+            &interner.intern_concat("http://example.com/", "a")
+        ));
+        interner.clear();
+        assert!(!Arc::ptr_eq(&a, &interner.intern("http://example.com/a")));
+    }
+
+    #[test]
+    fn intern_long_concat() {
+        let interner = Interner::default();
+        let prefix = "http://example.com/".repeat(16);
+        let long = interner.intern_concat(&prefix, "a");
+        assert_eq!(&*long, format!("{}a", prefix));
+        assert!(Arc::ptr_eq(&long, &interner.intern_concat(&prefix, "a")));
+    }
+}
@@ -79,6 +79,85 @@ pub(crate) fn is_gen_delims_byte(b: u8) -> bool {
     }
 }
 
+/// Splits the given IRI into the root (scheme and authority), path, query, and fragment.
+///
+/// This does not validate the IRI.
+fn split_iri(s: &str) -> (&str, &str, Option<&str>, Option<&str>) {
+    let (rest, fragment) = match s.find('#') {
+        Some(pos) => (&s[..pos], Some(&s[(pos + 1)..])),
+        None => (s, None),
+    };
+    let (rest, query) = match rest.find('?') {
+        Some(pos) => (&rest[..pos], Some(&rest[(pos + 1)..])),
+        None => (rest, None),
+    };
+    let scheme_end = match rest.find(|c| c == ':' || c == '/') {
+        Some(pos) if rest.as_bytes()[pos] == b':' => pos + 1,
+        _ => 0,
+    };
+    let root_end = if rest[scheme_end..].starts_with("//") {
+        let authority_start = scheme_end + 2;
+        rest[authority_start..]
+            .find('/')
+            .map_or(rest.len(), |pos| authority_start + pos)
+    } else {
+        scheme_end
+    };
+    (&rest[..root_end], &rest[root_end..], query, fragment)
+}
+
+/// Converts the given IRI to an IRI reference relative to the given base IRI, if possible.
+///
+/// Returns `None` if the IRI does not share the scheme and the authority with the base.
+pub(crate) fn to_relative_iri(base: &str, iri: &str) -> Option<String> {
+    let (base_root, base_path, base_query, _) = split_iri(base);
+    if base_root.is_empty() || !iri.starts_with(base_root) {
+        return None;
+    }
+    let rest = &iri[base_root.len()..];
+    match rest.as_bytes().first() {
+        None | Some(b'/') | Some(b'?') | Some(b'#') => {}
+        // Different authority which has the base authority as prefix.
+        Some(_) => return None,
+    }
+    let (_, path, query, fragment) = split_iri(rest);
+
+    let base_segments = base_path.split('/').collect::<Vec<_>>();
+    let iri_segments = path.split('/').collect::<Vec<_>>();
+    // Do not remove the last segment unless there is a query or a fragment which can be
+    // resolved against the base without the last segment.
+    let last = if query.is_some() || (fragment.is_some() && base_query.is_none()) {
+        0
+    } else {
+        1
+    };
+    let common = base_segments
+        .iter()
+        .zip(&iri_segments[..(iri_segments.len() - last)])
+        .take_while(|(base, iri)| base == iri)
+        .count();
+
+    let mut result = String::new();
+    // The last segment of the base is not a directory.
+    for _ in 1..(base_segments.len() - common) {
+        result.push_str("../");
+    }
+    result.push_str(&iri_segments[common..].join("/"));
+    if result.is_empty() && path != base_path {
+        result.push_str("./");
+    }
+    if let Some(query) = query {
+        result.push('?');
+        result.push_str(query);
+    }
+    if let Some(fragment) = fragment {
+        result.push('#');
+        result.push_str(fragment);
+    }
+
+    Some(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,4 +171,42 @@ mod tests {
         assert_eq!(to_prefix_and_suffix(":foo:"), Some((":foo", "")));
         assert_eq!(to_prefix_and_suffix(":foo:bar:"), Some((":foo", "bar:")));
     }
+
+    #[test]
+    fn relative_iri() {
+        let base = "http://example.com/foo/bar?q#f";
+        assert_eq!(
+            to_relative_iri(base, "http://example.com/foo/bar"),
+            Some("bar".to_owned())
+        );
+        assert_eq!(
+            to_relative_iri(base, "http://example.com/foo/"),
+            Some("./".to_owned())
+        );
+        assert_eq!(
+            to_relative_iri(base, "http://example.com/baz"),
+            Some("../baz".to_owned())
+        );
+        assert_eq!(
+            to_relative_iri(base, "http://example.com/foo/bar#frag"),
+            Some("bar#frag".to_owned())
+        );
+        assert_eq!(
+            to_relative_iri(base, "http://example.com/foo/#frag"),
+            Some("./#frag".to_owned())
+        );
+        assert_eq!(
+            to_relative_iri(
+                "http://example.com/foo/bar",
+                "http://example.com/foo/bar#frag"
+            ),
+            Some("#frag".to_owned())
+        );
+        assert_eq!(
+            to_relative_iri(base, "http://example.com/foo/bar?x"),
+            Some("?x".to_owned())
+        );
+        assert_eq!(to_relative_iri(base, "http://example.org/foo/bar"), None);
+        assert_eq!(to_relative_iri(base, "http://example.com.org/foo"), None);
+    }
 }
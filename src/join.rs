@@ -0,0 +1,270 @@
+//! Concurrent execution of futures.
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Future which polls the given futures concurrently and collects their outputs in order.
+pub(crate) struct JoinAll<F: Future> {
+    /// Futures.
+    futures: Vec<Pin<Box<F>>>,
+    /// Outputs of completed futures.
+    outputs: Vec<Option<F::Output>>,
+}
+
+// Outputs are never pinned.
+impl<F: Future> Unpin for JoinAll<F> {}
+
+impl<F: Future> Future for JoinAll<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut is_done = true;
+        for (future, output) in this.futures.iter_mut().zip(this.outputs.iter_mut()) {
+            if output.is_some() {
+                continue;
+            }
+            match future.as_mut().poll(cx) {
+                Poll::Ready(v) => *output = Some(v),
+                Poll::Pending => is_done = false,
+            }
+        }
+        if !is_done {
+            return Poll::Pending;
+        }
+        Poll::Ready(
+            this.outputs
+                .iter_mut()
+                .map(|output| output.take().expect("All futures should have completed"))
+                .collect(),
+        )
+    }
+}
+
+/// Returns a future which runs the given futures concurrently.
+pub(crate) fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> JoinAll<F> {
+    let futures: Vec<_> = futures.into_iter().map(Box::pin).collect();
+    let outputs = futures.iter().map(|_| None).collect();
+    JoinAll { futures, outputs }
+}
+
+/// Future which runs the fallible futures with at most `limit` of them in flight, and collects
+/// their outputs in order.
+///
+/// A new future is started as soon as any future in flight completes, so the window slides
+/// instead of waiting for a whole batch. The first error (in the order of the futures) is returned with the
+/// index of the future, and the remaining futures are dropped.
+pub(crate) struct TryJoinBuffered<I, T, E>
+where
+    I: Iterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    /// Futures not started yet.
+    pending: I,
+    /// Started futures with their indices and outputs, in order.
+    running: VecDeque<(usize, Pin<Box<I::Item>>, Option<Result<T, E>>)>,
+    /// Maximum number of futures in flight.
+    limit: usize,
+    /// Number of started futures which have not completed yet.
+    in_flight: usize,
+    /// Index of the next future to start.
+    next_index: usize,
+    /// Outputs of the completed futures, in order.
+    outputs: Vec<T>,
+}
+
+// Futures are boxed, and the iterator and outputs are never pinned.
+impl<I, T, E> Unpin for TryJoinBuffered<I, T, E>
+where
+    I: Iterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+}
+
+impl<I, T, E> Future for TryJoinBuffered<I, T, E>
+where
+    I: Iterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    type Output = Result<Vec<T>, (usize, E)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            while this.in_flight < this.limit {
+                match this.pending.next() {
+                    Some(future) => {
+                        this.running
+                            .push_back((this.next_index, Box::pin(future), None));
+                        this.next_index += 1;
+                        this.in_flight += 1;
+                    }
+                    None => break,
+                }
+            }
+            if this.running.is_empty() {
+                return Poll::Ready(Ok(std::mem::replace(&mut this.outputs, Vec::new())));
+            }
+            let mut has_progress = false;
+            for (_, future, output) in this.running.iter_mut() {
+                if output.is_none() {
+                    if let Poll::Ready(v) = future.as_mut().poll(cx) {
+                        *output = Some(v);
+                        this.in_flight -= 1;
+                        has_progress = true;
+                    }
+                }
+            }
+            while this
+                .running
+                .front()
+                .map_or(false, |(_, _, output)| output.is_some())
+            {
+                let (index, _, output) = this
+                    .running
+                    .pop_front()
+                    .expect("Should never fail: the front entry exists");
+                match output.expect("Should never fail: the output exists") {
+                    Ok(v) => this.outputs.push(v),
+                    Err(e) => return Poll::Ready(Err((index, e))),
+                }
+            }
+            if !has_progress {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// Returns a future which runs the given fallible futures with at most `limit` of them in
+/// flight.
+///
+/// `0` is treated as `1`.
+pub(crate) fn try_join_buffered<I, T, E>(
+    futures: I,
+    limit: usize,
+) -> TryJoinBuffered<I::IntoIter, T, E>
+where
+    I: IntoIterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    TryJoinBuffered {
+        pending: futures.into_iter(),
+        running: VecDeque::new(),
+        limit: limit.max(1),
+        in_flight: 0,
+        next_index: 0,
+        outputs: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    use futures_executor::block_on;
+
+    use super::*;
+
+    /// State shared by the test futures.
+    #[derive(Default)]
+    struct Tracker {
+        /// Number of futures in flight, and the maximum of it.
+        in_flight: Cell<(usize, usize)>,
+        /// Outputs of the completed futures, in the order of completion.
+        completed: RefCell<Vec<Result<usize, usize>>>,
+    }
+
+    /// Future which is pending for the given number of polls, and tracks the futures in flight.
+    struct Yield {
+        /// Output.
+        output: Result<usize, usize>,
+        /// Number of polls returning `Poll::Pending`.
+        pending_polls: usize,
+        /// Whether the future has been polled.
+        polled: bool,
+        /// Shared state.
+        tracker: Rc<Tracker>,
+    }
+
+    impl Future for Yield {
+        type Output = Result<usize, usize>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let (current, max) = self.tracker.in_flight.get();
+            if !self.polled {
+                self.polled = true;
+                self.tracker
+                    .in_flight
+                    .set((current + 1, max.max(current + 1)));
+            }
+            if self.pending_polls > 0 {
+                self.pending_polls -= 1;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let (current, max) = self.tracker.in_flight.get();
+            self.tracker.in_flight.set((current - 1, max));
+            self.tracker.completed.borrow_mut().push(self.output);
+            Poll::Ready(self.output)
+        }
+    }
+
+    /// Creates futures with the given outputs and numbers of pending polls.
+    fn futures(outputs: &[(Result<usize, usize>, usize)], tracker: &Rc<Tracker>) -> Vec<Yield> {
+        outputs
+            .iter()
+            .map(|&(output, pending_polls)| Yield {
+                output,
+                pending_polls,
+                polled: false,
+                tracker: tracker.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn buffered_in_order() {
+        let tracker = Rc::new(Tracker::default());
+        let outputs = (0..10).map(|i| (Ok(i), 1)).collect::<Vec<_>>();
+        let result = block_on(try_join_buffered(futures(&outputs, &tracker), 3));
+        assert_eq!(result, Ok((0..10).collect::<Vec<_>>()));
+        assert_eq!(tracker.in_flight.get(), (0, 3));
+    }
+
+    #[test]
+    fn buffered_sliding_window() {
+        let tracker = Rc::new(Tracker::default());
+        let mut outputs = (0..5).map(|i| (Ok(i), 1)).collect::<Vec<_>>();
+        outputs[0].1 = 10;
+        let result = block_on(try_join_buffered(futures(&outputs, &tracker), 2));
+        assert_eq!(result, Ok((0..5).collect::<Vec<_>>()));
+        assert_eq!(tracker.in_flight.get(), (0, 2));
+        // The slow first future does not block the others.
+        assert_eq!(
+            *tracker.completed.borrow(),
+            vec![Ok(1), Ok(2), Ok(3), Ok(4), Ok(0)]
+        );
+    }
+
+    #[test]
+    fn buffered_first_error() {
+        let tracker = Rc::new(Tracker::default());
+        let outputs = [Ok(0), Err(1), Ok(2), Err(3), Ok(4), Ok(5), Ok(6)]
+            .iter()
+            .map(|&output| (output, 1))
+            .collect::<Vec<_>>();
+        let result = block_on(try_join_buffered(futures(&outputs, &tracker), 2));
+        assert_eq!(result, Err((1, 1)));
+        // Futures after the window are never started.
+        assert_eq!(tracker.in_flight.get().1, 2);
+        assert_eq!(*tracker.completed.borrow(), vec![Ok(0), Err(1)]);
+    }
+}
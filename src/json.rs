@@ -2,9 +2,22 @@
 
 use serde_json::{Map as JsonMap, Value};
 
-pub(crate) use self::nullable::Nullable;
+pub use self::adapter::{JsonAdapter, JsonView};
+#[cfg(feature = "futures-io")]
+pub(crate) use self::split::ArraySplitter;
+pub(crate) use self::{
+    adapter::{from_serde_json, to_serde_json},
+    canonical::to_canonical_string,
+    nullable::Nullable,
+    span::locate_pointer,
+};
 
+mod adapter;
+mod canonical;
 mod nullable;
+mod span;
+#[cfg(feature = "futures-io")]
+mod split;
 
 /// Returns a map with single key-value entry.
 pub(crate) fn single_entry_map(
@@ -16,6 +29,22 @@ pub(crate) fn single_entry_map(
     map
 }
 
+/// Escapes the reference token of a JSON pointer.
+///
+/// See <https://tools.ietf.org/html/rfc6901#section-3>.
+pub(crate) fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Returns the entries of the map, sorted lexicographically by keys if `ordered` is true.
+pub(crate) fn map_entries(map: &JsonMap<String, Value>, ordered: bool) -> Vec<(&String, &Value)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    if ordered {
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    entries
+}
+
 /// Converts the given JSON value to a slice of elements.
 pub(crate) fn to_ref_array(v: &Value) -> &[Value] {
     match v {
@@ -23,3 +52,120 @@ pub(crate) fn to_ref_array(v: &Value) -> &[Value] {
         v => std::slice::from_ref(v),
     }
 }
+
+/// Converts the given JSON value into a vector of elements.
+pub(crate) fn into_array(v: Value) -> Vec<Value> {
+    match v {
+        Value::Array(v) => v,
+        v => vec![v],
+    }
+}
+
+/// Converts the given JSON value into a vector of elements, treating `null` as an empty array.
+pub(crate) fn into_nonnull_array(v: Value) -> Vec<Value> {
+    match v {
+        Value::Null => Vec::new(),
+        v => into_array(v),
+    }
+}
+
+/// Adds the value to the entry of the given map.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dfn-add-value>.
+pub(crate) fn add_value(
+    object: &mut JsonMap<String, Value>,
+    key: &str,
+    value: Value,
+    as_array: bool,
+) {
+    // Step 1
+    if as_array {
+        let entry = object
+            .entry(key)
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if !entry.is_array() {
+            let original = std::mem::replace(entry, Value::Null);
+            *entry = Value::Array(vec![original]);
+        }
+    }
+    match value {
+        // Step 2
+        Value::Array(values) => {
+            for v in values {
+                add_value(object, key, v, false);
+            }
+        }
+        // Step 3
+        value => match object.get_mut(key) {
+            // Step 3.2.2
+            Some(Value::Array(arr)) => arr.push(value),
+            // Step 3.2.1, 3.2.2
+            Some(entry) => {
+                let original = std::mem::replace(entry, Value::Null);
+                *entry = Value::Array(vec![original, value]);
+            }
+            // Step 3.1
+            None => {
+                object.insert(key.to_owned(), value);
+            }
+        },
+    }
+}
+
+/// Checks whether the value has more than `max` JSON values, including the value itself and
+/// the nested values.
+pub(crate) fn exceeds_node_count(value: &Value, max: usize) -> bool {
+    let mut count = 0;
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        count += 1;
+        if count > max {
+            return true;
+        }
+        match value {
+            Value::Array(arr) => stack.extend(arr),
+            Value::Object(obj) => stack.extend(obj.values()),
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn add_value_as_array() {
+        let mut map = JsonMap::new();
+        add_value(&mut map, "foo", json!(1), false);
+        assert_eq!(map["foo"], json!(1));
+        add_value(&mut map, "foo", json!([2, 3]), false);
+        assert_eq!(map["foo"], json!([1, 2, 3]));
+
+        add_value(&mut map, "bar", json!(1), true);
+        assert_eq!(map["bar"], json!([1]));
+        add_value(&mut map, "baz", json!([]), true);
+        assert_eq!(map["baz"], json!([]));
+    }
+
+    #[test]
+    fn ordered_map_entries() {
+        let map = json!({"b": 1, "@id": 2, "a": 3});
+        let map = map.as_object().expect("object");
+        let keys = map_entries(map, true)
+            .into_iter()
+            .map(|(key, _)| key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["@id", "a", "b"]);
+    }
+
+    #[test]
+    fn node_count() {
+        let value = json!({"a": [1, 2], "b": null});
+        assert!(!exceeds_node_count(&value, 5));
+        assert!(exceeds_node_count(&value, 4));
+    }
+}
@@ -0,0 +1,196 @@
+//! Conversion adapters for other JSON value representations.
+//!
+//! The adapters work at the API boundary only. The processing algorithms work on
+//! `serde_json::Value`, and other representations are converted from and to it.
+
+use serde_json::{Map as JsonMap, Number, Value};
+
+/// View of a JSON value.
+pub enum JsonView<'a, J: ?Sized> {
+    /// `null`.
+    Null,
+    /// Boolean.
+    Bool(bool),
+    /// Number.
+    Number(Number),
+    /// String.
+    String(&'a str),
+    /// Array.
+    Array(Box<dyn Iterator<Item = &'a J> + 'a>),
+    /// Object, with entries in the document order.
+    Object(Box<dyn Iterator<Item = (&'a str, &'a J)> + 'a>),
+}
+
+/// Conversion adapter of a JSON value representation from and to `serde_json::Value`.
+///
+/// Implement this for a JSON value type (such as an arena-backed one) to pass documents of that
+/// type to [`Processor::expand_json`] and [`Processor::compact_json`].
+///
+/// This is not a JSON backend: the algorithms are not generic over this trait and work on
+/// `serde_json::Value`, so documents are converted to it on input and back on output. This saves
+/// the conversion code in applications, but not memory: both representations of the input are
+/// alive during processing.
+///
+/// [`Processor::expand_json`]: struct.Processor.html#method.expand_json
+/// [`Processor::compact_json`]: struct.Processor.html#method.compact_json
+pub trait JsonAdapter: Sized {
+    /// Returns the view of the value.
+    fn view(&self) -> JsonView<'_, Self>;
+
+    /// Creates `null`.
+    fn null() -> Self;
+
+    /// Creates a boolean.
+    fn bool(v: bool) -> Self;
+
+    /// Creates a number.
+    fn number(v: Number) -> Self;
+
+    /// Creates a string.
+    fn string(v: String) -> Self;
+
+    /// Creates an array.
+    fn array(items: Vec<Self>) -> Self;
+
+    /// Creates an object.
+    fn object(entries: Vec<(String, Self)>) -> Self;
+}
+
+impl JsonAdapter for Value {
+    fn view(&self) -> JsonView<'_, Self> {
+        match self {
+            Value::Null => JsonView::Null,
+            Value::Bool(v) => JsonView::Bool(*v),
+            Value::Number(v) => JsonView::Number(v.clone()),
+            Value::String(v) => JsonView::String(v),
+            Value::Array(v) => JsonView::Array(Box::new(v.iter())),
+            Value::Object(v) => JsonView::Object(Box::new(v.iter().map(|(k, v)| (k.as_str(), v)))),
+        }
+    }
+
+    fn null() -> Self {
+        Value::Null
+    }
+
+    fn bool(v: bool) -> Self {
+        Value::Bool(v)
+    }
+
+    fn number(v: Number) -> Self {
+        Value::Number(v)
+    }
+
+    fn string(v: String) -> Self {
+        Value::String(v)
+    }
+
+    fn array(items: Vec<Self>) -> Self {
+        Value::Array(items)
+    }
+
+    fn object(entries: Vec<(String, Self)>) -> Self {
+        Value::Object(entries.into_iter().collect())
+    }
+}
+
+/// Converts the given JSON value into `serde_json::Value`.
+pub(crate) fn to_serde_json<J: JsonAdapter>(v: &J) -> Value {
+    match v.view() {
+        JsonView::Null => Value::Null,
+        JsonView::Bool(v) => Value::Bool(v),
+        JsonView::Number(v) => Value::Number(v),
+        JsonView::String(v) => Value::String(v.to_owned()),
+        JsonView::Array(items) => Value::Array(items.map(to_serde_json).collect()),
+        JsonView::Object(entries) => Value::Object(
+            entries
+                .map(|(k, v)| (k.to_owned(), to_serde_json(v)))
+                .collect::<JsonMap<_, _>>(),
+        ),
+    }
+}
+
+/// Converts the given `serde_json::Value` into another JSON value representation.
+pub(crate) fn from_serde_json<J: JsonAdapter>(v: Value) -> J {
+    match v {
+        Value::Null => J::null(),
+        Value::Bool(v) => J::bool(v),
+        Value::Number(v) => J::number(v),
+        Value::String(v) => J::string(v),
+        Value::Array(items) => J::array(items.into_iter().map(from_serde_json).collect()),
+        Value::Object(entries) => J::object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k, from_serde_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    /// Minimal JSON representation for tests.
+    #[derive(Debug, Clone, PartialEq)]
+    enum Tree {
+        /// Scalar value.
+        Scalar(Value),
+        /// Array.
+        Array(Vec<Tree>),
+        /// Object.
+        Object(Vec<(String, Tree)>),
+    }
+
+    impl JsonAdapter for Tree {
+        fn view(&self) -> JsonView<'_, Self> {
+            match self {
+                Tree::Scalar(v) => match v {
+                    Value::Bool(v) => JsonView::Bool(*v),
+                    Value::Number(v) => JsonView::Number(v.clone()),
+                    Value::String(v) => JsonView::String(v),
+                    _ => JsonView::Null,
+                },
+                Tree::Array(v) => JsonView::Array(Box::new(v.iter())),
+                Tree::Object(v) => {
+                    JsonView::Object(Box::new(v.iter().map(|(k, v)| (k.as_str(), v))))
+                }
+            }
+        }
+
+        fn null() -> Self {
+            Tree::Scalar(Value::Null)
+        }
+
+        fn bool(v: bool) -> Self {
+            Tree::Scalar(Value::Bool(v))
+        }
+
+        fn number(v: Number) -> Self {
+            Tree::Scalar(Value::Number(v))
+        }
+
+        fn string(v: String) -> Self {
+            Tree::Scalar(Value::String(v))
+        }
+
+        fn array(items: Vec<Self>) -> Self {
+            Tree::Array(items)
+        }
+
+        fn object(entries: Vec<(String, Self)>) -> Self {
+            Tree::Object(entries)
+        }
+    }
+
+    #[test]
+    fn convert_roundtrip() {
+        let value = json!({
+            "@id": "http://example.com/",
+            "http://example.com/p": [null, true, 42, {"@value": "v"}],
+        });
+        let tree: Tree = from_serde_json(value.clone());
+        assert_eq!(to_serde_json(&tree), value);
+    }
+}
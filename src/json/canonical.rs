@@ -0,0 +1,161 @@
+//! JSON canonicalization scheme.
+//!
+//! See <https://tools.ietf.org/html/rfc8785>.
+
+use std::fmt::Write;
+
+use serde_json::{Number, Value};
+
+/// Serializes the JSON value in the canonical form.
+///
+/// See <https://tools.ietf.org/html/rfc8785#section-3.2>.
+pub(crate) fn to_canonical_string(v: &Value) -> String {
+    let mut buf = String::new();
+    write_value(&mut buf, v);
+    buf
+}
+
+/// Pending output of the canonical serialization.
+enum Pending<'a> {
+    /// JSON value.
+    Value(&'a Value),
+    /// Object key, followed by a colon.
+    Key(&'a str),
+    /// Punctuation.
+    Punct(char),
+}
+
+/// Writes the canonical serialization of the value.
+///
+/// Nested values are written using an explicit stack, so deeply nested values do not exhaust
+/// the call stack.
+fn write_value(buf: &mut String, v: &Value) {
+    let mut stack = vec![Pending::Value(v)];
+    while let Some(pending) = stack.pop() {
+        let v = match pending {
+            Pending::Value(v) => v,
+            Pending::Key(key) => {
+                buf.push_str(&Value::String(key.to_owned()).to_string());
+                buf.push(':');
+                continue;
+            }
+            Pending::Punct(c) => {
+                buf.push(c);
+                continue;
+            }
+        };
+        match v {
+            Value::Null | Value::Bool(_) => buf.push_str(&v.to_string()),
+            Value::Number(v) => write_number(buf, v),
+            // Strings are escaped in the same way as `serde_json` does: `\b`, `\t`, `\n`, `\f`,
+            // `\r`, `\"`, `\\`, and `\u00xx` for other control characters.
+            Value::String(_) => buf.push_str(&v.to_string()),
+            Value::Array(arr) => {
+                buf.push('[');
+                stack.push(Pending::Punct(']'));
+                for (i, item) in arr.iter().enumerate().rev() {
+                    stack.push(Pending::Value(item));
+                    if i != 0 {
+                        stack.push(Pending::Punct(','));
+                    }
+                }
+            }
+            Value::Object(map) => {
+                // Keys are sorted by their UTF-16 code units.
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+                buf.push('{');
+                stack.push(Pending::Punct('}'));
+                for (i, (key, value)) in entries.into_iter().enumerate().rev() {
+                    stack.push(Pending::Value(value));
+                    stack.push(Pending::Key(key));
+                    if i != 0 {
+                        stack.push(Pending::Punct(','));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Writes the number in the same way as ECMAScript `Number.prototype.toString()`.
+///
+/// See <https://tools.ietf.org/html/rfc8785#section-3.2.2.3>.
+fn write_number(buf: &mut String, v: &Number) {
+    let v = v.as_f64().unwrap_or(0.0);
+    if v == 0.0 {
+        buf.push('0');
+        return;
+    }
+    if v < 0.0 {
+        buf.push('-');
+    }
+    // Shortest representation which roundtrips, such as `1.2345e2`.
+    let formatted = format!("{:e}", v.abs());
+    let pos = formatted
+        .find('e')
+        .expect("Should never fail: the exponent is always printed");
+    let digits = formatted[..pos].replace('.', "");
+    let exponent: i32 = formatted[(pos + 1)..]
+        .parse()
+        .expect("Should never fail: the exponent is an integer");
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+    if k <= n && n <= 21 {
+        buf.push_str(&digits);
+        buf.extend((0..(n - k)).map(|_| '0'));
+    } else if 0 < n && n <= 21 {
+        buf.push_str(&digits[..(n as usize)]);
+        buf.push('.');
+        buf.push_str(&digits[(n as usize)..]);
+    } else if -6 < n && n <= 0 {
+        buf.push_str("0.");
+        buf.extend((0..(-n)).map(|_| '0'));
+        buf.push_str(&digits);
+    } else {
+        buf.push_str(&digits[..1]);
+        if k > 1 {
+            buf.push('.');
+            buf.push_str(&digits[1..]);
+        }
+        write!(buf, "e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs())
+            .expect("Should never fail: writing to a string");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn numbers() {
+        let check = |v: f64, expected: &str| {
+            assert_eq!(to_canonical_string(&json!(v)), expected, "value={:?}", v);
+        };
+        check(0.0, "0");
+        check(-0.0, "0");
+        check(1.0, "1");
+        check(-1.5, "-1.5");
+        check(123_456_789.0, "123456789");
+        check(1e21, "1e+21");
+        check(1e20, "100000000000000000000");
+        check(0.000_001, "0.000001");
+        check(1e-7, "1e-7");
+        check(4.5e-10, "4.5e-10");
+        check(333_333_333.333_333_3, "333333333.3333333");
+    }
+
+    #[test]
+    fn structures() {
+        let v = json!({
+            "b": [true, null, "\u{1f}\"\n"],
+            "a": {"\u{e000}": 1, "\u{1f600}": 2},
+        });
+        assert_eq!(
+            to_canonical_string(&v),
+            "{\"a\":{\"\u{1f600}\":2,\"\u{e000}\":1},\"b\":[true,null,\"\\u001f\\\"\\n\"]}"
+        );
+    }
+}
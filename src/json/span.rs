@@ -0,0 +1,170 @@
+//! Locating values in JSON source text.
+
+use crate::error::Position;
+
+/// Returns the position of the value pointed by the given JSON pointer in the JSON source text.
+///
+/// Returns `None` if the source is not valid JSON or the pointer does not point to a value.
+///
+/// See <https://tools.ietf.org/html/rfc6901>.
+pub(crate) fn locate_pointer(source: &str, pointer: &str) -> Option<Position> {
+    let offset = find_pointer(source, pointer)?;
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    Some(Position::new(
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    ))
+}
+
+/// Returns the byte offset of the value pointed by the given JSON pointer.
+fn find_pointer(source: &str, pointer: &str) -> Option<usize> {
+    let mut pos = skip_whitespace(source, 0);
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        pos = match source.as_bytes().get(pos)? {
+            b'{' => find_entry(source, pos, &token)?,
+            b'[' => find_item(source, pos, token.parse().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(pos)
+}
+
+/// Returns the byte offset of the value of the entry with the given key.
+///
+/// If the key appears more than once, the last one is used as `serde_json` does.
+fn find_entry(source: &str, start: usize, key: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut found = None;
+    let mut pos = skip_whitespace(source, start + 1);
+    if bytes.get(pos)? == &b'}' {
+        return None;
+    }
+    loop {
+        if bytes.get(pos)? != &b'"' {
+            return None;
+        }
+        let key_end = skip_string(source, pos)?;
+        let current_key: String = serde_json::from_str(&source[pos..key_end]).ok()?;
+        pos = skip_whitespace(source, key_end);
+        if bytes.get(pos)? != &b':' {
+            return None;
+        }
+        pos = skip_whitespace(source, pos + 1);
+        if current_key == key {
+            found = Some(pos);
+        }
+        pos = skip_whitespace(source, skip_value(source, pos)?);
+        match bytes.get(pos)? {
+            b',' => pos = skip_whitespace(source, pos + 1),
+            b'}' => return found,
+            _ => return None,
+        }
+    }
+}
+
+/// Returns the byte offset of the array item with the given index.
+fn find_item(source: &str, start: usize, index: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut pos = skip_whitespace(source, start + 1);
+    if bytes.get(pos)? == &b']' {
+        return None;
+    }
+    for _ in 0..index {
+        pos = skip_whitespace(source, skip_value(source, pos)?);
+        if bytes.get(pos)? != &b',' {
+            return None;
+        }
+        pos = skip_whitespace(source, pos + 1);
+    }
+    Some(pos)
+}
+
+/// Returns the byte offset next to the value starting at the given position.
+fn skip_value(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    match bytes.get(start)? {
+        b'"' => skip_string(source, start),
+        b'{' | b'[' => {
+            let mut depth = 0_usize;
+            let mut pos = start;
+            while let Some(&b) = bytes.get(pos) {
+                match b {
+                    b'"' => {
+                        pos = skip_string(source, pos)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(pos + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+            None
+        }
+        _ => {
+            let len = source[start..]
+                .find(|c: char| c == ',' || c == ']' || c == '}' || c.is_ascii_whitespace())
+                .unwrap_or_else(|| source.len() - start);
+            Some(start + len)
+        }
+    }
+}
+
+/// Returns the byte offset next to the string starting at the given position.
+fn skip_string(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut pos = start + 1;
+    loop {
+        match bytes.get(pos)? {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+}
+
+/// Returns the byte offset of the first non-whitespace character at or after the given position.
+fn skip_whitespace(source: &str, start: usize) -> usize {
+    source[start..]
+        .find(|c: char| !c.is_ascii_whitespace())
+        .map_or(source.len(), |len| start + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// JSON source text.
+    const SOURCE: &str = r#"{
+  "@context": {"a/b": "http://example.com/"},
+  "@graph": [
+    {"@id": "x"},
+    {"name": "[\"}", "@id": 42}
+  ]
+}"#;
+
+    #[test]
+    fn pointers() {
+        let check = |pointer: &str, line: usize, column: usize| {
+            assert_eq!(
+                locate_pointer(SOURCE, pointer),
+                Some(Position::new(line, column)),
+                "pointer={:?}",
+                pointer
+            );
+        };
+        check("", 1, 1);
+        check("/@context/a~1b", 2, 23);
+        check("/@graph/0/@id", 4, 13);
+        check("/@graph/1/@id", 5, 29);
+        assert_eq!(locate_pointer(SOURCE, "/@graph/2"), None);
+        assert_eq!(locate_pointer(SOURCE, "/missing"), None);
+    }
+}
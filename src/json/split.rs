@@ -0,0 +1,172 @@
+//! Incremental splitter of JSON texts.
+
+use crate::error::{Error, ErrorCode, Result};
+
+/// State of the splitter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the first value.
+    Start,
+    /// In the top-level array.
+    Array,
+    /// After the top-level array.
+    End,
+    /// In the top-level value other than an array.
+    Other,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::Start
+    }
+}
+
+/// Splitter of the top-level array of a JSON text into the source texts of its elements.
+///
+/// The elements are not validated here, and are expected to be parsed by the caller.
+/// If the top-level value is not an array, the whole text is buffered and returned by
+/// `finish()`.
+#[derive(Default, Debug)]
+pub(crate) struct ArraySplitter {
+    /// State.
+    state: State,
+    /// Source of the current element, or the whole text if the top-level value is not an array.
+    current: Vec<u8>,
+    /// Nesting level of arrays and objects inside the top-level array.
+    depth: usize,
+    /// Whether the current byte is in a string.
+    in_string: bool,
+    /// Whether the previous byte is a backslash escaping the current byte.
+    escaped: bool,
+    /// Whether a comma is found after the last element.
+    after_comma: bool,
+}
+
+impl ArraySplitter {
+    /// Creates a new `ArraySplitter`.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the chunk of the text, and appends the sources of the completed elements.
+    pub(crate) fn feed(&mut self, chunk: &[u8], elements: &mut Vec<Vec<u8>>) -> Result<()> {
+        for &byte in chunk {
+            match self.state {
+                State::Start => match byte {
+                    b'[' => self.state = State::Array,
+                    // Byte order mark (U+FEFF) and whitespaces.
+                    0xEF | 0xBB | 0xBF | b' ' | b'\t' | b'\n' | b'\r' => {}
+                    byte => {
+                        self.state = State::Other;
+                        self.current.push(byte);
+                    }
+                },
+                State::Array => self.feed_array_byte(byte, elements)?,
+                State::End => {
+                    if !byte.is_ascii_whitespace() {
+                        return Err(invalid_json("unexpected data after the top-level array"));
+                    }
+                }
+                State::Other => self.current.push(byte),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Feeds the byte in the top-level array.
+    fn feed_array_byte(&mut self, byte: u8, elements: &mut Vec<Vec<u8>>) -> Result<()> {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+            }
+            self.current.push(byte);
+            return Ok(());
+        }
+        match byte {
+            b',' | b']' if self.depth == 0 => {
+                let is_empty = self.current.iter().all(u8::is_ascii_whitespace);
+                if is_empty {
+                    if byte == b',' || self.after_comma {
+                        return Err(invalid_json("missing array element"));
+                    }
+                } else {
+                    elements.push(std::mem::replace(&mut self.current, Vec::new()));
+                }
+                self.after_comma = byte == b',';
+                if byte == b']' {
+                    self.state = State::End;
+                }
+                return Ok(());
+            }
+            b'"' => self.in_string = true,
+            b'[' | b'{' => self.depth += 1,
+            b']' | b'}' => {
+                self.depth = self
+                    .depth
+                    .checked_sub(1)
+                    .ok_or_else(|| invalid_json("unbalanced brackets"))?
+            }
+            _ => {}
+        }
+        self.current.push(byte);
+        Ok(())
+    }
+
+    /// Finishes splitting.
+    ///
+    /// Returns the whole text if the top-level value is not an array.
+    pub(crate) fn finish(self) -> Result<Option<Vec<u8>>> {
+        match self.state {
+            State::Start => Err(invalid_json("empty document")),
+            State::Array => Err(invalid_json("unterminated top-level array")),
+            State::End => Ok(None),
+            State::Other => Ok(Some(self.current)),
+        }
+    }
+}
+
+/// Returns an error for an invalid JSON text.
+fn invalid_json(msg: &str) -> Error {
+    ErrorCode::LoadingDocumentFailed.and_message(format!("Invalid JSON: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits the chunks.
+    fn split(chunks: &[&str]) -> Result<(Vec<String>, Option<Vec<u8>>)> {
+        let mut splitter = ArraySplitter::new();
+        let mut elements = Vec::new();
+        for chunk in chunks {
+            splitter.feed(chunk.as_bytes(), &mut elements)?;
+        }
+        let elements = elements
+            .into_iter()
+            .map(|v| String::from_utf8(v).expect("valid UTF-8").trim().to_owned())
+            .collect();
+        Ok((elements, splitter.finish()?))
+    }
+
+    #[test]
+    fn split_array() {
+        let (elements, rest) =
+            split(&[" [{\"a\": [1, \"],\\\"\"]}", ", 2 ,\"x\"", "] "]).expect("valid JSON");
+        assert_eq!(elements, vec!["{\"a\": [1, \"],\\\"\"]}", "2", "\"x\""]);
+        assert_eq!(rest, None);
+
+        let (elements, rest) = split(&["{\"a\"", ": 1}"]).expect("valid JSON");
+        assert!(elements.is_empty());
+        assert_eq!(rest, Some(b"{\"a\": 1}".to_vec()));
+
+        assert!(split(&["[1,]"]).is_err());
+        assert!(split(&["[1, 2"]).is_err());
+        assert!(split(&["[1] 2"]).is_err());
+        assert!(split(&["[1}]"]).is_err());
+    }
+}
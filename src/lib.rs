@@ -1,4 +1,14 @@
 //! JSON-LD processing library.
+//!
+//! The processor futures do not depend on any async runtime, and can be run by any executor
+//! (including single-threaded ones, or `json_ld::blocking` with `blocking` feature).
+//! Runtime-specific code is behind features: `remote::HttpLoader` (`reqwest` feature) requires
+//! a tokio runtime, and `remote::SurfLoader` (`surf` feature) can be used with async-std.
+//!
+//! The crate can be compiled for `wasm32-unknown-unknown`, and `remote::FetchLoader` (`wasm`
+//! feature) loads remote documents in web browsers.
+//! Deadlines (`JsonLdOptions::deadline()`) and retry policies of loaders are not available on
+//! that target, since they need the system clock and threads.
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
@@ -7,17 +17,52 @@
 pub use async_trait;
 pub use iri_string;
 
+#[cfg(feature = "futures-io")]
+pub use self::expand::NdjsonExpansion;
 pub use self::{
-    context::Context,
-    error::{Error, ErrorCode, Result},
+    cancel::CancellationToken,
+    context::{
+        CompiledContext, Container, ContainerItem, ContainerLoadError, Context, ContextBuilder,
+        ContextDiff, ContextLint, Definition as TermDefinition, DefinitionAspect, Direction,
+        DirectionLoadError, LintKind, TermChange, TermDefinitionBuilder,
+    },
+    error::{Error, ErrorCode, KeywordValueError, Position, Result},
+    expand::{DroppedTerm, ExpansionEvent},
+    json::{JsonAdapter, JsonView},
+    node_map::{Graph, NodeMap},
+    options::{JsonLdOptions, ProcessingMode, RdfDirection},
     processor::{Processor, ProcessorOptions},
+    warning::{Warning, WarningHandler},
 };
 
+// This should be declared first, since the macros are used by other modules.
+#[macro_use]
+pub(crate) mod trace;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub(crate) mod budget;
+pub(crate) mod cancel;
+pub(crate) mod compact;
 pub(crate) mod context;
 pub(crate) mod error;
 pub(crate) mod expand;
+pub(crate) mod flatten;
+pub(crate) mod frame;
+pub(crate) mod from_rdf;
+pub(crate) mod hash;
+pub mod html;
+pub(crate) mod intern;
 pub(crate) mod iri;
+pub(crate) mod join;
 pub(crate) mod json;
+pub(crate) mod node_map;
+pub(crate) mod options;
 pub(crate) mod processor;
+pub mod rdf;
 pub mod remote;
 pub(crate) mod syntax;
+pub(crate) mod to_rdf;
+pub(crate) mod warning;
+#[cfg(feature = "futures-io")]
+pub(crate) mod write;
@@ -0,0 +1,394 @@
+//! Node map generation.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#node-map-generation>.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Map as JsonMap, Value};
+
+use crate::{
+    error::{ErrorCode, Result},
+    expand::expand,
+    json::{map_entries, single_entry_map, to_ref_array},
+    processor::Processor,
+    remote::LoadRemoteDocument,
+    syntax::is_blank_node_identifier,
+};
+
+pub(crate) use self::blank_node::BlankNodeIdGenerator;
+
+mod blank_node;
+
+/// Node objects in a graph, indexed by their identifiers.
+pub type Graph = BTreeMap<String, JsonMap<String, Value>>;
+
+/// Graphs indexed by their names.
+///
+/// The default graph has the name `@default`.
+pub type NodeMap = BTreeMap<String, Graph>;
+
+/// Active subject for node map generation.
+#[derive(Debug, Clone, Copy)]
+enum ActiveSubject<'a> {
+    /// No active subject.
+    None,
+    /// Node identifier.
+    Id(&'a str),
+    /// Node reference for reverse properties.
+    Reverse(&'a Value),
+}
+
+/// Expands the given document, and runs node map generation algorithm for it.
+///
+/// Blank node identifiers in the document are relabeled.
+pub(crate) async fn node_map<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+) -> Result<NodeMap> {
+    let expanded = expand(processor, input).await?;
+    let mut node_map = NodeMap::new();
+    node_map.insert("@default".to_owned(), Graph::new());
+    generate_node_map(&expanded, &mut node_map, &mut BlankNodeIdGenerator::new())?;
+
+    Ok(node_map)
+}
+
+/// Runs node map generation algorithm for the given expanded element.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#node-map-generation>.
+pub(crate) fn generate_node_map(
+    element: &Value,
+    node_map: &mut NodeMap,
+    generator: &mut BlankNodeIdGenerator,
+) -> Result<()> {
+    generate_node_map_impl(
+        element,
+        node_map,
+        generator,
+        "@default",
+        ActiveSubject::None,
+        None,
+        None,
+    )
+}
+
+/// Runs node map generation algorithm.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#node-map-generation>.
+#[allow(clippy::too_many_arguments)] // TODO: FIXME
+fn generate_node_map_impl(
+    element: &Value,
+    node_map: &mut NodeMap,
+    generator: &mut BlankNodeIdGenerator,
+    active_graph: &str,
+    active_subject: ActiveSubject<'_>,
+    active_property: Option<&str>,
+    mut list: Option<&mut Vec<Value>>,
+) -> Result<()> {
+    let element = match element {
+        // Step 1
+        Value::Array(items) => {
+            for item in items {
+                generate_node_map_impl(
+                    item,
+                    node_map,
+                    generator,
+                    active_graph,
+                    active_subject,
+                    active_property,
+                    list.as_mut().map(|list| &mut **list),
+                )?;
+            }
+            return Ok(());
+        }
+        Value::Object(map) => map,
+        // Expanded document has no scalar nodes here.
+        _ => return Ok(()),
+    };
+    // Step 2
+    node_map
+        .entry(active_graph.to_owned())
+        .or_insert_with(Graph::new);
+    // Step 3
+    let types = element
+        .get("@type")
+        .map(|types| relabel_types(types, generator));
+
+    if element.contains_key("@value") {
+        // Step 4
+        let mut element = element.clone();
+        if let Some(types) = types {
+            element.insert("@type".to_owned(), types);
+        }
+        let element = Value::Object(element);
+        match list {
+            // Step 4.1
+            None => {
+                if let Some(entry) =
+                    subject_property_entry(node_map, active_graph, active_subject, active_property)
+                {
+                    push_unique(entry, element);
+                }
+            }
+            // Step 4.2
+            Some(list) => list.push(element),
+        }
+    } else if let Some(list_value) = element.get("@list") {
+        // Step 5
+        // Step 5.1
+        let mut result = Vec::new();
+        // Step 5.2
+        generate_node_map_impl(
+            list_value,
+            node_map,
+            generator,
+            active_graph,
+            active_subject,
+            active_property,
+            Some(&mut result),
+        )?;
+        let result = Value::Object(single_entry_map("@list", Value::Array(result)));
+        match list {
+            // Step 5.3
+            None => {
+                if let Some(entry) =
+                    subject_property_entry(node_map, active_graph, active_subject, active_property)
+                {
+                    entry.push(result);
+                }
+            }
+            // Step 5.4
+            Some(list) => list.push(result),
+        }
+    } else {
+        // Step 6
+        // Step 6.1, 6.2
+        let id = match element.get("@id").and_then(Value::as_str) {
+            Some(id) if is_blank_node_identifier(id) => generator.generate(Some(id)),
+            Some(id) => id.to_owned(),
+            None => generator.generate(None),
+        };
+        let reference = Value::Object(single_entry_map("@id", id.clone()));
+        // Step 6.3, 6.4
+        let node = node_entry(node_map, active_graph, &id);
+        if let ActiveSubject::Reverse(active_subject) = active_subject {
+            // Step 6.5
+            if let Some(active_property) = active_property {
+                // Step 6.5.1, 6.5.2
+                push_unique(array_entry(node, active_property), active_subject.clone());
+            }
+        } else if active_property.is_some() {
+            // Step 6.6
+            // Step 6.6.1: `reference` is the node reference.
+            let reference = reference.clone();
+            match list {
+                // Step 6.6.2
+                None => {
+                    if let Some(entry) = subject_property_entry(
+                        node_map,
+                        active_graph,
+                        active_subject,
+                        active_property,
+                    ) {
+                        push_unique(entry, reference);
+                    }
+                }
+                // Step 6.6.3
+                Some(list) => list.push(reference),
+            }
+        }
+        let node = node_entry(node_map, active_graph, &id);
+        // Step 6.7
+        if let Some(types) = types {
+            let node_types = array_entry(node, "@type");
+            for ty in to_ref_array(&types) {
+                push_unique(node_types, ty.clone());
+            }
+        }
+        // Step 6.8
+        if let Some(index) = element.get("@index") {
+            match node.get("@index") {
+                Some(node_index) if node_index != index => {
+                    return Err(ErrorCode::ConflictingIndexes.and_message(format!(
+                        "Node {:?} has conflicting indexes {:?} and {:?}",
+                        id, node_index, index
+                    )))
+                }
+                _ => {
+                    node.insert("@index".to_owned(), index.clone());
+                }
+            }
+        }
+        // Step 6.9
+        if let Some(Value::Object(reverse_map)) = element.get("@reverse") {
+            // Step 6.9.1: `reference` is the referenced node.
+            // Step 6.9.2, 6.9.3
+            for (property, values) in map_entries(reverse_map, true) {
+                // Step 6.9.3.1
+                for value in to_ref_array(values) {
+                    // Step 6.9.3.1.1
+                    generate_node_map_impl(
+                        value,
+                        node_map,
+                        generator,
+                        active_graph,
+                        ActiveSubject::Reverse(&reference),
+                        Some(property),
+                        None,
+                    )?;
+                }
+            }
+        }
+        // Step 6.10
+        if let Some(graph) = element.get("@graph") {
+            generate_node_map_impl(
+                graph,
+                node_map,
+                generator,
+                &id,
+                ActiveSubject::None,
+                None,
+                None,
+            )?;
+        }
+        // Step 6.11
+        if let Some(included) = element.get("@included") {
+            generate_node_map_impl(
+                included,
+                node_map,
+                generator,
+                active_graph,
+                ActiveSubject::None,
+                None,
+                None,
+            )?;
+        }
+        // Step 6.12
+        for (property, value) in map_entries(element, true) {
+            // Keywords are already processed.
+            if property.starts_with('@') {
+                continue;
+            }
+            // Step 6.12.1
+            let property = if is_blank_node_identifier(property) {
+                generator.generate(Some(property))
+            } else {
+                property.clone()
+            };
+            // Step 6.12.2
+            array_entry(node_entry(node_map, active_graph, &id), &property);
+            // Step 6.12.3
+            generate_node_map_impl(
+                value,
+                node_map,
+                generator,
+                active_graph,
+                ActiveSubject::Id(&id),
+                Some(&property),
+                None,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Merges all graphs in the node map into a single graph.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#merge-node-maps>.
+pub(crate) fn merge_node_maps(node_map: &NodeMap) -> Graph {
+    // Step 1
+    let mut result = Graph::new();
+    // Step 2
+    for graph in node_map.values() {
+        for (id, node) in graph {
+            // Step 2.1
+            let merged_node = result
+                .entry(id.clone())
+                .or_insert_with(|| single_entry_map("@id", id.as_str()));
+            // Step 2.2
+            for (property, values) in node {
+                if property != "@type" && property.starts_with('@') {
+                    // Step 2.2.1
+                    merged_node.insert(property.clone(), values.clone());
+                } else {
+                    // Step 2.2.2
+                    let entry = array_entry(merged_node, property);
+                    for value in to_ref_array(values) {
+                        push_unique(entry, value.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    // Step 3
+    result
+}
+
+/// Replaces blank node identifiers in the given `@type` value with generated ones.
+// Step 3
+fn relabel_types(types: &Value, generator: &mut BlankNodeIdGenerator) -> Value {
+    let mut relabel = |ty: &Value| match ty {
+        Value::String(ty) if is_blank_node_identifier(ty) => {
+            Value::String(generator.generate(Some(ty)))
+        }
+        v => v.clone(),
+    };
+    match types {
+        Value::Array(types) => Value::Array(types.iter().map(relabel).collect()),
+        ty => relabel(ty),
+    }
+}
+
+/// Returns the node in the given graph, creating it if necessary.
+// Step 6.3, 6.4
+fn node_entry<'a>(
+    node_map: &'a mut NodeMap,
+    active_graph: &str,
+    id: &str,
+) -> &'a mut JsonMap<String, Value> {
+    node_map
+        .entry(active_graph.to_owned())
+        .or_insert_with(Graph::new)
+        .entry(id.to_owned())
+        .or_insert_with(|| single_entry_map("@id", id))
+}
+
+/// Returns the entry of the active property in the subject node, if the active subject is a node
+/// identifier.
+fn subject_property_entry<'a>(
+    node_map: &'a mut NodeMap,
+    active_graph: &str,
+    active_subject: ActiveSubject<'_>,
+    active_property: Option<&str>,
+) -> Option<&'a mut Vec<Value>> {
+    let (subject, property) = match (active_subject, active_property) {
+        (ActiveSubject::Id(subject), Some(property)) => (subject, property),
+        _ => return None,
+    };
+    let subject_node = node_map.get_mut(active_graph)?.get_mut(subject)?;
+    Some(array_entry(subject_node, property))
+}
+
+/// Returns the array entry of the given map, creating it if necessary.
+pub(crate) fn array_entry<'a>(
+    map: &'a mut JsonMap<String, Value>,
+    key: &str,
+) -> &'a mut Vec<Value> {
+    let entry = map.entry(key).or_insert_with(|| Value::Array(Vec::new()));
+    if !entry.is_array() {
+        let original = std::mem::replace(entry, Value::Null);
+        *entry = Value::Array(vec![original]);
+    }
+    entry
+        .as_array_mut()
+        .expect("Should never fail: the entry is an array")
+}
+
+/// Appends the value to the array unless the array already contains it.
+pub(crate) fn push_unique(array: &mut Vec<Value>, value: Value) {
+    if !array.contains(&value) {
+        array.push(value);
+    }
+}
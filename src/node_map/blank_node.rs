@@ -0,0 +1,60 @@
+//! Blank node identifier generation.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#generate-blank-node-identifier>.
+
+use std::collections::HashMap;
+
+/// Blank node identifier generator.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#generate-blank-node-identifier>.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct BlankNodeIdGenerator {
+    /// Identifier map.
+    identifier_map: HashMap<String, String>,
+    /// Counter.
+    counter: u64,
+}
+
+impl BlankNodeIdGenerator {
+    /// Creates a new `BlankNodeIdGenerator`.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generates a new blank node identifier for the given identifier.
+    ///
+    /// If the identifier is `None`, a fresh blank node identifier is returned.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#generate-blank-node-identifier>.
+    pub(crate) fn generate(&mut self, identifier: Option<&str>) -> String {
+        // Step 1
+        if let Some(generated) = identifier.and_then(|id| self.identifier_map.get(id)) {
+            return generated.clone();
+        }
+        // Step 2, 3
+        let blank_node_id = format!("_:b{}", self.counter);
+        self.counter += 1;
+        // Step 4
+        if let Some(identifier) = identifier {
+            self.identifier_map
+                .insert(identifier.to_owned(), blank_node_id.clone());
+        }
+
+        // Step 5
+        blank_node_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_generated_id() {
+        let mut generator = BlankNodeIdGenerator::new();
+        assert_eq!(generator.generate(Some("_:foo")), "_:b0");
+        assert_eq!(generator.generate(None), "_:b1");
+        assert_eq!(generator.generate(Some("_:bar")), "_:b2");
+        assert_eq!(generator.generate(Some("_:foo")), "_:b0");
+    }
+}
@@ -0,0 +1,583 @@
+//! JSON-LD API options.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#the-jsonldoptions-type>.
+
+use std::time::{Duration, Instant};
+
+use iri_string::types::{IriStr, IriString};
+use serde_json::Value;
+
+use crate::cancel::CancellationToken;
+
+/// Processing mode.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldoptions-processingmode>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcessingMode {
+    /// `json-ld-1.0`.
+    ///
+    /// JSON-LD 1.1 features are rejected with errors.
+    JsonLd10,
+    /// `json-ld-1.1`.
+    JsonLd11,
+}
+
+impl ProcessingMode {
+    /// Returns the processing mode string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProcessingMode::JsonLd10 => "json-ld-1.0",
+            ProcessingMode::JsonLd11 => "json-ld-1.1",
+        }
+    }
+}
+
+impl Default for ProcessingMode {
+    fn default() -> Self {
+        ProcessingMode::JsonLd11
+    }
+}
+
+/// Method of representing `@direction` in RDF.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldoptions-rdfdirection>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RdfDirection {
+    /// `i18n-datatype`.
+    I18nDatatype,
+    /// `compound-literal`.
+    CompoundLiteral,
+}
+
+/// JSON-LD API options.
+///
+/// The document loader is not a part of this type, but given to the `Processor`.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#the-jsonldoptions-type>.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonLdOptions {
+    /// Base IRI.
+    ///
+    /// > The base IRI to use when expanding or compacting the document. If set, this overrides
+    /// > the input document's IRI.
+    base: Option<IriString>,
+    /// Processing mode.
+    processing_mode: ProcessingMode,
+    /// Context to be used to initialize the active context when expanding a document.
+    expand_context: Option<Value>,
+    /// `compactArrays` flag.
+    ///
+    /// > If set to `true`, the JSON-LD processor replaces arrays with just one element with that
+    /// > element during compaction. If set to `false`, all arrays will remain arrays even if they
+    /// > have just one element.
+    compact_arrays: bool,
+    /// `compactToRelative` flag.
+    ///
+    /// > Determines if IRIs are compacted relative to the base option or document location when
+    /// > compacting.
+    compact_to_relative: bool,
+    /// `ordered` flag.
+    ///
+    /// > If set to `true`, certain algorithm processing steps where indicated are ordered
+    /// > lexicographically. If `false`, order is not considered in processing.
+    ordered: bool,
+    /// `produceGeneralizedRdf` flag.
+    ///
+    /// > If set to `true`, the JSON-LD processor may emit blank nodes for triple predicates,
+    /// > otherwise they will be omitted.
+    produce_generalized_rdf: bool,
+    /// Method of representing `@direction` in RDF.
+    ///
+    /// `None` means `@direction` is not represented in RDF.
+    rdf_direction: Option<RdfDirection>,
+    /// `useNativeTypes` flag.
+    ///
+    /// > Causes the Serialize RDF as JSON-LD Algorithm to use native JSON values in value objects
+    /// > avoiding the need for an explicit `@type`.
+    use_native_types: bool,
+    /// `useRdfType` flag.
+    ///
+    /// > Enables special handling of `rdf:type` properties, which avoids the creation of `@type`.
+    use_rdf_type: bool,
+    /// `frameExpansion` flag.
+    ///
+    /// > Enables special frame processing rules for the Expansion Algorithm.
+    frame_expansion: bool,
+    /// `extractAllScripts` flag.
+    ///
+    /// > If set to `true`, when extracting JSON-LD script elements from HTML, unless a specific
+    /// > fragment identifier is targeted, extracts all encountered JSON-LD script elements using an
+    /// > array form, if necessary.
+    extract_all_scripts: bool,
+    /// `safe` flag.
+    ///
+    /// If set to `true`, data which would be silently dropped during processing (such as
+    /// properties which do not expand to IRIs, or relative IRIs in RDF serialization) causes an
+    /// error instead.
+    safe: bool,
+    /// Maximum size of loaded remote documents in bytes.
+    ///
+    /// `None` means there are no limits.
+    max_document_size: Option<u64>,
+    /// Maximum number of JSON values in a loaded remote document.
+    ///
+    /// `None` means there are no limits.
+    max_document_nodes: Option<usize>,
+    /// Timeout of loading a remote document.
+    ///
+    /// `None` means the processor default is used.
+    load_timeout: Option<Duration>,
+    /// Additional HTTP request headers to load remote documents.
+    request_headers: Vec<(String, String)>,
+    /// Maximum number of remote contexts.
+    ///
+    /// `None` means the processor default is used.
+    max_remote_contexts: Option<usize>,
+    /// Maximum nesting depth of arrays and maps in the input document.
+    ///
+    /// `None` means the processor default is used.
+    max_nesting_depth: Option<usize>,
+    /// Approximate maximum number of bytes allocated for values created during the operation.
+    ///
+    /// `None` means no limits.
+    memory_budget: Option<usize>,
+    /// Whether protected term definitions can be redefined.
+    ///
+    /// This is `overrideProtected` flag of the context processing algorithm, applied to every
+    /// context processed in the operation.
+    override_protected: bool,
+    /// Maximum number of items of the top-level array whose remote contexts are loaded
+    /// concurrently.
+    load_concurrency: usize,
+    /// Maximum number of worker threads expanding the items of the top-level array.
+    #[cfg(feature = "parallel")]
+    expansion_workers: usize,
+    /// Token to cancel the operation.
+    cancellation_token: Option<CancellationToken>,
+    /// Deadline of the operation.
+    deadline: Option<Instant>,
+}
+
+impl Default for JsonLdOptions {
+    fn default() -> Self {
+        Self {
+            base: None,
+            processing_mode: ProcessingMode::default(),
+            expand_context: None,
+            compact_arrays: true,
+            compact_to_relative: true,
+            ordered: false,
+            produce_generalized_rdf: false,
+            rdf_direction: None,
+            use_native_types: false,
+            use_rdf_type: false,
+            frame_expansion: false,
+            extract_all_scripts: false,
+            safe: false,
+            max_document_size: None,
+            max_document_nodes: None,
+            load_timeout: None,
+            request_headers: Vec::new(),
+            max_remote_contexts: None,
+            max_nesting_depth: None,
+            memory_budget: None,
+            override_protected: false,
+            load_concurrency: 1,
+            #[cfg(feature = "parallel")]
+            expansion_workers: 1,
+            cancellation_token: None,
+            deadline: None,
+        }
+    }
+}
+
+impl JsonLdOptions {
+    /// Creates a new default `JsonLdOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the base IRI.
+    pub fn base(self, base: impl Into<Option<IriString>>) -> Self {
+        Self {
+            base: base.into(),
+            ..self
+        }
+    }
+
+    /// Sets the processing mode.
+    pub fn processing_mode(self, processing_mode: ProcessingMode) -> Self {
+        Self {
+            processing_mode,
+            ..self
+        }
+    }
+
+    /// Sets the context to be used to initialize the active context when expanding a document.
+    pub fn expand_context(self, expand_context: impl Into<Option<Value>>) -> Self {
+        Self {
+            expand_context: expand_context.into(),
+            ..self
+        }
+    }
+
+    /// Sets the `compactArrays` flag.
+    pub fn compact_arrays(self, compact_arrays: bool) -> Self {
+        Self {
+            compact_arrays,
+            ..self
+        }
+    }
+
+    /// Sets the `compactToRelative` flag.
+    pub fn compact_to_relative(self, compact_to_relative: bool) -> Self {
+        Self {
+            compact_to_relative,
+            ..self
+        }
+    }
+
+    /// Sets the `ordered` flag.
+    pub fn ordered(self, ordered: bool) -> Self {
+        Self { ordered, ..self }
+    }
+
+    /// Sets the `produceGeneralizedRdf` flag.
+    pub fn produce_generalized_rdf(self, produce_generalized_rdf: bool) -> Self {
+        Self {
+            produce_generalized_rdf,
+            ..self
+        }
+    }
+
+    /// Sets the method of representing `@direction` in RDF.
+    pub fn rdf_direction(self, rdf_direction: impl Into<Option<RdfDirection>>) -> Self {
+        Self {
+            rdf_direction: rdf_direction.into(),
+            ..self
+        }
+    }
+
+    /// Sets the `useNativeTypes` flag.
+    pub fn use_native_types(self, use_native_types: bool) -> Self {
+        Self {
+            use_native_types,
+            ..self
+        }
+    }
+
+    /// Sets the `useRdfType` flag.
+    pub fn use_rdf_type(self, use_rdf_type: bool) -> Self {
+        Self {
+            use_rdf_type,
+            ..self
+        }
+    }
+
+    /// Sets the `frameExpansion` flag.
+    pub fn frame_expansion(self, frame_expansion: bool) -> Self {
+        Self {
+            frame_expansion,
+            ..self
+        }
+    }
+
+    /// Sets the `extractAllScripts` flag.
+    pub fn extract_all_scripts(self, extract_all_scripts: bool) -> Self {
+        Self {
+            extract_all_scripts,
+            ..self
+        }
+    }
+
+    /// Sets the `safe` flag.
+    ///
+    /// If set, expansion and RDF conversion fail with `safe mode violation` error instead of
+    /// dropping data silently.
+    pub fn safe(self, safe: bool) -> Self {
+        Self { safe, ..self }
+    }
+
+    /// Sets whether protected term definitions can be redefined.
+    ///
+    /// This disables the protection of terms, so it should only be used by trusted tools such as
+    /// context migration scripts.
+    pub fn override_protected(self, override_protected: bool) -> Self {
+        Self {
+            override_protected,
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of loaded remote documents in bytes.
+    ///
+    /// The limit is passed to the document loader, which is responsible for enforcing it.
+    pub fn max_document_size(self, max_document_size: impl Into<Option<u64>>) -> Self {
+        Self {
+            max_document_size: max_document_size.into(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of JSON values (including nested ones) in a loaded remote
+    /// document.
+    pub fn max_document_nodes(self, max_document_nodes: impl Into<Option<usize>>) -> Self {
+        Self {
+            max_document_nodes: max_document_nodes.into(),
+            ..self
+        }
+    }
+
+    /// Sets the timeout of loading each remote document.
+    ///
+    /// This overrides the processor default set by `ProcessorOptions::default_load_timeout()`.
+    /// The timeout is passed to the document loader, which is responsible for enforcing it.
+    pub fn load_timeout(self, load_timeout: impl Into<Option<Duration>>) -> Self {
+        Self {
+            load_timeout: load_timeout.into(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of remote contexts dereferenced while processing a context.
+    ///
+    /// This overrides the processor default, so that untrusted input can be processed with a
+    /// tighter bound.
+    /// Exceeding the limit results in `context overflow` error.
+    pub fn max_remote_contexts(self, max_remote_contexts: impl Into<Option<usize>>) -> Self {
+        Self {
+            max_remote_contexts: max_remote_contexts.into(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum nesting depth of arrays and maps in the input document.
+    ///
+    /// This overrides the processor default, so that deeply nested documents are rejected
+    /// before they exhaust the stack.
+    /// Expansion is recursive, so each nesting level consumes stack space; raise the limit only
+    /// together with the stack size of the executing thread.
+    /// Debug builds use several times more stack per level than release builds, and need a
+    /// larger stack or a lower limit.
+    /// Term definitions depending on other term definitions are created without recursion, so
+    /// the number of terms in a context is not limited by this.
+    /// Exceeding the limit results in `nesting too deep` error.
+    pub fn max_nesting_depth(self, max_nesting_depth: impl Into<Option<usize>>) -> Self {
+        Self {
+            max_nesting_depth: max_nesting_depth.into(),
+            ..self
+        }
+    }
+
+    /// Sets the approximate maximum number of bytes allocated for values created during the
+    /// operation.
+    ///
+    /// Sizes of the nodes and strings created by expansion, compaction, and framing are roughly
+    /// accounted, so that a document expanding combinatorially is rejected before it exhausts
+    /// the memory.
+    /// Values no longer used, such as the expanded input of flattening and framing, are released
+    /// from the budget.
+    /// Exceeding the budget results in `memory budget exceeded` error.
+    pub fn memory_budget(self, memory_budget: impl Into<Option<usize>>) -> Self {
+        Self {
+            memory_budget: memory_budget.into(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of items of the top-level array whose remote contexts are loaded
+    /// concurrently.
+    ///
+    /// Items of the top-level array are independent, so an item can be expanded while remote
+    /// contexts for other items are being loaded. A new item is started as soon as the oldest
+    /// one in flight completes.
+    /// This overlaps remote loads only: the items are polled on the caller's task, so CPU-bound
+    /// expansion is not parallelized. Use `expansion_workers()` (with `parallel` feature) for CPU
+    /// parallelism.
+    /// `0` is treated as `1`, which means items are expanded one by one (the default).
+    pub fn load_concurrency(self, load_concurrency: usize) -> Self {
+        Self {
+            load_concurrency,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of worker threads expanding the items of the top-level array.
+    ///
+    /// Items of the top-level array are independent, so they are expanded in parallel on up to
+    /// this number of threads, which are spawned for each expansion. Remote documents requested
+    /// by the workers are still loaded on the caller's task, so any loader can be used.
+    /// The items are copied for the workers, and this is ignored when the dropped keys are
+    /// reported by `Processor::expand_with_report()`.
+    /// `0` is treated as `1`, which means items are expanded on the caller's task (the default).
+    #[cfg(feature = "parallel")]
+    pub fn expansion_workers(self, expansion_workers: usize) -> Self {
+        Self {
+            expansion_workers,
+            ..self
+        }
+    }
+
+    /// Sets the token to cancel the operation.
+    ///
+    /// Cancelling the token aborts the operation, including pending loads of remote documents,
+    /// with `ErrorCode::Cancelled`.
+    pub fn cancellation_token(
+        self,
+        cancellation_token: impl Into<Option<CancellationToken>>,
+    ) -> Self {
+        Self {
+            cancellation_token: cancellation_token.into(),
+            ..self
+        }
+    }
+
+    /// Sets the deadline of the operation.
+    ///
+    /// The operation fails with `ErrorCode::Cancelled` after the deadline.
+    /// The timeout of loading remote documents is shortened so that loads end by the deadline.
+    pub fn deadline(self, deadline: impl Into<Option<Instant>>) -> Self {
+        Self {
+            deadline: deadline.into(),
+            ..self
+        }
+    }
+
+    /// Adds the HTTP request header to load remote documents.
+    ///
+    /// Headers such as `Authorization`, API keys, and `Accept-Language` are forwarded by the
+    /// built-in HTTP loader, so that documents behind authenticated gateways can be loaded.
+    /// Loaders not using HTTP ignore them.
+    pub fn request_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.request_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Returns the base IRI.
+    pub fn get_base(&self) -> Option<&IriStr> {
+        self.base.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Returns the processing mode.
+    pub fn get_processing_mode(&self) -> ProcessingMode {
+        self.processing_mode
+    }
+
+    /// Returns the context to be used to initialize the active context when expanding a
+    /// document.
+    pub fn get_expand_context(&self) -> Option<&Value> {
+        self.expand_context.as_ref()
+    }
+
+    /// Returns the `compactArrays` flag.
+    pub fn is_compact_arrays(&self) -> bool {
+        self.compact_arrays
+    }
+
+    /// Returns the `compactToRelative` flag.
+    pub fn is_compact_to_relative(&self) -> bool {
+        self.compact_to_relative
+    }
+
+    /// Returns the `ordered` flag.
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    /// Returns the `produceGeneralizedRdf` flag.
+    pub fn is_produce_generalized_rdf(&self) -> bool {
+        self.produce_generalized_rdf
+    }
+
+    /// Returns the method of representing `@direction` in RDF.
+    pub fn get_rdf_direction(&self) -> Option<RdfDirection> {
+        self.rdf_direction
+    }
+
+    /// Returns the `useNativeTypes` flag.
+    pub fn is_use_native_types(&self) -> bool {
+        self.use_native_types
+    }
+
+    /// Returns the `useRdfType` flag.
+    pub fn is_use_rdf_type(&self) -> bool {
+        self.use_rdf_type
+    }
+
+    /// Returns the `frameExpansion` flag.
+    pub fn is_frame_expansion(&self) -> bool {
+        self.frame_expansion
+    }
+
+    /// Returns the `extractAllScripts` flag.
+    pub fn is_extract_all_scripts(&self) -> bool {
+        self.extract_all_scripts
+    }
+
+    /// Returns the `safe` flag.
+    pub fn is_safe(&self) -> bool {
+        self.safe
+    }
+
+    /// Returns whether protected term definitions can be redefined.
+    pub fn is_override_protected(&self) -> bool {
+        self.override_protected
+    }
+
+    /// Returns the maximum size of loaded remote documents in bytes.
+    pub fn get_max_document_size(&self) -> Option<u64> {
+        self.max_document_size
+    }
+
+    /// Returns the maximum number of JSON values in a loaded remote document.
+    pub fn get_max_document_nodes(&self) -> Option<usize> {
+        self.max_document_nodes
+    }
+
+    /// Returns the timeout of loading each remote document.
+    pub fn get_load_timeout(&self) -> Option<Duration> {
+        self.load_timeout
+    }
+
+    /// Returns the maximum number of remote contexts.
+    pub fn get_max_remote_contexts(&self) -> Option<usize> {
+        self.max_remote_contexts
+    }
+
+    /// Returns the maximum nesting depth of arrays and maps in the input document.
+    pub fn get_max_nesting_depth(&self) -> Option<usize> {
+        self.max_nesting_depth
+    }
+
+    /// Returns the approximate maximum number of bytes allocated during the operation.
+    pub fn get_memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
+
+    /// Returns the maximum number of items of the top-level array whose remote contexts are
+    /// loaded concurrently.
+    pub fn get_load_concurrency(&self) -> usize {
+        self.load_concurrency
+    }
+
+    /// Returns the maximum number of worker threads expanding the items of the top-level array.
+    #[cfg(feature = "parallel")]
+    pub fn get_expansion_workers(&self) -> usize {
+        self.expansion_workers
+    }
+
+    /// Returns the token to cancel the operation.
+    pub fn get_cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+
+    /// Returns the deadline of the operation.
+    pub fn get_deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Returns the additional HTTP request headers to load remote documents.
+    pub fn get_request_headers(&self) -> &[(String, String)] {
+        &self.request_headers
+    }
+}
@@ -2,11 +2,66 @@
 //!
 //! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#the-jsonldprocessor-interface>.
 
-use std::borrow::Cow;
+use std::{
+    borrow::{Borrow, Cow},
+    convert::Infallible,
+    future::Future,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
 
 use iri_string::types::{IriStr, IriString};
+use serde_json::{Map as JsonMap, Value};
 
-use crate::{context::Context, json::Nullable, remote::LoadRemoteDocument};
+use crate::{
+    budget::MemoryBudget,
+    cancel::{Cancellable, CancellationToken},
+    compact::{compact, compact_to_writer, compact_with_active_context},
+    context::{CompiledContext, Context, ProcessedContextKey},
+    error::{ErrorCode, Result},
+    expand::{
+        expand_events, expand_to_writer, expand_with_active_context, expand_with_context_url,
+        expand_with_options, DroppedTerm, DroppedTermCollector, ExpandOptions, ExpansionEvent,
+    },
+    flatten::flatten,
+    from_rdf::{from_rdf, FromRdfOptions},
+    hash::LruMap,
+    intern::{Interner, Symbol},
+    join::{join_all, try_join_buffered},
+    json::{exceeds_node_count, from_serde_json, to_serde_json, JsonAdapter, Nullable},
+    node_map::{node_map, NodeMap},
+    options::{JsonLdOptions, ProcessingMode},
+    rdf::Quad,
+    remote::{LoadDocumentOptions, LoadRemoteDocument, Profile, RemoteDocument},
+    to_rdf::{to_rdf, to_rdf_with_handler, ToRdfOptions},
+    warning::{Warning, WarningHandler},
+};
+#[cfg(feature = "futures-io")]
+use crate::{
+    expand::{expand_reader_events, NdjsonExpansion},
+    write::{SharedBuffer, WriteThrough},
+};
+
+/// Cache of remote contexts, keyed by the IRI and the load options.
+type RemoteContextCache = LruMap<(IriString, LoadDocumentOptions), Arc<RemoteDocument>>;
+
+/// Cache of processed remote contexts, as pairs of the active context and the result.
+type ProcessedContextCache = LruMap<ProcessedContextKey, Vec<(Context, Context)>>;
+
+/// Maximum number of remote contexts cached by a processor.
+///
+/// The least recently used context is evicted when the cache is full.
+const MAX_CACHED_REMOTE_CONTEXTS: usize = 256;
+
+/// Maximum number of remote contexts whose processed results are cached by a processor.
+const MAX_PROCESSED_CONTEXT_KEYS: usize = 256;
+
+/// Maximum number of processed contexts cached for each key.
+///
+/// Results for different active contexts are cached separately, so this bounds the cache for
+/// documents with various base IRIs.
+const MAX_PROCESSED_CONTEXTS_PER_KEY: usize = 8;
 
 /// JSON-LD processor options.
 ///
@@ -15,6 +70,10 @@ use crate::{context::Context, json::Nullable, remote::LoadRemoteDocument};
 pub struct ProcessorOptions {
     /// Base IRI (or document IRI).
     document_iri: IriString,
+    /// JSON-LD API options for the current operation.
+    json_ld: JsonLdOptions,
+    /// Default timeout of loading a remote document.
+    default_load_timeout: Option<Duration>,
 }
 
 impl ProcessorOptions {
@@ -22,6 +81,32 @@ impl ProcessorOptions {
     pub fn with_base(document_iri: impl Into<IriString>) -> Self {
         Self {
             document_iri: document_iri.into(),
+            json_ld: JsonLdOptions::new(),
+            default_load_timeout: None,
+        }
+    }
+
+    /// Sets the default timeout of loading each remote document.
+    ///
+    /// This is used when the JSON-LD API options for the operation have no timeout.
+    pub fn default_load_timeout(self, default_load_timeout: impl Into<Option<Duration>>) -> Self {
+        Self {
+            default_load_timeout: default_load_timeout.into(),
+            ..self
+        }
+    }
+
+    /// Sets the JSON-LD API options.
+    ///
+    /// If the options have the base IRI, it overrides the document IRI.
+    fn json_ld_options(self, json_ld: JsonLdOptions) -> Self {
+        let document_iri = json_ld
+            .get_base()
+            .map_or(self.document_iri, ToOwned::to_owned);
+        Self {
+            document_iri,
+            json_ld,
+            ..self
         }
     }
 
@@ -32,8 +117,51 @@ impl ProcessorOptions {
 
     /// Checks if the processing mode is `json-ld-1.0`.
     pub(crate) fn is_processing_mode_1_0(&self) -> bool {
-        // Currently unsupported.
-        false
+        self.json_ld.get_processing_mode() == ProcessingMode::JsonLd10
+    }
+
+    /// Returns the context to be used to initialize the active context when expanding a document.
+    pub(crate) fn expand_context(&self) -> Option<&Value> {
+        self.json_ld.get_expand_context()
+    }
+
+    /// Checks if the `ordered` flag is set.
+    pub(crate) fn is_ordered(&self) -> bool {
+        self.json_ld.is_ordered()
+    }
+
+    /// Checks if the `safe` flag is set.
+    pub(crate) fn is_safe(&self) -> bool {
+        self.json_ld.is_safe()
+    }
+
+    /// Checks if protected term definitions can be redefined.
+    pub(crate) fn is_override_protected(&self) -> bool {
+        self.json_ld.is_override_protected()
+    }
+
+    /// Returns the maximum number of items of the top-level array whose remote contexts are loaded
+    /// concurrently.
+    ///
+    /// This is at least 1.
+    pub(crate) fn load_concurrency(&self) -> usize {
+        self.json_ld.get_load_concurrency().max(1)
+    }
+
+    /// Returns the maximum number of worker threads expanding the items of the top-level array.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn expansion_workers(&self) -> usize {
+        self.json_ld.get_expansion_workers().max(1)
+    }
+
+    /// Checks if the `compactArrays` flag is set.
+    pub(crate) fn is_compact_arrays(&self) -> bool {
+        self.json_ld.is_compact_arrays()
+    }
+
+    /// Checks if the `compactToRelative` flag is set.
+    pub(crate) fn is_compact_to_relative(&self) -> bool {
+        self.json_ld.is_compact_to_relative()
     }
 
     /// Checks if the given string is a keyword.
@@ -87,13 +215,82 @@ impl ProcessorOptions {
         }
     }
 
+    /// Returns the additional HTTP request headers to load remote documents.
+    pub(crate) fn request_headers(&self) -> &[(String, String)] {
+        self.json_ld.get_request_headers()
+    }
+
+    /// Returns the maximum size of loaded remote documents in bytes.
+    pub(crate) fn max_document_size(&self) -> Option<u64> {
+        self.json_ld.get_max_document_size()
+    }
+
+    /// Returns the maximum number of JSON values in a loaded remote document.
+    pub(crate) fn max_document_nodes(&self) -> Option<usize> {
+        self.json_ld.get_max_document_nodes()
+    }
+
+    /// Returns the timeout of loading a remote document.
+    pub(crate) fn load_timeout(&self) -> Option<Duration> {
+        let timeout = self
+            .json_ld
+            .get_load_timeout()
+            .or(self.default_load_timeout);
+        match self.json_ld.get_deadline() {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                Some(timeout.map_or(remaining, |timeout| timeout.min(remaining)))
+            }
+            None => timeout,
+        }
+    }
+
+    /// Returns an error if the operation is cancelled or the deadline has passed.
+    pub(crate) fn check_cancelled(&self) -> Result<()> {
+        if self
+            .json_ld
+            .get_cancellation_token()
+            .map_or(false, CancellationToken::is_cancelled)
+        {
+            return Err(ErrorCode::Cancelled.and_message("The operation is cancelled"));
+        }
+        if let Some(deadline) = self.json_ld.get_deadline() {
+            if Instant::now() >= deadline {
+                return Err(ErrorCode::Cancelled.and_message("The deadline has passed"));
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the limit of number of remote contexts.
     ///
     /// If `Some(n)` is returned, `n` remote contexts is allowed, and one more remote context will
     /// be rejected.
     /// `None` means there are no limits.
+    ///
+    /// The limit given by the JSON-LD API options is used if available.
     pub(crate) fn allowed_max_remote_context(&self) -> Option<usize> {
-        unimplemented!()
+        /// Default limit of number of remote contexts.
+        const DEFAULT_MAX_REMOTE_CONTEXTS: usize = 32;
+        Some(
+            self.json_ld
+                .get_max_remote_contexts()
+                .unwrap_or(DEFAULT_MAX_REMOTE_CONTEXTS),
+        )
+    }
+
+    /// Returns the maximum nesting depth of arrays and maps in the input document.
+    ///
+    /// The limit given by the JSON-LD API options is used if available.
+    pub(crate) fn max_nesting_depth(&self) -> usize {
+        /// Default limit of nesting depth, same as the recursion limit of `serde_json` parser.
+        ///
+        /// Expansion uses about 10 KiB of stack per nesting level in release builds, so this fits
+        /// in the 2 MiB stack of threads spawned by `std::thread`.
+        const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+        self.json_ld
+            .get_max_nesting_depth()
+            .unwrap_or(DEFAULT_MAX_NESTING_DEPTH)
     }
 
     /// Creates a processor from the option and the given loader.
@@ -101,6 +298,12 @@ impl ProcessorOptions {
         Processor {
             options: self,
             loader,
+            warning_handler: None,
+            dropped_terms: None,
+            remote_contexts: Arc::new(Mutex::new(LruMap::new(MAX_CACHED_REMOTE_CONTEXTS))),
+            processed_contexts: Arc::new(Mutex::new(LruMap::new(MAX_PROCESSED_CONTEXT_KEYS))),
+            interner: Default::default(),
+            memory_budget: None,
         }
     }
 }
@@ -114,6 +317,33 @@ pub struct Processor<L> {
     options: ProcessorOptions,
     /// Remote context loader.
     loader: L,
+    /// Warning handler.
+    warning_handler: Option<Arc<dyn WarningHandler>>,
+    /// Collector of keys dropped during expansion (if requested).
+    dropped_terms: Option<Arc<DroppedTermCollector>>,
+    /// Remote contexts dereferenced so far.
+    ///
+    /// This is shared among operations (and processors created by `with_options()`), so that the
+    /// same context is not dereferenced again.
+    remote_contexts: Arc<Mutex<RemoteContextCache>>,
+    /// Remote contexts processed so far.
+    processed_contexts: Arc<Mutex<ProcessedContextCache>>,
+    /// Symbol table of IRIs and terms in term definitions.
+    interner: Arc<Interner>,
+    /// Memory budget of the current operation.
+    memory_budget: Option<Arc<MemoryBudget>>,
+}
+
+impl<L> Processor<L> {
+    /// Sets the warning handler.
+    ///
+    /// Warnings are discarded if no handler is set.
+    pub fn warning_handler(self, handler: impl WarningHandler + 'static) -> Self {
+        Self {
+            warning_handler: Some(Arc::new(handler)),
+            ..self
+        }
+    }
 }
 
 impl<L: LoadRemoteDocument> Processor<L> {
@@ -126,6 +356,582 @@ impl<L: LoadRemoteDocument> Processor<L> {
     pub fn loader(&self) -> &L {
         &self.loader
     }
+
+    /// Clears the remote contexts dereferenced and cached by the processor.
+    ///
+    /// Remote contexts (and the results of processing them) are cached for the lifetime of the
+    /// processor (up to 256 contexts, evicting the least recently used one), so call this (or
+    /// create a new processor) when they may have been updated.
+    /// IRIs interned while processing contexts are also discarded.
+    pub fn clear_remote_contexts(&self) {
+        self.lock_remote_contexts().clear();
+        self.lock_processed_contexts().clear();
+        self.interner.clear();
+    }
+
+    /// Loads the given remote contexts concurrently ahead of time.
+    ///
+    /// This warms up the cache of the loader (such as `CachingLoader`), so that the first
+    /// processing using the contexts does not pay the fetch latency.
+    /// All contexts are loaded even if some of them fail, and the first error is returned.
+    pub async fn prefetch_contexts(&self, iris: &[&IriStr]) -> Result<()> {
+        let load_opts = self.context_load_options();
+        let loads = iris.iter().map(|&iri| {
+            let load_opts = load_opts.clone();
+            async move {
+                let doc = self
+                    .cancellable(instrument!(
+                        self.loader.load(iri, load_opts.clone()),
+                        "prefetch_context",
+                        iri = %iri
+                    ))
+                    .await?
+                    .map_err(|e| {
+                        ErrorCode::LoadingRemoteContextFailed.and_load_error(iri.as_str(), e)
+                    })?;
+                self.check_document_type(iri.as_str(), &doc, &load_opts)
+                    .map_err(|e| ErrorCode::LoadingRemoteContextFailed.and_cause(e))?;
+                self.check_document_nodes(iri.as_str(), &doc)?;
+                self.cache_remote_context(iri.to_owned(), load_opts, doc);
+                Ok(())
+            }
+        });
+        join_all(loads).await.into_iter().collect()
+    }
+
+    /// Processes the given context in advance, so that it can be reused for many documents.
+    ///
+    /// The context may be a value of `@context` entry, or a map containing `@context` entry.
+    /// Relative IRIs in the context are resolved against the base IRI of the options (or the
+    /// processor), and the base IRI of the result is fixed to it unless the context has `@base`.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#context-processing-algorithm>.
+    pub async fn compile_context(
+        &self,
+        context: &Value,
+        options: &JsonLdOptions,
+    ) -> Result<CompiledContext> {
+        let processor = self.with_options(options);
+        let context = match context {
+            Value::Object(map) if map.contains_key("@context") => &map["@context"],
+            v => v,
+        };
+        let base = processor.options().document_iri();
+        let active_context = Context::with_base(base.to_owned())
+            .join_context_value(&processor, context, base, false)
+            .await?;
+        Ok(CompiledContext::new(active_context, context.clone()))
+    }
+
+    /// Expands the given JSON-LD document using the precompiled context as the initial context.
+    ///
+    /// `expandContext` option is ignored, as the compiled context replaces it.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    pub async fn expand_with_context(
+        &self,
+        input: &Value,
+        context: &CompiledContext,
+        options: &JsonLdOptions,
+    ) -> Result<Value> {
+        let expand_options = ExpandOptions::new().frame_expansion(options.is_frame_expansion());
+        expand_with_active_context(
+            &self.with_options(options),
+            input,
+            context.context(),
+            expand_options,
+        )
+        .await
+    }
+
+    /// Expands the given JSON-LD document.
+    ///
+    /// If `frameExpansion` flag is set, the document is expanded as a frame: frame-specific
+    /// values such as `{}`, `[]`, `@default`, and value patterns are preserved.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    pub async fn expand(&self, input: &Value, options: &JsonLdOptions) -> Result<Value> {
+        let expand_options = ExpandOptions::new().frame_expansion(options.is_frame_expansion());
+        expand_with_options(&self.with_options(options), input, expand_options).await
+    }
+
+    /// Expands the given remote document.
+    ///
+    /// The document URL of the remote document is used as the base IRI unless the base IRI is
+    /// given by the options, and the context URL given by the HTTP `Link` header is applied.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    pub async fn expand_remote(
+        &self,
+        input: &RemoteDocument,
+        options: &JsonLdOptions,
+    ) -> Result<Value> {
+        let mut processor = self.with_options(options);
+        if options.get_base().is_none() {
+            let document_url = input.get_document_url();
+            processor.options.document_iri = IriStr::new(document_url)
+                .map_err(|e| ErrorCode::LoadingDocumentFailed.and_iri_error(document_url, e))?
+                .to_owned();
+        }
+        let expand_options = ExpandOptions::new().frame_expansion(options.is_frame_expansion());
+        expand_with_context_url(
+            &processor,
+            input.document(),
+            input.get_context_url(),
+            expand_options,
+        )
+        .await
+    }
+
+    /// Loads the document from the given IRI by the loader, and expands it.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    pub async fn expand_iri(&self, input: &IriStr, options: &JsonLdOptions) -> Result<Value> {
+        let processor = self.with_options(options);
+        let mut load_opts = LoadDocumentOptions::new();
+        load_opts.set_extract_all_scripts(options.is_extract_all_scripts());
+        load_opts.set_max_size(processor.options().max_document_size());
+        load_opts.set_timeout(processor.options().load_timeout());
+        load_opts.set_headers(processor.options().request_headers().to_vec());
+        let document = processor
+            .cancellable(self.loader.load(input, load_opts.clone()))
+            .await?
+            .map_err(|e| ErrorCode::LoadingDocumentFailed.and_load_error(input.as_str(), e))?;
+        processor.check_document_type(input.as_str(), &document, &load_opts)?;
+        processor.check_document_nodes(input.as_str(), &document)?;
+        self.expand_remote(&document, options).await
+    }
+
+    /// Expands the given JSON-LD document, and reports the keys dropped during the expansion.
+    ///
+    /// Keys which do not expand to absolute IRIs or keywords are silently dropped by the
+    /// expansion algorithm. This returns them along with the expanded document.
+    pub async fn expand_with_report(
+        &self,
+        input: &Value,
+        options: &JsonLdOptions,
+    ) -> Result<(Value, Vec<DroppedTerm>)> {
+        let collector = Arc::new(DroppedTermCollector::new());
+        let processor = Processor {
+            dropped_terms: Some(collector.clone()),
+            ..self.with_options(options)
+        };
+        let expand_options = ExpandOptions::new().frame_expansion(options.is_frame_expansion());
+        let expanded = expand_with_options(&processor, input, expand_options).await?;
+        Ok((expanded, collector.report(input)))
+    }
+
+    /// Expands the given JSON-LD document, and passes the expanded document to the handler as
+    /// events instead of returning it.
+    ///
+    /// Items of the top-level array (or of the `@graph` entry of the top-level map with no other
+    /// entries than `@context`) are expanded one at a time, and memory for the whole expanded
+    /// document is not required. Each item is expanded as a whole before its events are passed.
+    /// `frameExpansion` flag is not supported and ignored.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    pub async fn expand_events(
+        &self,
+        input: &Value,
+        options: &JsonLdOptions,
+        mut handler: impl FnMut(ExpansionEvent<'_>),
+    ) -> Result<()> {
+        expand_events(&self.with_options(options), input, &mut handler).await
+    }
+
+    /// Expands the given JSON-LD document, and writes the expanded document as JSON.
+    ///
+    /// Items of the top-level array (or of the `@graph` entry of the top-level map with no other
+    /// entries than `@context`) are expanded and written one at a time, and memory for the whole
+    /// expanded document is not required. Each item is expanded as a whole before it is written.
+    /// `frameExpansion` flag is not supported and ignored.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    pub async fn expand_to_writer(
+        &self,
+        input: &Value,
+        options: &JsonLdOptions,
+        mut writer: impl io::Write,
+    ) -> Result<()> {
+        expand_to_writer(&self.with_options(options), input, &mut writer).await
+    }
+
+    /// Expands the given JSON-LD document, and writes the expanded document as JSON into the
+    /// async writer.
+    ///
+    /// This is same as [`expand_to_writer()`](#method.expand_to_writer), but the output is
+    /// written as it is produced without blocking the task on the writer.
+    /// The writer is flushed after the whole document is written.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    #[cfg(feature = "futures-io")]
+    pub async fn expand_to_async_writer(
+        &self,
+        input: &Value,
+        options: &JsonLdOptions,
+        writer: impl futures_io::AsyncWrite + Unpin,
+    ) -> Result<()> {
+        let processor = self.with_options(options);
+        let buf = Mutex::new(Vec::new());
+        let mut buffer = SharedBuffer(&buf);
+        let expansion = expand_to_writer(&processor, input, &mut buffer);
+        WriteThrough::new(expansion, &buf, writer).await
+    }
+
+    /// Expands the JSON-LD document read from the reader, and passes the expanded document to
+    /// the handler as events.
+    ///
+    /// If the document is an array, the source text of each element is split from the input as
+    /// it is read, and the element is parsed and expanded before the next one, so memory for the
+    /// whole document is not required.
+    /// Other documents (including a map with `@graph` entry) are read and parsed as a whole
+    /// before expansion starts, although the items of the `@graph` entry are still expanded and
+    /// passed to the handler one at a time.
+    /// In either case each item is parsed and expanded as a whole before its events are emitted.
+    /// `frameExpansion` flag is not supported and ignored.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    #[cfg(feature = "futures-io")]
+    pub async fn expand_from_reader(
+        &self,
+        reader: impl futures_io::AsyncRead + Unpin,
+        options: &JsonLdOptions,
+        mut handler: impl FnMut(ExpansionEvent<'_>),
+    ) -> Result<()> {
+        expand_reader_events(&self.with_options(options), reader, &mut handler).await
+    }
+
+    /// Expands the given JSON-LD document in the JSON source text.
+    ///
+    /// This is same as [`expand()`](#method.expand), but errors are located in the source text,
+    /// so that the line and the column are available by `Error::position()`.
+    pub async fn expand_str(&self, input: &str, options: &JsonLdOptions) -> Result<Value> {
+        let value: Value = serde_json::from_str(input).map_err(|e| {
+            ErrorCode::LoadingDocumentFailed.and_message(format!("Invalid JSON: {}", e))
+        })?;
+        self.expand(&value, options)
+            .await
+            .map_err(|e| e.locate(input))
+    }
+
+    /// Expands the given JSON-LD document in the JSON source bytes, parsing it with simd-json.
+    ///
+    /// simd-json uses the given buffer as a scratch space, so its content is unspecified after
+    /// the call.
+    /// Unlike [`expand_str()`](#method.expand_str), errors are not located in the source text.
+    #[cfg(feature = "simd-json")]
+    pub async fn expand_slice(&self, input: &mut [u8], options: &JsonLdOptions) -> Result<Value> {
+        let value: Value = simd_json::serde::from_slice(input).map_err(|e| {
+            ErrorCode::LoadingDocumentFailed.and_message(format!("Invalid JSON: {}", e))
+        })?;
+        self.expand(&value, options).await
+    }
+
+    /// Expands the given JSON-LD documents, and returns the results in order.
+    ///
+    /// Up to `concurrency` documents are expanded concurrently (at least one at a time), and the
+    /// next document is started as soon as any document in flight completes.
+    /// The documents share the caches of remote and processed contexts and the interned
+    /// strings, so contexts common to the documents are processed only once.
+    /// The memory budget in the options applies to each document separately.
+    /// A failure on a document does not stop processing of the other documents.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    pub async fn expand_all<D: Borrow<Value>>(
+        &self,
+        documents: impl IntoIterator<Item = D>,
+        options: &JsonLdOptions,
+        concurrency: usize,
+    ) -> Vec<Result<Value>> {
+        let expand_options = ExpandOptions::new().frame_expansion(options.is_frame_expansion());
+        let expansions = documents.into_iter().map(move |doc| {
+            async move {
+                // Each document has its own memory budget.
+                let processor = self.with_options(options);
+                let expanded = expand_with_options(&processor, doc.borrow(), expand_options).await;
+                Ok::<_, Infallible>(expanded)
+            }
+        });
+        match try_join_buffered(expansions, concurrency).await {
+            Ok(results) => results,
+            Err((_, never)) => match never {},
+        }
+    }
+
+    /// Expands each document of the newline-delimited JSON-LD text read from the reader.
+    ///
+    /// Each non-empty line is processed as a JSON-LD document, and the results are returned one
+    /// by one with the line number (starting from 1) as the lines are read, so the input can be
+    /// an unbounded stream such as a log pipeline.
+    /// A failure on a document does not stop processing of the following lines.
+    /// Remote contexts loaded for a document are cached and reused for the following documents.
+    ///
+    /// See [`blocking::expand_ndjson`](blocking/fn.expand_ndjson.html) for `std::io::BufRead`
+    /// readers.
+    #[cfg(feature = "futures-io")]
+    pub fn expand_ndjson<'a, R: futures_io::AsyncBufRead + Unpin>(
+        &'a self,
+        reader: R,
+        options: &'a JsonLdOptions,
+    ) -> NdjsonExpansion<'a, L, R> {
+        NdjsonExpansion::new(self, reader, options)
+    }
+
+    /// Compacts the given JSON-LD document using the given context.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+    pub async fn compact(
+        &self,
+        input: &Value,
+        context: &Value,
+        options: &JsonLdOptions,
+    ) -> Result<Value> {
+        compact(&self.with_options(options), input, context).await
+    }
+
+    /// Expands the given JSON-LD document in another JSON value representation, converting it by
+    /// `JsonAdapter`.
+    ///
+    /// This is a conversion wrapper of [`expand()`](#method.expand): the expansion algorithm is
+    /// not generic over `JsonAdapter`. The document is copied into `serde_json::Value` and
+    /// processed as it, and the result is converted back to the representation of the input.
+    /// The copy of the input is dropped before the result is converted.
+    /// Use `expand()` if the document is already a `serde_json::Value`.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-expand>.
+    // The input is converted before the future is created, so that the future does not borrow
+    // the input and is `Send` even if `J` is not `Sync`.
+    pub fn expand_json<'a, J: JsonAdapter + 'a>(
+        &'a self,
+        input: &J,
+        options: &'a JsonLdOptions,
+    ) -> impl Future<Output = Result<J>> + 'a {
+        let input = to_serde_json(input);
+        async move {
+            let expanded = self.expand(&input, options).await;
+            drop(input);
+            expanded.map(from_serde_json)
+        }
+    }
+
+    /// Compacts the given JSON-LD document in another JSON value representation, converting it by
+    /// `JsonAdapter`.
+    ///
+    /// This is a conversion wrapper of [`compact()`](#method.compact): the compaction algorithm
+    /// is not generic over `JsonAdapter`. The document and the context are copied into
+    /// `serde_json::Value` and processed as it, and the result is converted back to the
+    /// representation of the input. The copies of the inputs are dropped before the result is
+    /// converted.
+    /// Use `compact()` if the document is already a `serde_json::Value`.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+    pub fn compact_json<'a, J: JsonAdapter + 'a>(
+        &'a self,
+        input: &J,
+        context: &J,
+        options: &'a JsonLdOptions,
+    ) -> impl Future<Output = Result<J>> + 'a {
+        let input = to_serde_json(input);
+        let context = to_serde_json(context);
+        async move {
+            let compacted = self.compact(&input, &context, options).await;
+            drop((input, context));
+            compacted.map(from_serde_json)
+        }
+    }
+
+    /// Compacts the given JSON-LD document using the given context, and writes the result as
+    /// JSON.
+    ///
+    /// Top-level nodes are compacted and written one at a time, and memory for the whole
+    /// compacted document is not required. The document is expanded as a whole before the
+    /// compaction starts.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+    pub async fn compact_to_writer(
+        &self,
+        input: &Value,
+        context: &Value,
+        options: &JsonLdOptions,
+        mut writer: impl io::Write,
+    ) -> Result<()> {
+        compact_to_writer(&self.with_options(options), input, context, &mut writer).await
+    }
+
+    /// Compacts the given JSON-LD document using the given context, and writes the result as
+    /// JSON into the async writer.
+    ///
+    /// This is same as [`compact_to_writer()`](#method.compact_to_writer), but the output is
+    /// written as it is produced without blocking the task on the writer.
+    /// The writer is flushed after the whole document is written.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+    #[cfg(feature = "futures-io")]
+    pub async fn compact_to_async_writer(
+        &self,
+        input: &Value,
+        context: &Value,
+        options: &JsonLdOptions,
+        writer: impl futures_io::AsyncWrite + Unpin,
+    ) -> Result<()> {
+        let processor = self.with_options(options);
+        let buf = Mutex::new(Vec::new());
+        let mut buffer = SharedBuffer(&buf);
+        let compaction = compact_to_writer(&processor, input, context, &mut buffer);
+        WriteThrough::new(compaction, &buf, writer).await
+    }
+
+    /// Compacts the given JSON-LD document using the precompiled context.
+    ///
+    /// The local context the compiled context is created from is used as the `@context` entry of
+    /// the result.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-compact>.
+    pub async fn compact_with_context(
+        &self,
+        input: &Value,
+        context: &CompiledContext,
+        options: &JsonLdOptions,
+    ) -> Result<Value> {
+        let processor = self.with_options(options);
+        let expanded_input = expand_with_options(&processor, input, ExpandOptions::new()).await?;
+        compact_with_active_context(
+            &processor,
+            &expanded_input,
+            context.context(),
+            context.local_context(),
+            false,
+        )
+        .await
+    }
+
+    /// Flattens the given JSON-LD document, and compacts it if the context is given.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-flatten>.
+    pub async fn flatten(
+        &self,
+        input: &Value,
+        context: Option<&Value>,
+        options: &JsonLdOptions,
+    ) -> Result<Value> {
+        flatten(&self.with_options(options), input, context).await
+    }
+
+    /// Frames the given JSON-LD document using the given frame.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#dom-jsonldprocessor-frame>.
+    pub async fn frame(
+        &self,
+        input: &Value,
+        frame: &Value,
+        options: &JsonLdOptions,
+    ) -> Result<Value> {
+        crate::frame::frame(&self.with_options(options), input, frame).await
+    }
+
+    /// Generates the node map of the given JSON-LD document.
+    ///
+    /// The result maps graph names (`@default` for the default graph) to node objects indexed
+    /// by their identifiers.
+    /// Blank nodes are relabeled to `_:b0`, `_:b1`, ... as in flattening.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#node-map-generation>.
+    pub async fn generate_node_map(
+        &self,
+        input: &Value,
+        options: &JsonLdOptions,
+    ) -> Result<NodeMap> {
+        node_map(&self.with_options(options), input).await
+    }
+
+    /// Deserializes the given JSON-LD document into an RDF dataset.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-tordf>.
+    pub async fn to_rdf(&self, input: &Value, options: &JsonLdOptions) -> Result<Vec<Quad>> {
+        let to_rdf_options = ToRdfOptions::new()
+            .produce_generalized_rdf(options.is_produce_generalized_rdf())
+            .rdf_direction(options.get_rdf_direction())
+            .safe(options.is_safe());
+        to_rdf(&self.with_options(options), input, to_rdf_options).await
+    }
+
+    /// Deserializes the given JSON-LD document into RDF, and passes each quad to the handler
+    /// instead of collecting the dataset.
+    ///
+    /// The node map of the document is still created in memory.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-tordf>.
+    pub async fn to_rdf_with_handler(
+        &self,
+        input: &Value,
+        options: &JsonLdOptions,
+        mut handler: impl FnMut(Quad),
+    ) -> Result<()> {
+        let to_rdf_options = ToRdfOptions::new()
+            .produce_generalized_rdf(options.is_produce_generalized_rdf())
+            .rdf_direction(options.get_rdf_direction())
+            .safe(options.is_safe());
+        to_rdf_with_handler(
+            &self.with_options(options),
+            input,
+            to_rdf_options,
+            &mut handler,
+        )
+        .await
+    }
+
+    /// Serializes the given RDF dataset as an expanded JSON-LD document.
+    ///
+    /// The dataset can be any iterator of quads (or references to them), so it need not be
+    /// collected into a slice beforehand.
+    /// Note that the conversion itself is not incremental: the whole graph map is built in memory
+    /// before the document is returned.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-fromrdf>.
+    pub fn from_rdf<Q: Borrow<Quad>>(
+        &self,
+        dataset: impl IntoIterator<Item = Q>,
+        options: &JsonLdOptions,
+    ) -> Result<Value> {
+        let from_rdf_options = FromRdfOptions::new()
+            .use_native_types(options.is_use_native_types())
+            .use_rdf_type(options.is_use_rdf_type())
+            .rdf_direction(options.get_rdf_direction());
+        from_rdf(&self.with_options(options), dataset, from_rdf_options)
+    }
+
+    /// Returns the processor for an operation with the given options.
+    pub(crate) fn with_options(&self, options: &JsonLdOptions) -> Processor<&L> {
+        Processor {
+            options: self.options.clone().json_ld_options(options.clone()),
+            loader: &self.loader,
+            warning_handler: self.warning_handler.clone(),
+            dropped_terms: None,
+            remote_contexts: self.remote_contexts.clone(),
+            processed_contexts: self.processed_contexts.clone(),
+            interner: self.interner.clone(),
+            memory_budget: options
+                .get_memory_budget()
+                .map(|limit| Arc::new(MemoryBudget::new(limit))),
+        }
+    }
+
+    /// Returns the processor for the same operation with the given loader.
+    ///
+    /// The caches, the warning handler, and the memory budget are shared with this processor.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn with_loader<M>(&self, loader: M) -> Processor<M> {
+        Processor {
+            options: self.options.clone(),
+            loader,
+            warning_handler: self.warning_handler.clone(),
+            dropped_terms: self.dropped_terms.clone(),
+            remote_contexts: self.remote_contexts.clone(),
+            processed_contexts: self.processed_contexts.clone(),
+            interner: self.interner.clone(),
+            memory_budget: self.memory_budget.clone(),
+        }
+    }
 }
 
 impl<L: LoadRemoteDocument> Processor<L> {
@@ -134,6 +940,88 @@ impl<L: LoadRemoteDocument> Processor<L> {
         self.options().is_processing_mode_1_0()
     }
 
+    /// Checks if the items of the top-level array are expanded on worker threads.
+    ///
+    /// Dropped keys are located by the addresses of the maps in the input, so the items are not
+    /// copied for the workers when they are reported.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn is_parallel_expansion(&self) -> bool {
+        self.options().expansion_workers() > 1 && self.dropped_terms.is_none()
+    }
+
+    /// Returns the shared string equal to the given string.
+    pub(crate) fn intern(&self, s: &str) -> Arc<str> {
+        self.interner.intern(s)
+    }
+
+    /// Returns the shared string equal to the concatenation of the given strings.
+    pub(crate) fn intern_concat(&self, prefix: &str, suffix: &str) -> Arc<str> {
+        self.interner.intern_concat(prefix, suffix)
+    }
+
+    /// Returns the shared string equal to the given symbol.
+    ///
+    /// Symbols already interned are returned as is.
+    pub(crate) fn intern_symbol(&self, s: Symbol<'_>) -> Arc<str> {
+        match s {
+            Symbol::Interned(s) => s,
+            s => self.intern(&s),
+        }
+    }
+
+    /// Checks if the `ordered` flag is set.
+    pub(crate) fn is_ordered(&self) -> bool {
+        self.options().is_ordered()
+    }
+
+    /// Runs the given future, aborting it if the operation is cancelled.
+    ///
+    /// This is used for the futures which can be pending for a long time, such as loading remote
+    /// documents.
+    pub(crate) async fn cancellable<F: Future>(&self, future: F) -> Result<F::Output> {
+        self.options().check_cancelled()?;
+        let output = match self.options().json_ld.get_cancellation_token() {
+            Some(token) => Cancellable::new(future, token).await,
+            None => Some(future.await),
+        };
+        output.ok_or_else(|| ErrorCode::Cancelled.and_message("The operation is cancelled"))
+    }
+
+    /// Returns the memory budget of the current operation.
+    pub(crate) fn memory_budget(&self) -> Option<&MemoryBudget> {
+        self.memory_budget.as_ref().map(|budget| &**budget)
+    }
+
+    /// Charges the size of the newly created value to the memory budget.
+    pub(crate) fn charge_memory(&self, v: &Value) -> Result<()> {
+        match self.memory_budget() {
+            Some(budget) => budget.charge_value(v),
+            None => Ok(()),
+        }
+    }
+
+    /// Releases the size of the value no longer used from the memory budget.
+    pub(crate) fn release_memory(&self, v: &Value) {
+        if let Some(budget) = self.memory_budget() {
+            budget.release_value(v);
+        }
+    }
+
+    /// Checks if the `safe` flag is set.
+    pub(crate) fn is_safe(&self) -> bool {
+        self.options().is_safe()
+    }
+
+    /// Checks if the `compactArrays` flag is set.
+    pub(crate) fn is_compact_arrays(&self) -> bool {
+        self.options().is_compact_arrays()
+    }
+
+    /// Checks if the `compactToRelative` flag is set.
+    pub(crate) fn is_compact_to_relative(&self) -> bool {
+        self.options().is_compact_to_relative()
+    }
+
     /// Checks if the given string is a keyword.
     ///
     /// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#syntax-tokens-and-keywords>.
@@ -149,6 +1037,151 @@ impl<L: LoadRemoteDocument> Processor<L> {
         self.options().base(context)
     }
 
+    /// Records the key dropped during expansion, if the report is requested.
+    pub(crate) fn report_dropped_term(
+        &self,
+        map: &JsonMap<String, Value>,
+        key: &str,
+        active_context: &Context,
+    ) {
+        if let Some(collector) = &self.dropped_terms {
+            collector.record(map, key, active_context);
+        }
+    }
+
+    /// Issues the warning to the warning handler.
+    pub(crate) fn warn(&self, warning: Warning) {
+        if let Some(handler) = &self.warning_handler {
+            handler.handle(warning);
+        }
+    }
+
+    /// Returns the options to load a remote context.
+    pub(crate) fn context_load_options(&self) -> LoadDocumentOptions {
+        let mut load_opts = LoadDocumentOptions::new();
+        load_opts.set_profile(Profile::Context);
+        load_opts.set_request_profile(Profile::Context);
+        load_opts.set_max_size(self.options().max_document_size());
+        load_opts.set_timeout(self.options().load_timeout());
+        load_opts.set_headers(self.options().request_headers().to_vec());
+        load_opts
+    }
+
+    /// Returns the cached remote context.
+    pub(crate) fn cached_remote_context(
+        &self,
+        iri: &IriStr,
+        options: &LoadDocumentOptions,
+    ) -> Option<Arc<RemoteDocument>> {
+        self.lock_remote_contexts()
+            .get(&(iri.to_owned(), options.clone()))
+            .cloned()
+    }
+
+    /// Caches the remote context.
+    pub(crate) fn cache_remote_context(
+        &self,
+        iri: IriString,
+        options: LoadDocumentOptions,
+        document: Arc<RemoteDocument>,
+    ) {
+        self.lock_remote_contexts().insert((iri, options), document);
+    }
+
+    /// Returns the cached result of processing the remote context against the active context.
+    pub(crate) fn cached_processed_context(
+        &self,
+        key: &ProcessedContextKey,
+        active_context: &Context,
+    ) -> Option<Context> {
+        self.lock_processed_contexts()
+            .get(key)?
+            .iter()
+            .find(|(input, _)| input == active_context)
+            .map(|(_, output)| output.clone())
+    }
+
+    /// Caches the result of processing the remote context against the active context.
+    pub(crate) fn cache_processed_context(
+        &self,
+        key: ProcessedContextKey,
+        active_context: Context,
+        processed: Context,
+    ) {
+        let mut cache = self.lock_processed_contexts();
+        let entries = cache.get_or_insert_with(key, Vec::new);
+        if entries.len() >= MAX_PROCESSED_CONTEXTS_PER_KEY {
+            entries.remove(0);
+        }
+        entries.push((active_context, processed));
+    }
+
+    /// Locks the processed context cache.
+    fn lock_processed_contexts(&self) -> MutexGuard<'_, ProcessedContextCache> {
+        self.processed_contexts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Locks the remote context cache.
+    fn lock_remote_contexts(&self) -> MutexGuard<'_, RemoteContextCache> {
+        self.remote_contexts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Checks if the loaded remote document has a JSON media type and the requested profile.
+    ///
+    /// The `profile` parameter is checked only for `application/ld+json` documents with the
+    /// parameter, since servers are not required to honor the requested profile.
+    ///
+    /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#loaddocumentcallback>.
+    pub(crate) fn check_document_type(
+        &self,
+        iri: &str,
+        document: &RemoteDocument,
+        options: &LoadDocumentOptions,
+    ) -> Result<()> {
+        // > Otherwise, if the retrieved resource's Content-Type is not `application/json`,
+        // > `application/ld+json` nor any other media type using a `+json` suffix, reject the
+        // > promise passing a `loading document failed` error.
+        if !document.has_json_content_type() {
+            return Err(ErrorCode::LoadingDocumentFailed.and_message(format!(
+                "The document <{}> has non-JSON media type {:?}",
+                iri,
+                document.get_content_type().unwrap_or_default()
+            )));
+        }
+        let request_profile = options.request_profile();
+        let has_profile_param = document.get_content_type() == Some("application/ld+json")
+            && document.get_profile().is_some();
+        if has_profile_param
+            && !request_profile.is_empty()
+            && !request_profile.iter().any(|p| document.has_profile(p))
+        {
+            return Err(ErrorCode::LoadingDocumentFailed.and_message(format!(
+                "The document <{}> has profile {:?}, but {:?} is requested",
+                iri,
+                document.get_profile().unwrap_or_default(),
+                request_profile
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks if the loaded remote document exceeds the node count limit.
+    pub(crate) fn check_document_nodes(&self, iri: &str, document: &RemoteDocument) -> Result<()> {
+        match self.options().max_document_nodes() {
+            Some(max) if exceeds_node_count(document.document(), max) => {
+                Err(ErrorCode::DocumentTooLarge.and_message(format!(
+                    "The document <{}> has more than {} JSON values",
+                    iri, max
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Checks if the number of context exceeds the processor limit.
     pub(crate) fn is_remote_context_limit_exceeded(&self, num_ctx: usize) -> bool {
         match self.options().allowed_max_remote_context() {
@@ -157,3 +1190,623 @@ impl<L: LoadRemoteDocument> Processor<L> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        pin::Pin,
+        sync::atomic::{AtomicUsize, Ordering},
+        task::{Context as TaskContext, Poll},
+    };
+
+    use async_trait::async_trait;
+    use futures_executor::block_on;
+
+    use crate::remote::StaticLoader;
+
+    /// Loader counting the loads.
+    #[derive(Debug)]
+    struct CountingLoader {
+        /// Inner loader.
+        loader: StaticLoader,
+        /// Number of loads.
+        count: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LoadRemoteDocument for CountingLoader {
+        type Error = <StaticLoader as LoadRemoteDocument>::Error;
+
+        async fn load(
+            &self,
+            iri: &IriStr,
+            options: LoadDocumentOptions,
+        ) -> std::result::Result<Arc<RemoteDocument>, Self::Error> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            self.loader.load(iri, options).await
+        }
+    }
+
+    /// Loader which never completes.
+    #[derive(Debug)]
+    struct PendingLoader;
+
+    #[async_trait]
+    impl LoadRemoteDocument for PendingLoader {
+        type Error = <StaticLoader as LoadRemoteDocument>::Error;
+
+        async fn load(
+            &self,
+            _iri: &IriStr,
+            _options: LoadDocumentOptions,
+        ) -> std::result::Result<Arc<RemoteDocument>, Self::Error> {
+            /// Future which never completes.
+            struct Never;
+
+            impl Future for Never {
+                type Output = ();
+
+                fn poll(self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<Self::Output> {
+                    Poll::Pending
+                }
+            }
+
+            Never.await;
+            unreachable!("the future never completes")
+        }
+    }
+
+    /// Asserts the value is `Send`.
+    fn assert_send<T: Send>(_: T) {}
+
+    /// Futures should be `Send` so that they can be spawned on multi-threaded executors.
+    #[test]
+    fn futures_are_send() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let options = JsonLdOptions::new();
+        let input = Value::Null;
+        assert_send(processor.expand(&input, &options));
+        assert_send(processor.compact(&input, &input, &options));
+        assert_send(processor.flatten(&input, None, &options));
+        assert_send(processor.frame(&input, &input, &options));
+        assert_send(processor.to_rdf(&input, &options));
+        assert_send(processor.expand_json(&input, &options));
+        assert_send(processor.compact_json(&input, &input, &options));
+    }
+
+    /// Pending loads should be aborted when the operation is cancelled.
+    #[test]
+    fn cancel_pending_load() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(PendingLoader);
+        let token = CancellationToken::new();
+        let options = JsonLdOptions::new().cancellation_token(token.clone());
+        let input = serde_json::json!({
+            "@context": "http://example.com/context.jsonld",
+            "name": "Alice",
+        });
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+        let err = block_on(processor.expand(&input, &options))
+            .expect_err("cancelled operation should fail");
+        assert_eq!(err.code(), ErrorCode::Cancelled);
+        handle.join().expect("the thread should not panic");
+    }
+
+    /// The remote context cache should hit even if the load timeout is derived from a deadline.
+    #[test]
+    fn remote_context_cached_under_deadline() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let mut loader = StaticLoader::new();
+        loader
+            .insert_source(
+                "http://example.com/context.jsonld",
+                r#"{ "@context": { "name": "http://schema.org/name" } }"#,
+            )
+            .expect("valid document");
+        let loader = CountingLoader {
+            loader,
+            count: AtomicUsize::new(0),
+        };
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(loader);
+        let options = JsonLdOptions::new().deadline(Instant::now() + Duration::from_secs(60));
+        let input = serde_json::json!({
+            "@context": "http://example.com/context.jsonld",
+            "name": "Alice",
+        });
+        for _ in 0..2 {
+            block_on(processor.expand(&input, &options)).expect("expansion should succeed");
+        }
+        assert_eq!(processor.loader.count.load(Ordering::SeqCst), 1);
+    }
+
+    /// Creates a document with nested maps of the given depth.
+    ///
+    /// The maps are built directly rather than with `json!`, since `json!` clones the
+    /// interpolated value and building would take quadratic time.
+    fn nested_document(depth: usize) -> Value {
+        (1..depth).fold(
+            serde_json::json!({ "@id": "http://example.com/" }),
+            |value, _| {
+                let mut map = serde_json::Map::new();
+                map.insert("http://example.com/p".to_owned(), value);
+                Value::Object(map)
+            },
+        )
+    }
+
+    /// Runs the function on a thread with a stack large enough for the default nesting depth.
+    ///
+    /// Each nesting level uses about 10 KiB of stack in release builds, but several times more
+    /// in debug builds, which would overflow the default stack of test threads.
+    fn with_large_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+        /// Stack size of the thread.
+        const STACK_SIZE: usize = 32 * 1024 * 1024;
+
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(f)
+            .expect("should spawn the thread")
+            .join()
+            .expect("the thread should not panic")
+    }
+
+    #[test]
+    fn max_nesting_depth() {
+        with_large_stack(|| {
+            let base = IriStr::new("http://example.com/").expect("valid IRI");
+            let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+            let options = JsonLdOptions::new();
+            assert!(block_on(processor.expand(&nested_document(128), &options)).is_ok());
+            let err = block_on(processor.expand(&nested_document(129), &options))
+                .expect_err("too deep document should be rejected");
+            assert_eq!(err.code(), ErrorCode::NestingTooDeep);
+        });
+    }
+
+    /// Drops the nested document without recursion, so that dropping it does not overflow the
+    /// stack.
+    fn drop_nested(value: Value) {
+        let mut stack = vec![value];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(items) => stack.extend(items),
+                Value::Object(map) => stack.extend(map.into_iter().map(|(_, v)| v)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Documents nested far beyond the limit should be rejected instead of overflowing the stack.
+    #[test]
+    fn deeply_nested_document() {
+        /// Nesting depth of the documents, which would overflow the stack without the limit.
+        const DEPTH: usize = 100_000;
+
+        with_large_stack(|| {
+            let base = IriStr::new("http://example.com/").expect("valid IRI");
+            let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+            let options = JsonLdOptions::new();
+
+            let maps = nested_document(DEPTH);
+            let err = block_on(processor.expand(&maps, &options))
+                .expect_err("too deep document should be rejected");
+            assert_eq!(err.code(), ErrorCode::NestingTooDeep);
+            drop_nested(maps);
+
+            let arrays = (1..DEPTH).fold(Value::Array(Vec::new()), |value, _| {
+                Value::Array(vec![value])
+            });
+            let err = block_on(processor.expand(&arrays, &options))
+                .expect_err("too deep document should be rejected");
+            assert_eq!(err.code(), ErrorCode::NestingTooDeep);
+            drop_nested(arrays);
+        });
+    }
+
+    /// Long chains of terms depending on each other should not overflow the stack.
+    #[test]
+    fn term_dependency_chain() {
+        /// Number of the terms in the chain, which would overflow the stack with recursion.
+        const LEN: usize = 100_000;
+
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let options = JsonLdOptions::new();
+
+        // `t0` is defined using `t1`, `t1` is defined using `t2`, and so on.
+        let mut context = JsonMap::new();
+        for i in 0..LEN {
+            context.insert(format!("t{}", i), Value::from(format!("t{}:", i + 1)));
+        }
+        context.insert(format!("t{}", LEN), Value::from("http://example.com/"));
+        let input = serde_json::json!({ "@context": context, "t0:name": "Alice" });
+        let expanded =
+            block_on(processor.expand(&input, &options)).expect("expansion should succeed");
+        assert_eq!(
+            expanded,
+            serde_json::json!([{ "http://example.com/name": [{ "@value": "Alice" }] }])
+        );
+
+        // `b` is left undefined, and `a` is created after it without the cycle error.
+        let input = serde_json::json!({
+            "@context": {
+                "@vocab": "http://example.com/",
+                "a": { "@id": "b" },
+                "b": { "@id": "@ignored" },
+            },
+            "a": "Alice",
+        });
+        let expanded =
+            block_on(processor.expand(&input, &options)).expect("expansion should succeed");
+        assert_eq!(
+            expanded,
+            serde_json::json!([{ "http://example.com/b": [{ "@value": "Alice" }] }])
+        );
+
+        let input = serde_json::json!({
+            "@context": { "a": "b:x", "b": "a:x" },
+            "a": "Alice",
+        });
+        let err = block_on(processor.expand(&input, &options))
+            .expect_err("cyclic term definitions should be rejected");
+        assert_eq!(err.code(), ErrorCode::CyclicIriMapping);
+    }
+
+    /// Expanding top-level items concurrently should not change the result or the reported
+    /// error location.
+    #[test]
+    fn load_concurrency() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let mut loader = StaticLoader::new();
+        for i in 0..4 {
+            loader
+                .insert_source(
+                    &format!("http://example.com/context{}.jsonld", i),
+                    &format!(
+                        r#"{{ "@context": {{ "name": "http://example.com/name{}" }} }}"#,
+                        i
+                    ),
+                )
+                .expect("valid document");
+        }
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(loader);
+        let input = Value::Array(
+            (0..4)
+                .map(|i| {
+                    serde_json::json!({
+                        "@context": format!("http://example.com/context{}.jsonld", i),
+                        "name": i,
+                    })
+                })
+                .collect(),
+        );
+        let sequential = block_on(processor.expand(&input, &JsonLdOptions::new()))
+            .expect("expansion should succeed");
+        for concurrency in 0..=5 {
+            let options = JsonLdOptions::new().load_concurrency(concurrency);
+            let concurrent =
+                block_on(processor.expand(&input, &options)).expect("expansion should succeed");
+            assert_eq!(concurrent, sequential);
+        }
+
+        let mut input = input;
+        input.as_array_mut().expect("array")[2] = serde_json::json!({
+            "@context": "http://example.com/missing.jsonld",
+        });
+        let options = JsonLdOptions::new().load_concurrency(3);
+        let err = block_on(processor.expand(&input, &options))
+            .expect_err("missing context should be rejected");
+        assert_eq!(err.code(), ErrorCode::LoadingRemoteContextFailed);
+        assert_eq!(err.path(), Some("/2/@context"));
+    }
+
+    /// Each document of the batch should have its own memory budget.
+    #[test]
+    fn expand_all_memory_budget() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let input = serde_json::json!({
+            "@id": "http://example.com/node",
+            "http://example.com/name": ["Alice", "Bob", "Carol", "Dave"],
+        });
+        // The smallest power of two enough for a document, which is less than two documents need.
+        let budget = (0..)
+            .map(|exp| 1_usize << exp)
+            .find(|&budget| {
+                let options = JsonLdOptions::new().memory_budget(budget);
+                block_on(processor.expand(&input, &options)).is_ok()
+            })
+            .expect("the document should be expanded with some budget");
+        let options = JsonLdOptions::new().memory_budget(budget);
+        let results = block_on(processor.expand_all(vec![&input; 4], &options, 2));
+        assert_eq!(results.len(), 4);
+        for result in results {
+            assert!(result.is_ok());
+        }
+    }
+
+    /// Expanding top-level items on worker threads should not change the result or the reported
+    /// error location.
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn expansion_workers() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let mut loader = StaticLoader::new();
+        for i in 0..4 {
+            loader
+                .insert_source(
+                    &format!("http://example.com/context{}.jsonld", i),
+                    &format!(
+                        r#"{{ "@context": {{ "name": "http://example.com/name{}" }} }}"#,
+                        i
+                    ),
+                )
+                .expect("valid document");
+        }
+        let loader = CountingLoader {
+            loader,
+            count: AtomicUsize::new(0),
+        };
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(loader);
+        let input = Value::Array(
+            (0..16)
+                .map(|i| {
+                    serde_json::json!({
+                        "@context": format!("http://example.com/context{}.jsonld", i % 4),
+                        "name": i,
+                    })
+                })
+                .collect(),
+        );
+        let sequential = block_on(processor.expand(&input, &JsonLdOptions::new()))
+            .expect("expansion should succeed");
+        for workers in 0..=5 {
+            let options = JsonLdOptions::new().expansion_workers(workers);
+            let parallel =
+                block_on(processor.expand(&input, &options)).expect("expansion should succeed");
+            assert_eq!(parallel, sequential);
+        }
+        // Loads from the workers share the cache of the processor.
+        assert_eq!(processor.loader.count.load(Ordering::SeqCst), 4);
+
+        let mut input = input;
+        input.as_array_mut().expect("array")[2] = serde_json::json!({
+            "@context": "http://example.com/missing.jsonld",
+        });
+        let options = JsonLdOptions::new().expansion_workers(3);
+        let err = block_on(processor.expand(&input, &options))
+            .expect_err("missing context should be rejected");
+        assert_eq!(err.code(), ErrorCode::LoadingRemoteContextFailed);
+        assert_eq!(err.path(), Some("/2/@context"));
+    }
+
+    /// Items of the top-level `@graph` should be written one at a time as the expanded document.
+    #[test]
+    fn expand_to_writer_graph() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let options = JsonLdOptions::new();
+        let input = serde_json::json!({
+            "@context": { "name": "http://example.com/name" },
+            "@graph": [
+                { "@id": "http://example.com/a", "name": "Alice" },
+                "free-floating",
+                [{ "@id": "http://example.com/b", "name": "Bob" }],
+            ],
+        });
+        let expanded =
+            block_on(processor.expand(&input, &options)).expect("expansion should succeed");
+        let mut written = Vec::new();
+        block_on(processor.expand_to_writer(&input, &options, &mut written))
+            .expect("expansion should succeed");
+        let written: Value = serde_json::from_slice(&written).expect("valid JSON");
+        assert_eq!(written, expanded);
+
+        let input = serde_json::json!({ "@graph": [{}, { "@id": 1 }] });
+        let err = block_on(processor.expand_to_writer(&input, &options, Vec::new()))
+            .expect_err("invalid `@id` should be rejected");
+        assert_eq!(err.code(), ErrorCode::InvalidIdValue);
+        assert_eq!(err.path(), Some("/@graph/1/@id"));
+    }
+
+    /// Compacted output should be written node by node as the same JSON as `compact()`.
+    #[test]
+    fn compact_to_writer() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let context = serde_json::json!({
+            "@context": { "name": "http://example.com/name", "graph": "@graph" },
+        });
+        let node = |id: &str| serde_json::json!({ "@id": id, "http://example.com/name": id });
+        let inputs = vec![
+            serde_json::json!([]),
+            serde_json::json!([node("http://example.com/a")]),
+            serde_json::json!([node("http://example.com/a"), node("http://example.com/b")]),
+        ];
+        for options in &[
+            JsonLdOptions::new(),
+            JsonLdOptions::new().compact_arrays(false),
+        ] {
+            for input in &inputs {
+                for context in &[context.clone(), Value::Null] {
+                    let compacted = block_on(processor.compact(input, context, options))
+                        .expect("compaction should succeed");
+                    let mut written = Vec::new();
+                    block_on(processor.compact_to_writer(input, context, options, &mut written))
+                        .expect("compaction should succeed");
+                    assert_eq!(
+                        String::from_utf8(written).expect("valid UTF-8"),
+                        serde_json::to_string(&compacted).expect("serializable")
+                    );
+                }
+            }
+        }
+    }
+
+    /// Writer accepting the data in small chunks, and not ready at every other call.
+    #[cfg(feature = "futures-io")]
+    #[derive(Default)]
+    struct ChunkedWriter {
+        /// Written data.
+        data: Vec<u8>,
+        /// Whether the next call is not ready.
+        is_busy: bool,
+    }
+
+    #[cfg(feature = "futures-io")]
+    impl ChunkedWriter {
+        /// Maximum length of a chunk.
+        const CHUNK_LEN: usize = 3;
+
+        /// Returns `Poll::Pending` at every other call.
+        fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<()> {
+            self.is_busy = !self.is_busy;
+            if self.is_busy {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        }
+    }
+
+    #[cfg(feature = "futures-io")]
+    impl futures_io::AsyncWrite for ChunkedWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut TaskContext<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            if self.poll_ready(cx).is_pending() {
+                return Poll::Pending;
+            }
+            let len = buf.len().min(Self::CHUNK_LEN);
+            self.data.extend_from_slice(&buf[..len]);
+            Poll::Ready(Ok(len))
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+            self.poll_ready(cx).map(Ok)
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+            futures_io::AsyncWrite::poll_flush(self, cx)
+        }
+    }
+
+    /// Output should be written into the async writer as the synchronous writer does.
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn to_async_writer() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let options = JsonLdOptions::new();
+        let context = serde_json::json!({ "name": "http://example.com/name" });
+        let input = serde_json::json!({
+            "@context": context,
+            "@graph": [
+                { "@id": "http://example.com/a", "name": "Alice" },
+                { "@id": "http://example.com/b", "name": "Bob" },
+            ],
+        });
+
+        let mut expected = Vec::new();
+        block_on(processor.expand_to_writer(&input, &options, &mut expected))
+            .expect("expansion should succeed");
+        let mut writer = ChunkedWriter::default();
+        block_on(processor.expand_to_async_writer(&input, &options, &mut writer))
+            .expect("expansion should succeed");
+        assert_eq!(writer.data, expected);
+
+        let mut expected = Vec::new();
+        block_on(processor.compact_to_writer(&input, &context, &options, &mut expected))
+            .expect("compaction should succeed");
+        let mut writer = ChunkedWriter::default();
+        block_on(processor.compact_to_async_writer(&input, &context, &options, &mut writer))
+            .expect("compaction should succeed");
+        assert_eq!(writer.data, expected);
+
+        let input = serde_json::json!([{ "@id": 1 }]);
+        let err =
+            block_on(processor.expand_to_async_writer(&input, &options, ChunkedWriter::default()))
+                .expect_err("invalid `@id` should be rejected");
+        assert_eq!(err.code(), ErrorCode::InvalidIdValue);
+    }
+
+    /// Reader returning the data in small chunks.
+    #[cfg(feature = "futures-io")]
+    struct ChunkedReader<'a> {
+        /// Remaining data.
+        data: &'a [u8],
+    }
+
+    #[cfg(feature = "futures-io")]
+    impl ChunkedReader<'_> {
+        /// Maximum length of a chunk.
+        const CHUNK_LEN: usize = 3;
+    }
+
+    #[cfg(feature = "futures-io")]
+    impl futures_io::AsyncRead for ChunkedReader<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut TaskContext<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let len = buf.len().min(self.data.len()).min(Self::CHUNK_LEN);
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    #[cfg(feature = "futures-io")]
+    impl futures_io::AsyncBufRead for ChunkedReader<'_> {
+        fn poll_fill_buf(
+            self: Pin<&mut Self>,
+            _cx: &mut TaskContext<'_>,
+        ) -> Poll<io::Result<&[u8]>> {
+            let data = self.get_mut().data;
+            Poll::Ready(Ok(&data[..data.len().min(Self::CHUNK_LEN)]))
+        }
+
+        fn consume(mut self: Pin<&mut Self>, amt: usize) {
+            self.data = &self.data[amt..];
+        }
+    }
+
+    /// Lines should be expanded one by one as they are read, and blank lines are skipped.
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn expand_ndjson() {
+        let base = IriStr::new("http://example.com/").expect("valid IRI");
+        let processor = ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new());
+        let options = JsonLdOptions::new();
+        let input =
+            b"{\"@id\": \"http://example.com/a\", \"http://example.com/p\": \"v\"}\n\n{\n[]";
+        let reader = ChunkedReader { data: &input[..] };
+        let mut expansion = processor.expand_ndjson(reader, &options);
+        let mut results = Vec::new();
+        while let Some((line, result)) = block_on(expansion.next()) {
+            results.push((line, result.map_err(|e| e.code())));
+        }
+        let expanded = serde_json::json!([{
+            "@id": "http://example.com/a",
+            "http://example.com/p": [{ "@value": "v" }],
+        }]);
+        assert_eq!(
+            results,
+            vec![
+                (1, Ok(expanded)),
+                (3, Err(ErrorCode::LoadingDocumentFailed)),
+                (4, Ok(serde_json::json!([]))),
+            ]
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! RDF dataset.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#rdf-serialization-deserialization-algorithms>.
+
+pub use self::canonicalize::{
+    canonicalize, canonicalize_with_options, CanonicalDataset, CanonicalizeOptions,
+};
+
+mod canonicalize;
+pub mod nquads;
+
+/// IRI of `rdf:type`.
+pub(crate) const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+/// IRI of `rdf:first`.
+pub(crate) const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+/// IRI of `rdf:rest`.
+pub(crate) const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+/// IRI of `rdf:List`.
+pub(crate) const RDF_LIST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#List";
+/// IRI of `rdf:nil`.
+pub(crate) const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+/// IRI of `rdf:value`.
+pub(crate) const RDF_VALUE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#value";
+/// IRI of `rdf:language`.
+pub(crate) const RDF_LANGUAGE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#language";
+/// IRI of `rdf:direction`.
+pub(crate) const RDF_DIRECTION: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#direction";
+/// IRI of `rdf:langString`.
+pub(crate) const RDF_LANG_STRING: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#langString";
+/// IRI of `rdf:JSON`.
+pub(crate) const RDF_JSON: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#JSON";
+/// Base IRI of datatypes for language-tagged strings with base directions.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-rdfdirection-i18n-datatype>.
+pub(crate) const I18N_BASE: &str = "https://www.w3.org/ns/i18n#";
+/// IRI of `xsd:boolean`.
+pub(crate) const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+/// IRI of `xsd:double`.
+pub(crate) const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+/// IRI of `xsd:integer`.
+pub(crate) const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+/// IRI of `xsd:string`.
+pub(crate) const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+
+/// Implements common methods of node terms (IRIs and blank nodes).
+macro_rules! impl_node_term {
+    ($ty:ident) => {
+        impl $ty {
+            /// Creates an IRI or a blank node term from the given node identifier.
+            pub fn from_node_id(id: impl Into<String>) -> Self {
+                let id = id.into();
+                if id.starts_with("_:") {
+                    $ty::BlankNode(id)
+                } else {
+                    $ty::Iri(id)
+                }
+            }
+
+            /// Returns the IRI or the blank node identifier.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $ty::Iri(s) | $ty::BlankNode(s) => s,
+                }
+            }
+
+            /// Returns the IRI if the term is an IRI.
+            pub fn as_iri(&self) -> Option<&str> {
+                match self {
+                    $ty::Iri(iri) => Some(iri),
+                    $ty::BlankNode(_) => None,
+                }
+            }
+
+            /// Returns the blank node identifier if the term is a blank node.
+            pub fn as_blank_node(&self) -> Option<&str> {
+                match self {
+                    $ty::BlankNode(id) => Some(id),
+                    $ty::Iri(_) => None,
+                }
+            }
+
+            /// Replaces the blank node identifier using the given function.
+            pub(crate) fn map_blank_node(&self, f: impl FnOnce(&str) -> String) -> Self {
+                match self {
+                    $ty::BlankNode(id) => $ty::BlankNode(f(id)),
+                    $ty::Iri(iri) => $ty::Iri(iri.clone()),
+                }
+            }
+        }
+
+        impl From<$ty> for Object {
+            fn from(v: $ty) -> Self {
+                match v {
+                    $ty::Iri(iri) => Object::Iri(iri),
+                    $ty::BlankNode(id) => Object::BlankNode(id),
+                }
+            }
+        }
+    };
+}
+
+/// Subject of a triple.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Subject {
+    /// IRI.
+    Iri(String),
+    /// Blank node identifier (with `_:` prefix).
+    BlankNode(String),
+}
+
+impl_node_term!(Subject);
+
+/// Predicate of a triple.
+///
+/// A blank node predicate appears only in generalized RDF.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Predicate {
+    /// IRI.
+    Iri(String),
+    /// Blank node identifier (with `_:` prefix).
+    BlankNode(String),
+}
+
+impl_node_term!(Predicate);
+
+/// Graph name of a quad.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GraphName {
+    /// IRI.
+    Iri(String),
+    /// Blank node identifier (with `_:` prefix).
+    BlankNode(String),
+}
+
+impl_node_term!(GraphName);
+
+/// Object of a triple.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Object {
+    /// IRI.
+    Iri(String),
+    /// Blank node identifier (with `_:` prefix).
+    BlankNode(String),
+    /// Literal.
+    Literal(Literal),
+}
+
+impl Object {
+    /// Creates an IRI or a blank node term from the given node identifier.
+    pub fn from_node_id(id: impl Into<String>) -> Self {
+        Subject::from_node_id(id).into()
+    }
+
+    /// Returns the IRI if the term is an IRI.
+    pub fn as_iri(&self) -> Option<&str> {
+        match self {
+            Object::Iri(iri) => Some(iri),
+            _ => None,
+        }
+    }
+
+    /// Returns the blank node identifier if the term is a blank node.
+    pub fn as_blank_node(&self) -> Option<&str> {
+        match self {
+            Object::BlankNode(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Returns the literal if the term is a literal.
+    pub fn as_literal(&self) -> Option<&Literal> {
+        match self {
+            Object::Literal(literal) => Some(literal),
+            _ => None,
+        }
+    }
+
+    /// Replaces the blank node identifier using the given function.
+    pub(crate) fn map_blank_node(&self, f: impl FnOnce(&str) -> String) -> Self {
+        match self {
+            Object::BlankNode(id) => Object::BlankNode(f(id)),
+            term => term.clone(),
+        }
+    }
+}
+
+impl From<Literal> for Object {
+    fn from(v: Literal) -> Self {
+        Object::Literal(v)
+    }
+}
+
+/// RDF literal.
+///
+/// See <https://www.w3.org/TR/2014/REC-rdf11-concepts-20140225/#section-Graph-Literal>.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Literal {
+    /// Lexical form.
+    value: String,
+    /// Datatype IRI.
+    datatype: String,
+    /// Language tag.
+    language: Option<String>,
+}
+
+impl Literal {
+    /// Creates a new `Literal`.
+    pub fn new(
+        value: impl Into<String>,
+        datatype: impl Into<String>,
+        language: Option<String>,
+    ) -> Self {
+        Self {
+            value: value.into(),
+            datatype: datatype.into(),
+            language,
+        }
+    }
+
+    /// Returns the lexical form.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the datatype IRI.
+    pub fn datatype(&self) -> &str {
+        &self.datatype
+    }
+
+    /// Returns the language tag.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_ref().map(|s| &**s)
+    }
+}
+
+/// RDF triple.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Triple {
+    /// Subject.
+    subject: Subject,
+    /// Predicate.
+    predicate: Predicate,
+    /// Object.
+    object: Object,
+}
+
+impl Triple {
+    /// Creates a new `Triple`.
+    pub fn new(subject: Subject, predicate: Predicate, object: Object) -> Self {
+        Self {
+            subject,
+            predicate,
+            object,
+        }
+    }
+
+    /// Returns the subject.
+    pub fn subject(&self) -> &Subject {
+        &self.subject
+    }
+
+    /// Returns the predicate.
+    pub fn predicate(&self) -> &Predicate {
+        &self.predicate
+    }
+
+    /// Returns the object.
+    pub fn object(&self) -> &Object {
+        &self.object
+    }
+
+    /// Creates a quad by putting the triple into the given graph.
+    pub fn in_graph(self, graph: Option<GraphName>) -> Quad {
+        Quad {
+            triple: self,
+            graph,
+        }
+    }
+}
+
+/// RDF quad.
+///
+/// The graph name is `None` for the default graph.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Quad {
+    /// Triple.
+    triple: Triple,
+    /// Graph name.
+    graph: Option<GraphName>,
+}
+
+impl Quad {
+    /// Creates a new `Quad`.
+    pub fn new(
+        subject: Subject,
+        predicate: Predicate,
+        object: Object,
+        graph: Option<GraphName>,
+    ) -> Self {
+        Triple::new(subject, predicate, object).in_graph(graph)
+    }
+
+    /// Returns the subject.
+    pub fn subject(&self) -> &Subject {
+        self.triple.subject()
+    }
+
+    /// Returns the predicate.
+    pub fn predicate(&self) -> &Predicate {
+        self.triple.predicate()
+    }
+
+    /// Returns the object.
+    pub fn object(&self) -> &Object {
+        self.triple.object()
+    }
+
+    /// Returns the graph name, or `None` for the default graph.
+    pub fn graph(&self) -> Option<&GraphName> {
+        self.graph.as_ref()
+    }
+
+    /// Returns the triple.
+    pub fn triple(&self) -> &Triple {
+        &self.triple
+    }
+
+    /// Splits the quad into the triple and the graph name.
+    pub fn into_parts(self) -> (Triple, Option<GraphName>) {
+        (self.triple, self.graph)
+    }
+
+    /// Returns blank node identifiers in the quad.
+    pub(crate) fn blank_node_ids(&self) -> impl Iterator<Item = &str> {
+        vec![
+            self.subject().as_blank_node(),
+            self.predicate().as_blank_node(),
+            self.object().as_blank_node(),
+            self.graph().and_then(GraphName::as_blank_node),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Replaces all blank node identifiers in the quad using the given function.
+    pub(crate) fn map_blank_nodes(&self, mut f: impl FnMut(&str) -> String) -> Self {
+        Quad::new(
+            self.subject().map_blank_node(&mut f),
+            self.predicate().map_blank_node(&mut f),
+            self.object().map_blank_node(&mut f),
+            self.graph().map(|graph| graph.map_blank_node(&mut f)),
+        )
+    }
+}
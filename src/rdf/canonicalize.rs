@@ -0,0 +1,643 @@
+//! RDF dataset canonicalization.
+//!
+//! See <https://www.w3.org/TR/rdf-canon/>.
+
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{ErrorCode, Result},
+    rdf::{
+        nquads::{to_nquad, to_nquads},
+        GraphName, Quad,
+    },
+};
+
+/// Default maximum number of steps of the hash N-degree quads algorithm.
+const DEFAULT_MAX_STEPS: usize = 100_000;
+
+/// Options of RDF dataset canonicalization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CanonicalizeOptions {
+    /// Maximum number of steps of the hash N-degree quads algorithm.
+    max_steps: usize,
+}
+
+impl Default for CanonicalizeOptions {
+    fn default() -> Self {
+        Self {
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+}
+
+impl CanonicalizeOptions {
+    /// Creates a new default `CanonicalizeOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of steps of the hash N-degree quads algorithm.
+    ///
+    /// Each call of the algorithm and each permutation of related blank nodes it examines is a
+    /// step. The number of steps grows factorially for some crafted datasets, so untrusted
+    /// datasets should be canonicalized with a limit.
+    /// Exceeding the limit results in `canonicalization limit exceeded` error.
+    /// The default is 100,000.
+    pub fn max_steps(self, max_steps: usize) -> Self {
+        Self { max_steps }
+    }
+
+    /// Returns the maximum number of steps of the hash N-degree quads algorithm.
+    pub fn get_max_steps(&self) -> usize {
+        self.max_steps
+    }
+}
+
+/// Canonicalized RDF dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalDataset {
+    /// Canonical quads, sorted in canonical N-Quads order.
+    quads: Vec<Quad>,
+    /// Issued canonical blank node identifiers, indexed by the original identifiers.
+    issued: HashMap<String, String>,
+}
+
+impl CanonicalDataset {
+    /// Returns the canonical quads, sorted in canonical N-Quads order.
+    pub fn quads(&self) -> &[Quad] {
+        &self.quads
+    }
+
+    /// Returns the map from the original blank node identifiers to the canonical ones.
+    pub fn issued_identifiers(&self) -> &HashMap<String, String> {
+        &self.issued
+    }
+
+    /// Returns the canonical N-Quads document.
+    pub fn to_nquads(&self) -> String {
+        to_nquads(&self.quads)
+    }
+}
+
+/// Identifier issuer.
+///
+/// See <https://www.w3.org/TR/rdf-canon/#issue-identifier-algorithm>.
+#[derive(Debug, Clone)]
+struct IdentifierIssuer {
+    /// Identifier prefix.
+    prefix: &'static str,
+    /// Identifier counter.
+    counter: usize,
+    /// Issued identifiers, in issued order.
+    issued_order: Vec<String>,
+    /// Issued identifiers, indexed by the existing identifiers.
+    issued: HashMap<String, String>,
+}
+
+impl IdentifierIssuer {
+    /// Creates a new `IdentifierIssuer`.
+    fn new(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            counter: 0,
+            issued_order: Vec::new(),
+            issued: HashMap::new(),
+        }
+    }
+
+    /// Returns the issued identifier for the existing identifier, if available.
+    fn get(&self, existing: &str) -> Option<&str> {
+        self.issued.get(existing).map(|s| &**s)
+    }
+
+    /// Issues a new identifier for the existing identifier, or returns the already issued one.
+    fn issue(&mut self, existing: &str) -> String {
+        // Step 1
+        if let Some(issued) = self.issued.get(existing) {
+            return issued.clone();
+        }
+        // Step 2, 3, 4
+        let issued = format!("{}{}", self.prefix, self.counter);
+        self.counter += 1;
+        self.issued.insert(existing.to_owned(), issued.clone());
+        self.issued_order.push(existing.to_owned());
+        // Step 5
+        issued
+    }
+}
+
+/// Canonicalization state.
+///
+/// See <https://www.w3.org/TR/rdf-canon/#canon-state>.
+struct CanonicalizationState<'a> {
+    /// Quads that refer to each blank node.
+    blank_node_to_quads: HashMap<&'a str, Vec<&'a Quad>>,
+    /// Canonical identifier issuer.
+    canonical_issuer: IdentifierIssuer,
+    /// Maximum number of steps of the hash N-degree quads algorithm.
+    max_steps: usize,
+    /// Number of steps of the hash N-degree quads algorithm taken.
+    steps: Cell<usize>,
+}
+
+/// Canonicalizes the given RDF dataset with the default options.
+///
+/// See <https://www.w3.org/TR/rdf-canon/#canon-algorithm>.
+pub fn canonicalize(dataset: &[Quad]) -> Result<CanonicalDataset> {
+    canonicalize_with_options(dataset, &CanonicalizeOptions::new())
+}
+
+/// Canonicalizes the given RDF dataset with the given options.
+///
+/// See <https://www.w3.org/TR/rdf-canon/#canon-algorithm>.
+pub fn canonicalize_with_options(
+    dataset: &[Quad],
+    options: &CanonicalizeOptions,
+) -> Result<CanonicalDataset> {
+    // Step 1
+    let mut state = CanonicalizationState {
+        blank_node_to_quads: HashMap::new(),
+        canonical_issuer: IdentifierIssuer::new("_:c14n"),
+        max_steps: options.max_steps,
+        steps: Cell::new(0),
+    };
+    // Step 2
+    for quad in dataset {
+        for id in quad.blank_node_ids() {
+            let quads = state.blank_node_to_quads.entry(id).or_insert_with(Vec::new);
+            if !quads.iter().any(|q| std::ptr::eq(*q, quad)) {
+                quads.push(quad);
+            }
+        }
+    }
+    // Step 3
+    let mut non_normalized = state
+        .blank_node_to_quads
+        .keys()
+        .cloned()
+        .collect::<HashSet<_>>();
+    let mut hash_to_blank_nodes: BTreeMap<String, Vec<&str>>;
+    // Step 4, 5
+    loop {
+        // Step 5.2
+        hash_to_blank_nodes = BTreeMap::new();
+        // Step 5.3
+        let mut ids = non_normalized.iter().cloned().collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            let hash = state.hash_first_degree_quads(id);
+            hash_to_blank_nodes
+                .entry(hash)
+                .or_insert_with(Vec::new)
+                .push(id);
+        }
+        // Step 5.4
+        let mut simple = false;
+        let unique_hashes = hash_to_blank_nodes
+            .iter()
+            .filter(|(_, ids)| ids.len() == 1)
+            .map(|(hash, ids)| (hash.clone(), ids[0]))
+            .collect::<Vec<_>>();
+        for (hash, id) in unique_hashes {
+            state.canonical_issuer.issue(id);
+            non_normalized.remove(id);
+            hash_to_blank_nodes.remove(&hash);
+            simple = true;
+        }
+        if !simple {
+            break;
+        }
+    }
+    // Step 6
+    for ids in hash_to_blank_nodes.values() {
+        // Step 6.1
+        let mut hash_path_list = Vec::new();
+        // Step 6.2
+        for id in ids {
+            // Step 6.2.1
+            if state.canonical_issuer.get(id).is_some() {
+                continue;
+            }
+            // Step 6.2.2, 6.2.3
+            let mut temporary_issuer = IdentifierIssuer::new("_:b");
+            temporary_issuer.issue(id);
+            // Step 6.2.4
+            hash_path_list.push(state.hash_n_degree_quads(id, temporary_issuer)?);
+        }
+        // Step 6.3
+        hash_path_list.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, issuer) in hash_path_list {
+            for existing in &issuer.issued_order {
+                state.canonical_issuer.issue(existing);
+            }
+        }
+    }
+    // Step 7
+    let mut quads = dataset
+        .iter()
+        .map(|quad| {
+            quad.map_blank_nodes(|id| {
+                state
+                    .canonical_issuer
+                    .get(id)
+                    .expect("Should never fail: all blank nodes are issued canonical identifiers")
+                    .to_owned()
+            })
+        })
+        .map(|quad| (to_nquad(&quad), quad))
+        .collect::<Vec<_>>();
+    quads.sort_by(|(a, _), (b, _)| a.cmp(b));
+    quads.dedup_by(|(a, _), (b, _)| a == b);
+
+    Ok(CanonicalDataset {
+        quads: quads.into_iter().map(|(_, quad)| quad).collect(),
+        issued: state.canonical_issuer.issued,
+    })
+}
+
+impl<'a> CanonicalizationState<'a> {
+    /// Takes a step of the hash N-degree quads algorithm, and checks the step limit.
+    fn step(&self) -> Result<()> {
+        let steps = self.steps.get() + 1;
+        if steps > self.max_steps {
+            return Err(
+                ErrorCode::CanonicalizationLimitExceeded.and_message(format!(
+                    "Canonicalization needs more than {} steps",
+                    self.max_steps
+                )),
+            );
+        }
+        self.steps.set(steps);
+        Ok(())
+    }
+
+    /// Computes the first degree hash of the blank node.
+    ///
+    /// See <https://www.w3.org/TR/rdf-canon/#hash-1d-quads>.
+    fn hash_first_degree_quads(&self, reference_id: &str) -> String {
+        // Step 1, 2, 3
+        let mut nquads = self.blank_node_to_quads[reference_id]
+            .iter()
+            .map(|quad| {
+                // Step 3.1
+                to_nquad(&quad.map_blank_nodes(|id| {
+                    if id == reference_id {
+                        "_:a".to_owned()
+                    } else {
+                        "_:z".to_owned()
+                    }
+                }))
+            })
+            .collect::<Vec<_>>();
+        // Step 4
+        nquads.sort();
+        // Step 5
+        sha256_hex(&nquads.concat())
+    }
+
+    /// Computes the hash of the related blank node.
+    ///
+    /// See <https://www.w3.org/TR/rdf-canon/#hash-related-blank-node>.
+    fn hash_related_blank_node(
+        &self,
+        related: &str,
+        quad: &Quad,
+        issuer: &IdentifierIssuer,
+        position: char,
+    ) -> String {
+        // Step 1
+        let identifier = match self
+            .canonical_issuer
+            .get(related)
+            .or_else(|| issuer.get(related))
+        {
+            Some(identifier) => identifier.to_owned(),
+            None => self.hash_first_degree_quads(related),
+        };
+        // Step 2
+        let mut input = position.to_string();
+        // Step 3
+        if position != 'g' {
+            if let Some(predicate) = quad.predicate().as_iri() {
+                input.push('<');
+                input.push_str(predicate);
+                input.push('>');
+            }
+        }
+        // Step 4
+        input.push_str(&identifier);
+        // Step 5
+        sha256_hex(&input)
+    }
+
+    /// Computes the N-degree hash of the blank node.
+    ///
+    /// See <https://www.w3.org/TR/rdf-canon/#hash-nd-quads>.
+    fn hash_n_degree_quads(
+        &self,
+        id: &str,
+        mut issuer: IdentifierIssuer,
+    ) -> Result<(String, IdentifierIssuer)> {
+        self.step()?;
+        // Step 1
+        let mut hash_to_related: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        // Step 2, 3
+        for quad in &self.blank_node_to_quads[id] {
+            let components = [
+                ('s', quad.subject().as_blank_node()),
+                ('o', quad.object().as_blank_node()),
+                ('g', quad.graph().and_then(GraphName::as_blank_node)),
+            ];
+            for &(position, related) in components.iter() {
+                if let Some(related) = related {
+                    if related != id {
+                        // Step 3.1.1
+                        let hash = self.hash_related_blank_node(related, quad, &issuer, position);
+                        // Step 3.1.2
+                        hash_to_related
+                            .entry(hash)
+                            .or_insert_with(Vec::new)
+                            .push(related);
+                    }
+                }
+            }
+        }
+        // Step 4
+        let mut data_to_hash = String::new();
+        // Step 5
+        for (related_hash, blank_node_list) in hash_to_related {
+            // Step 5.1
+            data_to_hash.push_str(&related_hash);
+            // Step 5.2, 5.3
+            let mut chosen: Option<(String, IdentifierIssuer)> = None;
+            // Step 5.4
+            'permutations: for permutation in Permutations::new(&blank_node_list) {
+                self.step()?;
+                // Step 5.4.1, 5.4.2, 5.4.3
+                let mut issuer_copy = issuer.clone();
+                let mut path = String::new();
+                let mut recursion_list = Vec::new();
+                let is_longer_than_chosen = |path: &str, chosen: &Option<(String, _)>| match chosen
+                {
+                    Some((chosen_path, _)) => {
+                        path.len() >= chosen_path.len() && path > chosen_path.as_str()
+                    }
+                    None => false,
+                };
+                // Step 5.4.4
+                for related in permutation {
+                    match self.canonical_issuer.get(related) {
+                        // Step 5.4.4.1
+                        Some(canonical) => path.push_str(canonical),
+                        // Step 5.4.4.2
+                        None => {
+                            if issuer_copy.get(related).is_none() {
+                                recursion_list.push(related);
+                            }
+                            path.push_str(&issuer_copy.issue(related));
+                        }
+                    }
+                    // Step 5.4.4.3
+                    if is_longer_than_chosen(&path, &chosen) {
+                        continue 'permutations;
+                    }
+                }
+                // Step 5.4.5
+                for related in recursion_list {
+                    // Step 5.4.5.1
+                    let (result_hash, result_issuer) =
+                        self.hash_n_degree_quads(related, issuer_copy.clone())?;
+                    // Step 5.4.5.2, 5.4.5.3
+                    path.push_str(&issuer_copy.issue(related));
+                    path.push('<');
+                    path.push_str(&result_hash);
+                    path.push('>');
+                    // Step 5.4.5.4
+                    issuer_copy = result_issuer;
+                    // Step 5.4.5.5
+                    if is_longer_than_chosen(&path, &chosen) {
+                        continue 'permutations;
+                    }
+                }
+                // Step 5.4.6
+                let is_chosen = match &chosen {
+                    Some((chosen_path, _)) => path < *chosen_path,
+                    None => true,
+                };
+                if is_chosen {
+                    chosen = Some((path, issuer_copy));
+                }
+            }
+            if let Some((chosen_path, chosen_issuer)) = chosen {
+                // Step 5.5
+                data_to_hash.push_str(&chosen_path);
+                // Step 5.6
+                issuer = chosen_issuer;
+            }
+        }
+
+        // Step 6
+        Ok((sha256_hex(&data_to_hash), issuer))
+    }
+}
+
+/// Iterator over the permutations of the given items, generated lazily.
+///
+/// The permutations are generated in lexicographic order of the item indices.
+struct Permutations<'a, 'b> {
+    /// Items.
+    items: &'b [&'a str],
+    /// Item indices of the next permutation, or `None` if all permutations are generated.
+    indices: Option<Vec<usize>>,
+}
+
+impl<'a, 'b> Permutations<'a, 'b> {
+    /// Creates a new `Permutations`.
+    fn new(items: &'b [&'a str]) -> Self {
+        Self {
+            items,
+            indices: Some((0..items.len()).collect()),
+        }
+    }
+}
+
+impl<'a> Iterator for Permutations<'a, '_> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let items = self.items;
+        let indices = self.indices.as_mut()?;
+        let permutation = indices.iter().map(|&i| items[i]).collect();
+        // Find the last ascending pair, and make the next permutation in lexicographic order.
+        match (1..indices.len())
+            .rev()
+            .find(|&i| indices[i - 1] < indices[i])
+        {
+            Some(i) => {
+                let j = (i..indices.len())
+                    .rev()
+                    .find(|&j| indices[i - 1] < indices[j])
+                    .expect("Should never fail: `indices[i]` is greater than `indices[i - 1]`");
+                indices.swap(i - 1, j);
+                indices[i..].reverse();
+            }
+            None => self.indices = None,
+        }
+        Some(permutation)
+    }
+}
+
+/// Returns the hexadecimal SHA-256 digest of the given string.
+fn sha256_hex(data: &str) -> String {
+    format!("{:x}", Sha256::digest(data.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rdf::{nquads::parse_nquads, Object, Predicate, Subject};
+
+    /// Canonicalizes the N-Quads document with the given options.
+    fn canonicalize_nquads(input: &str, options: &CanonicalizeOptions) -> Result<String> {
+        let dataset = parse_nquads(input).expect("valid N-Quads document");
+        canonicalize_with_options(&dataset, options).map(|canonical| canonical.to_nquads())
+    }
+
+    #[test]
+    fn canonical_labels() {
+        let p = Predicate::Iri("http://example.com/p".to_owned());
+        let dataset = vec![
+            Quad::new(
+                Subject::BlankNode("_:x".to_owned()),
+                p.clone(),
+                Object::BlankNode("_:y".to_owned()),
+                None,
+            ),
+            Quad::new(
+                Subject::BlankNode("_:y".to_owned()),
+                p.clone(),
+                Object::BlankNode("_:x".to_owned()),
+                None,
+            ),
+        ];
+        let canonical = canonicalize(&dataset).expect("canonicalization should succeed");
+        assert_eq!(canonical.quads().len(), 2);
+        let mut labels = canonical
+            .issued_identifiers()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        labels.sort();
+        assert_eq!(labels, vec!["_:c14n0", "_:c14n1"]);
+    }
+
+    /// Blank nodes with unique first degree hashes.
+    #[test]
+    fn unique_hashes() {
+        let input = "\
+<http://example.com/#p> <http://example.com/#q> _:e0 .
+<http://example.com/#p> <http://example.com/#r> _:e1 .
+_:e0 <http://example.com/#s> <http://example.com/#u> .
+_:e1 <http://example.com/#t> <http://example.com/#u> .
+";
+        let expected = "\
+<http://example.com/#p> <http://example.com/#q> _:c14n0 .
+<http://example.com/#p> <http://example.com/#r> _:c14n1 .
+_:c14n0 <http://example.com/#s> <http://example.com/#u> .
+_:c14n1 <http://example.com/#t> <http://example.com/#u> .
+";
+        let output = canonicalize_nquads(input, &CanonicalizeOptions::new());
+        assert_eq!(output.expect("canonicalization should succeed"), expected);
+    }
+
+    /// Blank nodes with shared first degree hashes, which need the hash N-degree quads
+    /// algorithm.
+    #[test]
+    fn shared_hashes() {
+        let input = "\
+_:e0 <http://example.org/vocab#next> _:e1 .
+_:e0 <http://example.org/vocab#prev> _:e2 .
+_:e1 <http://example.org/vocab#next> _:e2 .
+_:e1 <http://example.org/vocab#prev> _:e0 .
+_:e2 <http://example.org/vocab#next> _:e0 .
+_:e2 <http://example.org/vocab#prev> _:e1 .
+";
+        let expected = "\
+_:c14n0 <http://example.org/vocab#next> _:c14n2 .
+_:c14n0 <http://example.org/vocab#prev> _:c14n1 .
+_:c14n1 <http://example.org/vocab#next> _:c14n0 .
+_:c14n1 <http://example.org/vocab#prev> _:c14n2 .
+_:c14n2 <http://example.org/vocab#next> _:c14n1 .
+_:c14n2 <http://example.org/vocab#prev> _:c14n0 .
+";
+        let output = canonicalize_nquads(input, &CanonicalizeOptions::new());
+        assert_eq!(output.expect("canonicalization should succeed"), expected);
+        // The result does not depend on the original labels.
+        let relabeled = input
+            .replace("_:e0", "_:x")
+            .replace("_:e1", "_:e0")
+            .replace("_:x", "_:e1");
+        let output = canonicalize_nquads(&relabeled, &CanonicalizeOptions::new());
+        assert_eq!(output.expect("canonicalization should succeed"), expected);
+    }
+
+    /// Datasets needing too many steps of the hash N-degree quads algorithm should be rejected.
+    #[test]
+    fn step_limit() {
+        // Every pair of the blank nodes is connected, so the number of steps grows factorially.
+        let nodes = 8;
+        let input = (0..nodes)
+            .flat_map(|i| {
+                (0..nodes)
+                    .filter(move |&j| i != j)
+                    .map(move |j| format!("_:n{} <http://example.com/p> _:n{} .\n", i, j))
+            })
+            .collect::<String>();
+        let options = CanonicalizeOptions::new().max_steps(1000);
+        let err = canonicalize_nquads(&input, &options)
+            .expect_err("canonicalization should exceed the limit");
+        assert_eq!(err.code(), ErrorCode::CanonicalizationLimitExceeded);
+
+        let input = "\
+_:a <http://example.com/p> _:b .
+_:b <http://example.com/p> _:a .
+";
+        let options = CanonicalizeOptions::new().max_steps(1);
+        let err = canonicalize_nquads(input, &options)
+            .expect_err("canonicalization should exceed the limit");
+        assert_eq!(err.code(), ErrorCode::CanonicalizationLimitExceeded);
+        // For each blank node, the algorithm is called for the node (1 step) with two groups of
+        // related nodes (1 step each), and recursively for the other node in the first group
+        // (3 steps).
+        let options = CanonicalizeOptions::new().max_steps(11);
+        assert!(canonicalize_nquads(input, &options).is_err());
+        let options = CanonicalizeOptions::new().max_steps(12);
+        assert!(canonicalize_nquads(input, &options).is_ok());
+    }
+
+    #[test]
+    fn permutations() {
+        let permutations = Permutations::new(&["a", "b", "c"]).collect::<Vec<_>>();
+        assert_eq!(
+            permutations,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["a", "c", "b"],
+                vec!["b", "a", "c"],
+                vec!["b", "c", "a"],
+                vec!["c", "a", "b"],
+                vec!["c", "b", "a"],
+            ]
+        );
+        assert_eq!(Permutations::new(&[]).count(), 1);
+        // Permutations are generated lazily.
+        let items = ["a"; 20];
+        assert_eq!(Permutations::new(&items).take(3).count(), 3);
+    }
+}
@@ -0,0 +1,153 @@
+//! N-Quads serialization and parsing.
+//!
+//! See <https://www.w3.org/TR/2014/REC-n-quads-20140225/>.
+
+use std::{fmt::Write as _, io};
+
+use crate::rdf::{GraphName, Object, Predicate, Quad, Subject, XSD_STRING};
+
+pub use self::parse::{parse_nquads, ParseError};
+
+mod parse;
+
+/// Media type of N-Quads.
+pub const MEDIA_TYPE: &str = "application/n-quads";
+
+/// Serializes the dataset into an N-Quads document.
+pub fn to_nquads(dataset: &[Quad]) -> String {
+    dataset.iter().map(to_nquad).collect()
+}
+
+/// Writes the dataset as an N-Quads document.
+pub fn write_nquads<W: io::Write>(mut writer: W, dataset: &[Quad]) -> io::Result<()> {
+    for quad in dataset {
+        writer.write_all(to_nquad(quad).as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Serializes the quad into a canonical N-Quads statement, with a trailing newline.
+///
+/// See <https://www.w3.org/TR/2014/REC-n-quads-20140225/#canonical-quads>.
+pub fn to_nquad(quad: &Quad) -> String {
+    let mut line = String::new();
+    match quad.subject() {
+        Subject::Iri(iri) => write_iri(&mut line, iri),
+        Subject::BlankNode(id) => line.push_str(id),
+    }
+    line.push(' ');
+    match quad.predicate() {
+        Predicate::Iri(iri) => write_iri(&mut line, iri),
+        Predicate::BlankNode(id) => line.push_str(id),
+    }
+    line.push(' ');
+    write_object(&mut line, quad.object());
+    match quad.graph() {
+        Some(GraphName::Iri(iri)) => {
+            line.push(' ');
+            write_iri(&mut line, iri);
+        }
+        Some(GraphName::BlankNode(id)) => {
+            line.push(' ');
+            line.push_str(id);
+        }
+        None => {}
+    }
+    line.push_str(" .\n");
+    line
+}
+
+/// Writes the IRI in N-Quads syntax.
+fn write_iri(out: &mut String, iri: &str) {
+    out.push('<');
+    escape_iri(out, iri);
+    out.push('>');
+}
+
+/// Writes the object term in N-Quads syntax.
+fn write_object(out: &mut String, object: &Object) {
+    match object {
+        Object::Iri(iri) => write_iri(out, iri),
+        Object::BlankNode(id) => out.push_str(id),
+        Object::Literal(literal) => {
+            out.push('"');
+            escape_literal(out, literal.value());
+            out.push('"');
+            if let Some(language) = literal.language() {
+                out.push('@');
+                out.push_str(language);
+            } else if literal.datatype() != XSD_STRING {
+                out.push_str("^^");
+                write_iri(out, literal.datatype());
+            }
+        }
+    }
+}
+
+/// Writes the IRI, escaping characters not allowed in `IRIREF`.
+///
+/// See <https://www.w3.org/TR/2014/REC-n-quads-20140225/#grammar-production-IRIREF>.
+fn escape_iri(out: &mut String, iri: &str) {
+    for c in iri.chars() {
+        match c {
+            '\u{0}'..='\u{20}' | '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' => {
+                write!(out, "\\u{:04X}", c as u32).expect("Should never fail: writing to string");
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+/// Writes the escaped lexical form of a literal.
+fn escape_literal(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{0}'..='\u{1f}' | '\u{7f}' => {
+                write!(out, "\\u{:04X}", c as u32).expect("Should never fail: writing to string");
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rdf::Literal;
+
+    #[test]
+    fn literal_escape() {
+        let quad = Quad::new(
+            Subject::BlankNode("_:b0".to_owned()),
+            Predicate::Iri("http://example.com/p".to_owned()),
+            Object::Literal(Literal::new("a\"b\\\n\u{1}", XSD_STRING, None)),
+            Some(GraphName::Iri("http://example.com/g".to_owned())),
+        );
+        assert_eq!(
+            to_nquad(&quad),
+            "_:b0 <http://example.com/p> \"a\\\"b\\\\\\n\\u0001\" <http://example.com/g> .\n"
+        );
+    }
+
+    #[test]
+    fn iri_escape() {
+        let quad = Quad::new(
+            Subject::Iri("http://example.com/a b".to_owned()),
+            Predicate::Iri("http://example.com/p".to_owned()),
+            Object::Literal(Literal::new("x", "http://example.com/dt", None)),
+            None,
+        );
+        assert_eq!(
+            to_nquads(&[quad]),
+            "<http://example.com/a\\u0020b> <http://example.com/p> \"x\"^^<http://example.com/dt> .\n"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! N-Quads parser.
+//!
+//! See <https://www.w3.org/TR/2014/REC-n-quads-20140225/#sec-grammar>.
+
+use crate::rdf::{
+    GraphName, Literal, Object, Predicate, Quad, Subject, RDF_LANG_STRING, XSD_STRING,
+};
+
+/// N-Quads syntax error.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("N-Quads syntax error at line {line}: {message}")]
+pub struct ParseError {
+    /// Line number (1-origin).
+    line: usize,
+    /// Error message.
+    message: String,
+}
+
+impl ParseError {
+    /// Returns the line number (1-origin) where the error occurred.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// Parses the N-Quads (or N-Triples) document.
+pub fn parse_nquads(input: &str) -> Result<Vec<Quad>, ParseError> {
+    let mut dataset = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let quad = LineParser::new(line)
+            .parse_statement()
+            .map_err(|message| ParseError {
+                line: index + 1,
+                message,
+            })?;
+        dataset.extend(quad);
+    }
+    Ok(dataset)
+}
+
+/// Parser for a line.
+struct LineParser<'a> {
+    /// Rest of the line.
+    rest: &'a str,
+}
+
+impl<'a> LineParser<'a> {
+    /// Creates a new `LineParser`.
+    fn new(line: &'a str) -> Self {
+        Self { rest: line }
+    }
+
+    /// Returns the next character without consuming it.
+    fn peek(&self) -> Option<char> {
+        self.rest.chars().next()
+    }
+
+    /// Consumes the next character.
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.rest = &self.rest[c.len_utf8()..];
+        Some(c)
+    }
+
+    /// Parses a statement, and returns `None` for an empty (or comment) line.
+    fn parse_statement(&mut self) -> Result<Option<Quad>, String> {
+        self.skip_whitespace();
+        if self.is_end_of_statement() {
+            return Ok(None);
+        }
+        let subject = match self.parse_term()? {
+            Object::Iri(iri) => Subject::Iri(iri),
+            Object::BlankNode(id) => Subject::BlankNode(id),
+            Object::Literal(_) => return Err("Literal cannot be a subject".to_owned()),
+        };
+        self.skip_whitespace();
+        let predicate = match self.parse_term()? {
+            Object::Iri(iri) => Predicate::Iri(iri),
+            _ => return Err("Predicate should be an IRI".to_owned()),
+        };
+        self.skip_whitespace();
+        let object = self.parse_term()?;
+        self.skip_whitespace();
+        let graph = if self.peek() == Some('.') {
+            None
+        } else {
+            match self.parse_term()? {
+                Object::Iri(iri) => Some(GraphName::Iri(iri)),
+                Object::BlankNode(id) => Some(GraphName::BlankNode(id)),
+                Object::Literal(_) => return Err("Literal cannot be a graph label".to_owned()),
+            }
+        };
+        self.skip_whitespace();
+        if self.next() != Some('.') {
+            return Err("Expected `.` at the end of the statement".to_owned());
+        }
+        self.skip_whitespace();
+        if !self.is_end_of_statement() {
+            return Err("Unexpected characters after the statement".to_owned());
+        }
+        Ok(Some(Quad::new(subject, predicate, object, graph)))
+    }
+
+    /// Skips whitespaces.
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |c| c == ' ' || c == '\t') {
+            self.next();
+        }
+    }
+
+    /// Checks whether the rest of the line is empty or a comment.
+    fn is_end_of_statement(&self) -> bool {
+        match self.peek() {
+            None | Some('#') => true,
+            Some(_) => false,
+        }
+    }
+
+    /// Parses an IRI, a blank node, or a literal.
+    fn parse_term(&mut self) -> Result<Object, String> {
+        match self.peek() {
+            Some('<') => self.parse_iri().map(Object::Iri),
+            Some('_') => self.parse_blank_node().map(Object::BlankNode),
+            Some('"') => self.parse_literal().map(Object::Literal),
+            Some(c) => Err(format!("Unexpected character {:?}", c)),
+            None => Err("Unexpected end of line".to_owned()),
+        }
+    }
+
+    /// Parses an `IRIREF`.
+    fn parse_iri(&mut self) -> Result<String, String> {
+        self.next();
+        let mut iri = String::new();
+        loop {
+            match self.next() {
+                Some('>') => return Ok(iri),
+                Some('\\') => match self.next() {
+                    Some('u') => iri.push(self.parse_hex(4)?),
+                    Some('U') => iri.push(self.parse_hex(8)?),
+                    c => return Err(format!("Invalid escape in IRI: {:?}", c)),
+                },
+                Some(c) if c <= ' ' || "<\"{}|^`".contains(c) => {
+                    return Err(format!("Invalid character in IRI: {:?}", c))
+                }
+                Some(c) => iri.push(c),
+                None => return Err("Unterminated IRI".to_owned()),
+            }
+        }
+    }
+
+    /// Parses a `BLANK_NODE_LABEL`.
+    fn parse_blank_node(&mut self) -> Result<String, String> {
+        self.next();
+        if self.next() != Some(':') {
+            return Err("Expected `_:` for a blank node label".to_owned());
+        }
+        let len = self
+            .rest
+            .find(|c: char| !(c.is_alphanumeric() || "_-.\u{b7}".contains(c)))
+            .unwrap_or_else(|| self.rest.len());
+        // Trailing `.` is not a part of the label but the end of the statement.
+        let label = self.rest[..len].trim_end_matches('.');
+        if label.is_empty() {
+            return Err("Empty blank node label".to_owned());
+        }
+        self.rest = &self.rest[label.len()..];
+        let label = format!("_:{}", label);
+        Ok(label)
+    }
+
+    /// Parses a literal.
+    fn parse_literal(&mut self) -> Result<Literal, String> {
+        self.next();
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => break,
+                Some('\\') => match self.next() {
+                    Some('t') => value.push('\t'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('"') => value.push('"'),
+                    Some('\'') => value.push('\''),
+                    Some('\\') => value.push('\\'),
+                    Some('u') => value.push(self.parse_hex(4)?),
+                    Some('U') => value.push(self.parse_hex(8)?),
+                    c => return Err(format!("Invalid escape in literal: {:?}", c)),
+                },
+                Some(c) => value.push(c),
+                None => return Err("Unterminated literal".to_owned()),
+            }
+        }
+        match self.peek() {
+            Some('@') => {
+                self.next();
+                let mut language = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == '-' {
+                        language.push(c);
+                        self.next();
+                    } else {
+                        break;
+                    }
+                }
+                if language.is_empty() {
+                    return Err("Empty language tag".to_owned());
+                }
+                Ok(Literal::new(value, RDF_LANG_STRING, Some(language)))
+            }
+            Some('^') => {
+                self.next();
+                if self.next() != Some('^') {
+                    return Err("Expected `^^` before the datatype IRI".to_owned());
+                }
+                if self.peek() != Some('<') {
+                    return Err("Expected datatype IRI".to_owned());
+                }
+                let datatype = self.parse_iri()?;
+                Ok(Literal::new(value, datatype, None))
+            }
+            _ => Ok(Literal::new(value, XSD_STRING, None)),
+        }
+    }
+
+    /// Parses the hexadecimal code point of `UCHAR`.
+    fn parse_hex(&mut self, len: usize) -> Result<char, String> {
+        let mut code = 0;
+        for _ in 0..len {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| "Invalid hexadecimal digit in escape".to_owned())?;
+            code = code * 16 + digit;
+        }
+        std::char::from_u32(code).ok_or_else(|| format!("Invalid code point U+{:X}", code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rdf::nquads::to_nquads;
+
+    #[test]
+    fn roundtrip() {
+        let doc = concat!(
+            "# comment\n",
+            "<http://example.com/s> <http://example.com/p> \"a\\\"b\\n\"@en-US .\n",
+            "\n",
+            "_:b0 <http://example.com/p> \"1\"^^<http://example.com/dt> _:g .\n",
+            "_:b0 <http://example.com/p> <http://example.com/o> <http://example.com/g> .\n",
+        );
+        let dataset = parse_nquads(doc).expect("valid N-Quads");
+        assert_eq!(dataset.len(), 3);
+        assert_eq!(
+            dataset[0].object(),
+            &Object::Literal(Literal::new(
+                "a\"b\n",
+                RDF_LANG_STRING,
+                Some("en-US".to_owned())
+            ))
+        );
+        assert_eq!(
+            to_nquads(&dataset),
+            doc.replace("# comment\n", "").replace("\n\n", "\n")
+        );
+    }
+
+    #[test]
+    fn syntax_error() {
+        let err = parse_nquads("<http://example.com/s> <http://example.com/p> .\n")
+            .expect_err("missing object");
+        assert_eq!(err.line(), 1);
+    }
+}
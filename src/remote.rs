@@ -1,14 +1,69 @@
 //! Remote-document related stuff.
 
-use std::sync::Arc;
+use std::{
+    hash::{Hash, Hasher},
+    io,
+    sync::Arc,
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use iri_string::types::IriStr;
 use serde_json::Value;
 
+pub use self::caching::CachingLoader;
+pub use self::data_uri::{DataUriLoadError, DataUriLoader};
+pub use self::disk_cache::DiskCacheLoader;
+pub use self::dynamic::{boxed_loader, BoxLoader, DynLoadError, ErasedLoader};
+#[cfg(feature = "wasm")]
+pub use self::fetch::{FetchLoadError, FetchLoader};
+pub use self::fs::{FsLoadError, FsLoader};
+pub use self::guard::{AccessDenied, AccessPolicy, GuardedLoadError, GuardedLoader};
+#[cfg(feature = "reqwest")]
+pub use self::http::{HttpLoadError, HttpLoader};
+pub use self::layer::{
+    layer_fn, Identity, InspectLayer, InspectLoader, LayerFn, LoaderBuilder, LoaderLayer, Stack,
+};
+pub use self::pinned::{IntegrityMismatch, PinnedLoadError, PinnedLoader};
 pub use self::profile::{Profile, RequestProfile};
+pub use self::redirect::RedirectPolicy;
+pub use self::retry::{ExponentialBackoff, FailureClass, RetryPolicy};
+#[cfg(feature = "tower")]
+pub use self::service::{ServiceLoadError, ServiceLoader};
+pub use self::static_loader::{StaticLoadError, StaticLoader};
+#[cfg(feature = "surf")]
+pub use self::surf_loader::{SurfLoadError, SurfLoader};
+pub use self::sync_loader::{LoadRemoteDocumentSync, SyncLoader};
 
+mod caching;
+mod data_uri;
+mod disk_cache;
+mod dynamic;
+#[cfg(feature = "wasm")]
+mod fetch;
+mod fs;
+mod guard;
+#[cfg(any(
+    feature = "reqwest",
+    feature = "surf",
+    feature = "tower",
+    feature = "wasm"
+))]
+mod header;
+#[cfg(feature = "reqwest")]
+mod http;
+mod layer;
+mod pinned;
 mod profile;
+mod redirect;
+mod retry;
+#[cfg(feature = "tower")]
+mod service;
+mod static_loader;
+#[cfg(feature = "surf")]
+mod surf_loader;
+mod sync_loader;
+mod unblock;
 
 /// A trait for types which can be used as remote document loader.
 ///
@@ -17,6 +72,9 @@ mod profile;
 /// custom loader type.
 /// `async_trait` trait is re-exported by this (json-ld) crate, so you can do
 /// `use json_ld::async_trait::async_trait`.
+///
+/// The trait is object safe. To select a loader at runtime, erase its error type by
+/// [`boxed_loader()`](fn.boxed_loader.html) and use [`BoxLoader`](type.BoxLoader.html).
 #[async_trait]
 pub trait LoadRemoteDocument: Send + Sync {
     /// Error type.
@@ -43,10 +101,53 @@ pub trait LoadRemoteDocument: Send + Sync {
     ) -> Result<Arc<RemoteDocument>, Self::Error>;
 }
 
+#[async_trait]
+impl<L: LoadRemoteDocument + ?Sized> LoadRemoteDocument for &L {
+    type Error = L::Error;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        (**self).load(iri, options).await
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument + ?Sized> LoadRemoteDocument for Box<L> {
+    type Error = L::Error;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        (**self).load(iri, options).await
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument + ?Sized> LoadRemoteDocument for Arc<L> {
+    type Error = L::Error;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        (**self).load(iri, options).await
+    }
+}
+
 /// Options for `LoadRemoteDocument::load()`.
 ///
 /// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#loaddocumentoptions>.
-#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// The timeout is ignored on comparison and hashing, since it does not affect the loaded document.
+/// This lets caches keyed by the options hit even when the timeout is derived from a deadline and
+/// differs on every load.
+#[derive(Default, Debug, Clone)]
 pub struct LoadDocumentOptions {
     /// A flag to let the loader extract JSON-LD script elements in HTML, if necessary.
     ///
@@ -64,6 +165,12 @@ pub struct LoadDocumentOptions {
     /// > One or more IRIs to use in the request as a `profile` parameter. (See IANA Considerations
     /// in \[JSON-LD11\]).
     request_profile: RequestProfile,
+    /// Maximum size of the document in bytes.
+    max_size: Option<u64>,
+    /// Timeout of loading the document.
+    timeout: Option<Duration>,
+    /// Additional request headers as pairs of the name and the value.
+    headers: Vec<(String, String)>,
 }
 
 impl LoadDocumentOptions {
@@ -77,6 +184,26 @@ impl LoadDocumentOptions {
         self.profile = profile.into();
     }
 
+    /// Sets the maximum size of the document in bytes.
+    pub(crate) fn set_max_size(&mut self, max_size: impl Into<Option<u64>>) {
+        self.max_size = max_size.into();
+    }
+
+    /// Sets the timeout of loading the document.
+    pub(crate) fn set_timeout(&mut self, timeout: impl Into<Option<Duration>>) {
+        self.timeout = timeout.into();
+    }
+
+    /// Sets the additional request headers.
+    pub(crate) fn set_headers(&mut self, headers: Vec<(String, String)>) {
+        self.headers = headers;
+    }
+
+    /// Sets the `extractAllScripts` flag.
+    pub(crate) fn set_extract_all_scripts(&mut self, extract_all_scripts: bool) {
+        self.extract_all_scripts = extract_all_scripts;
+    }
+
     /// Sets the given request profile.
     pub(crate) fn set_request_profile(&mut self, request_profile: impl Into<RequestProfile>) {
         self.request_profile = request_profile.into();
@@ -106,9 +233,86 @@ impl LoadDocumentOptions {
     pub fn request_profile(&self) -> RequestProfile {
         self.request_profile
     }
+
+    /// Returns the maximum size of the document in bytes.
+    ///
+    /// Loaders should refuse larger documents with an error caused by `SizeLimitExceeded`.
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Returns the timeout of loading the document.
+    ///
+    /// Loaders doing I/O which can stall (such as network access) should give up loading after
+    /// the timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Returns the additional request headers as pairs of the name and the value.
+    ///
+    /// Loaders using HTTP should send them with the request.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
+
+impl PartialEq for LoadDocumentOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.extract_all_scripts == other.extract_all_scripts
+            && self.profile == other.profile
+            && self.request_profile == other.request_profile
+            && self.max_size == other.max_size
+            && self.headers == other.headers
+    }
+}
+
+impl Eq for LoadDocumentOptions {}
+
+impl Hash for LoadDocumentOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.extract_all_scripts.hash(state);
+        self.profile.hash(state);
+        self.request_profile.hash(state);
+        self.max_size.hash(state);
+        self.headers.hash(state);
+    }
+}
+
+/// Error indicating the response has multiple HTTP `Link` headers for the JSON-LD context.
+///
+/// Loaders can return this as the source of their errors, so that the processor reports
+/// `ErrorCode::MultipleContextLinkHeaders`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Multiple context link headers")]
+pub struct MultipleContextLinkHeaders;
+
+/// Error indicating the document exceeds the size limit.
+///
+/// Loaders can return this as the source of their errors, so that the processor reports
+/// `ErrorCode::DocumentTooLarge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("The document exceeds the size limit ({limit} bytes)")]
+pub struct SizeLimitExceeded {
+    /// The size limit in bytes.
+    limit: u64,
+}
+
+impl SizeLimitExceeded {
+    /// Creates a new `SizeLimitExceeded`.
+    pub fn new(limit: u64) -> Self {
+        Self { limit }
+    }
+
+    /// Returns the size limit in bytes.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
 }
 
 /// Remote document.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#remotedocument>.
 #[derive(Debug, Clone, PartialEq)]
 pub struct RemoteDocument {
     /// Context URL.
@@ -117,9 +321,92 @@ pub struct RemoteDocument {
     document_url: String,
     /// Document.
     document: Value,
+    /// Content type of the document (without parameters).
+    content_type: Option<String>,
+    /// Value of the `profile` parameter of the content type.
+    profile: Option<String>,
 }
 
 impl RemoteDocument {
+    /// Creates a new `RemoteDocument` from the final URL and the document.
+    pub fn new(document_url: impl Into<String>, document: Value) -> Self {
+        Self {
+            context_url: None,
+            document_url: document_url.into(),
+            document,
+            content_type: None,
+            profile: None,
+        }
+    }
+
+    /// Sets the context URL given by the HTTP `Link` header.
+    pub fn context_url(self, context_url: impl Into<Option<String>>) -> Self {
+        Self {
+            context_url: context_url.into(),
+            ..self
+        }
+    }
+
+    /// Sets the content type of the document (without parameters).
+    pub fn content_type(self, content_type: impl Into<Option<String>>) -> Self {
+        Self {
+            content_type: content_type.into(),
+            ..self
+        }
+    }
+
+    /// Sets the value of the `profile` parameter of the content type.
+    pub fn profile(self, profile: impl Into<Option<String>>) -> Self {
+        Self {
+            profile: profile.into(),
+            ..self
+        }
+    }
+
+    /// Returns the context URL given by the HTTP `Link` header.
+    pub fn get_context_url(&self) -> Option<&str> {
+        self.context_url.as_ref().map(String::as_str)
+    }
+
+    /// Returns the final URL of the loaded document.
+    pub fn get_document_url(&self) -> &str {
+        &self.document_url
+    }
+
+    /// Returns the content type of the document (without parameters).
+    pub fn get_content_type(&self) -> Option<&str> {
+        self.content_type.as_ref().map(String::as_str)
+    }
+
+    /// Returns the value of the `profile` parameter of the content type.
+    pub fn get_profile(&self) -> Option<&str> {
+        self.profile.as_ref().map(String::as_str)
+    }
+
+    /// Checks whether the content type is JSON (`application/json`, `application/ld+json`, or
+    /// any other media type with `+json` suffix), or HTML which JSON-LD script elements are
+    /// extracted from.
+    ///
+    /// Returns `true` if the content type is unknown.
+    pub fn has_json_content_type(&self) -> bool {
+        match self.get_content_type() {
+            Some(ty) => {
+                ty == "application/json"
+                    || ty.ends_with("+json")
+                    || ty == "text/html"
+                    || ty == "application/xhtml+xml"
+            }
+            None => true,
+        }
+    }
+
+    /// Checks whether the `profile` parameter of the content type contains the given profile.
+    pub fn has_profile(&self, profile: Profile) -> bool {
+        self.get_profile().map_or(false, |v| {
+            v.split_whitespace().any(|uri| uri == profile.uri())
+        })
+    }
+
     /// Returns a reference to the document.
     pub fn document(&self) -> &Value {
         &self.document
@@ -129,4 +416,40 @@ impl RemoteDocument {
     pub fn into_document(self) -> Value {
         self.document
     }
+
+    /// Checks the size of the serialized document against the size limit.
+    ///
+    /// This is used by loaders serving documents from memory or caches, which have no response
+    /// body to measure.
+    pub(crate) fn check_size(&self, max_size: Option<u64>) -> Result<(), SizeLimitExceeded> {
+        let limit = match max_size {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        let mut counter = SizeCounter { size: 0, limit };
+        serde_json::to_writer(&mut counter, &self.document)
+            .map_err(|_| SizeLimitExceeded::new(limit))
+    }
+}
+
+/// Writer which only counts the written bytes, and fails when the limit is exceeded.
+struct SizeCounter {
+    /// Written bytes.
+    size: u64,
+    /// Size limit in bytes.
+    limit: u64,
+}
+
+impl io::Write for SizeCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.size += buf.len() as u64;
+        if self.size > self.limit {
+            return Err(io::Error::new(io::ErrorKind::Other, "size limit exceeded"));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
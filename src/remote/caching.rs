@@ -0,0 +1,231 @@
+//! Remote document loader wrapper with cache.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+
+use crate::remote::{
+    retry::with_retry, LoadDocumentOptions, LoadRemoteDocument, RemoteDocument, RetryPolicy,
+};
+
+/// Remote document loader wrapper which caches loaded documents.
+///
+/// Documents are cached per IRI and load options.
+/// Failed loads are not cached, and retried if the retry policy is set.
+///
+/// * If the TTL is set, cached documents older than the TTL are loaded again.
+/// * If the maximum number of entries is set, the least recently used document is evicted when
+///   the cache is full.
+pub struct CachingLoader<L> {
+    /// Inner loader.
+    loader: L,
+    /// Cache.
+    cache: Mutex<Cache>,
+    /// Retry policy.
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
+}
+
+impl<L> CachingLoader<L> {
+    /// Creates a new `CachingLoader` with no TTL and no limit of entries.
+    pub fn new(loader: L) -> Self {
+        Self {
+            loader,
+            cache: Mutex::new(Cache::default()),
+            retry_policy: None,
+        }
+    }
+
+    /// Sets the retry policy for failed loads of the inner loader.
+    pub fn retry_policy(self, retry_policy: impl RetryPolicy + 'static) -> Self {
+        Self {
+            retry_policy: Some(Arc::new(retry_policy)),
+            ..self
+        }
+    }
+
+    /// Sets the TTL of cached documents.
+    pub fn ttl(mut self, ttl: impl Into<Option<Duration>>) -> Self {
+        self.cache_mut().ttl = ttl.into();
+        self
+    }
+
+    /// Sets the maximum number of cached documents.
+    pub fn max_entries(mut self, max_entries: impl Into<Option<usize>>) -> Self {
+        self.cache_mut().max_entries = max_entries.into();
+        self
+    }
+
+    /// Returns the inner loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Removes all cached documents.
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// Returns the mutable reference to the cache.
+    fn cache_mut(&mut self) -> &mut Cache {
+        self.cache.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Locks the cache.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cache> {
+        // The cache is always consistent, so it can be used even if another thread panicked.
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<L: fmt::Debug> fmt::Debug for CachingLoader<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachingLoader")
+            .field("loader", &self.loader)
+            .field("cache", &*self.lock())
+            .field("retry_policy", &self.retry_policy)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument> LoadRemoteDocument for CachingLoader<L> {
+    type Error = L::Error;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        let key = (iri.as_str().to_owned(), options.clone());
+        if let Some(doc) = self.lock().get(&key, Instant::now()) {
+            trace_event!(iri = iri.as_str(), "Cached document is used");
+            return Ok(doc);
+        }
+        let doc = with_retry(self.retry_policy.as_ref().map(|p| &**p), || {
+            self.loader.load(iri, options.clone())
+        })
+        .await?;
+        self.lock().insert(key, doc.clone(), Instant::now());
+        Ok(doc)
+    }
+}
+
+/// Cache key.
+type CacheKey = (String, LoadDocumentOptions);
+
+/// Cached document.
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    /// Document.
+    document: Arc<RemoteDocument>,
+    /// Time when the document is loaded.
+    loaded_at: Instant,
+    /// Time when the document is used last time.
+    used_at: Instant,
+}
+
+/// Document cache.
+#[derive(Default, Debug, Clone)]
+struct Cache {
+    /// TTL.
+    ttl: Option<Duration>,
+    /// Maximum number of entries.
+    max_entries: Option<usize>,
+    /// Entries.
+    entries: HashMap<CacheKey, CacheEntry>,
+}
+
+impl Cache {
+    /// Returns the fresh document for the key, if available.
+    fn get(&mut self, key: &CacheKey, now: Instant) -> Option<Arc<RemoteDocument>> {
+        let ttl = self.ttl;
+        let entry = self.entries.get_mut(key)?;
+        if ttl.map_or(false, |ttl| now.duration_since(entry.loaded_at) >= ttl) {
+            self.entries.remove(key);
+            return None;
+        }
+        entry.used_at = now;
+        Some(entry.document.clone())
+    }
+
+    /// Inserts the document, evicting the least recently used one if necessary.
+    fn insert(&mut self, key: CacheKey, document: Arc<RemoteDocument>, now: Instant) {
+        let max_entries = match self.max_entries {
+            Some(0) => return,
+            Some(v) => v,
+            None => usize::max_value(),
+        };
+        if !self.entries.contains_key(&key) && self.entries.len() >= max_entries {
+            let lru_key = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used_at)
+                .map(|(key, _)| key.clone());
+            if let Some(lru_key) = lru_key {
+                self.entries.remove(&lru_key);
+            }
+        }
+        self.entries.insert(
+            key,
+            CacheEntry {
+                document,
+                loaded_at: now,
+                used_at: now,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    /// Returns a cache key and a document for the given IRI.
+    fn entry(iri: &str) -> (CacheKey, Arc<RemoteDocument>) {
+        (
+            (iri.to_owned(), LoadDocumentOptions::new()),
+            Arc::new(RemoteDocument::new(iri, json!({}))),
+        )
+    }
+
+    #[test]
+    fn ttl() {
+        let mut cache = Cache {
+            ttl: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let (key, doc) = entry("http://example.com/a");
+        cache.insert(key.clone(), doc, start);
+        assert!(cache.get(&key, start + Duration::from_secs(5)).is_some());
+        assert!(cache.get(&key, start + Duration::from_secs(10)).is_none());
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn max_entries() {
+        let mut cache = Cache {
+            max_entries: Some(2),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let (a, doc_a) = entry("http://example.com/a");
+        let (b, doc_b) = entry("http://example.com/b");
+        let (c, doc_c) = entry("http://example.com/c");
+        cache.insert(a.clone(), doc_a, start);
+        cache.insert(b.clone(), doc_b, start + Duration::from_secs(1));
+        assert!(cache.get(&a, start + Duration::from_secs(2)).is_some());
+        cache.insert(c.clone(), doc_c, start + Duration::from_secs(3));
+        assert!(cache.get(&a, start + Duration::from_secs(4)).is_some());
+        assert!(cache.get(&b, start + Duration::from_secs(4)).is_none());
+        assert!(cache.get(&c, start + Duration::from_secs(4)).is_some());
+    }
+}
@@ -0,0 +1,259 @@
+//! Remote document loader wrapper supporting `data:` URIs.
+
+use std::{error, sync::Arc};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+
+use crate::remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument};
+
+/// Error on loading a remote document by `DataUriLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum DataUriLoadError<E: error::Error + 'static> {
+    /// The inner loader failed.
+    #[error("{0}")]
+    Load(#[source] E),
+    /// The `data:` URI is malformed.
+    #[error("Malformed data URI: {0}")]
+    Malformed(&'static str),
+    /// The data is not a valid JSON.
+    #[error("Failed to parse the data as JSON: {0}")]
+    Json(#[source] serde_json::Error),
+}
+
+/// Remote document loader wrapper which decodes `data:` URIs ([RFC 2397]) by itself, and passes
+/// other IRIs to the inner loader.
+///
+/// Both base64 and percent-encoded data are supported, so a context can be given inline as
+/// `data:application/ld+json;base64,eyJAY29udGV4dCI6e319`.
+/// The media type of the URI is used as the content type of the document, so it should be a JSON
+/// media type (the default `text/plain` is rejected by the processor).
+///
+/// [RFC 2397]: https://tools.ietf.org/html/rfc2397
+#[derive(Debug, Clone)]
+pub struct DataUriLoader<L> {
+    /// Inner loader.
+    loader: L,
+}
+
+impl<L> DataUriLoader<L> {
+    /// Creates a new `DataUriLoader`.
+    pub fn new(loader: L) -> Self {
+        Self { loader }
+    }
+
+    /// Returns the inner loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument> LoadRemoteDocument for DataUriLoader<L> {
+    type Error = DataUriLoadError<L::Error>;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        let data_uri = match DataUri::parse(iri.as_str()) {
+            Some(v) => v.map_err(DataUriLoadError::Malformed)?,
+            None => {
+                return self
+                    .loader
+                    .load(iri, options)
+                    .await
+                    .map_err(DataUriLoadError::Load)
+            }
+        };
+        let document = serde_json::from_slice(&data_uri.data).map_err(DataUriLoadError::Json)?;
+        Ok(Arc::new(
+            RemoteDocument::new(iri.as_str(), document)
+                .content_type(data_uri.media_type)
+                .profile(data_uri.profile),
+        ))
+    }
+}
+
+/// Decoded `data:` URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DataUri {
+    /// Media type in lower case, without parameters.
+    media_type: String,
+    /// `profile` parameter.
+    profile: Option<String>,
+    /// Decoded data.
+    data: Vec<u8>,
+}
+
+impl DataUri {
+    /// Parses and decodes the `data:` URI.
+    ///
+    /// Returns `None` if the IRI is not a `data:` URI.
+    fn parse(iri: &str) -> Option<Result<Self, &'static str>> {
+        if iri.len() < 5 || !iri[..5].eq_ignore_ascii_case("data:") {
+            return None;
+        }
+        // Fragments are not a part of the data.
+        let rest = iri[5..].splitn(2, '#').next().unwrap_or("");
+        Some(Self::parse_rest(rest))
+    }
+
+    /// Parses and decodes the part after `data:`.
+    fn parse_rest(rest: &str) -> Result<Self, &'static str> {
+        let comma = rest.find(',').ok_or("missing comma")?;
+        let (header, data) = (&rest[..comma], &rest[(comma + 1)..]);
+        let mut params = header.split(';');
+        let media_type = params.next().unwrap_or("").trim().to_ascii_lowercase();
+        let mut profile = None;
+        let mut is_base64 = false;
+        for param in params {
+            let param = param.trim();
+            if param.eq_ignore_ascii_case("base64") {
+                is_base64 = true;
+                continue;
+            }
+            let mut kv = param.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            if let Some(value) = kv.next() {
+                if key.eq_ignore_ascii_case("profile") {
+                    let value = percent_decode(value).ok_or("invalid percent-encoding")?;
+                    let value = String::from_utf8(value).map_err(|_| "invalid profile")?;
+                    profile = Some(value.trim_matches('"').to_owned());
+                }
+            }
+        }
+        // > If `<mediatype>` is omitted, it defaults to `text/plain;charset=US-ASCII`.
+        let media_type = if media_type.is_empty() {
+            "text/plain".to_owned()
+        } else {
+            media_type
+        };
+        let data = percent_decode(data).ok_or("invalid percent-encoding")?;
+        let data = if is_base64 {
+            base64_decode(&data).ok_or("invalid base64")?
+        } else {
+            data
+        };
+        Ok(Self {
+            media_type,
+            profile,
+            data,
+        })
+    }
+}
+
+/// Decodes the percent-encoded string.
+pub(crate) fn percent_decode(s: &str) -> Option<Vec<u8>> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get((i + 1)..(i + 3))?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Decodes the base64 data.
+///
+/// Both the standard and the URL-safe alphabets are accepted, and padding is optional.
+fn base64_decode(data: &[u8]) -> Option<Vec<u8>> {
+    /// Returns the 6-bit value of the base64 digit.
+    fn digit(b: u8) -> Option<u32> {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        Some(u32::from(v))
+    }
+
+    let data: Vec<u8> = data
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    let unpadded = {
+        let len = data
+            .iter()
+            .rposition(|&b| b != b'=')
+            .map_or(0, |pos| pos + 1);
+        if data.len() - len > 2 {
+            return None;
+        }
+        &data[..len]
+    };
+    if unpadded.len() % 4 == 1 {
+        return None;
+    }
+    let mut decoded = Vec::with_capacity(unpadded.len() * 3 / 4);
+    for chunk in unpadded.chunks(4) {
+        let mut buf = 0_u32;
+        for &b in chunk {
+            buf = (buf << 6) | digit(b)?;
+        }
+        buf <<= 6 * (4 - chunk.len() as u32);
+        let bytes = [(buf >> 16) as u8, (buf >> 8) as u8, buf as u8];
+        decoded.extend_from_slice(&bytes[..(chunk.len() - 1)]);
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        assert_eq!(
+            base64_decode(b"").as_ref().map(Vec::as_slice),
+            Some(&b""[..])
+        );
+        assert_eq!(
+            base64_decode(b"Zg==").as_ref().map(Vec::as_slice),
+            Some(&b"f"[..])
+        );
+        assert_eq!(
+            base64_decode(b"Zm8").as_ref().map(Vec::as_slice),
+            Some(&b"fo"[..])
+        );
+        assert_eq!(
+            base64_decode(b"Zm9v").as_ref().map(Vec::as_slice),
+            Some(&b"foo"[..])
+        );
+        assert_eq!(base64_decode(b"Z"), None);
+        assert_eq!(base64_decode(b"Zm9v!"), None);
+    }
+
+    #[test]
+    fn parse_data_uri() {
+        let uri = DataUri::parse("data:application/ld+json;base64,eyJAY29udGV4dCI6e319")
+            .expect("data URI")
+            .expect("valid data URI");
+        assert_eq!(uri.media_type, "application/ld+json");
+        assert_eq!(uri.data, br#"{"@context":{}}"#);
+
+        let uri = DataUri::parse("data:,%7B%7D#frag")
+            .expect("data URI")
+            .expect("valid data URI");
+        assert_eq!(uri.media_type, "text/plain");
+        assert_eq!(uri.data, b"{}");
+
+        assert!(DataUri::parse("https://example.com/").is_none());
+        assert_eq!(
+            DataUri::parse("data:application/json"),
+            Some(Err("missing comma"))
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! Remote document loader wrapper with persistent on-disk cache.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument};
+
+/// Extension of cache entry files.
+const ENTRY_EXTENSION: &str = "json";
+
+/// Remote document loader wrapper which caches loaded documents in a local directory.
+///
+/// The cache survives process restarts, so it can be used to avoid refetching contexts on cold
+/// start.
+/// Each document is stored as a JSON file named by the SHA-256 hash of the requested IRI.
+///
+/// Documents are keyed by the requested IRI only, and load options are ignored on cache lookup
+/// except for the size limit: cached documents exceeding it are loaded by the inner loader again.
+/// Failed loads are not cached, and I/O errors on the cache are ignored (the inner loader is used
+/// instead).
+#[derive(Debug, Clone)]
+pub struct DiskCacheLoader<L> {
+    /// Inner loader.
+    loader: L,
+    /// Cache directory.
+    dir: PathBuf,
+    /// TTL of cached documents.
+    ttl: Option<Duration>,
+}
+
+impl<L> DiskCacheLoader<L> {
+    /// Creates a new `DiskCacheLoader` with no TTL.
+    ///
+    /// The directory is created on the first write if it does not exist.
+    pub fn new(loader: L, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            loader,
+            dir: dir.into(),
+            ttl: None,
+        }
+    }
+
+    /// Sets the TTL of cached documents.
+    ///
+    /// Documents older than the TTL are loaded again by the inner loader.
+    pub fn ttl(self, ttl: impl Into<Option<Duration>>) -> Self {
+        Self {
+            ttl: ttl.into(),
+            ..self
+        }
+    }
+
+    /// Returns the inner loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Returns the cache directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stores the document for the given IRI into the cache.
+    ///
+    /// This can be used to pre-seed the cache.
+    pub fn insert(&self, iri: &str, document: &RemoteDocument) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(iri);
+        // Write to the temporary file first, so that readers never see partially written files.
+        let tmp_path = temp_path(&path);
+        fs::write(&tmp_path, encode_entry(iri, document).to_string())
+            .and_then(|()| fs::rename(&tmp_path, &path))
+            .map_err(|e| {
+                // The temporary file may be partially written.
+                let _ = fs::remove_file(&tmp_path);
+                e
+            })
+    }
+
+    /// Returns the cached document for the given IRI, regardless of TTL.
+    pub fn get(&self, iri: &str) -> io::Result<Option<RemoteDocument>> {
+        let text = match fs::read_to_string(self.entry_path(iri)) {
+            Ok(v) => v,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let entry: Value = serde_json::from_str(&text)?;
+        Ok(decode_entry(iri, entry))
+    }
+
+    /// Removes the cached document for the given IRI.
+    ///
+    /// Returns `true` if the document was cached.
+    pub fn remove(&self, iri: &str) -> io::Result<bool> {
+        match fs::remove_file(self.entry_path(iri)) {
+            Ok(()) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Removes cached documents older than `max_age`.
+    ///
+    /// Returns the number of removed documents.
+    /// Temporary files of writes older than `max_age` are also removed, since they are left only
+    /// if the process is killed during the write.
+    /// Files other than cache entries in the directory are left untouched.
+    pub fn prune(&self, max_age: Duration) -> io::Result<usize> {
+        let now = SystemTime::now();
+        self.remove_entries(|modified| {
+            now.duration_since(modified)
+                .map(|age| age >= max_age)
+                .unwrap_or(false)
+        })
+    }
+
+    /// Removes all cached documents.
+    ///
+    /// Returns the number of removed documents.
+    /// Temporary files of writes are also removed.
+    pub fn clear(&self) -> io::Result<usize> {
+        self.remove_entries(|_| true)
+    }
+
+    /// Removes cache entries and temporary files whose modification time satisfies the predicate.
+    ///
+    /// Returns the number of removed cache entries.
+    fn remove_entries(&self, pred: impl Fn(SystemTime) -> bool) -> io::Result<usize> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(v) => v,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut count = 0;
+        for dir_entry in dir {
+            let dir_entry = dir_entry?;
+            let file_name = dir_entry.file_name();
+            let is_entry = is_entry_file_name(&file_name.to_string_lossy());
+            if !is_entry && !is_temp_file_name(&file_name.to_string_lossy()) {
+                continue;
+            }
+            let removed = dir_entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .and_then(|modified| {
+                    if !pred(modified) {
+                        return Ok(false);
+                    }
+                    fs::remove_file(dir_entry.path()).map(|()| true)
+                });
+            match removed {
+                Ok(true) if is_entry => count += 1,
+                Ok(_) => {}
+                // The temporary file may have been renamed or removed by the writer meanwhile.
+                Err(ref e) if !is_entry && e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the fresh cached document for the given IRI.
+    fn get_fresh(&self, iri: &str) -> io::Result<Option<RemoteDocument>> {
+        if let Some(ttl) = self.ttl {
+            let modified = match fs::metadata(self.entry_path(iri)) {
+                Ok(v) => v.modified()?,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let is_expired = SystemTime::now()
+                .duration_since(modified)
+                .map(|age| age >= ttl)
+                .unwrap_or(false);
+            if is_expired {
+                return Ok(None);
+            }
+        }
+        self.get(iri)
+    }
+
+    /// Returns the path of the cache entry file for the given IRI.
+    fn entry_path(&self, iri: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{:x}.{}",
+            Sha256::digest(iri.as_bytes()),
+            ENTRY_EXTENSION
+        ))
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument> LoadRemoteDocument for DiskCacheLoader<L> {
+    type Error = L::Error;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        match self.get_fresh(iri.as_str()) {
+            Ok(Some(ref doc)) if doc.check_size(options.max_size()).is_err() => {
+                trace_event!(iri = iri.as_str(), "Document cached on disk is too large");
+            }
+            Ok(Some(doc)) => {
+                trace_event!(iri = iri.as_str(), "Document cached on disk is used");
+                return Ok(Arc::new(doc));
+            }
+            Ok(None) => {}
+            Err(_e) => {
+                trace_event!(iri = iri.as_str(), error = %_e, "Failed to read the disk cache");
+            }
+        }
+        let doc = self.loader.load(iri, options).await?;
+        if let Err(_e) = self.insert(iri.as_str(), &doc) {
+            trace_event!(iri = iri.as_str(), error = %_e, "Failed to write the disk cache");
+        }
+        Ok(doc)
+    }
+}
+
+/// Returns the unique path of the temporary file to write the cache entry.
+///
+/// The name contains the process ID and a counter, so that concurrent writes in this process and
+/// in others do not share the temporary file.
+fn temp_path(path: &Path) -> PathBuf {
+    /// Counter of temporary files created in this process.
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_extension(format!("{}.{}.tmp", process::id(), count))
+}
+
+/// Checks whether the string is a SHA-256 hash in hexadecimal.
+fn is_hash(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Checks whether the file name is of a cache entry.
+fn is_entry_file_name(name: &str) -> bool {
+    let mut parts = name.splitn(2, '.');
+    is_hash(parts.next().unwrap_or("")) && parts.next() == Some(ENTRY_EXTENSION)
+}
+
+/// Checks whether the file name is of a temporary file created by `temp_path()`.
+fn is_temp_file_name(name: &str) -> bool {
+    let is_number = |s: Option<&str>| {
+        s.map_or(false, |s| {
+            !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+        })
+    };
+    let mut parts = name.split('.');
+    is_hash(parts.next().unwrap_or(""))
+        && is_number(parts.next())
+        && is_number(parts.next())
+        && parts.next() == Some("tmp")
+        && parts.next().is_none()
+}
+
+/// Encodes the cache entry into JSON.
+fn encode_entry(iri: &str, document: &RemoteDocument) -> Value {
+    json!({
+        "iri": iri,
+        "documentUrl": document.get_document_url(),
+        "contextUrl": document.get_context_url(),
+        "contentType": document.get_content_type(),
+        "profile": document.get_profile(),
+        "document": document.document(),
+    })
+}
+
+/// Decodes the cache entry for the given IRI.
+///
+/// Returns `None` if the entry is invalid or for another IRI.
+fn decode_entry(iri: &str, mut entry: Value) -> Option<RemoteDocument> {
+    let entry = entry.as_object_mut()?;
+    if entry.get("iri")?.as_str()? != iri {
+        return None;
+    }
+    let string = |key: &str| {
+        entry
+            .get(key)
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned)
+    };
+    let document_url = string("documentUrl")?;
+    let context_url = string("contextUrl");
+    let content_type = string("contentType");
+    let profile = string("profile");
+    let document = entry.remove("document")?;
+    Some(
+        RemoteDocument::new(document_url, document)
+            .context_url(context_url)
+            .content_type(content_type)
+            .profile(profile),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_executor::block_on;
+
+    use crate::remote::StaticLoader;
+
+    #[test]
+    fn entry_round_trip() {
+        let iri = "https://schema.org/";
+        let doc = RemoteDocument::new("https://schema.org/docs/jsonldcontext.json", json!({}))
+            .content_type("application/ld+json".to_owned());
+        let entry = encode_entry(iri, &doc);
+        assert_eq!(decode_entry(iri, entry.clone()), Some(doc));
+        assert_eq!(decode_entry("https://example.com/", entry), None);
+    }
+
+    #[test]
+    fn entry_file_name() {
+        let loader = DiskCacheLoader::new((), "/tmp/cache");
+        let path = loader.entry_path("https://schema.org/");
+        assert!(is_entry_file_name(
+            &path.file_name().expect("file name").to_string_lossy()
+        ));
+        assert!(!is_entry_file_name("README.json"));
+    }
+
+    #[test]
+    fn unique_temp_path() {
+        let path = Path::new("/tmp/cache/entry.json");
+        let tmp = temp_path(path);
+        assert_ne!(tmp, temp_path(path));
+        assert_eq!(tmp.parent(), path.parent());
+        assert!(!is_entry_file_name(
+            &tmp.file_name().expect("file name").to_string_lossy()
+        ));
+        assert!(!is_temp_file_name("entry.1.2.tmp"));
+
+        let loader = DiskCacheLoader::new((), "/tmp/cache");
+        let tmp = temp_path(&loader.entry_path("https://schema.org/"));
+        assert!(is_temp_file_name(
+            &tmp.file_name().expect("file name").to_string_lossy()
+        ));
+    }
+
+    #[test]
+    fn cached_document_too_large() {
+        let dir = std::env::temp_dir().join(format!("json-ld-disk-cache-{}", process::id()));
+        let iri = IriStr::new("https://example.com/context.jsonld").expect("valid IRI");
+        let mut inner = StaticLoader::new();
+        inner
+            .insert_source(iri.as_str(), "{}")
+            .expect("valid document");
+        let loader = DiskCacheLoader::new(inner, &dir);
+        let cached = RemoteDocument::new(
+            iri.as_str(),
+            json!({ "@context": { "name": "http://schema.org/name" } }),
+        );
+        loader
+            .insert(iri.as_str(), &cached)
+            .expect("cache should be writable");
+
+        let doc = block_on(loader.load(iri, LoadDocumentOptions::new())).expect("cached document");
+        assert_eq!(*doc, cached);
+        let mut options = LoadDocumentOptions::new();
+        options.set_max_size(16);
+        let doc = block_on(loader.load(iri, options)).expect("document of the inner loader");
+        assert_eq!(doc.document(), &json!({}));
+
+        fs::remove_dir_all(&dir).expect("cache directory should be removable");
+    }
+    #[test]
+    fn prune_temp_files() {
+        let dir = std::env::temp_dir().join(format!("json-ld-disk-cache-prune-{}", process::id()));
+        let loader = DiskCacheLoader::new((), &dir);
+        let iri = "https://example.com/context.jsonld";
+        loader
+            .insert(iri, &RemoteDocument::new(iri, json!({})))
+            .expect("cache should be writable");
+        // Temporary file left by an interrupted write.
+        let stale = temp_path(&loader.entry_path("https://example.com/stale.jsonld"));
+        fs::write(&stale, "{").expect("cache should be writable");
+        let other = dir.join("README.txt");
+        fs::write(&other, "").expect("cache directory should be writable");
+        assert_eq!(fs::read_dir(&dir).expect("cache directory").count(), 3);
+
+        assert_eq!(loader.prune(Duration::from_secs(3600)).expect("prune"), 0);
+        assert!(stale.exists());
+        assert_eq!(loader.prune(Duration::from_secs(0)).expect("prune"), 1);
+        assert!(!stale.exists());
+        assert!(other.exists());
+        assert_eq!(loader.get(iri).expect("cache should be readable"), None);
+
+        fs::remove_dir_all(&dir).expect("cache directory should be removable");
+    }
+}
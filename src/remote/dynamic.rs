@@ -0,0 +1,110 @@
+//! Type-erased remote document loader.
+
+use std::{error::Error, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+
+use crate::remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument};
+
+/// Remote document loader selected at runtime.
+///
+/// Create it by [`boxed_loader()`](fn.boxed_loader.html).
+pub type BoxLoader = Box<dyn LoadRemoteDocument<Error = DynLoadError>>;
+
+/// Boxes the loader, erasing its type and its error type.
+pub fn boxed_loader<L: LoadRemoteDocument + 'static>(loader: L) -> BoxLoader {
+    Box::new(ErasedLoader::new(loader))
+}
+
+/// Type-erased error on loading a remote document.
+///
+/// The original error is available as the source.
+#[derive(Debug)]
+pub struct DynLoadError(Box<dyn Error + Send + Sync + 'static>);
+
+impl DynLoadError {
+    /// Creates a new `DynLoadError`.
+    pub fn new(source: impl Into<Box<dyn Error + Send + Sync + 'static>>) -> Self {
+        Self(source.into())
+    }
+
+    /// Returns the original error.
+    pub fn into_inner(self) -> Box<dyn Error + Send + Sync + 'static> {
+        self.0
+    }
+}
+
+impl fmt::Display for DynLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for DynLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+/// Remote document loader which erases the error type of the inner loader.
+#[derive(Debug, Clone)]
+pub struct ErasedLoader<L> {
+    /// Inner loader.
+    loader: L,
+}
+
+impl<L> ErasedLoader<L> {
+    /// Creates a new `ErasedLoader`.
+    pub fn new(loader: L) -> Self {
+        Self { loader }
+    }
+
+    /// Returns the inner loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Returns the inner loader.
+    pub fn into_inner(self) -> L {
+        self.loader
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument> LoadRemoteDocument for ErasedLoader<L> {
+    type Error = DynLoadError;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        self.loader
+            .load(iri, options)
+            .await
+            .map_err(DynLoadError::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::remote::SizeLimitExceeded;
+
+    #[test]
+    fn error_source() {
+        let err = DynLoadError::new(SizeLimitExceeded::new(42));
+        assert_eq!(err.to_string(), SizeLimitExceeded::new(42).to_string());
+        assert!(err
+            .source()
+            .map_or(false, |source| source.is::<SizeLimitExceeded>()));
+    }
+
+    #[test]
+    fn boxed_loader_is_loader() {
+        fn assert_loader<L: LoadRemoteDocument>(_: &L) {}
+        assert_loader(&boxed_loader(crate::remote::StaticLoader::new()));
+    }
+}
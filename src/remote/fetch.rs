@@ -0,0 +1,301 @@
+//! Remote document loader using the `fetch` API of web browsers.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#loaddocumentcallback>.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{Request, RequestCredentials, RequestInit, Response, Window, WorkerGlobalScope};
+
+use crate::{
+    html::extract_json_ld,
+    remote::{
+        header::{
+            accept_header, alternate_link, check_body_size, check_content_length, context_link,
+            MediaType,
+        },
+        LoadDocumentOptions, LoadRemoteDocument, MultipleContextLinkHeaders, RemoteDocument,
+        SizeLimitExceeded,
+    },
+};
+
+/// Error on loading a remote document by `FetchLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchLoadError {
+    /// `fetch` failed, or is not available.
+    #[error("Fetch failed: {0}")]
+    Fetch(String),
+    /// The server responded with an unsuccessful status.
+    #[error("Unsuccessful HTTP status: {0}")]
+    Status(u16),
+    /// The document has an unsupported content type.
+    #[error("Unsupported content type: {0:?}")]
+    UnsupportedContentType(String),
+    /// The response has multiple HTTP `Link` headers for the JSON-LD context.
+    #[error("{0}")]
+    MultipleContextLinkHeaders(#[source] MultipleContextLinkHeaders),
+    /// The document is not valid JSON.
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Failed to extract JSON-LD from the HTML document.
+    #[error("Failed to extract JSON-LD from HTML: {0}")]
+    Html(#[source] crate::Error),
+    /// The document exceeds the size limit.
+    #[error("{0}")]
+    TooLarge(#[source] SizeLimitExceeded),
+}
+
+/// Remote document loader using the `fetch` API, for `wasm32-unknown-unknown` targets running in
+/// web browsers (windows or workers).
+///
+/// Documents are requested and interpreted in the same way as
+/// [`HttpLoader`](struct.HttpLoader.html), except for the following:
+///
+/// * Redirects and caching are handled by the browser.
+/// * The `Link` and `Content-Length` headers are only available if the server exposes them by
+///   `Access-Control-Expose-Headers` for cross-origin requests.
+/// * Timeouts given by `LoadDocumentOptions::timeout()` are not enforced.
+///
+/// `fetch` runs in a task spawned on the current thread, so the loader must be used in the
+/// browser's event loop (for example, by `wasm_bindgen_futures::spawn_local()`).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FetchLoader {
+    /// Whether to send credentials (cookies) with cross-origin requests.
+    include_credentials: bool,
+}
+
+impl FetchLoader {
+    /// Creates a new `FetchLoader`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to send credentials (cookies) with cross-origin requests.
+    ///
+    /// Credentials are sent only with same-origin requests by default.
+    pub fn include_credentials(self, include_credentials: bool) -> Self {
+        Self {
+            include_credentials,
+        }
+    }
+
+    /// Starts fetching the document.
+    fn fetch(&self, url: &str, options: &LoadDocumentOptions) -> FetchTask {
+        FetchTask::spawn(FetchRequest {
+            url: url.to_owned(),
+            accept: accept_header(options),
+            headers: options.headers().to_vec(),
+            include_credentials: self.include_credentials,
+            max_size: options.max_size(),
+        })
+    }
+}
+
+#[async_trait]
+impl LoadRemoteDocument for FetchLoader {
+    type Error = FetchLoadError;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        let mut fetched = self.fetch(iri.as_str(), &options).await?;
+        let document_url = fetched.url.clone();
+        // Follow the alternate JSON-LD document.
+        if !fetched.media_type.is_json() {
+            if let Some(alternate) = alternate_link(fetched.link_header(), &fetched.url) {
+                trace_event!(url = alternate.as_str(), "Following the alternate link");
+                fetched = self.fetch(&alternate, &options).await?;
+            }
+        }
+        let media_type = fetched.media_type.clone();
+
+        let (document, context_url) = if media_type.is_html() {
+            let fragment = iri.as_str().splitn(2, '#').nth(1);
+            let document = extract_json_ld(
+                &fetched.body,
+                fragment,
+                options.should_extract_all_scripts(),
+            )
+            .map_err(FetchLoadError::Html)?;
+            (document, None)
+        } else if media_type.is_json() {
+            let context_url = if media_type.essence == "application/ld+json" {
+                None
+            } else {
+                context_link(fetched.link_header(), &fetched.url)
+                    .map_err(FetchLoadError::MultipleContextLinkHeaders)?
+            };
+            (serde_json::from_str(&fetched.body)?, context_url)
+        } else {
+            return Err(FetchLoadError::UnsupportedContentType(media_type.essence));
+        };
+
+        Ok(Arc::new(
+            RemoteDocument::new(document_url, document)
+                .context_url(context_url)
+                .content_type(media_type.essence)
+                .profile(media_type.profile),
+        ))
+    }
+}
+
+/// Request to `fetch`.
+#[derive(Debug, Clone)]
+struct FetchRequest {
+    /// URL.
+    url: String,
+    /// Value of the `Accept` header.
+    accept: String,
+    /// Additional request headers.
+    headers: Vec<(String, String)>,
+    /// Whether to send credentials with cross-origin requests.
+    include_credentials: bool,
+    /// Maximum size of the body.
+    max_size: Option<u64>,
+}
+
+/// Successful response of `fetch`.
+#[derive(Debug, Clone)]
+struct Fetched {
+    /// URL of the response.
+    url: String,
+    /// Media type given by the `Content-Type` header.
+    media_type: MediaType,
+    /// Value of the `Link` header.
+    link: Option<String>,
+    /// Body.
+    body: String,
+}
+
+impl Fetched {
+    /// Returns the closure to look up the `Link` header, which is the only header kept.
+    fn link_header(&self) -> impl Fn(&str) -> Option<String> + '_ {
+        move |name: &str| {
+            if name.eq_ignore_ascii_case("Link") {
+                self.link.clone()
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Future which completes when `fetch` running in a local task completes.
+///
+/// JavaScript values are not `Send`, so they are kept in the local task and only the result is
+/// passed to this future, which is `Send` as `LoadRemoteDocument` requires.
+struct FetchTask {
+    /// Shared state.
+    state: Arc<Mutex<FetchState>>,
+}
+
+/// State of `FetchTask`.
+#[derive(Default)]
+struct FetchState {
+    /// Result of `fetch`.
+    result: Option<Result<Fetched, FetchLoadError>>,
+    /// Waker of the task waiting for the result.
+    waker: Option<Waker>,
+}
+
+impl FetchTask {
+    /// Spawns a local task running `fetch`.
+    fn spawn(request: FetchRequest) -> Self {
+        let state = Arc::new(Mutex::new(FetchState::default()));
+        let task_state = state.clone();
+        spawn_local(async move {
+            let result = fetch(request).await;
+            let mut state = task_state.lock().unwrap_or_else(|e| e.into_inner());
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Self { state }
+    }
+}
+
+impl Future for FetchTask {
+    type Output = Result<Fetched, FetchLoadError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Fetches the document.
+async fn fetch(request: FetchRequest) -> Result<Fetched, FetchLoadError> {
+    let mut init = RequestInit::new();
+    init.method("GET");
+    init.credentials(if request.include_credentials {
+        RequestCredentials::Include
+    } else {
+        RequestCredentials::SameOrigin
+    });
+    let js_request = Request::new_with_str_and_init(&request.url, &init).map_err(js_error)?;
+    let request_headers = js_request.headers();
+    request_headers
+        .set("Accept", &request.accept)
+        .map_err(js_error)?;
+    for (name, value) in &request.headers {
+        request_headers.set(name, value).map_err(js_error)?;
+    }
+
+    let global = js_sys::global();
+    let promise = if let Some(window) = global.dyn_ref::<Window>() {
+        window.fetch_with_request(&js_request)
+    } else if let Some(worker) = global.dyn_ref::<WorkerGlobalScope>() {
+        worker.fetch_with_request(&js_request)
+    } else {
+        return Err(FetchLoadError::Fetch("`fetch` is not available".to_owned()));
+    };
+    let response: Response = JsFuture::from(promise)
+        .await
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(js_error)?;
+    if !response.ok() {
+        return Err(FetchLoadError::Status(response.status()));
+    }
+
+    let response_headers = response.headers();
+    let header = |name: &str| response_headers.get(name).ok().and_then(|v| v);
+    check_content_length(header, request.max_size).map_err(FetchLoadError::TooLarge)?;
+    let media_type = MediaType::from_header(header);
+    let link = header("Link");
+    let body = JsFuture::from(response.text().map_err(js_error)?)
+        .await
+        .map_err(js_error)?
+        .as_string()
+        .unwrap_or_default();
+    check_body_size(body.len(), request.max_size).map_err(FetchLoadError::TooLarge)?;
+
+    Ok(Fetched {
+        url: response.url(),
+        media_type,
+        link,
+        body,
+    })
+}
+
+/// Converts the JavaScript exception into an error.
+fn js_error(e: JsValue) -> FetchLoadError {
+    FetchLoadError::Fetch(e.as_string().unwrap_or_else(|| format!("{:?}", e)))
+}
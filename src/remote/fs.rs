@@ -0,0 +1,308 @@
+//! Remote document loader using local filesystem.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+
+use crate::{
+    html::extract_json_ld,
+    remote::{
+        data_uri::percent_decode, unblock::unblock, LoadDocumentOptions, LoadRemoteDocument,
+        RemoteDocument, SizeLimitExceeded,
+    },
+};
+
+/// Error on loading a remote document by `FsLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum FsLoadError {
+    /// No directory is mapped for the IRI.
+    #[error("No directory is mapped for the IRI <{0}>")]
+    NotMapped(String),
+    /// The IRI cannot be mapped to a file path safely.
+    #[error("The IRI <{0}> cannot be mapped to a file path")]
+    InvalidPath(String),
+    /// Failed to read the file.
+    #[error("Failed to read {path:?}: {source}")]
+    Io {
+        /// File path.
+        path: PathBuf,
+        /// I/O error.
+        source: io::Error,
+    },
+    /// The document is not valid JSON.
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Failed to extract JSON-LD from the HTML document.
+    #[error("Failed to extract JSON-LD from HTML: {0}")]
+    Html(#[source] crate::Error),
+    /// The file exceeds the size limit.
+    #[error("{0}")]
+    TooLarge(#[source] SizeLimitExceeded),
+}
+
+/// Remote document loader using local filesystem.
+///
+/// IRIs are mapped to local files by the registered IRI prefixes and directories.
+/// For example, if `https://example.com/contexts/` is mapped to `/srv/contexts`,
+/// `https://example.com/contexts/v1.jsonld` is loaded from `/srv/contexts/v1.jsonld`.
+/// If the IRI matches multiple prefixes, the longest one is used.
+///
+/// `file:` IRIs are also loaded if the file is inside a directory allowed by
+/// [`allow_file_root`](#method.allow_file_root).
+/// Symbolic links in such files are resolved, and files outside of the allowed directories are
+/// refused.
+///
+/// Files with `.html` or `.htm` extensions are loaded as HTML documents, and others are loaded as
+/// JSON documents.
+///
+/// Files are read on another thread, so that the executor is not blocked.
+#[derive(Debug, Clone, Default)]
+pub struct FsLoader {
+    /// Pairs of IRI prefixes and directories.
+    mappings: Vec<(String, PathBuf)>,
+    /// Directories allowed for `file:` IRIs.
+    file_roots: Vec<PathBuf>,
+}
+
+impl FsLoader {
+    /// Creates a new `FsLoader` without mappings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps the IRI prefix to the given directory.
+    pub fn map(mut self, prefix: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        self.mappings.push((prefix.into(), dir.into()));
+        self
+    }
+
+    /// Allows `file:` IRIs for files inside the given directory.
+    ///
+    /// `file:` IRIs are refused unless at least one directory is allowed.
+    pub fn allow_file_root(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.file_roots.push(dir.into());
+        self
+    }
+
+    /// Returns the path of the file for the given `file:` IRI, and the allowed directory
+    /// containing it.
+    ///
+    /// Symbolic links are not resolved here.
+    fn resolve_file_path(&self, iri: &str) -> Result<(PathBuf, &Path), FsLoadError> {
+        let invalid = || FsLoadError::InvalidPath(iri.to_owned());
+        if !is_file_iri(iri) {
+            return Err(FsLoadError::NotMapped(iri.to_owned()));
+        }
+        let target = iri.split(|c| c == '#' || c == '?').next().unwrap_or(iri);
+        let rest = &target["file:".len()..];
+        let abs_path = if rest.starts_with("//") {
+            let authority_end = rest[2..].find('/').map_or(rest.len(), |pos| pos + 2);
+            let host = &rest[2..authority_end];
+            if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+                return Err(invalid());
+            }
+            &rest[authority_end..]
+        } else {
+            rest
+        };
+        if !abs_path.starts_with('/') {
+            return Err(invalid());
+        }
+        let mut path = PathBuf::from(MAIN_SEPARATOR.to_string());
+        for (i, segment) in abs_path[1..].split('/').enumerate() {
+            let segment = percent_decode(segment)
+                .and_then(|v| String::from_utf8(v).ok())
+                .ok_or_else(invalid)?;
+            match segment.as_str() {
+                "" | "." => {}
+                ".." => return Err(invalid()),
+                segment if segment.contains(|c: char| c == '/' || c == '\\' || c == '\0') => {
+                    return Err(invalid())
+                }
+                // Drive letter such as `file:///C:/path`.
+                segment if cfg!(windows) && i == 0 && segment.ends_with(':') => {
+                    path = PathBuf::from(format!("{}{}", segment, MAIN_SEPARATOR));
+                }
+                segment if Path::new(segment).is_absolute() => return Err(invalid()),
+                segment => path.push(segment),
+            }
+        }
+        let root = self
+            .file_roots
+            .iter()
+            .map(PathBuf::as_path)
+            .find(|root| path.starts_with(root))
+            .ok_or_else(|| FsLoadError::NotMapped(iri.to_owned()))?;
+        Ok((path, root))
+    }
+
+    /// Returns the path of the file for the given IRI.
+    fn resolve_path(&self, iri: &str) -> Result<PathBuf, FsLoadError> {
+        // Fragment and query are not a part of the file path.
+        let iri_without_fragment = iri.split('#').next().unwrap_or(iri);
+        let target = iri_without_fragment
+            .split('?')
+            .next()
+            .unwrap_or(iri_without_fragment);
+        let (prefix, dir) = self
+            .mappings
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .ok_or_else(|| FsLoadError::NotMapped(iri.to_owned()))?;
+        let mut path = dir.clone();
+        for segment in target[prefix.len()..].split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return Err(FsLoadError::InvalidPath(iri.to_owned())),
+                segment if segment.contains('\\') || Path::new(segment).is_absolute() => {
+                    return Err(FsLoadError::InvalidPath(iri.to_owned()))
+                }
+                segment => path.push(segment),
+            }
+        }
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl LoadRemoteDocument for FsLoader {
+    type Error = FsLoadError;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        let (path, root) = if is_file_iri(iri.as_str()) {
+            let (path, root) = self.resolve_file_path(iri.as_str())?;
+            (path, Some(root.to_owned()))
+        } else {
+            (self.resolve_path(iri.as_str())?, None)
+        };
+        let iri_owned = iri.as_str().to_owned();
+        let max_size = options.max_size();
+        let spawn_error_path = path.clone();
+        // File system operations are blocking, so they are run on another thread.
+        let (path, text) = unblock(move || read_file(&iri_owned, path, root, max_size))
+            .await
+            .map_err(|source| FsLoadError::Io {
+                path: spawn_error_path,
+                source,
+            })??;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let (document, content_type) = match extension.as_ref().map(String::as_str) {
+            Some("html") | Some("htm") => {
+                let fragment = iri.as_str().splitn(2, '#').nth(1);
+                let document =
+                    extract_json_ld(&text, fragment, options.should_extract_all_scripts())
+                        .map_err(FsLoadError::Html)?;
+                (document, "text/html")
+            }
+            Some("json") => (serde_json::from_str(&text)?, "application/json"),
+            _ => (serde_json::from_str(&text)?, "application/ld+json"),
+        };
+
+        Ok(Arc::new(
+            RemoteDocument::new(iri.as_str(), document).content_type(content_type.to_owned()),
+        ))
+    }
+}
+
+/// Checks whether the IRI has `file` scheme.
+fn is_file_iri(iri: &str) -> bool {
+    iri.len() >= 5 && iri[..5].eq_ignore_ascii_case("file:")
+}
+
+/// Reads the file at the path, and returns the path and the content.
+///
+/// If the root directory is given, symbolic links in the path are resolved and the file is
+/// confined to the root, and the resolved path is returned.
+fn read_file(
+    iri: &str,
+    path: PathBuf,
+    root: Option<PathBuf>,
+    max_size: Option<u64>,
+) -> Result<(PathBuf, String), FsLoadError> {
+    let path = match root {
+        Some(root) => confine(iri, &path, &root)?,
+        None => path,
+    };
+    let io_error = |source| FsLoadError::Io {
+        path: path.clone(),
+        source,
+    };
+    if let Some(max_size) = max_size {
+        if fs::metadata(&path).map_err(io_error)?.len() > max_size {
+            return Err(FsLoadError::TooLarge(SizeLimitExceeded::new(max_size)));
+        }
+    }
+    let text = fs::read_to_string(&path).map_err(io_error)?;
+    Ok((path, text))
+}
+
+/// Resolves symbolic links in the path, and checks the file is inside the root directory.
+fn confine(iri: &str, path: &Path, root: &Path) -> Result<PathBuf, FsLoadError> {
+    let canonicalize = |path: &Path| {
+        path.canonicalize().map_err(|source| FsLoadError::Io {
+            path: path.to_owned(),
+            source,
+        })
+    };
+    let real_path = canonicalize(path)?;
+    if real_path.starts_with(canonicalize(root)?) {
+        Ok(real_path)
+    } else {
+        Err(FsLoadError::InvalidPath(iri.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_path() {
+        let loader = FsLoader::new()
+            .map("https://example.com/", "/srv/root")
+            .map("https://example.com/contexts/", "/srv/contexts");
+        let resolve = |iri: &str| loader.resolve_path(iri).ok();
+        assert_eq!(
+            resolve("https://example.com/contexts/v1.jsonld#frag"),
+            Some(PathBuf::from("/srv/contexts/v1.jsonld"))
+        );
+        assert_eq!(
+            resolve("https://example.com/a/b.json?q=1"),
+            Some(PathBuf::from("/srv/root/a/b.json"))
+        );
+        assert_eq!(resolve("https://example.com/a/../../etc/passwd"), None);
+        assert_eq!(resolve("https://example.org/a.jsonld"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_file_path() {
+        let loader = FsLoader::new().allow_file_root("/srv/data");
+        let resolve = |iri: &str| loader.resolve_file_path(iri).ok().map(|(path, _)| path);
+        assert_eq!(
+            resolve("file:///srv/data/ctx%20v1.jsonld#frag"),
+            Some(PathBuf::from("/srv/data/ctx v1.jsonld"))
+        );
+        assert_eq!(
+            resolve("file://localhost/srv/data/a.json"),
+            Some(PathBuf::from("/srv/data/a.json"))
+        );
+        assert_eq!(resolve("file:///srv/data/../../etc/passwd"), None);
+        assert_eq!(resolve("file:///srv/data/a%2F..%2F..%2Fetc"), None);
+        assert_eq!(resolve("file:///srv/database.json"), None);
+        assert_eq!(resolve("file://example.com/srv/data/a.json"), None);
+    }
+}
@@ -0,0 +1,777 @@
+//! Remote document loader wrapper with access policy.
+
+use std::{
+    collections::VecDeque,
+    error, fmt,
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs},
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+
+use crate::remote::{LoadDocumentOptions, LoadRemoteDocument, RedirectPolicy, RemoteDocument};
+
+/// Default maximum number of threads resolving host names for an access policy.
+const DEFAULT_MAX_RESOLVER_THREADS: usize = 4;
+
+/// Duration for idle resolver threads to wait for new host names before exiting.
+const RESOLVER_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Error on loading a remote document by `GuardedLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum GuardedLoadError<E: error::Error + 'static> {
+    /// The inner loader failed.
+    #[error("{0}")]
+    Load(#[source] E),
+    /// The IRI is not permitted by the access policy.
+    #[error("{0}")]
+    Denied(#[source] AccessDenied),
+}
+
+/// Error on refusing an IRI by `AccessPolicy`.
+#[derive(Debug, thiserror::Error)]
+pub enum AccessDenied {
+    /// The scheme of the IRI is not allowed.
+    #[error("The scheme of <{0}> is not allowed")]
+    SchemeNotAllowed(String),
+    /// The host of the IRI is not allowed, or the IRI has no host.
+    #[error("The host of <{0}> is not allowed")]
+    HostNotAllowed(String),
+    /// The host of the IRI resolves to a private, loopback, or otherwise non-public address.
+    #[error("The host of <{iri}> resolves to a non-public address {addr}")]
+    NonPublicAddress {
+        /// The requested IRI.
+        iri: String,
+        /// The resolved address.
+        addr: IpAddr,
+    },
+    /// Failed to resolve the host.
+    #[error("Failed to resolve the host of <{iri}>: {source}")]
+    Resolve {
+        /// The requested IRI.
+        iri: String,
+        /// I/O error.
+        source: io::Error,
+    },
+}
+
+/// Policy of IRIs permitted to be loaded.
+///
+/// * Only `https` scheme is allowed by default.
+/// * If the allowed hosts are given, only they (and their subdomains) are allowed.
+/// * The denied hosts (and their subdomains) are always refused.
+/// * If non-public addresses are denied, the host is resolved and refused if any of the
+///   addresses is loopback, private, link-local, or otherwise non-public.
+///
+/// This is also a `RedirectPolicy`, so that loaders following redirects by themselves can check
+/// every hop by the same policy.
+///
+/// Host names are resolved on a bounded pool of threads, which is shared by the clones of the
+/// policy.
+#[derive(Debug, Clone)]
+pub struct AccessPolicy {
+    /// Allowed schemes (in lower case).
+    schemes: Vec<String>,
+    /// Allowed hosts (in lower case), or `None` if any hosts are allowed.
+    allowed_hosts: Option<Vec<String>>,
+    /// Denied hosts (in lower case).
+    denied_hosts: Vec<String>,
+    /// Whether to refuse hosts resolving to non-public addresses.
+    deny_non_public: bool,
+    /// Resolver of host names.
+    resolver: Resolver,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        Self {
+            schemes: vec!["https".to_owned()],
+            allowed_hosts: None,
+            denied_hosts: Vec::new(),
+            deny_non_public: false,
+            resolver: Resolver::new(DEFAULT_MAX_RESOLVER_THREADS),
+        }
+    }
+}
+
+impl AccessPolicy {
+    /// Creates a new `AccessPolicy` allowing only `https` IRIs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the allowed schemes.
+    pub fn schemes<I, S>(self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            schemes: schemes
+                .into_iter()
+                .map(|s| s.as_ref().to_ascii_lowercase())
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Allows the host and its subdomains.
+    ///
+    /// Once any host is allowed, hosts not allowed explicitly are refused.
+    pub fn allow_host(mut self, host: &str) -> Self {
+        self.allowed_hosts
+            .get_or_insert_with(Vec::new)
+            .push(host.to_ascii_lowercase());
+        self
+    }
+
+    /// Denies the host and its subdomains.
+    pub fn deny_host(mut self, host: &str) -> Self {
+        self.denied_hosts.push(host.to_ascii_lowercase());
+        self
+    }
+
+    /// Sets whether to refuse hosts resolving to loopback, private, or other non-public addresses.
+    pub fn deny_non_public_addresses(self, deny_non_public: bool) -> Self {
+        Self {
+            deny_non_public,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of threads resolving host names.
+    ///
+    /// Host names are resolved on a pool of threads so that the executor is not blocked, and
+    /// further names wait in a queue once the limit is reached. Threads exit after being idle
+    /// for a while.
+    /// `0` is treated as `1`. The default is 4.
+    pub fn max_resolver_threads(self, max_threads: usize) -> Self {
+        Self {
+            resolver: Resolver::new(max_threads),
+            ..self
+        }
+    }
+
+    /// Checks whether the IRI is permitted.
+    ///
+    /// Host names are resolved on another thread, so that the executor is not blocked.
+    pub async fn check(&self, iri: &str) -> Result<(), AccessDenied> {
+        let target = Target::parse(iri);
+        let scheme = target.scheme.to_ascii_lowercase();
+        if !self.schemes.iter().any(|s| *s == scheme) {
+            return Err(AccessDenied::SchemeNotAllowed(iri.to_owned()));
+        }
+        let host = match target.host {
+            Some(host) if !host.is_empty() => host,
+            _ => return Err(AccessDenied::HostNotAllowed(iri.to_owned())),
+        };
+        let is_allowed = self
+            .allowed_hosts
+            .as_ref()
+            .map_or(true, |hosts| hosts.iter().any(|h| host_matches(&host, h)));
+        if !is_allowed || self.denied_hosts.iter().any(|h| host_matches(&host, h)) {
+            return Err(AccessDenied::HostNotAllowed(iri.to_owned()));
+        }
+        if !self.deny_non_public {
+            return Ok(());
+        }
+        let addrs = match host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            Ok(addr) => vec![addr],
+            Err(_) => {
+                let port = target
+                    .port
+                    .unwrap_or(if scheme == "http" { 80 } else { 443 });
+                self.resolver
+                    .resolve(host, port)
+                    .await
+                    .map_err(|source| AccessDenied::Resolve {
+                        iri: iri.to_owned(),
+                        source,
+                    })?
+            }
+        };
+        match addrs.into_iter().find(|addr| !is_public(*addr)) {
+            Some(addr) => Err(AccessDenied::NonPublicAddress {
+                iri: iri.to_owned(),
+                addr,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl RedirectPolicy for AccessPolicy {
+    async fn check_redirect(
+        &self,
+        _from: &str,
+        to: &str,
+    ) -> Result<(), Box<dyn error::Error + Send + Sync + 'static>> {
+        self.check(to).await.map_err(Into::into)
+    }
+}
+
+/// Remote document loader wrapper which refuses IRIs not permitted by the access policy.
+///
+/// This is intended to guard against server-side request forgery when processing untrusted
+/// documents with arbitrary `@context` URLs. See [`AccessPolicy`](struct.AccessPolicy.html) for
+/// the rules.
+///
+/// Redirects are followed by the inner loader, so the loader should check them by the same
+/// policy:
+///
+/// ```no_run
+/// # #[cfg(feature = "reqwest")]
+/// # fn main() {
+/// use json_ld::remote::{AccessPolicy, GuardedLoader, HttpLoader};
+///
+/// let policy = AccessPolicy::new().deny_non_public_addresses(true);
+/// let loader = GuardedLoader::with_policy(
+///     HttpLoader::new().redirect_policy(policy.clone()),
+///     policy,
+/// );
+/// # }
+/// # #[cfg(not(feature = "reqwest"))]
+/// # fn main() {}
+/// ```
+///
+/// As a last resort, the document URL of the loaded document (the final URL after redirects) is
+/// also checked, and the document is refused if it is not permitted. This prevents the document
+/// from being used, but not the redirected request from being sent.
+///
+/// Note that the inner loader resolves the host again, so the address check does not prevent DNS
+/// rebinding completely.
+#[derive(Debug, Clone)]
+pub struct GuardedLoader<L> {
+    /// Inner loader.
+    loader: L,
+    /// Access policy.
+    policy: AccessPolicy,
+}
+
+impl<L> GuardedLoader<L> {
+    /// Creates a new `GuardedLoader` allowing only `https` IRIs.
+    pub fn new(loader: L) -> Self {
+        Self::with_policy(loader, AccessPolicy::new())
+    }
+
+    /// Creates a new `GuardedLoader` with the given access policy.
+    pub fn with_policy(loader: L, policy: AccessPolicy) -> Self {
+        Self { loader, policy }
+    }
+
+    /// Sets the allowed schemes.
+    pub fn schemes<I, S>(self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            policy: self.policy.schemes(schemes),
+            ..self
+        }
+    }
+
+    /// Allows the host and its subdomains.
+    ///
+    /// Once any host is allowed, hosts not allowed explicitly are refused.
+    pub fn allow_host(self, host: &str) -> Self {
+        Self {
+            policy: self.policy.allow_host(host),
+            ..self
+        }
+    }
+
+    /// Denies the host and its subdomains.
+    pub fn deny_host(self, host: &str) -> Self {
+        Self {
+            policy: self.policy.deny_host(host),
+            ..self
+        }
+    }
+
+    /// Sets whether to refuse hosts resolving to loopback, private, or other non-public addresses.
+    pub fn deny_non_public_addresses(self, deny_non_public: bool) -> Self {
+        Self {
+            policy: self.policy.deny_non_public_addresses(deny_non_public),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of threads resolving host names.
+    pub fn max_resolver_threads(self, max_threads: usize) -> Self {
+        Self {
+            policy: self.policy.max_resolver_threads(max_threads),
+            ..self
+        }
+    }
+
+    /// Returns the access policy.
+    pub fn policy(&self) -> &AccessPolicy {
+        &self.policy
+    }
+
+    /// Returns the inner loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument> LoadRemoteDocument for GuardedLoader<L> {
+    type Error = GuardedLoadError<L::Error>;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        self.policy
+            .check(iri.as_str())
+            .await
+            .map_err(GuardedLoadError::Denied)?;
+        let document = self
+            .loader
+            .load(iri, options)
+            .await
+            .map_err(GuardedLoadError::Load)?;
+        // The inner loader may have followed redirects without checking them.
+        if document.get_document_url() != iri.as_str() {
+            self.policy
+                .check(document.get_document_url())
+                .await
+                .map_err(GuardedLoadError::Denied)?;
+        }
+        Ok(document)
+    }
+}
+
+/// Pool of threads resolving host names.
+///
+/// Clones share the threads and the queue of host names.
+#[derive(Clone)]
+struct Resolver {
+    /// Shared state.
+    inner: Arc<ResolverInner>,
+}
+
+/// Shared state of `Resolver`.
+struct ResolverInner {
+    /// Maximum number of threads.
+    max_threads: usize,
+    /// Queue of host names and the threads.
+    queue: Mutex<ResolverQueue>,
+    /// Condition variable notified when a host name is queued.
+    queued: Condvar,
+}
+
+/// Queue of host names to be resolved, and the threads resolving them.
+#[derive(Default)]
+struct ResolverQueue {
+    /// Host names waiting to be resolved.
+    jobs: VecDeque<ResolveJob>,
+    /// Number of the running threads.
+    threads: usize,
+    /// Number of the threads waiting for host names.
+    idle: usize,
+}
+
+/// Host name to be resolved.
+struct ResolveJob {
+    /// Host name.
+    host: String,
+    /// Port.
+    port: u16,
+    /// State of the future waiting for the resolution.
+    state: Arc<Mutex<ResolveState>>,
+}
+
+impl Resolver {
+    /// Creates a new `Resolver` with the given maximum number of threads.
+    ///
+    /// `0` is treated as `1`.
+    fn new(max_threads: usize) -> Self {
+        Self {
+            inner: Arc::new(ResolverInner {
+                max_threads: max_threads.max(1),
+                queue: Mutex::new(ResolverQueue::default()),
+                queued: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Starts resolving the host name, and returns the future of the result.
+    fn resolve(&self, host: String, port: u16) -> Resolve {
+        let state = Arc::new(Mutex::new(ResolveState::default()));
+        let mut queue = self.inner.lock_queue();
+        queue.jobs.push_back(ResolveJob {
+            host,
+            port,
+            state: state.clone(),
+        });
+        if queue.idle > 0 || queue.threads >= self.inner.max_threads {
+            self.inner.queued.notify_one();
+            return Resolve { state };
+        }
+        let inner = self.inner.clone();
+        match thread::Builder::new()
+            .name("json-ld-resolver".to_owned())
+            .spawn(move || inner.run())
+        {
+            Ok(_) => queue.threads += 1,
+            // Other threads will resolve the name.
+            Err(_) if queue.threads > 0 => {}
+            Err(e) => {
+                queue.jobs.pop_back();
+                state.lock().unwrap_or_else(|e| e.into_inner()).result = Some(Err(e));
+            }
+        }
+        Resolve { state }
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resolver")
+            .field("max_threads", &self.inner.max_threads)
+            .finish()
+    }
+}
+
+impl ResolverInner {
+    /// Locks the queue.
+    fn lock_queue(&self) -> MutexGuard<'_, ResolverQueue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Resolves the queued host names until the thread is idle for a while.
+    fn run(&self) {
+        let mut queue = self.lock_queue();
+        loop {
+            if let Some(job) = queue.jobs.pop_front() {
+                drop(queue);
+                job.run();
+                queue = self.lock_queue();
+                continue;
+            }
+            queue.idle += 1;
+            let (new_queue, timeout) = self
+                .queued
+                .wait_timeout(queue, RESOLVER_IDLE_TIMEOUT)
+                .unwrap_or_else(|e| e.into_inner());
+            queue = new_queue;
+            queue.idle -= 1;
+            if timeout.timed_out() && queue.jobs.is_empty() {
+                queue.threads -= 1;
+                return;
+            }
+        }
+    }
+}
+
+impl ResolveJob {
+    /// Resolves the host name, and wakes the future waiting for it.
+    fn run(self) {
+        let result = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .map(|addrs| addrs.map(|addr| addr.ip()).collect());
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Future which resolves the host name.
+///
+/// The name is resolved by `Resolver` on another thread, so that it works with any async runtime
+/// without blocking the executor.
+struct Resolve {
+    /// Shared state.
+    state: Arc<Mutex<ResolveState>>,
+}
+
+/// State of `Resolve`.
+#[derive(Default)]
+struct ResolveState {
+    /// Resolved addresses, or `None` if not yet resolved.
+    result: Option<io::Result<Vec<IpAddr>>>,
+    /// Waker of the task waiting for the resolution.
+    waker: Option<Waker>,
+}
+
+impl Future for Resolve {
+    type Output = io::Result<Vec<IpAddr>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Scheme, host, and port of the IRI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Target<'a> {
+    /// Scheme.
+    scheme: &'a str,
+    /// Host in lower case (IPv6 addresses are enclosed with brackets).
+    host: Option<String>,
+    /// Port.
+    port: Option<u16>,
+}
+
+impl<'a> Target<'a> {
+    /// Parses the IRI.
+    fn parse(iri: &'a str) -> Self {
+        let mut scheme_rest = iri.splitn(2, ':');
+        let scheme = scheme_rest.next().unwrap_or("");
+        let rest = scheme_rest.next().unwrap_or("");
+        if !rest.starts_with("//") {
+            return Self {
+                scheme,
+                host: None,
+                port: None,
+            };
+        }
+        let authority = rest[2..]
+            .split(|c| c == '/' || c == '?' || c == '#')
+            .next()
+            .unwrap_or("");
+        let host_port = authority.rsplitn(2, '@').next().unwrap_or("");
+        let (host, port) = if host_port.starts_with('[') {
+            let end = host_port.find(']').map_or(host_port.len(), |pos| pos + 1);
+            (&host_port[..end], host_port[end..].trim_start_matches(':'))
+        } else {
+            let mut parts = host_port.splitn(2, ':');
+            (parts.next().unwrap_or(""), parts.next().unwrap_or(""))
+        };
+        Self {
+            scheme,
+            host: Some(host.trim_end_matches('.').to_ascii_lowercase()),
+            port: port.parse().ok(),
+        }
+    }
+}
+
+/// Checks whether the host is the given domain or its subdomain.
+fn host_matches(host: &str, domain: &str) -> bool {
+    host == domain || (host.ends_with(domain) && host[..(host.len() - domain.len())].ends_with('.'))
+}
+
+/// Checks whether the address is a public address.
+fn is_public(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => is_public_v4(addr),
+        IpAddr::V6(addr) => is_public_v6(addr),
+    }
+}
+
+/// Checks whether the IPv4 address is a public address.
+fn is_public_v4(addr: Ipv4Addr) -> bool {
+    let octets = addr.octets();
+    !(addr.is_loopback()
+        || addr.is_private()
+        || addr.is_link_local()
+        || addr.is_unspecified()
+        || addr.is_broadcast()
+        || addr.is_multicast()
+        // `0.0.0.0/8`.
+        || octets[0] == 0
+        // Shared address space `100.64.0.0/10`.
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        // Reserved `240.0.0.0/4`.
+        || octets[0] >= 240)
+}
+
+/// Checks whether the IPv6 address is a public address.
+///
+/// Addresses embedding IPv4 addresses are public only if the embedded addresses are public.
+fn is_public_v6(addr: Ipv6Addr) -> bool {
+    let segments = addr.segments();
+    if addr.is_loopback() || addr.is_unspecified() {
+        return false;
+    }
+    // IPv4-compatible addresses `::/96` and IPv4-mapped addresses `::ffff:0:0/96`.
+    if segments[..5].iter().all(|&s| s == 0) && (segments[5] == 0 || segments[5] == 0xffff) {
+        return is_public_v4(ipv4_from_segments(segments[6], segments[7]));
+    }
+    // NAT64 well-known prefix `64:ff9b::/96`.
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        return is_public_v4(ipv4_from_segments(segments[6], segments[7]));
+    }
+    // 6to4 `2002::/16`.
+    if segments[0] == 0x2002 {
+        return is_public_v4(ipv4_from_segments(segments[1], segments[2]));
+    }
+    // Teredo `2001::/32`, with the obfuscated client address.
+    if segments[..2] == [0x2001, 0] {
+        return is_public_v4(ipv4_from_segments(segments[2], segments[3]))
+            && is_public_v4(ipv4_from_segments(!segments[6], !segments[7]));
+    }
+    !(addr.is_multicast()
+        // NAT64 local-use prefix `64:ff9b:1::/48`.
+        || segments[..3] == [0x64, 0xff9b, 1]
+        // Documentation `2001:db8::/32`.
+        || segments[..2] == [0x2001, 0xdb8]
+        // Unique local `fc00::/7`.
+        || (segments[0] & 0xfe00) == 0xfc00
+        // Link-local `fe80::/10`.
+        || (segments[0] & 0xffc0) == 0xfe80
+        // Deprecated site-local `fec0::/10`.
+        || (segments[0] & 0xffc0) == 0xfec0)
+}
+
+/// Creates an IPv4 address from the two IPv6 address segments.
+fn ipv4_from_segments(high: u16, low: u16) -> Ipv4Addr {
+    let [a, b] = high.to_be_bytes();
+    let [c, d] = low.to_be_bytes();
+    Ipv4Addr::new(a, b, c, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::block_on;
+
+    use super::*;
+
+    /// Loader which pretends to have followed a redirect to the given URL.
+    #[derive(Debug)]
+    struct RedirectedLoader(&'static str);
+
+    #[async_trait]
+    impl LoadRemoteDocument for RedirectedLoader {
+        type Error = io::Error;
+
+        async fn load(
+            &self,
+            _iri: &IriStr,
+            _options: LoadDocumentOptions,
+        ) -> Result<Arc<RemoteDocument>, Self::Error> {
+            Ok(Arc::new(RemoteDocument::new(
+                self.0,
+                serde_json::json!({ "@context": {} }),
+            )))
+        }
+    }
+
+    #[test]
+    fn parse_target() {
+        let target = Target::parse("https://user@Example.COM:8443/a?b#c");
+        assert_eq!(target.scheme, "https");
+        assert_eq!(
+            target.host.as_ref().map(String::as_str),
+            Some("example.com")
+        );
+        assert_eq!(target.port, Some(8443));
+        let target = Target::parse("http://[::1]/");
+        assert_eq!(target.host.as_ref().map(String::as_str), Some("[::1]"));
+        assert_eq!(target.port, None);
+        assert_eq!(Target::parse("urn:example:a").host, None);
+    }
+
+    #[test]
+    fn host_match() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("www.example.com", "example.com"));
+        assert!(!host_matches("badexample.com", "example.com"));
+    }
+
+    #[test]
+    fn public_address() {
+        let is_public_str = |s: &str| is_public(s.parse().expect("valid address"));
+        assert!(is_public_str("93.184.216.34"));
+        assert!(is_public_str("2606:2800:220:1:248:1893:25c8:1946"));
+        assert!(!is_public_str("127.0.0.1"));
+        assert!(!is_public_str("10.1.2.3"));
+        assert!(!is_public_str("169.254.169.254"));
+        assert!(!is_public_str("100.64.0.1"));
+        assert!(!is_public_str("::1"));
+        assert!(!is_public_str("fd00::1"));
+        assert!(!is_public_str("::ffff:192.168.0.1"));
+        assert!(is_public_str("::ffff:93.184.216.34"));
+        // IPv4-compatible.
+        assert!(!is_public_str("::7f00:1"));
+        assert!(!is_public_str("::10.0.0.1"));
+        assert!(is_public_str("::93.184.216.34"));
+        // NAT64.
+        assert!(!is_public_str("64:ff9b::7f00:1"));
+        assert!(!is_public_str("64:ff9b::169.254.169.254"));
+        assert!(is_public_str("64:ff9b::93.184.216.34"));
+        assert!(!is_public_str("64:ff9b:1::5db8:d822"));
+        // 6to4.
+        assert!(!is_public_str("2002:c0a8:1::1"));
+        assert!(!is_public_str("2002:7f00:1::"));
+        assert!(is_public_str("2002:5db8:d822::1"));
+        // Teredo, with the client address `10.0.0.1` obfuscated.
+        assert!(!is_public_str("2001:0:5db8:d822::f5ff:fffe"));
+        // Site-local.
+        assert!(!is_public_str("fec0::1"));
+        assert!(!is_public_str("2001:db8::1"));
+    }
+
+    #[test]
+    fn bounded_resolver() {
+        let resolver = Resolver::new(2);
+        let futures = (0..8)
+            .map(|_| resolver.resolve("localhost".to_owned(), 443))
+            .collect::<Vec<_>>();
+        assert!(resolver.inner.lock_queue().threads <= 2);
+        // The names are resolved even if there are more names than threads.
+        for future in futures {
+            let _ = block_on(future);
+        }
+        assert!(resolver.inner.lock_queue().threads <= 2);
+    }
+
+    #[test]
+    fn redirect_to_non_public_address() {
+        let policy = AccessPolicy::new()
+            .schemes(&["http", "https"])
+            .deny_non_public_addresses(true);
+        let from = "https://93.184.216.34/context.jsonld";
+        for to in &["https://10.0.0.1/context.jsonld", "http://169.254.169.254/"] {
+            match block_on(policy.check_redirect(from, to)) {
+                Err(e) => assert!(e.is::<AccessDenied>()),
+                Ok(()) => panic!("redirect to <{}> should be refused", to),
+            }
+        }
+        assert!(block_on(policy.check_redirect(from, "https://93.184.216.35/")).is_ok());
+        assert!(
+            block_on(AccessPolicy::new().check_redirect(from, "http://93.184.216.35/")).is_err()
+        );
+    }
+
+    #[test]
+    fn redirected_document() {
+        let iri = IriStr::new("https://93.184.216.34/context.jsonld").expect("valid IRI");
+        let loader = GuardedLoader::new(RedirectedLoader("https://10.0.0.1/context.jsonld"))
+            .deny_non_public_addresses(true);
+        match block_on(loader.load(iri, LoadDocumentOptions::new())) {
+            Err(GuardedLoadError::Denied(AccessDenied::NonPublicAddress { iri, .. })) => {
+                assert_eq!(iri, "https://10.0.0.1/context.jsonld")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        let loader = GuardedLoader::new(RedirectedLoader("https://93.184.216.35/context.jsonld"))
+            .deny_non_public_addresses(true);
+        assert!(block_on(loader.load(iri, LoadDocumentOptions::new())).is_ok());
+    }
+}
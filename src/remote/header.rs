@@ -0,0 +1,348 @@
+//! HTTP header values for loading remote documents.
+//!
+//! Helpers here receive a closure to look up response headers, so that they can be shared by the
+//! loaders using different HTTP clients.
+//! The closure returns the value of the header with the given name (case-insensitive), or `None`
+//! if the header is absent.
+//! Values of multiple header fields with the same name should be joined by commas.
+
+#[cfg(all(feature = "tower", not(feature = "reqwest")))]
+use http::header::HeaderMap;
+use iri_string::types::{IriReferenceStr, IriStr};
+// `reqwest` re-exports `HeaderMap` of `http` crate, so this is the same type as above.
+#[cfg(feature = "reqwest")]
+use reqwest::header::HeaderMap;
+
+use crate::remote::{LoadDocumentOptions, MultipleContextLinkHeaders, SizeLimitExceeded};
+
+/// Link relation of the JSON-LD context.
+pub(crate) const CONTEXT_LINK_REL: &str = "http://www.w3.org/ns/json-ld#context";
+
+/// Returns the value of the HTTP `Accept` header.
+pub(crate) fn accept_header(options: &LoadDocumentOptions) -> String {
+    let profiles = options
+        .request_profile()
+        .iter()
+        .map(|profile| profile.uri())
+        .collect::<Vec<_>>();
+    let json_ld = if profiles.is_empty() {
+        "application/ld+json".to_owned()
+    } else {
+        format!("application/ld+json;profile=\"{}\"", profiles.join(" "))
+    };
+    format!(
+        "{}, application/json;q=0.9, text/html;q=0.8, */*;q=0.1",
+        json_ld
+    )
+}
+
+/// Media type of the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MediaType {
+    /// Type and subtype in lower case, without parameters.
+    pub(crate) essence: String,
+    /// `profile` parameter.
+    pub(crate) profile: Option<String>,
+}
+
+impl MediaType {
+    /// Returns the media type given by the `Content-Type` header.
+    pub(crate) fn from_header(header: impl Fn(&str) -> Option<String>) -> Self {
+        Self::parse(header("Content-Type").as_ref().map_or("", String::as_str))
+    }
+
+    /// Parses the media type.
+    pub(crate) fn parse(s: &str) -> Self {
+        let mut parts = s.split(';');
+        let essence = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let profile = parts.find_map(|param| {
+            let mut kv = param.splitn(2, '=');
+            let key = kv.next()?.trim();
+            let value = kv.next()?.trim();
+            if key.eq_ignore_ascii_case("profile") {
+                Some(value.trim_matches('"').to_owned())
+            } else {
+                None
+            }
+        });
+        Self { essence, profile }
+    }
+
+    /// Checks whether the media type is JSON (`application/json` or `*/*+json`).
+    pub(crate) fn is_json(&self) -> bool {
+        self.essence == "application/json" || self.essence.ends_with("+json")
+    }
+
+    /// Checks whether the media type is HTML.
+    pub(crate) fn is_html(&self) -> bool {
+        self.essence == "text/html" || self.essence == "application/xhtml+xml"
+    }
+}
+
+/// Link in the HTTP `Link` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Link {
+    /// Target URL (possibly relative).
+    pub(crate) target: String,
+    /// `rel` parameter.
+    pub(crate) rel: String,
+    /// `type` parameter.
+    pub(crate) ty: Option<String>,
+}
+
+impl Link {
+    /// Checks whether the link has the given relation type.
+    pub(crate) fn has_rel(&self, rel: &str) -> bool {
+        self.rel.split_ascii_whitespace().any(|v| v == rel)
+    }
+}
+
+/// Returns the closure to look up the header in the header map.
+///
+/// Values of the header fields with the same name are joined by commas.
+/// See <https://tools.ietf.org/html/rfc7230#section-3.2.2>.
+#[cfg(any(feature = "reqwest", feature = "tower"))]
+pub(crate) fn header_map_lookup(headers: &HeaderMap) -> impl Fn(&str) -> Option<String> + '_ {
+    move |name: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .fold(None, |joined, value| match joined {
+                Some(joined) => Some(format!("{}, {}", joined, value)),
+                None => Some(value.to_owned()),
+            })
+    }
+}
+
+/// Returns the links in the HTTP `Link` header.
+fn links(header: impl Fn(&str) -> Option<String>) -> Vec<Link> {
+    header("Link").map_or_else(Vec::new, |value| parse_link_header(&value))
+}
+
+/// Returns the URL of the alternate JSON-LD document given by the HTTP `Link` header.
+///
+/// The link target is resolved against `base`, the URL of the response.
+/// Loaders following the link should keep the original URL as the document URL.
+///
+/// > If the retrieved resource's Content-Type is not `application/json` nor any media type
+/// > with a `+json` suffix as defined in [RFC6839], and the response has an HTTP Link
+/// > Header [RFC8288] using the `alternate` link relation with type `application/ld+json`,
+/// > set _url_ to the associated `href` relative to the previous _url_ and restart the
+/// > algorithm from step 2, ensuring that _documentUrl_ is set to the original _url_.
+///
+/// --- <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#loaddocumentcallback>
+pub(crate) fn alternate_link(
+    header: impl Fn(&str) -> Option<String>,
+    base: &str,
+) -> Option<String> {
+    links(header)
+        .into_iter()
+        .find(|link| {
+            link.has_rel("alternate")
+                && link.ty.as_ref().map(String::as_str) == Some("application/ld+json")
+        })
+        .map(|link| resolve(base, &link.target))
+}
+
+/// Returns the context URL given by the HTTP `Link` header.
+///
+/// The link target is resolved against `base`, the URL of the response.
+pub(crate) fn context_link(
+    header: impl Fn(&str) -> Option<String>,
+    base: &str,
+) -> Result<Option<String>, MultipleContextLinkHeaders> {
+    let mut context_links = links(header)
+        .into_iter()
+        .filter(|link| link.has_rel(CONTEXT_LINK_REL));
+    let link = match context_links.next() {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    if context_links.next().is_some() {
+        return Err(MultipleContextLinkHeaders);
+    }
+    Ok(Some(resolve(base, &link.target)))
+}
+
+/// Checks the `Content-Length` header against the size limit, before reading the body.
+pub(crate) fn check_content_length(
+    header: impl Fn(&str) -> Option<String>,
+    max_size: Option<u64>,
+) -> Result<(), SizeLimitExceeded> {
+    let content_length = header("Content-Length").and_then(|v| v.trim().parse::<u64>().ok());
+    match (content_length, max_size) {
+        (Some(len), Some(max_size)) if len > max_size => Err(SizeLimitExceeded::new(max_size)),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the size of the (partially) read body against the size limit.
+pub(crate) fn check_body_size(len: usize, max_size: Option<u64>) -> Result<(), SizeLimitExceeded> {
+    match max_size {
+        Some(max_size) if len as u64 > max_size => Err(SizeLimitExceeded::new(max_size)),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves the link target against the URL of the response.
+///
+/// The target is returned as is if the URL or the target is invalid.
+pub(crate) fn resolve(base: &str, target: &str) -> String {
+    match (IriStr::new(base), IriReferenceStr::new(target)) {
+        (Ok(base), Ok(target)) => target.resolve_against(base.to_absolute()).into(),
+        _ => target.to_owned(),
+    }
+}
+
+/// Parses the value of the HTTP `Link` header.
+///
+/// See <https://tools.ietf.org/html/rfc8288#section-3>.
+pub(crate) fn parse_link_header(value: &str) -> Vec<Link> {
+    let mut result = Vec::new();
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+        if !rest.starts_with('<') {
+            return result;
+        }
+        let target_end = match rest.find('>') {
+            Some(v) => v,
+            None => return result,
+        };
+        let target = rest[1..target_end].to_owned();
+        rest = &rest[(target_end + 1)..];
+        let mut rel = String::new();
+        let mut ty = None;
+        loop {
+            rest = rest.trim_start();
+            if !rest.starts_with(';') {
+                break;
+            }
+            rest = rest[1..].trim_start();
+            let name_end = rest
+                .find(|c: char| c == '=' || c == ';' || c == ',')
+                .unwrap_or_else(|| rest.len());
+            let name = rest[..name_end].trim().to_ascii_lowercase();
+            rest = &rest[name_end..];
+            let param_value = if rest.starts_with('=') {
+                rest = rest[1..].trim_start();
+                if rest.starts_with('"') {
+                    let end = rest[1..].find('"').map_or(rest.len(), |pos| pos + 1);
+                    let v = rest[1..end].to_owned();
+                    rest = &rest[(end + 1).min(rest.len())..];
+                    v
+                } else {
+                    let end = rest
+                        .find(|c: char| c == ';' || c == ',')
+                        .unwrap_or_else(|| rest.len());
+                    let v = rest[..end].trim().to_owned();
+                    rest = &rest[end..];
+                    v
+                }
+            } else {
+                String::new()
+            };
+            match name.as_str() {
+                "rel" => rel = param_value,
+                "type" => ty = Some(param_value.to_ascii_lowercase()),
+                _ => {}
+            }
+        }
+        result.push(Link { target, rel, ty });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_header() {
+        let links = parse_link_header(
+            "<ctx.jsonld>; rel=\"http://www.w3.org/ns/json-ld#context\"; \
+             type=\"application/ld+json\", <http://example.com/alt>;rel=alternate",
+        );
+        assert_eq!(
+            links,
+            vec![
+                Link {
+                    target: "ctx.jsonld".to_owned(),
+                    rel: CONTEXT_LINK_REL.to_owned(),
+                    ty: Some("application/ld+json".to_owned()),
+                },
+                Link {
+                    target: "http://example.com/alt".to_owned(),
+                    rel: "alternate".to_owned(),
+                    ty: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn alternate() {
+        let header = |name: &str| {
+            if name == "Link" {
+                Some(
+                    "<style.css>; rel=stylesheet, <doc.jsonld>; rel=\"alternate\"; \
+                     type=\"application/ld+json\""
+                        .to_owned(),
+                )
+            } else {
+                None
+            }
+        };
+        assert_eq!(
+            alternate_link(header, "http://example.com/dir/doc"),
+            Some("http://example.com/dir/doc.jsonld".to_owned())
+        );
+        assert_eq!(alternate_link(|_| None, "http://example.com/dir/doc"), None);
+    }
+
+    #[test]
+    fn context() {
+        let header = |value: &'static str| move |_: &str| Some(value.to_owned());
+        assert_eq!(
+            context_link(
+                header("<ctx.jsonld>; rel=\"http://www.w3.org/ns/json-ld#context\""),
+                "http://example.com/dir/doc"
+            ),
+            Ok(Some("http://example.com/dir/ctx.jsonld".to_owned()))
+        );
+        assert_eq!(
+            context_link(
+                header(
+                    "<a.jsonld>; rel=\"http://www.w3.org/ns/json-ld#context\", \
+                     <b.jsonld>; rel=\"http://www.w3.org/ns/json-ld#context\""
+                ),
+                "http://example.com/dir/doc"
+            ),
+            Err(MultipleContextLinkHeaders)
+        );
+    }
+
+    #[test]
+    fn size_limit() {
+        let content_length = |_: &str| Some("100".to_owned());
+        assert!(check_content_length(content_length, Some(99)).is_err());
+        assert!(check_content_length(content_length, Some(100)).is_ok());
+        assert!(check_content_length(content_length, None).is_ok());
+        assert!(check_body_size(10, Some(9)).is_err());
+        assert!(check_body_size(10, Some(10)).is_ok());
+    }
+
+    #[test]
+    fn media_type() {
+        let ty = MediaType::parse(
+            "Application/LD+JSON; charset=utf-8; profile=\"http://www.w3.org/ns/json-ld#expanded\"",
+        );
+        assert_eq!(ty.essence, "application/ld+json");
+        assert_eq!(
+            ty.profile.as_ref().map(String::as_str),
+            Some("http://www.w3.org/ns/json-ld#expanded")
+        );
+        assert!(ty.is_json());
+        assert!(!ty.is_html());
+    }
+}
@@ -0,0 +1,679 @@
+//! Remote document loader using HTTP(S).
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#loaddocumentcallback>.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, ACCEPT, CACHE_CONTROL, DATE, ETAG, EXPIRES, IF_NONE_MATCH, LOCATION,
+    },
+    redirect, Client, StatusCode, Url,
+};
+
+use crate::{
+    html::extract_json_ld,
+    remote::{
+        header::{
+            accept_header, alternate_link, check_body_size, check_content_length, context_link,
+            header_map_lookup, MediaType,
+        },
+        retry::with_retry,
+        LoadDocumentOptions, LoadRemoteDocument, MultipleContextLinkHeaders, RedirectPolicy,
+        RemoteDocument, RetryPolicy, SizeLimitExceeded,
+    },
+};
+
+/// Default maximum number of redirects to follow.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Default maximum number of cached documents.
+const DEFAULT_MAX_CACHE_ENTRIES: usize = 1024;
+
+/// Error on loading a remote document by `HttpLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum HttpLoadError {
+    /// HTTP request failed.
+    #[error("HTTP request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    /// The server responded with an unsuccessful status.
+    #[error("Unsuccessful HTTP status: {0}")]
+    Status(StatusCode),
+    /// The document has an unsupported content type.
+    #[error("Unsupported content type: {0:?}")]
+    UnsupportedContentType(String),
+    /// The response has multiple HTTP `Link` headers for the JSON-LD context.
+    #[error("{0}")]
+    MultipleContextLinkHeaders(#[source] MultipleContextLinkHeaders),
+    /// The redirect response has no valid `Location` header.
+    #[error("Invalid redirect from <{0}>")]
+    InvalidRedirect(String),
+    /// The number of redirects exceeds the limit.
+    #[error("Too many redirects (more than {0})")]
+    TooManyRedirects(usize),
+    /// The redirect is refused by the redirect policy.
+    #[error("Redirect to <{url}> is refused: {source}")]
+    RedirectRefused {
+        /// Redirect target.
+        url: String,
+        /// Reason of the refusal.
+        source: Box<dyn Error + Send + Sync + 'static>,
+    },
+    /// The document is not valid JSON.
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Failed to extract JSON-LD from the HTML document.
+    #[error("Failed to extract JSON-LD from HTML: {0}")]
+    Html(#[source] crate::Error),
+    /// The document exceeds the size limit.
+    #[error("{0}")]
+    TooLarge(#[source] SizeLimitExceeded),
+}
+
+/// Remote document loader using HTTP(S).
+///
+/// This requests `application/ld+json` documents (with the `profile` parameter if requested),
+/// and also accepts other JSON documents and HTML documents.
+///
+/// * For JSON documents other than `application/ld+json`, the context URL is taken from the HTTP
+///   `Link` header.
+/// * For HTML documents, JSON-LD script elements are extracted.
+/// * For documents of other types, the alternate `application/ld+json` document given by the
+///   HTTP `Link` header is loaded if available.
+///
+/// Additional request headers given by `LoadDocumentOptions::headers()` are sent with requests,
+/// but not after redirects to other origins.
+///
+/// Loaded documents are cached in memory according to the `Cache-Control` and `Expires` headers,
+/// and stale documents with `ETag` are revalidated by conditional requests (`If-None-Match`).
+/// Stale documents without `ETag` are purged, and the least recently used document is evicted
+/// when the cache is full (1024 documents by default).
+/// Clones of the loader share the cache.
+/// Requests with additional headers bypass the cache, since the headers may contain credentials
+/// and responses to them should not be shared.
+///
+/// Failed loads are retried if the retry policy is set.
+///
+/// Redirects are followed up to the limit (10 by default), and the final URL is reported as the
+/// document URL. If the redirect policy is set, every redirect is checked by it before the request
+/// is sent.
+#[derive(Debug, Clone)]
+pub struct HttpLoader {
+    /// HTTP client.
+    client: Client,
+    /// Maximum number of redirects to follow.
+    max_redirects: usize,
+    /// Cached documents.
+    cache: Arc<Mutex<Cache>>,
+    /// Retry policy.
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
+    /// Redirect policy.
+    redirect_policy: Option<Arc<dyn RedirectPolicy>>,
+}
+
+impl Default for HttpLoader {
+    /// Same as [`HttpLoader::new()`](#method.new).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HTTP client cannot be initialized.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpLoader {
+    /// Creates a new `HttpLoader` with the default HTTP client.
+    ///
+    /// This is a thin wrapper of [`try_new()`](#method.try_new), which panics on failure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HTTP client cannot be initialized, as `reqwest::Client::new()` does.
+    pub fn new() -> Self {
+        Self::try_new().expect("Failed to initialize the HTTP client")
+    }
+
+    /// Creates a new `HttpLoader` with the default HTTP client.
+    ///
+    /// Returns an error if the HTTP client cannot be initialized (for example, if the TLS backend
+    /// cannot be initialized).
+    pub fn try_new() -> Result<Self, HttpLoadError> {
+        let client = Client::builder()
+            .redirect(redirect::Policy::none())
+            .build()?;
+        Ok(Self::with_client(client))
+    }
+
+    /// Creates a new `HttpLoader` with the given HTTP client.
+    ///
+    /// The client should be configured not to follow redirects by itself
+    /// (`reqwest::redirect::Policy::none()`), otherwise the redirect limit of the loader has no
+    /// effect.
+    pub fn with_client(client: Client) -> Self {
+        Self {
+            client,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            cache: Arc::new(Mutex::new(Cache::new(DEFAULT_MAX_CACHE_ENTRIES))),
+            retry_policy: None,
+            redirect_policy: None,
+        }
+    }
+
+    /// Sets the maximum number of redirects to follow.
+    pub fn max_redirects(self, max_redirects: usize) -> Self {
+        Self {
+            max_redirects,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of cached documents.
+    ///
+    /// Setting `0` disables the cache.
+    pub fn max_cache_entries(self, max_entries: usize) -> Self {
+        self.lock_cache().max_entries = max_entries;
+        self
+    }
+
+    /// Sets the retry policy for failed loads.
+    pub fn retry_policy(self, retry_policy: impl RetryPolicy + 'static) -> Self {
+        Self {
+            retry_policy: Some(Arc::new(retry_policy)),
+            ..self
+        }
+    }
+
+    /// Sets the policy to check redirects before following them.
+    pub fn redirect_policy(self, redirect_policy: impl RedirectPolicy + 'static) -> Self {
+        Self {
+            redirect_policy: Some(Arc::new(redirect_policy)),
+            ..self
+        }
+    }
+
+    /// Sends the request following redirects, and returns the response.
+    ///
+    /// If `etag` is given, the request is conditional and the response may be
+    /// `304 Not Modified`.
+    async fn fetch(
+        &self,
+        url: &str,
+        options: &LoadDocumentOptions,
+        etag: Option<&str>,
+    ) -> Result<reqwest::Response, HttpLoadError> {
+        let mut url = url.to_owned();
+        let mut num_redirects = 0;
+        // Custom headers may contain credentials, so they are not sent to other origins.
+        let origin = Url::parse(&url).ok().map(|url| url.origin());
+        loop {
+            let mut request = self.client.get(&url).header(ACCEPT, accept_header(options));
+            let is_same_origin = Url::parse(&url).ok().map(|url| url.origin()) == origin;
+            if is_same_origin {
+                for (name, value) in options.headers() {
+                    request = request.header(name.as_str(), value.as_str());
+                }
+            }
+            if let Some(timeout) = options.timeout() {
+                request = request.timeout(timeout);
+            }
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            let response = request.send().await?;
+            let status = response.status();
+            if status.is_redirection() && status != StatusCode::NOT_MODIFIED {
+                if num_redirects >= self.max_redirects {
+                    return Err(HttpLoadError::TooManyRedirects(self.max_redirects));
+                }
+                let next = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|location| response.url().join(location).ok())
+                    .ok_or_else(|| HttpLoadError::InvalidRedirect(url.clone()))?;
+                if let Some(policy) = &self.redirect_policy {
+                    policy
+                        .check_redirect(&url, next.as_str())
+                        .await
+                        .map_err(|source| HttpLoadError::RedirectRefused {
+                            url: next.to_string(),
+                            source,
+                        })?;
+                }
+                trace_event!(
+                    from = url.as_str(),
+                    to = next.as_str(),
+                    "Following the redirect"
+                );
+                url = next.to_string();
+                num_redirects += 1;
+                continue;
+            }
+            if !status.is_success() && !(etag.is_some() && status == StatusCode::NOT_MODIFIED) {
+                return Err(HttpLoadError::Status(status));
+            }
+            return Ok(response);
+        }
+    }
+
+    /// Loads the document without retrying.
+    async fn load_once(
+        &self,
+        iri: &IriStr,
+        options: &LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, HttpLoadError> {
+        // Responses to requests with custom headers may depend on credentials in them, so they
+        // are neither cached nor served from the cache.
+        let key = if options.headers().is_empty() {
+            Some((iri.as_str().to_owned(), options.clone()))
+        } else {
+            None
+        };
+        let cached = key
+            .as_ref()
+            .and_then(|key| self.lock_cache().get(key, Instant::now()));
+        let mut response = match cached {
+            Some(ref entry) if entry.is_fresh(Instant::now()) => {
+                trace_event!(iri = iri.as_str(), "Fresh cached document is used");
+                return Ok(entry.document.clone());
+            }
+            Some(entry) => match entry.etag {
+                Some(ref etag) => {
+                    let response = self.fetch(&entry.url, options, Some(etag)).await?;
+                    if response.status() == StatusCode::NOT_MODIFIED {
+                        trace_event!(iri = iri.as_str(), "Cached document is revalidated");
+                        let document = entry.document.clone();
+                        let policy = CachePolicy::from_headers(response.headers());
+                        // Only cached entries are revalidated, so the key is always available.
+                        if let Some(key) = key {
+                            if policy.no_store {
+                                self.lock_cache().remove(&key);
+                            } else {
+                                let now = Instant::now();
+                                let entry = CacheEntry {
+                                    expires_at: now + policy.fresh_for,
+                                    used_at: now,
+                                    ..entry
+                                };
+                                self.lock_cache().insert(key, entry, now);
+                            }
+                        }
+                        return Ok(document);
+                    }
+                    response
+                }
+                None => self.fetch(iri.as_str(), options, None).await?,
+            },
+            None => self.fetch(iri.as_str(), options, None).await?,
+        };
+        let document_url = response.url().clone();
+        let mut media_type = MediaType::from_header(header_map_lookup(response.headers()));
+        // Follow the alternate JSON-LD document.
+        if !media_type.is_json() {
+            if let Some(alternate) = alternate_link(
+                header_map_lookup(response.headers()),
+                response.url().as_str(),
+            ) {
+                trace_event!(url = alternate.as_str(), "Following the alternate link");
+                response = self.fetch(&alternate, options, None).await?;
+                media_type = MediaType::from_header(header_map_lookup(response.headers()));
+            }
+        }
+        let fetched_url = response.url().clone();
+        let policy = CachePolicy::from_headers(response.headers());
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(ToOwned::to_owned);
+
+        let (document, context_url) = if media_type.is_html() {
+            let text = read_body(response, options.max_size()).await?;
+            let document = extract_json_ld(
+                &text,
+                document_url.fragment(),
+                options.should_extract_all_scripts(),
+            )
+            .map_err(HttpLoadError::Html)?;
+            (document, None)
+        } else if media_type.is_json() {
+            let context_url = if media_type.essence == "application/ld+json" {
+                None
+            } else {
+                context_link(header_map_lookup(response.headers()), fetched_url.as_str())
+                    .map_err(HttpLoadError::MultipleContextLinkHeaders)?
+            };
+            let text = read_body(response, options.max_size()).await?;
+            (serde_json::from_str(&text)?, context_url)
+        } else {
+            return Err(HttpLoadError::UnsupportedContentType(media_type.essence));
+        };
+
+        let document = Arc::new(
+            RemoteDocument::new(document_url.as_str(), document)
+                .context_url(context_url)
+                .content_type(media_type.essence)
+                .profile(media_type.profile),
+        );
+        let key = match key {
+            Some(key) => key,
+            None => return Ok(document),
+        };
+        if policy.no_store || (policy.fresh_for == Duration::from_secs(0) && etag.is_none()) {
+            self.lock_cache().remove(&key);
+        } else {
+            let now = Instant::now();
+            let entry = CacheEntry {
+                document: document.clone(),
+                url: fetched_url.to_string(),
+                etag,
+                expires_at: now + policy.fresh_for,
+                used_at: now,
+            };
+            self.lock_cache().insert(key, entry, now);
+        }
+        Ok(document)
+    }
+
+    /// Locks the cache.
+    fn lock_cache(&self) -> MutexGuard<'_, Cache> {
+        // The cache is always consistent, so it can be used even if another thread panicked.
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl LoadRemoteDocument for HttpLoader {
+    type Error = HttpLoadError;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        with_retry(self.retry_policy.as_ref().map(|p| &**p), || {
+            self.load_once(iri, &options)
+        })
+        .await
+    }
+}
+
+/// Cache key, the requested IRI and the load options.
+type CacheKey = (String, LoadDocumentOptions);
+
+/// Cached documents.
+#[derive(Debug, Clone)]
+struct Cache {
+    /// Maximum number of entries.
+    max_entries: usize,
+    /// Entries.
+    entries: HashMap<CacheKey, CacheEntry>,
+}
+
+impl Cache {
+    /// Creates a new empty `Cache`.
+    fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the entry for the key, and marks it as recently used.
+    fn get(&mut self, key: &CacheKey, now: Instant) -> Option<CacheEntry> {
+        let entry = self.entries.get_mut(key)?;
+        entry.used_at = now;
+        Some(entry.clone())
+    }
+
+    /// Removes the entry for the key.
+    fn remove(&mut self, key: &CacheKey) {
+        self.entries.remove(key);
+    }
+
+    /// Inserts the entry.
+    ///
+    /// Stale entries which cannot be revalidated are purged, and the least recently used entry is
+    /// evicted if the cache is still full.
+    fn insert(&mut self, key: CacheKey, entry: CacheEntry, now: Instant) {
+        if self.max_entries == 0 {
+            return;
+        }
+        self.entries
+            .retain(|_, entry| entry.is_fresh(now) || entry.etag.is_some());
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.max_entries {
+            let lru_key = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used_at)
+                .map(|(key, _)| key.clone());
+            if let Some(lru_key) = lru_key {
+                self.entries.remove(&lru_key);
+            }
+        }
+        self.entries.insert(key, entry);
+    }
+}
+
+/// Cached document.
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    /// Document.
+    document: Arc<RemoteDocument>,
+    /// URL of the document, used for revalidation.
+    url: String,
+    /// Entity tag of the document.
+    etag: Option<String>,
+    /// Time when the document becomes stale.
+    expires_at: Instant,
+    /// Time when the document is used last time.
+    used_at: Instant,
+}
+
+impl CacheEntry {
+    /// Checks whether the document is fresh.
+    fn is_fresh(&self, now: Instant) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// Caching policy given by the response headers.
+///
+/// See <https://tools.ietf.org/html/rfc7234#section-4.2.1>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CachePolicy {
+    /// Whether the response must not be stored.
+    no_store: bool,
+    /// Freshness lifetime.
+    fresh_for: Duration,
+}
+
+impl CachePolicy {
+    /// Returns the caching policy given by the `Cache-Control`, `Expires`, and `Date` headers.
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: HeaderName| headers.get(name).and_then(|v| v.to_str().ok());
+        let mut policy = Self {
+            no_store: false,
+            fresh_for: Duration::from_secs(0),
+        };
+        let mut max_age = None;
+        let mut no_cache = false;
+        let directives = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','));
+        for directive in directives {
+            let mut kv = directive.splitn(2, '=');
+            let name = kv.next().unwrap_or("").trim().to_ascii_lowercase();
+            let value = kv.next().map(|v| v.trim().trim_matches('"'));
+            match name.as_str() {
+                "no-store" => policy.no_store = true,
+                "no-cache" => no_cache = true,
+                "max-age" => max_age = value.and_then(|v| v.parse::<u64>().ok()),
+                _ => {}
+            }
+        }
+        if no_cache {
+            return policy;
+        }
+        policy.fresh_for = match max_age {
+            Some(secs) => Duration::from_secs(secs),
+            None => {
+                // Invalid `Expires` value means the response is already expired.
+                let expires = header(EXPIRES).and_then(parse_http_date);
+                let date = header(DATE)
+                    .and_then(parse_http_date)
+                    .unwrap_or_else(SystemTime::now);
+                expires
+                    .and_then(|expires| expires.duration_since(date).ok())
+                    .unwrap_or_else(|| Duration::from_secs(0))
+            }
+        };
+        policy
+    }
+}
+
+/// Parses the HTTP date in the IMF-fixdate format (such as `Sun, 06 Nov 1994 08:49:37 GMT`).
+///
+/// See <https://tools.ietf.org/html/rfc7231#section-7.1.1.1>.
+fn parse_http_date(s: &str) -> Option<SystemTime> {
+    /// Month names.
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let mut parts = s.split_ascii_whitespace();
+    let _day_name = parts.next()?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|&name| name == month)? as u64 + 1;
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':').map(|v| v.parse::<u64>().ok());
+    let (hour, min, sec) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" || year < 1970 || day == 0 || day > 31 || hour > 23 || min > 59 {
+        return None;
+    }
+    // Days from the epoch, by the algorithm `days_from_civil` of
+    // <http://howardhinnant.github.io/date_algorithms.html>.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = ((days * 24 + hour) * 60 + min) * 60 + sec;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Reads the response body as text, refusing bodies larger than `max_size` bytes.
+async fn read_body(
+    mut response: reqwest::Response,
+    max_size: Option<u64>,
+) -> Result<String, HttpLoadError> {
+    if max_size.is_none() {
+        return Ok(response.text().await?);
+    }
+    check_content_length(header_map_lookup(response.headers()), max_size)
+        .map_err(HttpLoadError::TooLarge)?;
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        check_body_size(body.len() + chunk.len(), max_size).map_err(HttpLoadError::TooLarge)?;
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(parse_http_date("0"), None);
+    }
+
+    #[test]
+    fn cache_policy() {
+        let policy = |pairs: &[(HeaderName, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.append(name, value.parse().expect("valid header value"));
+            }
+            CachePolicy::from_headers(&headers)
+        };
+        assert_eq!(
+            policy(&[
+                (CACHE_CONTROL, "public, max-age=3600"),
+                (EXPIRES, "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ])
+            .fresh_for,
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            policy(&[
+                (DATE, "Sun, 06 Nov 1994 08:49:37 GMT"),
+                (EXPIRES, "Sun, 06 Nov 1994 09:49:37 GMT"),
+            ])
+            .fresh_for,
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            policy(&[(CACHE_CONTROL, "no-cache, max-age=60")]).fresh_for,
+            Duration::from_secs(0)
+        );
+        assert!(policy(&[(CACHE_CONTROL, "No-Store")]).no_store);
+    }
+
+    #[test]
+    fn cache_eviction() {
+        let now = Instant::now();
+        let entry = |etag: Option<&str>, fresh_for: u64, used_at: Instant| CacheEntry {
+            document: Arc::new(RemoteDocument::new(
+                "https://example.com/",
+                serde_json::json!({}),
+            )),
+            url: "https://example.com/".to_owned(),
+            etag: etag.map(ToOwned::to_owned),
+            expires_at: now + Duration::from_secs(fresh_for),
+            used_at,
+        };
+        let key = |iri: &str| (iri.to_owned(), LoadDocumentOptions::new());
+        let later = now + Duration::from_secs(10);
+
+        let mut cache = Cache::new(2);
+        cache.insert(key("a"), entry(None, 60, now), now);
+        cache.insert(key("b"), entry(None, 60, now), now);
+        assert!(cache.get(&key("a"), later).is_some());
+        // `b` is the least recently used.
+        cache.insert(key("c"), entry(None, 60, later), later);
+        assert!(cache.get(&key("b"), later).is_none());
+        assert!(cache.get(&key("a"), later).is_some());
+        assert!(cache.get(&key("c"), later).is_some());
+
+        let mut cache = Cache::new(8);
+        cache.insert(key("stale"), entry(None, 1, now), now);
+        cache.insert(key("stale-etag"), entry(Some("\"v1\""), 1, now), now);
+        cache.insert(key("fresh"), entry(None, 60, later), later);
+        // Stale entries without `ETag` cannot be revalidated, so they are purged.
+        assert!(cache.get(&key("stale"), later).is_none());
+        assert!(cache.get(&key("stale-etag"), later).is_some());
+        assert!(cache.get(&key("fresh"), later).is_some());
+    }
+}
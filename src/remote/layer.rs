@@ -0,0 +1,283 @@
+//! Composable middleware layers for remote document loaders.
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+
+use crate::remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument};
+
+/// A trait for types which wrap a loader into another loader.
+///
+/// Loader wrappers such as `CachingLoader` can be used as layers by [`layer_fn`](fn.layer_fn.html),
+/// and layers can be stacked by [`LoaderBuilder`](struct.LoaderBuilder.html).
+pub trait LoaderLayer<L> {
+    /// Wrapped loader type.
+    type Loader;
+
+    /// Wraps the given loader.
+    fn layer(&self, loader: L) -> Self::Loader;
+}
+
+/// Layer which returns the loader as is.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Identity;
+
+impl<L> LoaderLayer<L> for Identity {
+    type Loader = L;
+
+    fn layer(&self, loader: L) -> Self::Loader {
+        loader
+    }
+}
+
+/// Layer stacking two layers.
+///
+/// The inner layer wraps the loader first, and the outer layer wraps the result.
+#[derive(Debug, Clone)]
+pub struct Stack<Inner, Outer> {
+    /// Inner layer.
+    inner: Inner,
+    /// Outer layer.
+    outer: Outer,
+}
+
+impl<Inner, Outer> Stack<Inner, Outer> {
+    /// Creates a new `Stack`.
+    pub fn new(inner: Inner, outer: Outer) -> Self {
+        Self { inner, outer }
+    }
+}
+
+impl<L, Inner, Outer> LoaderLayer<L> for Stack<Inner, Outer>
+where
+    Inner: LoaderLayer<L>,
+    Outer: LoaderLayer<Inner::Loader>,
+{
+    type Loader = Outer::Loader;
+
+    fn layer(&self, loader: L) -> Self::Loader {
+        self.outer.layer(self.inner.layer(loader))
+    }
+}
+
+/// Layer using the function to wrap loaders.
+#[derive(Clone, Copy)]
+pub struct LayerFn<F> {
+    /// Function to wrap loaders.
+    f: F,
+}
+
+impl<F> fmt::Debug for LayerFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayerFn").finish()
+    }
+}
+
+impl<L, F, T> LoaderLayer<L> for LayerFn<F>
+where
+    F: Fn(L) -> T,
+{
+    type Loader = T;
+
+    fn layer(&self, loader: L) -> Self::Loader {
+        (self.f)(loader)
+    }
+}
+
+/// Creates a layer from the function to wrap loaders.
+///
+/// ```
+/// use json_ld::remote::{layer_fn, CachingLoader, LoaderLayer, StaticLoader};
+///
+/// let layer = layer_fn(CachingLoader::new);
+/// let loader = layer.layer(StaticLoader::new());
+/// ```
+pub fn layer_fn<F>(f: F) -> LayerFn<F> {
+    LayerFn { f }
+}
+
+/// Builder to stack layers around a loader.
+///
+/// Layers added earlier are outer, that is, the first layer sees the request first.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use json_ld::{
+///     iri_string::types::IriStr,
+///     remote::{CachingLoader, GuardedLoader, LoaderBuilder, RemoteDocument, StaticLoader},
+///     ProcessorOptions,
+/// };
+///
+/// let loader = LoaderBuilder::new()
+///     .inspect(
+///         |iri: &IriStr, result: Result<&RemoteDocument, &_>, elapsed: Duration| {
+///             eprintln!("{} ({:?}): {}", iri, elapsed, result.is_ok())
+///         },
+///     )
+///     .layer_fn(CachingLoader::new)
+///     .layer_fn(|loader: StaticLoader| {
+///         GuardedLoader::new(loader).deny_non_public_addresses(true)
+///     })
+///     .loader(StaticLoader::new());
+/// let base = IriStr::new("https://example.com/").expect("valid IRI");
+/// let processor = ProcessorOptions::with_base(base.to_owned()).build(loader);
+/// ```
+#[derive(Default, Debug, Clone)]
+pub struct LoaderBuilder<Layer> {
+    /// Stacked layers.
+    layer: Layer,
+}
+
+impl LoaderBuilder<Identity> {
+    /// Creates a new `LoaderBuilder` without layers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<Layer> LoaderBuilder<Layer> {
+    /// Adds the layer inside the layers added so far.
+    pub fn layer<T>(self, layer: T) -> LoaderBuilder<Stack<T, Layer>> {
+        LoaderBuilder {
+            layer: Stack::new(layer, self.layer),
+        }
+    }
+
+    /// Adds the layer using the function to wrap loaders.
+    pub fn layer_fn<F>(self, f: F) -> LoaderBuilder<Stack<LayerFn<F>, Layer>> {
+        self.layer(layer_fn(f))
+    }
+
+    /// Adds the layer calling the function after each load.
+    ///
+    /// See [`InspectLayer`](struct.InspectLayer.html).
+    pub fn inspect<F>(self, f: F) -> LoaderBuilder<Stack<InspectLayer<F>, Layer>> {
+        self.layer(InspectLayer::new(f))
+    }
+
+    /// Wraps the loader with the layers.
+    pub fn loader<L>(&self, loader: L) -> Layer::Loader
+    where
+        Layer: LoaderLayer<L>,
+    {
+        self.layer.layer(loader)
+    }
+}
+
+/// Layer which creates `InspectLoader`.
+pub struct InspectLayer<F> {
+    /// Function called after each load.
+    f: Arc<F>,
+}
+
+impl<F> InspectLayer<F> {
+    /// Creates a new `InspectLayer`.
+    pub fn new(f: F) -> Self {
+        Self { f: Arc::new(f) }
+    }
+}
+
+impl<F> Clone for InspectLayer<F> {
+    fn clone(&self) -> Self {
+        Self { f: self.f.clone() }
+    }
+}
+
+impl<F> fmt::Debug for InspectLayer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InspectLayer").finish()
+    }
+}
+
+impl<L, F> LoaderLayer<L> for InspectLayer<F> {
+    type Loader = InspectLoader<L, F>;
+
+    fn layer(&self, loader: L) -> Self::Loader {
+        InspectLoader {
+            loader,
+            f: self.f.clone(),
+        }
+    }
+}
+
+/// Remote document loader wrapper which calls the function after each load.
+///
+/// The function receives the requested IRI, the result, and the elapsed time, so it can be used
+/// for logging and metrics.
+pub struct InspectLoader<L, F> {
+    /// Inner loader.
+    loader: L,
+    /// Function called after each load.
+    f: Arc<F>,
+}
+
+impl<L, F> InspectLoader<L, F> {
+    /// Creates a new `InspectLoader`.
+    pub fn new(loader: L, f: F) -> Self {
+        Self {
+            loader,
+            f: Arc::new(f),
+        }
+    }
+
+    /// Returns the inner loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+}
+
+impl<L: Clone, F> Clone for InspectLoader<L, F> {
+    fn clone(&self) -> Self {
+        Self {
+            loader: self.loader.clone(),
+            f: self.f.clone(),
+        }
+    }
+}
+
+impl<L: fmt::Debug, F> fmt::Debug for InspectLoader<L, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InspectLoader")
+            .field("loader", &self.loader)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<L, F> LoadRemoteDocument for InspectLoader<L, F>
+where
+    L: LoadRemoteDocument,
+    F: Fn(&IriStr, Result<&RemoteDocument, &L::Error>, Duration) + Send + Sync,
+{
+    type Error = L::Error;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        let started_at = Instant::now();
+        let result = self.loader.load(iri, options).await;
+        (self.f)(iri, result.as_ref().map(|doc| &**doc), started_at.elapsed());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_order() {
+        let builder = LoaderBuilder::new()
+            .layer_fn(|inner: (&'static str, &'static str)| ("outer", inner))
+            .layer_fn(|inner: &'static str| ("inner", inner));
+        assert_eq!(builder.loader("loader"), ("outer", ("inner", "loader")));
+    }
+}
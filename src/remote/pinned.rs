@@ -0,0 +1,180 @@
+//! Remote document loader wrapper with integrity pinning.
+
+use std::{collections::HashMap, error, sync::Arc};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    json::to_canonical_string,
+    remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument},
+};
+
+/// Error on loading a remote document by `PinnedLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum PinnedLoadError<E: error::Error + 'static> {
+    /// The inner loader failed.
+    #[error("{0}")]
+    Load(#[source] E),
+    /// The document does not match the pinned hash.
+    #[error("{0}")]
+    IntegrityMismatch(#[source] IntegrityMismatch),
+}
+
+/// Error indicating the document does not match the pinned hash.
+///
+/// The processor reports `ErrorCode::IntegrityCheckFailed` for loader errors caused by this.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Integrity check failed for <{iri}>: expected SHA-256 {expected}, got {actual}")]
+pub struct IntegrityMismatch {
+    /// The requested IRI.
+    iri: String,
+    /// The pinned hash.
+    expected: String,
+    /// The hash of the loaded document.
+    actual: String,
+}
+
+impl IntegrityMismatch {
+    /// Returns the requested IRI.
+    pub fn iri(&self) -> &str {
+        &self.iri
+    }
+
+    /// Returns the pinned hash in lowercase hex.
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// Returns the hash of the loaded document in lowercase hex.
+    pub fn actual(&self) -> &str {
+        &self.actual
+    }
+}
+
+/// Remote document loader wrapper which checks documents against the pinned SHA-256 hashes.
+///
+/// Documents for IRIs without pins are passed through as is.
+///
+/// The hash is calculated from the canonical JSON serialization ([RFC 8785]) of the document, so
+/// it does not depend on whitespaces and key order of the served document.
+/// Use [`PinnedLoader::digest`](#method.digest) to calculate the hash to pin.
+///
+/// [RFC 8785]: https://tools.ietf.org/html/rfc8785
+#[derive(Debug, Clone)]
+pub struct PinnedLoader<L> {
+    /// Inner loader.
+    loader: L,
+    /// Pinned hashes (in lowercase hex) keyed by IRI.
+    pins: HashMap<String, String>,
+}
+
+impl<L> PinnedLoader<L> {
+    /// Creates a new `PinnedLoader` without pins.
+    pub fn new(loader: L) -> Self {
+        Self {
+            loader,
+            pins: HashMap::new(),
+        }
+    }
+
+    /// Pins the document for the IRI to the given SHA-256 hash in hex.
+    pub fn pin(mut self, iri: impl Into<String>, sha256_hex: &str) -> Self {
+        self.pins
+            .insert(iri.into(), sha256_hex.trim().to_ascii_lowercase());
+        self
+    }
+
+    /// Returns the inner loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Returns the SHA-256 hash (in lowercase hex) of the document, used for pinning.
+    pub fn digest(document: &Value) -> String {
+        format!(
+            "{:x}",
+            Sha256::digest(to_canonical_string(document).as_bytes())
+        )
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocument> LoadRemoteDocument for PinnedLoader<L> {
+    type Error = PinnedLoadError<L::Error>;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        let doc = self
+            .loader
+            .load(iri, options)
+            .await
+            .map_err(PinnedLoadError::Load)?;
+        if let Some(expected) = self.pins.get(iri.as_str()) {
+            let actual = Self::digest(doc.document());
+            if *expected != actual {
+                return Err(PinnedLoadError::IntegrityMismatch(IntegrityMismatch {
+                    iri: iri.as_str().to_owned(),
+                    expected: expected.clone(),
+                    actual,
+                }));
+            }
+        }
+        Ok(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_executor::block_on;
+    use serde_json::json;
+
+    use crate::{remote::StaticLoader, ErrorCode};
+
+    #[test]
+    fn digest() {
+        let doc: Value = serde_json::from_str(r#"{ "b": [1, {"d": null, "c": "x"}], "a": true }"#)
+            .expect("valid JSON");
+        assert_eq!(
+            PinnedLoader::<()>::digest(&doc),
+            PinnedLoader::<()>::digest(&json!({"a": true, "b": [1, {"c": "x", "d": null}]}))
+        );
+    }
+
+    #[test]
+    fn integrity_check() {
+        let iri = IriStr::new("https://example.com/context.jsonld").expect("valid IRI");
+        let mut inner = StaticLoader::new();
+        inner
+            .insert_source(
+                iri.as_str(),
+                r#"{"@context": {"a": "https://example.com/a"}}"#,
+            )
+            .expect("valid document");
+        let pin =
+            PinnedLoader::<()>::digest(&json!({ "@context": { "a": "https://example.com/a" } }));
+
+        let loader = PinnedLoader::new(inner.clone()).pin(iri.as_str(), &pin.to_ascii_uppercase());
+        assert!(block_on(loader.load(iri, LoadDocumentOptions::new())).is_ok());
+
+        let wrong = PinnedLoader::<()>::digest(&json!({ "@context": {} }));
+        let loader = PinnedLoader::new(inner).pin(iri.as_str(), &wrong);
+        match block_on(loader.load(iri, LoadDocumentOptions::new())) {
+            Err(PinnedLoadError::IntegrityMismatch(e)) => {
+                assert_eq!(e.iri(), iri.as_str());
+                assert_eq!(e.expected(), wrong);
+                assert_eq!(e.actual(), pin);
+                let err = ErrorCode::LoadingRemoteContextFailed.and_load_error(iri.as_str(), e);
+                assert_eq!(err.code(), ErrorCode::IntegrityCheckFailed);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
@@ -79,13 +79,18 @@ impl RequestProfile {
         Self::default()
     }
 
+    /// Checks whether the `RequestProfile` has no profiles.
+    pub fn is_empty(self) -> bool {
+        self.profiles == 0
+    }
+
     /// Checks whether the `RequestProfile` contains the given profile.
     pub fn contains(self, profile: Profile) -> bool {
         self.profiles & profile.single_bit() != 0
     }
 
     /// Returns an iterator of profiles.
-    fn iter(self) -> impl Iterator<Item = Profile> {
+    pub(crate) fn iter(self) -> impl Iterator<Item = Profile> {
         Profile::variants().filter(move |v| self.contains(*v))
     }
 }
@@ -0,0 +1,22 @@
+//! Policy of following redirects.
+
+use std::{error::Error, fmt};
+
+use async_trait::async_trait;
+
+/// Policy to decide whether to follow redirects.
+///
+/// Loaders following redirects by themselves (such as [`HttpLoader`](struct.HttpLoader.html))
+/// check every hop by the policy before sending the request, so that an allowed IRI cannot
+/// redirect to a forbidden one.
+#[async_trait]
+pub trait RedirectPolicy: fmt::Debug + Send + Sync {
+    /// Checks whether the redirect from `from` to `to` can be followed.
+    ///
+    /// Returns an error to refuse the redirect.
+    async fn check_redirect(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync + 'static>>;
+}
@@ -0,0 +1,362 @@
+//! Retry policy for remote document loaders.
+
+use std::{
+    collections::hash_map::RandomState,
+    error, fmt,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "reqwest")]
+use crate::remote::HttpLoadError;
+#[cfg(feature = "surf")]
+use crate::remote::SurfLoadError;
+
+/// Class of a failure on loading a remote document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureClass {
+    /// Timed out.
+    Timeout,
+    /// Network error, such as connection refused or reset.
+    Network,
+    /// The server responded with 5xx status.
+    ServerError,
+    /// The server responded with `429 Too Many Requests` status.
+    RateLimited,
+    /// Other failures, which are usually not transient.
+    Other,
+}
+
+impl FailureClass {
+    /// Classifies the error by the error and its sources.
+    pub fn of(error: &(dyn error::Error + 'static)) -> Self {
+        let mut current = Some(error);
+        while let Some(e) = current {
+            if let Some(class) = Self::of_single(e) {
+                return class;
+            }
+            current = e.source();
+        }
+        FailureClass::Other
+    }
+
+    /// Classifies the error without looking its sources.
+    fn of_single(e: &(dyn error::Error + 'static)) -> Option<Self> {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            return match e.kind() {
+                io::ErrorKind::TimedOut => Some(FailureClass::Timeout),
+                io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::Interrupted => Some(FailureClass::Network),
+                _ => None,
+            };
+        }
+        #[cfg(feature = "surf")]
+        {
+            if let Some(e) = e.downcast_ref::<SurfLoadError>() {
+                return match e {
+                    SurfLoadError::Status(429) => Some(FailureClass::RateLimited),
+                    SurfLoadError::Status(status) if (500..600).contains(status) => {
+                        Some(FailureClass::ServerError)
+                    }
+                    SurfLoadError::Timeout(_) => Some(FailureClass::Timeout),
+                    SurfLoadError::Request(_) => Some(FailureClass::Network),
+                    _ => None,
+                };
+            }
+        }
+        #[cfg(feature = "reqwest")]
+        {
+            if let Some(e) = e.downcast_ref::<HttpLoadError>() {
+                return match e {
+                    HttpLoadError::Status(status) if status.as_u16() == 429 => {
+                        Some(FailureClass::RateLimited)
+                    }
+                    HttpLoadError::Status(status) if status.is_server_error() => {
+                        Some(FailureClass::ServerError)
+                    }
+                    HttpLoadError::Request(e) if e.is_timeout() => Some(FailureClass::Timeout),
+                    HttpLoadError::Request(e) if e.status().is_none() => {
+                        Some(FailureClass::Network)
+                    }
+                    _ => None,
+                };
+            }
+        }
+        None
+    }
+}
+
+/// Policy to decide whether and when to retry failed loads.
+pub trait RetryPolicy: fmt::Debug + Send + Sync {
+    /// Returns the delay before the next attempt, or `None` to give up.
+    ///
+    /// `attempt` is the number of attempts made so far (`1` after the first failure).
+    fn retry_delay(&self, attempt: u32, class: FailureClass) -> Option<Duration>;
+}
+
+/// Retry policy with exponential backoff.
+///
+/// The delay before the `n`-th retry is `initial_delay * multiplier^(n-1)`, capped by
+/// `max_delay`.
+/// If jitter is enabled, the delay is randomized between the half and the whole of it.
+///
+/// By default, this makes at most 3 attempts with the initial delay of 200 ms, doubling the
+/// delay up to 10 seconds with jitter, and retries timeouts, network errors, server errors, and
+/// rate limiting.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    /// Maximum number of attempts (including the first one).
+    max_attempts: u32,
+    /// Delay before the first retry.
+    initial_delay: Duration,
+    /// Maximum delay.
+    max_delay: Duration,
+    /// Multiplier of the delay.
+    multiplier: f64,
+    /// Whether to randomize delays.
+    jitter: bool,
+    /// Failure classes to retry.
+    retry_on: Vec<FailureClass>,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            retry_on: vec![
+                FailureClass::Timeout,
+                FailureClass::Network,
+                FailureClass::ServerError,
+                FailureClass::RateLimited,
+            ],
+        }
+    }
+}
+
+impl ExponentialBackoff {
+    /// Creates a new `ExponentialBackoff` with the default parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of attempts (including the first one).
+    pub fn max_attempts(self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..self
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn initial_delay(self, initial_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            ..self
+        }
+    }
+
+    /// Sets the maximum delay.
+    pub fn max_delay(self, max_delay: Duration) -> Self {
+        Self { max_delay, ..self }
+    }
+
+    /// Sets the multiplier of the delay.
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        Self { multiplier, ..self }
+    }
+
+    /// Sets whether to randomize delays.
+    pub fn jitter(self, jitter: bool) -> Self {
+        Self { jitter, ..self }
+    }
+
+    /// Sets the failure classes to retry.
+    pub fn retry_on(self, retry_on: impl IntoIterator<Item = FailureClass>) -> Self {
+        Self {
+            retry_on: retry_on.into_iter().collect(),
+            ..self
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn retry_delay(&self, attempt: u32, class: FailureClass) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.retry_on.contains(&class) {
+            return None;
+        }
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let delay_secs =
+            (self.initial_delay.as_secs_f64() * factor).min(self.max_delay.as_secs_f64());
+        let delay = Duration::from_secs_f64(delay_secs.max(0.0));
+        if self.jitter {
+            Some(delay / 2 + (delay / 2).mul_f64(random_fraction()))
+        } else {
+            Some(delay)
+        }
+    }
+}
+
+/// Returns a pseudo-random number in `[0, 1)`.
+fn random_fraction() -> f64 {
+    // `RandomState` is randomly seeded, which is enough for jitter.
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+/// Runs the fallible operation, retrying it as the policy says.
+pub(crate) async fn with_retry<T, E, F, Fut>(
+    policy: Option<&dyn RetryPolicy>,
+    mut f: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: error::Error + 'static,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let e = match f().await {
+            Ok(v) => return Ok(v),
+            Err(e) => e,
+        };
+        match policy.and_then(|policy| policy.retry_delay(attempt, FailureClass::of(&e))) {
+            Some(delay) => {
+                trace_event!(attempt, error = %e, "Retrying to load the document");
+                Delay::new(delay).await;
+            }
+            None => return Err(e),
+        }
+    }
+}
+
+/// Runs the future with the timeout.
+///
+/// Returns `None` if the future does not complete in time.
+/// This uses a thread as the timer, so that it works with any async runtime.
+#[cfg(feature = "surf")]
+pub(crate) async fn with_timeout<F: Future>(timeout: Duration, future: F) -> Option<F::Output> {
+    Timeout {
+        future: Box::pin(future),
+        delay: Delay::new(timeout),
+    }
+    .await
+}
+
+/// Future which gives up the inner future after the delay.
+#[cfg(feature = "surf")]
+struct Timeout<F> {
+    /// Inner future.
+    future: Pin<Box<F>>,
+    /// Delay until the timeout.
+    delay: Delay,
+}
+
+#[cfg(feature = "surf")]
+impl<F: Future> Future for Timeout<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(v) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Some(v));
+        }
+        match Pin::new(&mut self.delay).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Future which completes after the given duration.
+///
+/// This uses a thread as the timer, so that it works with any async runtime.
+struct Delay {
+    /// Shared state.
+    state: Arc<Mutex<DelayState>>,
+}
+
+/// State of `Delay`.
+#[derive(Default)]
+struct DelayState {
+    /// Whether the duration has elapsed.
+    done: bool,
+    /// Waker of the task waiting for the delay.
+    waker: Option<Waker>,
+}
+
+impl Delay {
+    /// Creates a new `Delay` and starts the timer.
+    fn new(duration: Duration) -> Self {
+        let state = Arc::new(Mutex::new(DelayState::default()));
+        let timer_state = state.clone();
+        thread::spawn(move || {
+            thread::sleep(duration);
+            let mut state = timer_state.lock().unwrap_or_else(|e| e.into_inner());
+            state.done = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Self { state }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.done {
+            Poll::Ready(())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = ExponentialBackoff::new()
+            .max_attempts(4)
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(3))
+            .jitter(false);
+        let delay = |attempt| policy.retry_delay(attempt, FailureClass::ServerError);
+        assert_eq!(delay(1), Some(Duration::from_secs(1)));
+        assert_eq!(delay(2), Some(Duration::from_secs(2)));
+        assert_eq!(delay(3), Some(Duration::from_secs(3)));
+        assert_eq!(delay(4), None);
+        assert_eq!(policy.retry_delay(1, FailureClass::Other), None);
+
+        let jittered = policy.jitter(true).retry_delay(2, FailureClass::Timeout);
+        assert!(jittered.map_or(false, |d| d >= Duration::from_secs(1)
+            && d <= Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn classify() {
+        let e = io::Error::new(io::ErrorKind::ConnectionReset, "reset");
+        assert_eq!(FailureClass::of(&e), FailureClass::Network);
+        let e = io::Error::new(io::ErrorKind::NotFound, "not found");
+        assert_eq!(FailureClass::of(&e), FailureClass::Other);
+    }
+}
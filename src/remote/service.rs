@@ -0,0 +1,266 @@
+//! Remote document loader using a `tower::Service` HTTP client.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#loaddocumentcallback>.
+
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+use bytes::Buf;
+use http::{header::ACCEPT, Request, Response, StatusCode};
+use http_body::Body;
+use iri_string::types::IriStr;
+use tower_service::Service;
+
+use crate::{
+    html::extract_json_ld,
+    remote::{
+        header::{
+            accept_header, alternate_link, check_body_size, check_content_length, context_link,
+            header_map_lookup, MediaType,
+        },
+        LoadDocumentOptions, LoadRemoteDocument, MultipleContextLinkHeaders, RemoteDocument,
+        SizeLimitExceeded,
+    },
+};
+
+/// Boxed error of services and response bodies.
+type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+/// Error on loading a remote document by `ServiceLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceLoadError {
+    /// The request cannot be built.
+    #[error("Invalid HTTP request: {0}")]
+    Request(#[from] http::Error),
+    /// The service failed.
+    #[error("HTTP service failed: {0}")]
+    Service(#[source] BoxError),
+    /// Failed to read the response body.
+    #[error("Failed to read the HTTP response body: {0}")]
+    Body(#[source] BoxError),
+    /// The server responded with an unsuccessful status.
+    #[error("Unsuccessful HTTP status: {0}")]
+    Status(StatusCode),
+    /// The document has an unsupported content type.
+    #[error("Unsupported content type: {0:?}")]
+    UnsupportedContentType(String),
+    /// The response has multiple HTTP `Link` headers for the JSON-LD context.
+    #[error("{0}")]
+    MultipleContextLinkHeaders(#[source] MultipleContextLinkHeaders),
+    /// The document is not valid JSON.
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Failed to extract JSON-LD from the HTML document.
+    #[error("Failed to extract JSON-LD from HTML: {0}")]
+    Html(#[source] crate::Error),
+    /// The document exceeds the size limit.
+    #[error("{0}")]
+    TooLarge(#[source] SizeLimitExceeded),
+}
+
+/// Remote document loader using a `tower::Service` HTTP client, such as a `hyper` client wrapped
+/// by tower middleware.
+///
+/// `GET` requests with the request body `B::default()` are sent to the service, and documents are
+/// interpreted in the same way as [`HttpLoader`](struct.HttpLoader.html).
+///
+/// Redirects, caching, retries, and timeouts are up to the service (and its middleware), so
+/// `LoadDocumentOptions::timeout()` is not enforced by this loader.
+/// The service is cloned for each request.
+pub struct ServiceLoader<S, B> {
+    /// HTTP service.
+    service: S,
+    /// Request body type.
+    _body: PhantomData<fn() -> B>,
+}
+
+impl<S, B> ServiceLoader<S, B> {
+    /// Creates a new `ServiceLoader`.
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            _body: PhantomData,
+        }
+    }
+
+    /// Returns the HTTP service.
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+
+    /// Returns the HTTP service.
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+}
+
+impl<S: Clone, B> Clone for ServiceLoader<S, B> {
+    fn clone(&self) -> Self {
+        Self::new(self.service.clone())
+    }
+}
+
+impl<S: fmt::Debug, B> fmt::Debug for ServiceLoader<S, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceLoader")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<S, B, R> LoadRemoteDocument for ServiceLoader<S, B>
+where
+    S: Service<Request<B>, Response = Response<R>> + Clone + Send + Sync,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+    B: Default + Send + 'static,
+    R: Body + Send + 'static,
+    R::Data: Send,
+    R::Error: Into<BoxError>,
+{
+    type Error = ServiceLoadError;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        let mut response = send(&self.service, iri.as_str(), &options).await?;
+        let mut url = iri.as_str().to_owned();
+        let mut media_type = MediaType::from_header(header_map_lookup(response.headers()));
+        // Follow the alternate JSON-LD document.
+        if !media_type.is_json() {
+            if let Some(alternate) = alternate_link(header_map_lookup(response.headers()), &url) {
+                trace_event!(url = alternate.as_str(), "Following the alternate link");
+                response = send(&self.service, &alternate, &options).await?;
+                media_type = MediaType::from_header(header_map_lookup(response.headers()));
+                url = alternate;
+            }
+        }
+
+        let context_url = if media_type.is_json() && media_type.essence != "application/ld+json" {
+            context_link(header_map_lookup(response.headers()), &url)
+                .map_err(ServiceLoadError::MultipleContextLinkHeaders)?
+        } else {
+            None
+        };
+        let body = read_body(response, options.max_size()).await?;
+        let document = if media_type.is_html() {
+            let fragment = iri.as_str().splitn(2, '#').nth(1);
+            extract_json_ld(&body, fragment, options.should_extract_all_scripts())
+                .map_err(ServiceLoadError::Html)?
+        } else if media_type.is_json() {
+            serde_json::from_str(&body)?
+        } else {
+            return Err(ServiceLoadError::UnsupportedContentType(media_type.essence));
+        };
+
+        Ok(Arc::new(
+            RemoteDocument::new(iri.as_str(), document)
+                .context_url(context_url)
+                .content_type(media_type.essence)
+                .profile(media_type.profile),
+        ))
+    }
+}
+
+/// Sends the `GET` request to the service, and returns the successful response.
+async fn send<S, B, R>(
+    service: &S,
+    url: &str,
+    options: &LoadDocumentOptions,
+) -> Result<Response<R>, ServiceLoadError>
+where
+    S: Service<Request<B>, Response = Response<R>> + Clone,
+    S::Error: Into<BoxError>,
+    B: Default,
+{
+    let mut service = service.clone();
+    Ready::<_, Request<B>>::new(&mut service)
+        .await
+        .map_err(|e| ServiceLoadError::Service(e.into()))?;
+    let mut request = Request::get(url).header(ACCEPT, accept_header(options));
+    for (name, value) in options.headers() {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = service
+        .call(request.body(B::default())?)
+        .await
+        .map_err(|e| ServiceLoadError::Service(e.into()))?;
+    if !response.status().is_success() {
+        return Err(ServiceLoadError::Status(response.status()));
+    }
+    Ok(response)
+}
+
+/// Reads the response body as text, refusing bodies larger than `max_size` bytes.
+async fn read_body<R>(
+    response: Response<R>,
+    max_size: Option<u64>,
+) -> Result<String, ServiceLoadError>
+where
+    R: Body,
+    R::Error: Into<BoxError>,
+{
+    check_content_length(header_map_lookup(response.headers()), max_size)
+        .map_err(ServiceLoadError::TooLarge)?;
+    let mut body = Box::pin(response.into_body());
+    let mut buf = Vec::new();
+    while let Some(data) = NextData(body.as_mut()).await {
+        let mut data = data.map_err(|e| ServiceLoadError::Body(e.into()))?;
+        while data.has_remaining() {
+            let chunk = data.bytes();
+            let len = chunk.len();
+            check_body_size(buf.len() + len, max_size).map_err(ServiceLoadError::TooLarge)?;
+            buf.extend_from_slice(chunk);
+            data.advance(len);
+        }
+    }
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Future which completes when the service is ready.
+struct Ready<'a, S, Req> {
+    /// Service.
+    service: &'a mut S,
+    /// Request type.
+    _request: PhantomData<fn(Req)>,
+}
+
+impl<'a, S, Req> Ready<'a, S, Req> {
+    /// Creates a new `Ready`.
+    fn new(service: &'a mut S) -> Self {
+        Self {
+            service,
+            _request: PhantomData,
+        }
+    }
+}
+
+impl<S: Service<Req>, Req> Future for Ready<'_, S, Req> {
+    type Output = Result<(), S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.service.poll_ready(cx)
+    }
+}
+
+/// Future which completes with the next data frame of the body.
+struct NextData<'a, R>(Pin<&'a mut R>);
+
+impl<R: Body> Future for NextData<'_, R> {
+    type Output = Option<Result<R::Data, R::Error>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll_data(cx)
+    }
+}
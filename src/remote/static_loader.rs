@@ -0,0 +1,204 @@
+//! Remote document loader using in-memory documents.
+
+use std::{collections::HashMap, iter, sync::Arc};
+
+use async_trait::async_trait;
+use iri_string::types::{IriStr, IriString};
+use serde_json::Value;
+
+use crate::{
+    error::{ErrorCode, Result},
+    remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument},
+};
+
+/// Creates a `StaticLoader` with the contexts embedded into the binary.
+///
+/// The paths are relative to the file invoking the macro, as `include_str!` does, and absolute
+/// paths can be made from `CARGO_MANIFEST_DIR`.
+/// No I/O is performed at runtime.
+///
+/// The created expression evaluates to `Result<StaticLoader, json_ld::Error>`, and fails if an
+/// IRI or a document is invalid.
+/// Create the loader on startup, so that a broken bundle is reported before any document is
+/// processed.
+///
+/// ```
+/// # fn main() -> Result<(), json_ld::Error> {
+/// let loader = json_ld::embed_contexts! {
+///     "https://example.com/context.jsonld" =>
+///         concat!(env!("CARGO_MANIFEST_DIR"), "/tests/contexts/example.jsonld"),
+/// }?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! embed_contexts {
+    ($($iri:expr => $path:expr),* $(,)?) => {
+        $crate::remote::StaticLoader::from_sources(vec![$(($iri, include_str!($path))),*])
+    };
+}
+
+/// Error on loading a remote document by `StaticLoader`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("No document is registered for the IRI <{iri}>")]
+pub struct StaticLoadError {
+    /// The requested IRI.
+    iri: String,
+}
+
+impl StaticLoadError {
+    /// Returns the requested IRI.
+    pub fn iri(&self) -> &str {
+        &self.iri
+    }
+}
+
+/// Remote document loader using the fixed set of in-memory documents.
+///
+/// If no document is registered for the requested IRI with a fragment, the document for the IRI
+/// without the fragment is used.
+#[derive(Debug, Clone, Default)]
+pub struct StaticLoader {
+    /// Documents.
+    documents: HashMap<String, Arc<RemoteDocument>>,
+}
+
+impl StaticLoader {
+    /// Creates a new empty `StaticLoader`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the document for the given IRI.
+    pub fn insert(&mut self, iri: IriString, document: Value) {
+        let iri = iri.as_str().to_owned();
+        let document = RemoteDocument::new(iri.clone(), document)
+            .content_type("application/ld+json".to_owned());
+        self.documents.insert(iri, Arc::new(document));
+    }
+
+    /// Creates a new `StaticLoader` with the documents in the given pairs of the IRI and the JSON
+    /// source text.
+    ///
+    /// This is used by [`embed_contexts!`](../macro.embed_contexts.html) macro.
+    pub fn from_sources<'a>(sources: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self> {
+        let mut loader = Self::new();
+        for (iri, source) in sources {
+            loader.insert_source(iri, source)?;
+        }
+        Ok(loader)
+    }
+
+    /// Registers the document in the given JSON source text for the given IRI.
+    pub fn insert_source(&mut self, iri: &str, source: &str) -> Result<()> {
+        let iri_str =
+            IriStr::new(iri).map_err(|e| ErrorCode::LoadingDocumentFailed.and_iri_error(iri, e))?;
+        let document = serde_json::from_str(source).map_err(|e| {
+            ErrorCode::LoadingDocumentFailed
+                .and_message(format!("Invalid JSON for <{}>: {}", iri, e))
+        })?;
+        self.insert(iri_str.to_owned(), document);
+        Ok(())
+    }
+
+    /// Returns the document for the given IRI.
+    fn get(&self, iri: &str) -> Option<&Arc<RemoteDocument>> {
+        self.documents.get(iri).or_else(|| {
+            let iri_without_fragment = iri.splitn(2, '#').next()?;
+            self.documents.get(iri_without_fragment)
+        })
+    }
+}
+
+impl From<HashMap<IriString, Value>> for StaticLoader {
+    fn from(documents: HashMap<IriString, Value>) -> Self {
+        documents.into_iter().collect()
+    }
+}
+
+impl iter::FromIterator<(IriString, Value)> for StaticLoader {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = (IriString, Value)>,
+    {
+        let mut loader = StaticLoader::new();
+        loader.extend(iter);
+        loader
+    }
+}
+
+impl iter::Extend<(IriString, Value)> for StaticLoader {
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = (IriString, Value)>,
+    {
+        iter.into_iter()
+            .for_each(|(iri, document)| self.insert(iri, document));
+    }
+}
+
+#[async_trait]
+impl LoadRemoteDocument for StaticLoader {
+    type Error = StaticLoadError;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        _options: LoadDocumentOptions,
+    ) -> std::result::Result<Arc<RemoteDocument>, Self::Error> {
+        self.get(iri.as_str())
+            .cloned()
+            .ok_or_else(|| StaticLoadError {
+                iri: iri.as_str().to_owned(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn get() {
+        let iri = IriStr::new("http://example.com/context.jsonld")
+            .expect("valid IRI")
+            .to_owned();
+        let loader: StaticLoader = iter::once((iri, json!({"@context": {}}))).collect();
+        assert_eq!(
+            loader
+                .get("http://example.com/context.jsonld#frag")
+                .map(|doc| doc.document()),
+            Some(&json!({"@context": {}}))
+        );
+        assert!(loader.get("http://example.com/other.jsonld").is_none());
+    }
+
+    #[test]
+    fn insert_source() {
+        let mut loader = StaticLoader::new();
+        loader
+            .insert_source("http://example.com/a.jsonld", r#"{"@context": {}}"#)
+            .expect("valid document");
+        assert!(loader.get("http://example.com/a.jsonld").is_some());
+        assert!(loader
+            .insert_source("http://example.com/b.jsonld", "{")
+            .is_err());
+    }
+
+    #[test]
+    fn from_sources() {
+        let loader = StaticLoader::from_sources(vec![
+            ("http://example.com/a.jsonld", r#"{"@context": {}}"#),
+            ("http://example.com/b.jsonld", r#"{"@context": {"a": "b"}}"#),
+        ])
+        .expect("valid documents");
+        assert!(loader.get("http://example.com/b.jsonld").is_some());
+        assert!(StaticLoader::from_sources(vec![
+            ("http://example.com/a.jsonld", r#"{"@context": {}}"#),
+            ("http://example.com/b.jsonld", "{"),
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,339 @@
+//! Remote document loader using HTTP(S) by `surf`.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#loaddocumentcallback>.
+
+use std::{error::Error, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+use surf::{
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    middleware::{HttpClient, Middleware, Next, Request, Response},
+};
+
+use crate::{
+    html::extract_json_ld,
+    remote::{
+        header::{
+            accept_header, alternate_link, check_body_size, check_content_length, context_link,
+            resolve, MediaType,
+        },
+        retry::{with_retry, with_timeout},
+        LoadDocumentOptions, LoadRemoteDocument, MultipleContextLinkHeaders, RedirectPolicy,
+        RemoteDocument, RetryPolicy, SizeLimitExceeded,
+    },
+};
+
+/// Default maximum number of redirects to follow.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Error on loading a remote document by `SurfLoader`.
+#[derive(Debug, thiserror::Error)]
+pub enum SurfLoadError {
+    /// HTTP request failed.
+    #[error("HTTP request failed: {0}")]
+    Request(#[source] surf::Exception),
+    /// The server responded with an unsuccessful status.
+    #[error("Unsuccessful HTTP status: {0}")]
+    Status(u16),
+    /// Loading the document timed out.
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+    /// The document has an unsupported content type.
+    #[error("Unsupported content type: {0:?}")]
+    UnsupportedContentType(String),
+    /// The response has multiple HTTP `Link` headers for the JSON-LD context.
+    #[error("{0}")]
+    MultipleContextLinkHeaders(#[source] MultipleContextLinkHeaders),
+    /// The redirect response has no valid `Location` header.
+    #[error("Invalid redirect from <{0}>")]
+    InvalidRedirect(String),
+    /// The number of redirects exceeds the limit.
+    #[error("Too many redirects (more than {0})")]
+    TooManyRedirects(usize),
+    /// The redirect is refused by the redirect policy.
+    #[error("Redirect to <{url}> is refused: {source}")]
+    RedirectRefused {
+        /// Redirect target.
+        url: String,
+        /// Reason of the refusal.
+        source: Box<dyn Error + Send + Sync + 'static>,
+    },
+    /// The document is not valid JSON.
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Failed to extract JSON-LD from the HTML document.
+    #[error("Failed to extract JSON-LD from HTML: {0}")]
+    Html(#[source] crate::Error),
+    /// The document exceeds the size limit.
+    #[error("{0}")]
+    TooLarge(#[source] SizeLimitExceeded),
+}
+
+/// Remote document loader using HTTP(S) by `surf`, for projects using async-std instead of tokio.
+///
+/// Documents are requested and interpreted in the same way as
+/// [`HttpLoader`](struct.HttpLoader.html), except for the following:
+///
+/// * Loaded documents are not cached. Wrap the loader by
+///   [`CachingLoader`](struct.CachingLoader.html) to cache them.
+/// * The timeout given by `LoadDocumentOptions::timeout()` applies to each attempt as a whole,
+///   including redirects, the alternate document, and reading the body.
+///   The timer runs on a thread, so that it works with any async runtime.
+/// * The size limit is checked by the `Content-Length` header and after the body is read.
+///
+/// Failed loads are retried if the retry policy is set.
+///
+/// If the redirect policy is set, every redirect is checked by it before the request is sent.
+#[derive(Debug, Clone)]
+pub struct SurfLoader {
+    /// Maximum number of redirects to follow.
+    max_redirects: usize,
+    /// Retry policy.
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
+    /// Redirect policy.
+    redirect_policy: Option<Arc<dyn RedirectPolicy>>,
+}
+
+impl Default for SurfLoader {
+    fn default() -> Self {
+        Self {
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            retry_policy: None,
+            redirect_policy: None,
+        }
+    }
+}
+
+impl SurfLoader {
+    /// Creates a new `SurfLoader`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of redirects to follow.
+    pub fn max_redirects(self, max_redirects: usize) -> Self {
+        Self {
+            max_redirects,
+            ..self
+        }
+    }
+
+    /// Sets the retry policy for failed loads.
+    pub fn retry_policy(self, retry_policy: impl RetryPolicy + 'static) -> Self {
+        Self {
+            retry_policy: Some(Arc::new(retry_policy)),
+            ..self
+        }
+    }
+
+    /// Sets the policy to check redirects before following them.
+    pub fn redirect_policy(self, redirect_policy: impl RedirectPolicy + 'static) -> Self {
+        Self {
+            redirect_policy: Some(Arc::new(redirect_policy)),
+            ..self
+        }
+    }
+
+    /// Sends the request following redirects, and returns the final URL and the response.
+    async fn fetch(
+        &self,
+        url: &str,
+        options: &LoadDocumentOptions,
+    ) -> Result<(String, surf::Response), SurfLoadError> {
+        let mut url = url.to_owned();
+        let mut num_redirects = 0;
+        loop {
+            let mut request = surf::get(&url).set_header("Accept", accept_header(options));
+            // Custom headers may contain credentials, so they are not sent after redirects.
+            if num_redirects == 0 && !options.headers().is_empty() {
+                request = request.middleware(CustomHeaders(options.headers().to_vec()));
+            }
+            let response = request.await.map_err(SurfLoadError::Request)?;
+            let status = response.status().as_u16();
+            if (300..400).contains(&status) {
+                if num_redirects >= self.max_redirects {
+                    return Err(SurfLoadError::TooManyRedirects(self.max_redirects));
+                }
+                let next = response
+                    .header("Location")
+                    .map(|location| resolve(&url, location))
+                    .ok_or_else(|| SurfLoadError::InvalidRedirect(url.clone()))?;
+                if let Some(policy) = &self.redirect_policy {
+                    policy.check_redirect(&url, &next).await.map_err(|source| {
+                        SurfLoadError::RedirectRefused {
+                            url: next.clone(),
+                            source,
+                        }
+                    })?;
+                }
+                trace_event!(
+                    from = url.as_str(),
+                    to = next.as_str(),
+                    "Following the redirect"
+                );
+                url = next;
+                num_redirects += 1;
+                continue;
+            }
+            if !(200..300).contains(&status) {
+                return Err(SurfLoadError::Status(status));
+            }
+            return Ok((url, response));
+        }
+    }
+
+    /// Loads the document without retrying, giving up after the timeout.
+    async fn load_with_timeout(
+        &self,
+        iri: &IriStr,
+        options: &LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, SurfLoadError> {
+        match options.timeout() {
+            Some(timeout) => with_timeout(timeout, self.load_once(iri, options))
+                .await
+                .unwrap_or(Err(SurfLoadError::Timeout(timeout))),
+            None => self.load_once(iri, options).await,
+        }
+    }
+
+    /// Loads the document without retrying.
+    async fn load_once(
+        &self,
+        iri: &IriStr,
+        options: &LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, SurfLoadError> {
+        let (document_url, mut response) = self.fetch(iri.as_str(), options).await?;
+        let mut url = document_url.clone();
+        let mut headers = response_headers(&mut response);
+        let mut media_type = MediaType::from_header(header_lookup(&headers));
+        // Follow the alternate JSON-LD document.
+        if !media_type.is_json() {
+            if let Some(alternate) = alternate_link(header_lookup(&headers), &url) {
+                trace_event!(url = alternate.as_str(), "Following the alternate link");
+                let (alternate_url, alternate_response) = self.fetch(&alternate, options).await?;
+                url = alternate_url;
+                response = alternate_response;
+                headers = response_headers(&mut response);
+                media_type = MediaType::from_header(header_lookup(&headers));
+            }
+        }
+
+        let context_url = if media_type.is_json() && media_type.essence != "application/ld+json" {
+            context_link(header_lookup(&headers), &url)
+                .map_err(SurfLoadError::MultipleContextLinkHeaders)?
+        } else {
+            None
+        };
+        let body = read_body(&mut response, &headers, options.max_size()).await?;
+        let document = if media_type.is_html() {
+            let fragment = iri.as_str().splitn(2, '#').nth(1);
+            extract_json_ld(&body, fragment, options.should_extract_all_scripts())
+                .map_err(SurfLoadError::Html)?
+        } else if media_type.is_json() {
+            serde_json::from_str(&body)?
+        } else {
+            return Err(SurfLoadError::UnsupportedContentType(media_type.essence));
+        };
+
+        Ok(Arc::new(
+            RemoteDocument::new(document_url, document)
+                .context_url(context_url)
+                .content_type(media_type.essence)
+                .profile(media_type.profile),
+        ))
+    }
+}
+
+#[async_trait]
+impl LoadRemoteDocument for SurfLoader {
+    type Error = SurfLoadError;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        with_retry(self.retry_policy.as_ref().map(|p| &**p), || {
+            self.load_with_timeout(iri, &options)
+        })
+        .await
+    }
+}
+
+/// Reads the response body as text, refusing bodies larger than `max_size` bytes.
+async fn read_body(
+    response: &mut surf::Response,
+    headers: &[(String, String)],
+    max_size: Option<u64>,
+) -> Result<String, SurfLoadError> {
+    check_content_length(header_lookup(headers), max_size).map_err(SurfLoadError::TooLarge)?;
+    let body = response
+        .body_string()
+        .await
+        .map_err(SurfLoadError::Request)?;
+    check_body_size(body.len(), max_size).map_err(SurfLoadError::TooLarge)?;
+    Ok(body)
+}
+
+/// Returns the header fields of the response as pairs of the name and the value.
+///
+/// `surf::Response::header()` accepts only static names, so the fields are copied to be looked up
+/// by arbitrary names.
+fn response_headers(response: &mut surf::Response) -> Vec<(String, String)> {
+    response
+        .headers()
+        .iter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// Returns the closure to look up the header in the header fields of the response.
+///
+/// Values of the header fields with the same name are joined by commas.
+/// See <https://tools.ietf.org/html/rfc7230#section-3.2.2>.
+fn header_lookup(headers: &[(String, String)]) -> impl Fn(&str) -> Option<String> + '_ {
+    move |name: &str| {
+        headers
+            .iter()
+            .filter(|(field, _)| field.eq_ignore_ascii_case(name))
+            .fold(None, |joined, (_, value)| match joined {
+                Some(joined) => Some(format!("{}, {}", joined, value)),
+                None => Some(value.clone()),
+            })
+    }
+}
+
+/// Middleware adding the custom request headers.
+///
+/// `surf::Request::set_header()` accepts only static names, so the headers are added to the
+/// underlying HTTP request.
+struct CustomHeaders(Vec<(String, String)>);
+
+impl<C: HttpClient> Middleware<C> for CustomHeaders {
+    fn handle<'a>(
+        &'a self,
+        mut req: Request,
+        client: C,
+        next: Next<'a, C>,
+    ) -> Pin<Box<dyn Future<Output = Result<Response, surf::Exception>> + Send + 'a>> {
+        match insert_headers(req.headers_mut(), &self.0) {
+            Ok(()) => next.run(req, client),
+            Err(e) => Box::pin(async move { Err(e) }),
+        }
+    }
+}
+
+/// Inserts the headers given as pairs of the name and the value.
+fn insert_headers(
+    headers: &mut HeaderMap,
+    fields: &[(String, String)],
+) -> Result<(), surf::Exception> {
+    for (name, value) in fields {
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Adapter of synchronous remote document loaders.
+
+use std::{error::Error, sync::Arc};
+
+use async_trait::async_trait;
+use iri_string::types::IriStr;
+
+use crate::remote::{LoadDocumentOptions, LoadRemoteDocument, RemoteDocument};
+
+/// A trait for types which can be used as synchronous remote document loader.
+///
+/// Wrap the loader by [`SyncLoader`](struct.SyncLoader.html) to pass it to the processor.
+///
+/// See [`LoadRemoteDocument`](trait.LoadRemoteDocument.html) for caching.
+pub trait LoadRemoteDocumentSync: Send + Sync {
+    /// Error type.
+    type Error: Error + Send + Sync + 'static;
+
+    /// Loads a remote document.
+    ///
+    /// This blocks the task driving the processor until the document is loaded.
+    fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error>;
+}
+
+impl<L: LoadRemoteDocumentSync + ?Sized> LoadRemoteDocumentSync for &L {
+    type Error = L::Error;
+
+    fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        (**self).load(iri, options)
+    }
+}
+
+/// Remote document loader using a synchronous loader.
+#[derive(Debug, Clone, Default)]
+pub struct SyncLoader<L> {
+    /// Synchronous loader.
+    loader: L,
+}
+
+impl<L: LoadRemoteDocumentSync> SyncLoader<L> {
+    /// Creates a new `SyncLoader`.
+    pub fn new(loader: L) -> Self {
+        Self { loader }
+    }
+
+    /// Returns the synchronous loader.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Returns the synchronous loader.
+    pub fn into_inner(self) -> L {
+        self.loader
+    }
+}
+
+#[async_trait]
+impl<L: LoadRemoteDocumentSync> LoadRemoteDocument for SyncLoader<L> {
+    type Error = L::Error;
+
+    async fn load(
+        &self,
+        iri: &IriStr,
+        options: LoadDocumentOptions,
+    ) -> Result<Arc<RemoteDocument>, Self::Error> {
+        LoadRemoteDocumentSync::load(&self.loader, iri, options)
+    }
+}
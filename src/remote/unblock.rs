@@ -0,0 +1,123 @@
+//! Blocking operations run off the executor.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+/// Runs the blocking function on another thread, and returns the future of the result.
+///
+/// This works with any async runtime without blocking the executor.
+/// The future fails if the thread cannot be spawned or the function panics.
+pub(crate) fn unblock<T, F>(f: F) -> Unblock<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let state = Arc::new(Mutex::new(UnblockState {
+        result: None,
+        done: false,
+        waker: None,
+    }));
+    let completion = Completion {
+        state: state.clone(),
+    };
+    let spawn_error = thread::Builder::new()
+        .name("json-ld-blocking".to_owned())
+        .spawn(move || {
+            let result = f();
+            completion.lock().result = Some(result);
+            // `completion` is dropped here, and wakes the future.
+        })
+        .err();
+    Unblock { state, spawn_error }
+}
+
+/// Future of the result of a blocking function run by `unblock()`.
+pub(crate) struct Unblock<T> {
+    /// Shared state.
+    state: Arc<Mutex<UnblockState<T>>>,
+    /// Error on spawning the thread.
+    spawn_error: Option<io::Error>,
+}
+
+/// State of `Unblock`.
+struct UnblockState<T> {
+    /// Result of the function, or `None` if not yet available.
+    result: Option<T>,
+    /// Whether the thread finished (including by a panic).
+    done: bool,
+    /// Waker of the task waiting for the result.
+    waker: Option<Waker>,
+}
+
+/// Marker owned by the thread, which marks the operation done and wakes the future on drop.
+///
+/// This is dropped even if the function panics, so that the future does not wait forever.
+struct Completion<T> {
+    /// Shared state.
+    state: Arc<Mutex<UnblockState<T>>>,
+}
+
+impl<T> Completion<T> {
+    /// Locks the state.
+    fn lock(&self) -> MutexGuard<'_, UnblockState<T>> {
+        // The state is always consistent, so it can be used even if another thread panicked.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        let mut state = self.lock();
+        state.done = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Future for Unblock<T> {
+    type Output = io::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(e) = this.spawn_error.take() {
+            return Poll::Ready(Err(e));
+        }
+        let mut state = this.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(result) = state.result.take() {
+            return Poll::Ready(Ok(result));
+        }
+        if state.done {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the blocking operation panicked",
+            )));
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn result() {
+        assert_eq!(block_on(unblock(|| 42)).expect("thread should run"), 42);
+    }
+
+    #[test]
+    fn panic() {
+        let res = block_on(unblock(|| -> u32 { panic!("blocking function panicked") }));
+        assert!(res.is_err());
+    }
+}
@@ -1,5 +1,7 @@
 //! JSON-LD syntax related stuff.
 
+use serde_json::Value;
+
 /// Checks whether a string has the form of a keyword.
 ///
 /// > having the form of a keyword (i.e., it matches the ABNF rule `"@"1*ALPHA` from \[RFC5234\]),
@@ -8,3 +10,76 @@
 pub(crate) fn has_form_of_keyword(s: &str) -> bool {
     s.len() >= 2 && s.starts_with('@') && s[1..].bytes().all(|b| b.is_ascii_alphabetic())
 }
+
+/// Checks whether the given string is a blank node identifier.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-blank-node-identifier>.
+pub(crate) fn is_blank_node_identifier(s: &str) -> bool {
+    s.starts_with("_:")
+}
+
+/// Checks whether the given string is a framing keyword.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-framing-20191112/#framing-keywords>.
+pub(crate) fn is_framing_keyword(s: &str) -> bool {
+    match s {
+        "@default" | "@embed" | "@explicit" | "@omitDefault" | "@requireAll" => true,
+        _ => false,
+    }
+}
+
+/// Checks whether the given string is a well-formed language tag.
+///
+/// See <https://tools.ietf.org/html/bcp47#section-2.1>.
+pub(crate) fn is_well_formed_language(s: &str) -> bool {
+    let mut subtags = s.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    let is_alpha = |tag: &str| tag.bytes().all(|b| b.is_ascii_alphabetic());
+    let is_alnum = |tag: &str| tag.bytes().all(|b| b.is_ascii_alphanumeric());
+    (1..=8).contains(&primary.len())
+        && is_alpha(primary)
+        && subtags.all(|tag| (1..=8).contains(&tag.len()) && is_alnum(tag))
+}
+
+/// Checks whether the given value is a value object.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-value-object>.
+pub(crate) fn is_value_object(v: &Value) -> bool {
+    v.as_object()
+        .map_or(false, |map| map.contains_key("@value"))
+}
+
+/// Checks whether the given value is a list object.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-list-object>.
+pub(crate) fn is_list_object(v: &Value) -> bool {
+    v.as_object().map_or(false, |map| map.contains_key("@list"))
+}
+
+/// Checks whether the given value is a node object.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-node-object>.
+pub(crate) fn is_node_object(v: &Value) -> bool {
+    v.as_object().map_or(false, |map| {
+        !map.contains_key("@value") && !map.contains_key("@list") && !map.contains_key("@set")
+    })
+}
+
+/// Checks whether the given value is a graph object.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-graph-object>.
+pub(crate) fn is_graph_object(v: &Value) -> bool {
+    v.as_object().map_or(false, |map| {
+        map.contains_key("@graph")
+            && map
+                .keys()
+                .all(|k| k == "@graph" || k == "@id" || k == "@index" || k == "@context")
+    })
+}
+
+/// Checks whether the given value is a simple graph object.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-20191112/#dfn-simple-graph-object>.
+pub(crate) fn is_simple_graph_object(v: &Value) -> bool {
+    is_graph_object(v) && v.get("@id").is_none()
+}
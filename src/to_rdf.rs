@@ -0,0 +1,467 @@
+//! Deserialization of JSON-LD to RDF.
+//!
+//! See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#deserialize-json-ld-to-rdf-algorithm>.
+
+use serde_json::{Map as JsonMap, Number, Value};
+
+use crate::{
+    error::{ErrorCode, Result},
+    expand::expand,
+    iri::{is_absolute_iri_ref, is_absolute_ref_or_blank_node_ident},
+    json::{map_entries, to_canonical_string},
+    node_map::{generate_node_map, BlankNodeIdGenerator, Graph, NodeMap},
+    options::RdfDirection,
+    processor::Processor,
+    rdf::{
+        GraphName, Literal, Object, Predicate, Quad, Subject, Triple, I18N_BASE, RDF_DIRECTION,
+        RDF_FIRST, RDF_JSON, RDF_LANGUAGE, RDF_LANG_STRING, RDF_NIL, RDF_REST, RDF_TYPE, RDF_VALUE,
+        XSD_BOOLEAN, XSD_DOUBLE, XSD_INTEGER, XSD_STRING,
+    },
+    remote::LoadRemoteDocument,
+    syntax::{is_blank_node_identifier, is_well_formed_language},
+    warning::Warning,
+};
+
+/// Options for deserialization of JSON-LD to RDF.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ToRdfOptions {
+    /// `produceGeneralizedRdf` flag.
+    produce_generalized_rdf: bool,
+    /// Method of representing `@direction` in RDF.
+    rdf_direction: Option<RdfDirection>,
+    /// `safe` flag.
+    safe: bool,
+}
+
+impl ToRdfOptions {
+    /// Creates a new default `ToRdfOptions`.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `produceGeneralizedRdf` flag.
+    pub(crate) fn produce_generalized_rdf(self, produce_generalized_rdf: bool) -> Self {
+        Self {
+            produce_generalized_rdf,
+            ..self
+        }
+    }
+
+    /// Sets the method of representing `@direction` in RDF.
+    pub(crate) fn rdf_direction(self, rdf_direction: Option<RdfDirection>) -> Self {
+        Self {
+            rdf_direction,
+            ..self
+        }
+    }
+
+    /// Sets the `safe` flag.
+    pub(crate) fn safe(self, safe: bool) -> Self {
+        Self { safe, ..self }
+    }
+}
+
+/// Runs deserialization of JSON-LD to RDF for the given document, and returns the RDF dataset.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-tordf>.
+pub(crate) async fn to_rdf<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    options: ToRdfOptions,
+) -> Result<Vec<Quad>> {
+    let mut dataset = Vec::new();
+    to_rdf_with_handler(processor, input, options, &mut |quad| dataset.push(quad)).await?;
+    Ok(dataset)
+}
+
+/// Runs deserialization of JSON-LD to RDF for the given document, and passes each quad to the
+/// handler.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#dom-jsonldprocessor-tordf>.
+pub(crate) async fn to_rdf_with_handler<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    input: &Value,
+    options: ToRdfOptions,
+    handler: &mut impl FnMut(Quad),
+) -> Result<()> {
+    // Step 2
+    let expanded_input = expand(processor, input).await?;
+    // Step 3, 4
+    let mut generator = BlankNodeIdGenerator::new();
+    let mut node_map = NodeMap::new();
+    node_map.insert("@default".to_owned(), Graph::new());
+    generate_node_map(&expanded_input, &mut node_map, &mut generator)?;
+
+    node_map_to_rdf(processor, &node_map, &mut generator, options, handler)
+}
+
+/// Runs deserialization of JSON-LD to RDF for the given node map, and passes each quad to the
+/// handler.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#deserialize-json-ld-to-rdf-algorithm>.
+pub(crate) fn node_map_to_rdf<L: LoadRemoteDocument>(
+    processor: &Processor<L>,
+    node_map: &NodeMap,
+    generator: &mut BlankNodeIdGenerator,
+    options: ToRdfOptions,
+    handler: &mut impl FnMut(Quad),
+) -> Result<()> {
+    // Step 1
+    for (graph_name, graph) in node_map {
+        // Step 1.1
+        let graph_name = if graph_name == "@default" {
+            None
+        } else if is_absolute_ref_or_blank_node_ident(graph_name) {
+            Some(GraphName::from_node_id(graph_name.as_str()))
+        } else {
+            check_safe(options, || {
+                format!("Graph {:?} is dropped: not an absolute IRI", graph_name)
+            })?;
+            continue;
+        };
+        // Step 1.2, 1.3
+        for (subject, node) in graph {
+            // Step 1.3.1
+            if !is_absolute_ref_or_blank_node_ident(subject) {
+                check_safe(options, || {
+                    format!("Node {:?} is dropped: not an absolute IRI", subject)
+                })?;
+                continue;
+            }
+            let subject = Subject::from_node_id(subject.as_str());
+            // Step 1.3.2
+            for (property, values) in map_entries(node, true) {
+                if property == "@type" {
+                    // Step 1.3.2.1
+                    for ty in values.as_array().map_or(&[][..], Vec::as_slice) {
+                        if let Some(ty) = ty.as_str() {
+                            if is_absolute_ref_or_blank_node_ident(ty) {
+                                handler(Quad::new(
+                                    subject.clone(),
+                                    Predicate::Iri(RDF_TYPE.to_owned()),
+                                    Object::from_node_id(ty),
+                                    graph_name.clone(),
+                                ));
+                            } else {
+                                check_safe(options, || {
+                                    format!("Type {:?} is dropped: not an absolute IRI", ty)
+                                })?;
+                            }
+                        }
+                    }
+                    continue;
+                } else if property.starts_with('@') {
+                    // Step 1.3.2.2
+                    continue;
+                } else if is_blank_node_identifier(property) {
+                    // Step 1.3.2.3
+                    if !options.produce_generalized_rdf {
+                        check_safe(options, || {
+                            format!("Blank node predicate {:?} is dropped", property)
+                        })?;
+                        processor.warn(Warning::BlankNodePredicate(property.clone()));
+                        continue;
+                    }
+                } else if !is_absolute_iri_ref(property) {
+                    // Step 1.3.2.4
+                    check_safe(options, || {
+                        format!("Property {:?} is dropped: not an absolute IRI", property)
+                    })?;
+                    continue;
+                }
+                // Step 1.3.2.5
+                let predicate = Predicate::from_node_id(property.as_str());
+                for item in values.as_array().map_or(&[][..], Vec::as_slice) {
+                    // Step 1.3.2.5.1
+                    let mut list_triples = Vec::new();
+                    // Step 1.3.2.5.2
+                    if let Some(object) =
+                        object_to_rdf(item, &mut list_triples, generator, options)?
+                    {
+                        handler(Quad::new(
+                            subject.clone(),
+                            predicate.clone(),
+                            object,
+                            graph_name.clone(),
+                        ));
+                    }
+                    // Step 1.3.2.5.3
+                    for triple in list_triples {
+                        handler(triple.in_graph(graph_name.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns an error if the `safe` flag is set.
+///
+/// This is called when some data is about to be dropped silently.
+fn check_safe(options: ToRdfOptions, message: impl FnOnce() -> String) -> Result<()> {
+    if options.safe {
+        return Err(ErrorCode::SafeModeViolation.and_message(message()));
+    }
+    Ok(())
+}
+
+/// Converts the given item into an RDF term.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#object-to-rdf-conversion>.
+fn object_to_rdf(
+    item: &Value,
+    list_triples: &mut Vec<Triple>,
+    generator: &mut BlankNodeIdGenerator,
+    options: ToRdfOptions,
+) -> Result<Option<Object>> {
+    let item = match item.as_object() {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    // Step 3
+    if let Some(list) = item.get("@list") {
+        return list_to_rdf(
+            list.as_array().map_or(&[][..], Vec::as_slice),
+            list_triples,
+            generator,
+            options,
+        )
+        .map(Some);
+    }
+    let object = if !item.contains_key("@value") {
+        // Step 1, 2
+        item.get("@id")
+            .and_then(Value::as_str)
+            .filter(|id| is_absolute_ref_or_blank_node_ident(id))
+            .map(Object::from_node_id)
+    } else if let (Some(direction), Some(rdf_direction)) = (
+        item.get("@direction").and_then(Value::as_str),
+        options.rdf_direction,
+    ) {
+        // Step 13
+        directional_value_to_rdf(item, direction, rdf_direction, list_triples, generator)
+    } else {
+        value_to_rdf(item).map(Object::Literal)
+    };
+    if object.is_none() {
+        check_safe(options, || {
+            format!("Object {:?} is dropped: not convertible to RDF", item)
+        })?;
+    }
+    Ok(object)
+}
+
+/// Converts the given value object with a base direction into an RDF term.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#object-to-rdf-conversion>.
+// Step 13
+fn directional_value_to_rdf(
+    item: &JsonMap<String, Value>,
+    direction: &str,
+    rdf_direction: RdfDirection,
+    list_triples: &mut Vec<Triple>,
+    generator: &mut BlankNodeIdGenerator,
+) -> Option<Object> {
+    let value = item["@value"].as_str()?;
+    let language = item.get("@language").and_then(Value::as_str);
+    if let Some(language) = language {
+        if !is_well_formed_language(language) {
+            return None;
+        }
+    }
+    match rdf_direction {
+        // Step 13.1, 13.2
+        RdfDirection::I18nDatatype => {
+            let datatype = format!(
+                "{}{}_{}",
+                I18N_BASE,
+                language.map_or_else(String::new, str::to_ascii_lowercase),
+                direction
+            );
+            Some(Object::Literal(Literal::new(value, datatype, None)))
+        }
+        // Step 13.3
+        RdfDirection::CompoundLiteral => {
+            let id = generator.generate(None);
+            let subject = Subject::BlankNode(id.clone());
+            let string = |s: &str| Object::Literal(Literal::new(s, XSD_STRING, None));
+            list_triples.push(Triple::new(
+                subject.clone(),
+                Predicate::Iri(RDF_VALUE.to_owned()),
+                string(value),
+            ));
+            if let Some(language) = language {
+                list_triples.push(Triple::new(
+                    subject.clone(),
+                    Predicate::Iri(RDF_LANGUAGE.to_owned()),
+                    string(language),
+                ));
+            }
+            list_triples.push(Triple::new(
+                subject,
+                Predicate::Iri(RDF_DIRECTION.to_owned()),
+                string(direction),
+            ));
+            Some(Object::BlankNode(id))
+        }
+    }
+}
+
+/// Converts the given value object into an RDF literal.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#object-to-rdf-conversion>.
+// Step 4-14
+fn value_to_rdf(item: &JsonMap<String, Value>) -> Option<Literal> {
+    // Step 4
+    let value = &item["@value"];
+    // Step 5
+    let datatype = item.get("@type").and_then(Value::as_str);
+    // Step 6
+    if let Some(datatype) = datatype {
+        if datatype != "@json" && !is_absolute_iri_ref(datatype) {
+            return None;
+        }
+    }
+    // Step 7
+    let language = item.get("@language").and_then(Value::as_str);
+    if let Some(language) = language {
+        if !is_well_formed_language(language) {
+            return None;
+        }
+    }
+    let (value, datatype) = match value {
+        // Step 8
+        _ if datatype == Some("@json") => (to_canonical_string(value), RDF_JSON),
+        // Step 9
+        Value::Bool(v) => (v.to_string(), datatype.unwrap_or(XSD_BOOLEAN)),
+        Value::Number(v) => {
+            let f = v.as_f64().unwrap_or(0.0);
+            let is_integral = (v.is_i64() || v.is_u64() || f.fract() == 0.0) && f.abs() < 1e21;
+            if is_integral && datatype != Some(XSD_DOUBLE) {
+                // Step 11
+                let value = match (v.as_i64(), v.as_u64()) {
+                    (Some(i), _) => i.to_string(),
+                    (None, Some(u)) => u.to_string(),
+                    (None, None) => integer_literal(v).unwrap_or_else(|| format!("{:.0}", f)),
+                };
+                (value, datatype.unwrap_or(XSD_INTEGER))
+            } else {
+                // Step 10
+                (canonical_double(f), datatype.unwrap_or(XSD_DOUBLE))
+            }
+        }
+        Value::String(s) => {
+            // Step 12
+            let default_datatype = if language.is_some() {
+                RDF_LANG_STRING
+            } else {
+                XSD_STRING
+            };
+            (s.clone(), datatype.unwrap_or(default_datatype))
+        }
+        // Value objects never have null, array, or map values except for JSON literals.
+        _ => return None,
+    };
+    // Step 14
+    Some(Literal::new(
+        value,
+        datatype,
+        language.map(ToOwned::to_owned),
+    ))
+}
+
+/// Converts the given list into RDF triples, and returns the head of the list.
+///
+/// See <https://www.w3.org/TR/2019/WD-json-ld11-api-20191112/#list-to-rdf-conversion>.
+fn list_to_rdf(
+    list: &[Value],
+    list_triples: &mut Vec<Triple>,
+    generator: &mut BlankNodeIdGenerator,
+    options: ToRdfOptions,
+) -> Result<Object> {
+    // Step 1
+    if list.is_empty() {
+        return Ok(Object::Iri(RDF_NIL.to_owned()));
+    }
+    // Step 2
+    let bnodes = list
+        .iter()
+        .map(|_| generator.generate(None))
+        .collect::<Vec<_>>();
+    // Step 3
+    for (index, (item, subject)) in list.iter().zip(&bnodes).enumerate() {
+        let subject = Subject::BlankNode(subject.clone());
+        // Step 3.1
+        let mut embedded_triples = Vec::new();
+        // Step 3.2
+        if let Some(object) = object_to_rdf(item, &mut embedded_triples, generator, options)? {
+            list_triples.push(Triple::new(
+                subject.clone(),
+                Predicate::Iri(RDF_FIRST.to_owned()),
+                object,
+            ));
+        }
+        // Step 3.3
+        let rest = bnodes.get(index + 1).map_or_else(
+            || Object::Iri(RDF_NIL.to_owned()),
+            |id| Object::BlankNode(id.clone()),
+        );
+        list_triples.push(Triple::new(
+            subject,
+            Predicate::Iri(RDF_REST.to_owned()),
+            rest,
+        ));
+        // Step 3.4
+        list_triples.extend(embedded_triples);
+    }
+
+    // Step 4
+    Ok(Object::BlankNode(
+        bnodes
+            .into_iter()
+            .next()
+            .expect("Should never fail: the list is not empty"),
+    ))
+}
+
+/// Returns the canonical lexical form of `xsd:double`.
+///
+/// The mantissa has the shortest digits which are parsed to the same double value.
+fn canonical_double(v: f64) -> String {
+    let formatted = format!("{:E}", v);
+    match formatted.find('E') {
+        Some(pos) if !formatted[..pos].contains('.') => {
+            format!("{}.0{}", &formatted[..pos], &formatted[pos..])
+        }
+        _ => formatted,
+    }
+}
+
+/// Returns the number as is if it is written as an integer.
+///
+/// With `arbitrary_precision` feature, this keeps all the digits of integers which do not fit in
+/// 64 bits.
+fn integer_literal(v: &Number) -> Option<String> {
+    let literal = v.to_string();
+    if literal.contains(|c| c == '.' || c == 'e' || c == 'E') {
+        return None;
+    }
+    Some(literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_lexical_form() {
+        assert_eq!(canonical_double(1.1), "1.1E0");
+        assert_eq!(canonical_double(1.0), "1.0E0");
+        assert_eq!(canonical_double(-53.25), "-5.325E1");
+        assert_eq!(canonical_double(0.005), "5.0E-3");
+        assert_eq!(canonical_double(0.1 + 0.2), "3.0000000000000004E-1");
+        assert_eq!(canonical_double(1e300), "1.0E300");
+    }
+}
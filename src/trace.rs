@@ -0,0 +1,82 @@
+//! Tracing instrumentation.
+//!
+//! Spans and events are emitted only when `tracing` feature is enabled.
+//! Without the feature, the macros in this module expand to nothing (or the given future as is).
+
+#[cfg(feature = "tracing")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Instruments the given boxed future with a new `DEBUG` level span.
+///
+/// The arguments after the future are passed to `tracing::debug_span!`.
+#[cfg(feature = "tracing")]
+macro_rules! instrument {
+    ($future:expr, $($span:tt)+) => {
+        Box::pin($crate::trace::Instrumented::new(
+            $future,
+            tracing::debug_span!($($span)+),
+        ))
+    };
+}
+
+/// Instruments the given boxed future with a new `DEBUG` level span.
+///
+/// The arguments after the future are passed to `tracing::debug_span!`.
+#[cfg(not(feature = "tracing"))]
+macro_rules! instrument {
+    ($future:expr, $($span:tt)+) => {
+        $future
+    };
+}
+
+/// Emits a `DEBUG` level event.
+///
+/// The arguments are passed to `tracing::debug!`.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($($arg:tt)+) => {
+        tracing::debug!($($arg)+)
+    };
+}
+
+/// Emits a `DEBUG` level event.
+///
+/// The arguments are passed to `tracing::debug!`.
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($($arg:tt)+) => {};
+}
+
+/// Future instrumented with a span.
+///
+/// The span is entered every time the future is polled.
+#[cfg(feature = "tracing")]
+pub(crate) struct Instrumented<F> {
+    /// Inner future.
+    inner: F,
+    /// Span.
+    span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl<F: Future + Unpin> Instrumented<F> {
+    /// Creates a new `Instrumented`.
+    pub(crate) fn new(inner: F, span: tracing::Span) -> Self {
+        Self { inner, span }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<F: Future + Unpin> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let _enter = this.span.enter();
+        Pin::new(&mut this.inner).poll(cx)
+    }
+}
@@ -0,0 +1,55 @@
+//! Warnings.
+
+use std::fmt;
+
+/// Warning issued during processing.
+///
+/// The spec says processors SHOULD (or MAY) issue warnings in some situations where the input is
+/// suspicious but not an error. Such inputs are ignored or processed as usual, and a warning is
+/// given to the `WarningHandler` of the processor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Warning {
+    /// A term or an IRI has the form of a keyword (`"@"1*ALPHA`) but is not a keyword.
+    ///
+    /// The term definition or the value is ignored.
+    KeywordLikeValue(String),
+    /// A blank node is used as a predicate, and the triple is dropped because the
+    /// `produceGeneralizedRdf` flag is not set.
+    BlankNodePredicate(String),
+    /// A language tag is not well-formed according to BCP47.
+    ///
+    /// The language tag is used as is.
+    MalformedLanguageTag(String),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::KeywordLikeValue(v) => {
+                write!(f, "{:?} has the form of a keyword and is ignored", v)
+            }
+            Warning::BlankNodePredicate(v) => write!(
+                f,
+                "Blank node {:?} is used as a predicate, but generalized RDF is not allowed",
+                v
+            ),
+            Warning::MalformedLanguageTag(v) => {
+                write!(f, "Language tag {:?} is not well-formed", v)
+            }
+        }
+    }
+}
+
+/// Warning handler.
+///
+/// This is implemented for closures which take `Warning`.
+pub trait WarningHandler: Send + Sync {
+    /// Handles the warning.
+    fn handle(&self, warning: Warning);
+}
+
+impl<F: Fn(Warning) + Send + Sync> WarningHandler for F {
+    fn handle(&self, warning: Warning) {
+        self(warning)
+    }
+}
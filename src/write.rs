@@ -0,0 +1,131 @@
+//! Writing outputs into async writers.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+
+use futures_io::AsyncWrite;
+
+use crate::{error::Result, expand::write_error};
+
+/// Size of the buffered output above which the output is written before producing more.
+const MAX_BUFFERED_LEN: usize = 64 * 1024;
+
+/// Locks the buffer.
+fn lock(buf: &Mutex<Vec<u8>>) -> MutexGuard<'_, Vec<u8>> {
+    // The buffer is always consistent, so it can be used even if another thread panicked.
+    buf.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Synchronous writer appending to the buffer shared with `WriteThrough`.
+///
+/// This is a `Mutex` rather than a `RefCell`, so that the futures using it stay `Send`.
+#[derive(Debug)]
+pub(crate) struct SharedBuffer<'a>(pub(crate) &'a Mutex<Vec<u8>>);
+
+impl io::Write for SharedBuffer<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        lock(self.0).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Future which runs the producer writing into the shared buffer, and writes the buffered output
+/// into the async writer as it is produced.
+///
+/// The producer is not polled while the buffered output exceeds `MAX_BUFFERED_LEN` and the writer
+/// is not ready, so that a slow writer does not make the whole output buffered.
+pub(crate) struct WriteThrough<'a, F, W> {
+    /// Producer of the output.
+    producer: Pin<Box<F>>,
+    /// Whether the producer completed.
+    is_produced: bool,
+    /// Buffer shared with the producer.
+    buf: &'a Mutex<Vec<u8>>,
+    /// Writer.
+    writer: W,
+}
+
+impl<'a, F, W> WriteThrough<'a, F, W>
+where
+    F: Future<Output = Result<()>>,
+    W: AsyncWrite + Unpin,
+{
+    /// Creates a new `WriteThrough`.
+    pub(crate) fn new(producer: F, buf: &'a Mutex<Vec<u8>>, writer: W) -> Self {
+        Self {
+            producer: Box::pin(producer),
+            is_produced: false,
+            buf,
+            writer,
+        }
+    }
+
+    /// Writes the buffered output.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` if the buffer is emptied.
+    fn poll_write_buffered(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut buf = lock(self.buf);
+        while !buf.is_empty() {
+            match Pin::new(&mut self.writer).poll_write(cx, &buf) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the whole output",
+                    )))
+                }
+                Poll::Ready(Ok(len)) => {
+                    buf.drain(..len);
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<F, W> Future for WriteThrough<'_, F, W>
+where
+    F: Future<Output = Result<()>>,
+    W: AsyncWrite + Unpin,
+{
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            let written = match this.poll_write_buffered(cx) {
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(write_error(e))),
+                Poll::Ready(Ok(())) => true,
+                Poll::Pending => false,
+            };
+            if this.is_produced {
+                if !written {
+                    return Poll::Pending;
+                }
+                return Pin::new(&mut this.writer)
+                    .poll_flush(cx)
+                    .map_err(write_error);
+            }
+            if !written && lock(this.buf).len() >= MAX_BUFFERED_LEN {
+                return Poll::Pending;
+            }
+            match this.producer.as_mut().poll(cx) {
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(())) => this.is_produced = true,
+                // Write the output produced by this poll, unless the writer is not ready.
+                Poll::Pending if written && !lock(this.buf).is_empty() => {}
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Helpers shared by the integration tests.
+
+use json_ld::{
+    iri_string::types::IriStr, remote::StaticLoader, JsonLdOptions, Processor, ProcessorOptions,
+};
+
+/// Creates a processor with the base IRI `http://example.com/doc` and no remote documents.
+pub fn processor() -> Processor<StaticLoader> {
+    let base = IriStr::new("http://example.com/doc").expect("valid IRI");
+    ProcessorOptions::with_base(base.to_owned()).build(StaticLoader::new())
+}
+
+/// Options keeping IRIs absolute, so that results do not depend on the base IRI.
+// Not every test crate including this module uses it.
+#[allow(dead_code)]
+pub fn options() -> JsonLdOptions {
+    JsonLdOptions::new().compact_to_relative(false)
+}
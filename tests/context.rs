@@ -0,0 +1,87 @@
+//! Serialization of processed contexts, checked by processing the result again.
+
+mod common;
+
+use futures_executor::block_on;
+use json_ld::{iri_string::types::IriStr, Context, JsonLdOptions, ProcessingMode};
+use serde_json::{json, Value};
+
+use self::common::processor;
+
+/// Processes the local context from scratch with the given options.
+fn process(local_context: &Value, options: &JsonLdOptions) -> Context {
+    let base = IriStr::new("http://example.com/doc").expect("valid IRI");
+    block_on(Context::process(&processor(), local_context, base, options))
+        .expect("context processing should succeed")
+}
+
+/// Serializes the processed context, processes the result again, and compares the contexts.
+fn assert_round_trip(local_context: &Value, options: &JsonLdOptions) -> Value {
+    let context = process(local_context, options);
+    let serialized = context.to_json();
+    let reprocessed = process(&serialized, options);
+    assert_eq!(reprocessed, context, "serialized = {:#}", serialized);
+    serialized
+}
+
+#[test]
+fn round_trip_json_ld_10() {
+    let local_context = json!({
+        "@base": "base/",
+        "@vocab": "http://example.com/vocab#",
+        "@language": "en",
+        "ex": "http://example.com/ns#",
+        "name": "ex:name",
+        "knows": { "@id": "ex:knows", "@type": "@id" },
+        "tags": { "@id": "ex:tags", "@container": "@set" },
+        "label": { "@id": "ex:label", "@language": null },
+        "parent": { "@reverse": "ex:child" },
+        "ignored": null,
+    });
+    let options = JsonLdOptions::new().processing_mode(ProcessingMode::JsonLd10);
+    let serialized = assert_round_trip(&local_context, &options);
+    assert!(serialized.get("@version").is_none());
+    assert_eq!(serialized["@base"], "http://example.com/base/");
+    assert_eq!(serialized["ex"], "http://example.com/ns#");
+}
+
+#[test]
+fn round_trip_json_ld_11() {
+    let local_context = json!({
+        "@version": 1.1,
+        "@base": null,
+        "@direction": "ltr",
+        "@protected": true,
+        "@type": { "@container": "@set" },
+        "ex": "http://example.com/ns#",
+        "title": { "@id": "ex:title", "@direction": "rtl" },
+        "byType": { "@id": "ex:byType", "@container": ["@type", "@set"] },
+        "byKey": { "@id": "ex:byKey", "@container": "@index", "@index": "ex:key" },
+        "scoped": { "@id": "ex:scoped", "@context": { "inner": "ex:inner" } },
+        "nested": { "@id": "ex:nested", "@nest": "@nest" },
+        "data": { "@id": "ex:data", "@type": "@json" },
+        "vocab": { "@id": "http://example.com/vocab/", "@prefix": false },
+    });
+    let options = JsonLdOptions::new();
+    let serialized = assert_round_trip(&local_context, &options);
+    assert_eq!(serialized["@version"], 1.1);
+    assert_eq!(serialized["@base"], Value::Null);
+    assert_eq!(serialized["title"]["@protected"], true);
+}
+
+#[test]
+fn document_base_is_not_serialized() {
+    let local_context = json!({ "ex": "http://example.com/ns#" });
+    let serialized = assert_round_trip(&local_context, &JsonLdOptions::new());
+    assert!(serialized.get("@base").is_none());
+}
+
+#[test]
+fn round_trip_protected_type() {
+    let local_context = json!({
+        "@type": { "@protected": true },
+        "ex": "http://example.com/ns#",
+    });
+    let serialized = assert_round_trip(&local_context, &JsonLdOptions::new());
+    assert_eq!(serialized["@type"], json!({ "@protected": true }));
+}
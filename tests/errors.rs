@@ -0,0 +1,346 @@
+//! Errors reported for invalid documents, checked by their codes and sources.
+
+mod common;
+
+use std::error::Error as _;
+
+use futures_executor::block_on;
+use json_ld::{
+    iri_string::types::IriStr, remote::StaticLoader, Error, ErrorCode, JsonLdOptions,
+    KeywordValueError, ProcessingMode, Processor, ProcessorOptions,
+};
+use serde_json::{json, Value};
+
+use self::common::processor;
+
+/// Expands the document, and returns the error.
+fn expand_err(input: &Value) -> Error {
+    expand_err_with(input, &JsonLdOptions::new())
+}
+
+/// Expands the document with the options, and returns the error.
+fn expand_err_with(input: &Value, options: &JsonLdOptions) -> Error {
+    block_on(processor().expand(input, options)).expect_err("expansion should fail")
+}
+
+/// Expands a document with the context in JSON-LD 1.0 processing mode, and returns the error.
+fn expand_err_in_json_ld_10(context: Value) -> Error {
+    let input = json!({
+        "@context": context,
+        "http://example.com/p": "v",
+    });
+    let options = JsonLdOptions::new().processing_mode(ProcessingMode::JsonLd10);
+    expand_err_with(&input, &options)
+}
+
+/// Returns the keyword value error in the source chain of the error, if available.
+fn keyword_value_error(err: &Error) -> Option<&KeywordValueError> {
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<KeywordValueError>() {
+            return Some(err);
+        }
+        source = err.source();
+    }
+    None
+}
+
+#[test]
+fn invalid_container() {
+    for container in &[json!("@unknown"), json!(42)] {
+        let input = json!({
+            "@context": {
+                "p": { "@id": "http://example.com/p", "@container": container },
+            },
+            "p": "v",
+        });
+        let err = expand_err(&input);
+        assert_eq!(err.code(), ErrorCode::InvalidContainerMapping);
+        match keyword_value_error(&err) {
+            Some(KeywordValueError::Container(_)) => {}
+            source => panic!("unexpected source: {:?}", source),
+        }
+    }
+}
+
+#[test]
+fn invalid_direction() {
+    let contexts = [
+        json!({ "@direction": "up" }),
+        json!({ "p": { "@id": "http://example.com/p", "@direction": "up" } }),
+    ];
+    for context in &contexts {
+        let input = json!({
+            "@context": context,
+            "http://example.com/q": "v",
+        });
+        let err = expand_err(&input);
+        assert_eq!(err.code(), ErrorCode::InvalidBaseDirection);
+        match keyword_value_error(&err) {
+            Some(KeywordValueError::Direction(_)) => {}
+            source => panic!("unexpected source: {:?}", source),
+        }
+    }
+}
+
+#[test]
+fn memory_budget_exceeded() {
+    // Every entry expands to a long IRI, so the expanded document is much larger than the input.
+    let iri = format!("http://example.com/{}", "x".repeat(1000));
+    let input = json!({
+        "@context": {
+            "p": { "@id": iri, "@type": "@id" },
+        },
+        "@graph": (0..1000).map(|i| json!({ "p": i.to_string() })).collect::<Vec<_>>(),
+    });
+    assert!(input.to_string().len() < 32 * 1024);
+    let options = JsonLdOptions::new().memory_budget(256 * 1024);
+    let err = block_on(processor().expand(&input, &options))
+        .expect_err("expansion should exceed the budget");
+    assert_eq!(err.code(), ErrorCode::MemoryBudgetExceeded);
+
+    let options = JsonLdOptions::new().memory_budget(16 * 1024 * 1024);
+    let expanded =
+        block_on(processor().expand(&input, &options)).expect("expansion should succeed");
+    assert!(expanded.to_string().len() > 1000 * 1000);
+}
+
+#[test]
+fn safe_mode_expansion() {
+    let contexts = [
+        // Expanded to null.
+        json!({ "p": null }),
+        // Expanded to neither an IRI nor a keyword.
+        json!({}),
+    ];
+    for context in &contexts {
+        let input = json!({
+            "@context": context,
+            "@id": "http://example.com/s",
+            "p": "v",
+        });
+        let options = JsonLdOptions::new().safe(true);
+        let err = block_on(processor().expand(&input, &options))
+            .expect_err("dropping the property should fail");
+        assert_eq!(err.code(), ErrorCode::SafeModeViolation);
+        assert_eq!(err.path(), Some("/p"));
+        // The property is dropped silently without the `safe` flag.
+        assert!(block_on(processor().expand(&input, &JsonLdOptions::new())).is_ok());
+    }
+}
+
+#[test]
+fn safe_mode_to_rdf() {
+    // The node identifier is kept relative, since the base IRI is removed.
+    let input = json!({
+        "@context": { "@base": null },
+        "@id": "relative",
+        "http://example.com/p": "v",
+    });
+    let options = JsonLdOptions::new().safe(true);
+    let err =
+        block_on(processor().to_rdf(&input, &options)).expect_err("dropping the node should fail");
+    assert_eq!(err.code(), ErrorCode::SafeModeViolation);
+    let dataset = block_on(processor().to_rdf(&input, &JsonLdOptions::new()))
+        .expect("the node should be dropped silently");
+    assert!(dataset.is_empty());
+}
+
+#[test]
+fn invalid_included() {
+    let values = [
+        json!("http://example.com/b"),
+        json!({ "@value": "v" }),
+        json!([{ "@id": "http://example.com/b" }, { "@list": ["v"] }]),
+    ];
+    for value in &values {
+        // `@included` is expanded with the active property of the node, and free-floating
+        // values in a top-level node would be dropped instead of reported.
+        let input = json!({
+            "http://example.com/p": {
+                "@id": "http://example.com/a",
+                "@included": value,
+            },
+        });
+        let err = expand_err(&input);
+        assert_eq!(err.code(), ErrorCode::InvalidIncludedValue);
+    }
+}
+
+#[test]
+fn invalid_nest_term() {
+    let expanded = json!([{
+        "@id": "http://example.com/x",
+        "http://example.com/name": [{ "@value": "Alice" }],
+    }]);
+    // `other` is not an alias of `@nest`.
+    let context = json!({
+        "other": "http://example.com/other",
+        "name": { "@id": "http://example.com/name", "@nest": "other" },
+    });
+    let err = block_on(processor().compact(&expanded, &context, &JsonLdOptions::new()))
+        .expect_err("compaction should fail");
+    assert_eq!(err.code(), ErrorCode::InvalidNestValue);
+}
+
+#[test]
+fn invalid_propagate() {
+    for value in &[json!("true"), json!(null), json!(1)] {
+        let input = json!({
+            "@context": { "@propagate": value },
+            "http://example.com/p": "v",
+        });
+        assert_eq!(expand_err(&input).code(), ErrorCode::InvalidPropagateValue);
+    }
+    // `@propagate` is not available in JSON-LD 1.0, even with a valid value.
+    let input = json!({
+        "@context": { "@propagate": true },
+        "http://example.com/p": "v",
+    });
+    let options = JsonLdOptions::new().processing_mode(ProcessingMode::JsonLd10);
+    assert_eq!(
+        expand_err_with(&input, &options).code(),
+        ErrorCode::InvalidContextEntry
+    );
+}
+
+#[test]
+fn invalid_version() {
+    for version in &[json!("1.1"), json!(1.0)] {
+        let input = json!({
+            "@context": { "@version": version },
+            "http://example.com/p": "v",
+        });
+        assert_eq!(expand_err(&input).code(), ErrorCode::InvalidVersionValue);
+    }
+    // `1.1` is valid, but conflicts with the processing mode.
+    let input = json!({
+        "@context": { "@version": 1.1 },
+        "http://example.com/p": "v",
+    });
+    let options = JsonLdOptions::new().processing_mode(ProcessingMode::JsonLd10);
+    assert_eq!(
+        expand_err_with(&input, &options).code(),
+        ErrorCode::ProcessingModeConflict
+    );
+}
+
+#[test]
+fn scoped_context_in_json_ld_10() {
+    let err = expand_err_in_json_ld_10(json!({
+        "p": { "@id": "http://example.com/p", "@context": {} },
+    }));
+    assert_eq!(err.code(), ErrorCode::InvalidTermDefinition);
+}
+
+#[test]
+fn prefix_in_json_ld_10() {
+    let err = expand_err_in_json_ld_10(json!({
+        "ex": { "@id": "http://example.com/", "@prefix": true },
+    }));
+    assert_eq!(err.code(), ErrorCode::InvalidTermDefinition);
+}
+
+#[test]
+fn protected_in_json_ld_10() {
+    let err = expand_err_in_json_ld_10(json!({
+        "p": { "@id": "http://example.com/p", "@protected": true },
+    }));
+    assert_eq!(err.code(), ErrorCode::InvalidTermDefinition);
+}
+
+#[test]
+fn direction_in_json_ld_10() {
+    let err = expand_err_in_json_ld_10(json!({ "@direction": "ltr" }));
+    assert_eq!(err.code(), ErrorCode::InvalidContextEntry);
+    let err = expand_err_in_json_ld_10(json!({
+        "p": { "@id": "http://example.com/p", "@direction": "ltr" },
+    }));
+    assert_eq!(err.code(), ErrorCode::InvalidTermDefinition);
+}
+
+#[test]
+fn propagate_in_json_ld_10() {
+    let err = expand_err_in_json_ld_10(json!({ "@propagate": false }));
+    assert_eq!(err.code(), ErrorCode::InvalidContextEntry);
+}
+
+/// `@propagate` and `@direction` entries of a context are checked each by its own rules.
+#[test]
+fn propagate_and_direction_are_distinct() {
+    let input = json!({
+        "@context": { "@direction": "ltr" },
+        "http://example.com/p": "v",
+    });
+    assert!(block_on(processor().expand(&input, &JsonLdOptions::new())).is_ok());
+    let input = json!({
+        "@context": { "@direction": "ltr", "@propagate": "ltr" },
+        "http://example.com/p": "v",
+    });
+    assert_eq!(expand_err(&input).code(), ErrorCode::InvalidPropagateValue);
+}
+
+/// Creates a processor serving remote contexts `a.jsonld` and `b.jsonld`.
+///
+/// `a.jsonld` includes `b.jsonld`, which includes `a.jsonld` back if `cyclic`.
+/// The term `scoped` of `a.jsonld` has `a.jsonld` as its scoped context.
+fn remote_contexts_processor(cyclic: bool) -> Processor<StaticLoader> {
+    let mut loader = StaticLoader::new();
+    let a = json!({
+        "@context": [
+            "http://example.com/b.jsonld",
+            {
+                "scoped": {
+                    "@id": "http://example.com/scoped",
+                    "@context": "http://example.com/a.jsonld",
+                },
+            },
+        ],
+    });
+    let b = if cyclic {
+        json!({ "@context": ["http://example.com/a.jsonld", { "q": "http://example.com/q" }] })
+    } else {
+        json!({ "@context": { "q": "http://example.com/q" } })
+    };
+    loader
+        .insert_source("http://example.com/a.jsonld", &a.to_string())
+        .expect("valid document");
+    loader
+        .insert_source("http://example.com/b.jsonld", &b.to_string())
+        .expect("valid document");
+    let base = IriStr::new("http://example.com/doc").expect("valid IRI");
+    ProcessorOptions::with_base(base.to_owned()).build(loader)
+}
+
+#[test]
+fn recursive_context_inclusion() {
+    let input = json!({
+        "@context": "http://example.com/a.jsonld",
+        "q": "v",
+    });
+    let err = block_on(remote_contexts_processor(true).expand(&input, &JsonLdOptions::new()))
+        .expect_err("expansion should fail");
+    assert_eq!(err.code(), ErrorCode::RecursiveContextInclusion);
+    assert!(
+        err.to_string().contains(
+            "<http://example.com/a.jsonld> -> <http://example.com/b.jsonld> \
+             -> <http://example.com/a.jsonld>"
+        ),
+        "err = {}",
+        err
+    );
+
+    // A scoped context is processed on its own, and may refer to the remote context defining it.
+    let input = json!({
+        "@context": "http://example.com/a.jsonld",
+        "scoped": { "q": "v" },
+    });
+    let expanded = block_on(remote_contexts_processor(false).expand(&input, &JsonLdOptions::new()))
+        .expect("expansion should succeed");
+    assert_eq!(
+        expanded,
+        json!([{
+            "http://example.com/scoped": [{ "http://example.com/q": [{ "@value": "v" }] }],
+        }])
+    );
+}
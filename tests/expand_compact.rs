@@ -0,0 +1,329 @@
+//! Expansion and compaction of small documents, checked against hand-written results.
+
+mod common;
+
+use futures_executor::block_on;
+use json_ld::{iri_string::types::IriStr, JsonLdOptions};
+use serde_json::{json, Value};
+
+use self::common::{options, processor};
+
+/// Expands the document.
+fn expand(input: &Value) -> Value {
+    block_on(processor().expand(input, &options())).expect("expansion should succeed")
+}
+
+/// Compacts the document with the context.
+fn compact(input: &Value, context: &Value) -> Value {
+    block_on(processor().compact(input, context, &options())).expect("compaction should succeed")
+}
+
+/// Checks that `compacted` expands to `expanded` and `expanded` compacts back to `compacted`.
+fn assert_round_trip(compacted: Value, expanded: Value) {
+    let context = compacted["@context"].clone();
+    assert_eq!(expand(&compacted), expanded);
+    assert_eq!(compact(&expanded, &context), compacted);
+}
+
+#[test]
+fn expand_typed_values() {
+    let input = json!({
+        "@context": {
+            "name": "http://schema.org/name",
+            "knows": { "@id": "http://schema.org/knows", "@type": "@id" },
+        },
+        "@id": "http://example.com/alice",
+        "name": "Alice",
+        "knows": "http://example.com/bob",
+    });
+    assert_eq!(
+        expand(&input),
+        json!([{
+            "@id": "http://example.com/alice",
+            "http://schema.org/name": [{ "@value": "Alice" }],
+            "http://schema.org/knows": [{ "@id": "http://example.com/bob" }],
+        }])
+    );
+}
+
+#[test]
+fn iri_compaction() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "@vocab": "http://schema.org/",
+                "foaf": "http://xmlns.com/foaf/0.1/",
+            },
+            "@id": "http://example.com/alice",
+            "@type": "Person",
+            "name": "Alice",
+            "foaf:age": 42,
+        }),
+        json!([{
+            "@id": "http://example.com/alice",
+            "@type": ["http://schema.org/Person"],
+            "http://schema.org/name": [{ "@value": "Alice" }],
+            "http://xmlns.com/foaf/0.1/age": [{ "@value": 42 }],
+        }]),
+    );
+}
+
+#[test]
+fn relative_iri_compaction() {
+    let expanded = json!([{
+        "@id": "http://example.com/alice",
+        "http://schema.org/name": [{ "@value": "Alice" }],
+    }]);
+    let context = json!({ "name": "http://schema.org/name" });
+    let options = JsonLdOptions::new().base(
+        IriStr::new("http://example.com/doc")
+            .expect("valid IRI")
+            .to_owned(),
+    );
+    let compacted = block_on(processor().compact(&expanded, &context, &options))
+        .expect("compaction should succeed");
+    assert_eq!(
+        compacted,
+        json!({ "@context": context, "@id": "alice", "name": "Alice" })
+    );
+}
+
+#[test]
+fn container_variants() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "@vocab": "http://example.com/",
+                "tags": { "@id": "http://example.com/tags", "@container": "@set" },
+                "list": { "@id": "http://example.com/list", "@container": "@list" },
+                "label": { "@id": "http://example.com/label", "@container": "@language" },
+                "byIndex": { "@id": "http://example.com/byIndex", "@container": "@index" },
+            },
+            "@id": "http://example.com/x",
+            "tags": ["a"],
+            "list": ["b", "c"],
+            "label": { "en": "Hello", "ja": "こんにちは" },
+            "byIndex": { "first": "d" },
+        }),
+        json!([{
+            "@id": "http://example.com/x",
+            "http://example.com/tags": [{ "@value": "a" }],
+            "http://example.com/list": [{ "@list": [{ "@value": "b" }, { "@value": "c" }] }],
+            "http://example.com/label": [
+                { "@value": "Hello", "@language": "en" },
+                { "@value": "こんにちは", "@language": "ja" },
+            ],
+            "http://example.com/byIndex": [{ "@value": "d", "@index": "first" }],
+        }]),
+    );
+}
+
+#[test]
+fn term_selection() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "name": "http://schema.org/name",
+                "nameEn": { "@id": "http://schema.org/name", "@language": "en" },
+            },
+            "name": "Alicia",
+            "nameEn": "Alice",
+        }),
+        json!([{
+            "http://schema.org/name": [
+                { "@value": "Alicia" },
+                { "@value": "Alice", "@language": "en" },
+            ],
+        }]),
+    );
+}
+
+#[test]
+fn type_scoped_context() {
+    let context = json!({
+        "@vocab": "http://example.com/vocab#",
+        "Person": {
+            "@id": "http://example.com/vocab#Person",
+            "@context": { "name": "http://xmlns.com/foaf/0.1/name" },
+        },
+    });
+    assert_round_trip(
+        json!({ "@context": context, "@type": "Person", "name": "Alice" }),
+        json!([{
+            "@type": ["http://example.com/vocab#Person"],
+            "http://xmlns.com/foaf/0.1/name": [{ "@value": "Alice" }],
+        }]),
+    );
+    // Type-scoped contexts do not propagate to nested nodes.
+    let input = json!({
+        "@context": context,
+        "@type": "Person",
+        "knows": { "name": "Bob" },
+    });
+    assert_eq!(
+        expand(&input),
+        json!([{
+            "@type": ["http://example.com/vocab#Person"],
+            "http://example.com/vocab#knows": [{
+                "http://example.com/vocab#name": [{ "@value": "Bob" }],
+            }],
+        }])
+    );
+}
+
+#[test]
+fn reverse_property() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "@vocab": "http://schema.org/",
+                "children": { "@reverse": "http://schema.org/parent" },
+            },
+            "@id": "http://example.com/alice",
+            "children": { "@id": "http://example.com/bob", "name": "Bob" },
+        }),
+        json!([{
+            "@id": "http://example.com/alice",
+            "@reverse": {
+                "http://schema.org/parent": [{
+                    "@id": "http://example.com/bob",
+                    "http://schema.org/name": [{ "@value": "Bob" }],
+                }],
+            },
+        }]),
+    );
+}
+
+#[test]
+fn json_literal() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "data": { "@id": "http://example.com/data", "@type": "@json" },
+                "items": { "@id": "http://example.com/items", "@type": "@json" },
+            },
+            "@id": "http://example.com/x",
+            "data": { "name": "Alice", "tags": [true, null] },
+            "items": [1, "two", { "three": 3 }],
+        }),
+        json!([{
+            "@id": "http://example.com/x",
+            "http://example.com/data": [{
+                "@value": { "name": "Alice", "tags": [true, null] },
+                "@type": "@json",
+            }],
+            "http://example.com/items": [{
+                "@value": [1, "two", { "three": 3 }],
+                "@type": "@json",
+            }],
+        }]),
+    );
+}
+
+#[test]
+fn nested_included() {
+    assert_round_trip(
+        json!({
+            "@context": { "@vocab": "http://example.com/" },
+            "@id": "http://example.com/a",
+            "@included": {
+                "@id": "http://example.com/b",
+                "@included": { "@id": "http://example.com/c", "name": "C" },
+            },
+        }),
+        json!([{
+            "@id": "http://example.com/a",
+            "@included": [{
+                "@id": "http://example.com/b",
+                "@included": [{
+                    "@id": "http://example.com/c",
+                    "http://example.com/name": [{ "@value": "C" }],
+                }],
+            }],
+        }]),
+    );
+}
+
+#[test]
+fn nested_properties() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "@vocab": "http://example.com/",
+                "labels": "@nest",
+                "name": { "@id": "http://example.com/name", "@nest": "labels" },
+            },
+            "@id": "http://example.com/x",
+            "labels": { "name": "Alice" },
+        }),
+        json!([{
+            "@id": "http://example.com/x",
+            "http://example.com/name": [{ "@value": "Alice" }],
+        }]),
+    );
+}
+
+#[test]
+fn base_direction() {
+    // Default base direction of the context, and base direction of the term.
+    assert_round_trip(
+        json!({
+            "@context": {
+                "@vocab": "http://example.com/",
+                "@direction": "rtl",
+                "title": { "@id": "http://example.com/title", "@direction": "ltr" },
+            },
+            "name": "Alice",
+            "title": "Dr.",
+        }),
+        json!([{
+            "http://example.com/name": [{ "@value": "Alice", "@direction": "rtl" }],
+            "http://example.com/title": [{ "@value": "Dr.", "@direction": "ltr" }],
+        }]),
+    );
+}
+
+#[test]
+fn language_map_with_direction() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "label": {
+                    "@id": "http://example.com/label",
+                    "@container": "@language",
+                    "@direction": "rtl",
+                },
+            },
+            "label": { "ar": "مرحبا", "he": "שלום" },
+        }),
+        json!([{
+            "http://example.com/label": [
+                { "@value": "مرحبا", "@language": "ar", "@direction": "rtl" },
+                { "@value": "שלום", "@language": "he", "@direction": "rtl" },
+            ],
+        }]),
+    );
+}
+
+#[test]
+fn direction_term_selection() {
+    assert_round_trip(
+        json!({
+            "@context": {
+                "name": "http://example.com/name",
+                "nameLtr": { "@id": "http://example.com/name", "@direction": "ltr" },
+                "nameRtl": { "@id": "http://example.com/name", "@direction": "rtl" },
+            },
+            "name": "Alice",
+            "nameLtr": "Bob",
+            "nameRtl": "Carol",
+        }),
+        json!([{
+            "http://example.com/name": [
+                { "@value": "Alice" },
+                { "@value": "Bob", "@direction": "ltr" },
+                { "@value": "Carol", "@direction": "rtl" },
+            ],
+        }]),
+    );
+}
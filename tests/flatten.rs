@@ -0,0 +1,133 @@
+//! Flattening of small documents, checked against hand-written results.
+
+mod common;
+
+use futures_executor::block_on;
+use serde_json::{json, Value};
+
+use self::common::{options, processor};
+
+/// Flattens the document, and compacts the result with the context if given.
+fn flatten(input: &Value, context: Option<&Value>) -> Value {
+    block_on(processor().flatten(input, context, &options())).expect("flattening should succeed")
+}
+
+/// Document with nested nodes, with and without blank node identifiers.
+fn nested_nodes() -> Value {
+    json!({
+        "@context": { "@vocab": "http://schema.org/" },
+        "@id": "http://example.com/alice",
+        "name": "Alice",
+        "knows": {
+            "@id": "_:bob",
+            "name": "Bob",
+            "knows": { "name": "Carol" },
+        },
+    })
+}
+
+#[test]
+fn relabel_blank_nodes() {
+    let expected = json!([
+        {
+            "@id": "_:b0",
+            "http://schema.org/knows": [{ "@id": "_:b1" }],
+            "http://schema.org/name": [{ "@value": "Bob" }],
+        },
+        {
+            "@id": "_:b1",
+            "http://schema.org/name": [{ "@value": "Carol" }],
+        },
+        {
+            "@id": "http://example.com/alice",
+            "http://schema.org/knows": [{ "@id": "_:b0" }],
+            "http://schema.org/name": [{ "@value": "Alice" }],
+        },
+    ]);
+    assert_eq!(flatten(&nested_nodes(), None), expected);
+}
+
+#[test]
+fn compact_flattened() {
+    let context = json!({ "@vocab": "http://schema.org/" });
+    let expected = json!({
+        "@context": { "@vocab": "http://schema.org/" },
+        "@graph": [
+            { "@id": "_:b0", "knows": { "@id": "_:b1" }, "name": "Bob" },
+            { "@id": "_:b1", "name": "Carol" },
+            { "@id": "http://example.com/alice", "knows": { "@id": "_:b0" }, "name": "Alice" },
+        ],
+    });
+    assert_eq!(flatten(&nested_nodes(), Some(&context)), expected);
+}
+
+#[test]
+fn merge_nodes_and_graphs() {
+    let input = json!([
+        {
+            "@id": "http://example.com/a",
+            "http://example.com/p": "x",
+        },
+        {
+            "@id": "http://example.com/g",
+            "@graph": {
+                "@id": "http://example.com/b",
+                "http://example.com/p": "y",
+            },
+        },
+        {
+            "@id": "http://example.com/a",
+            "http://example.com/q": "z",
+        },
+    ]);
+    let expected = json!([
+        {
+            "@id": "http://example.com/a",
+            "http://example.com/p": [{ "@value": "x" }],
+            "http://example.com/q": [{ "@value": "z" }],
+        },
+        {
+            "@id": "http://example.com/g",
+            "@graph": [
+                {
+                    "@id": "http://example.com/b",
+                    "http://example.com/p": [{ "@value": "y" }],
+                },
+            ],
+        },
+    ]);
+    assert_eq!(flatten(&input, None), expected);
+}
+
+#[test]
+fn merge_included_nodes() {
+    let input = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@id": "http://example.com/a",
+        "p": "x",
+        "@included": [
+            { "@id": "http://example.com/b", "q": "y" },
+            {
+                "@id": "http://example.com/a",
+                "q": "z",
+                "@included": { "@id": "http://example.com/c", "p": "w" },
+            },
+        ],
+    });
+    let expected = json!([
+        {
+            "@id": "http://example.com/a",
+            "http://example.com/p": [{ "@value": "x" }],
+            "http://example.com/q": [{ "@value": "z" }],
+        },
+        {
+            "@id": "http://example.com/b",
+            "http://example.com/q": [{ "@value": "y" }],
+        },
+        {
+            "@id": "http://example.com/c",
+            "http://example.com/p": [{ "@value": "w" }],
+        },
+    ]);
+    assert_eq!(flatten(&input, None), expected);
+}
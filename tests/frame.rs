@@ -0,0 +1,98 @@
+//! Framing of small documents, checked against hand-written results.
+
+mod common;
+
+use futures_executor::block_on;
+use serde_json::{json, Value};
+
+use self::common::{options, processor};
+
+/// Frames the document.
+fn frame(input: &Value, frame: &Value) -> Value {
+    block_on(processor().frame(input, frame, &options())).expect("framing should succeed")
+}
+
+/// Library containing a book, as a flat list of nodes.
+fn library() -> Value {
+    json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@graph": [
+            {
+                "@id": "http://example.com/lib",
+                "@type": "Library",
+                "contains": { "@id": "http://example.com/book" },
+            },
+            {
+                "@id": "http://example.com/book",
+                "@type": "Book",
+                "title": "JSON-LD",
+                "author": "Alice",
+            },
+        ],
+    })
+}
+
+#[test]
+fn embed_explicit() {
+    let frame_doc = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@type": "Library",
+        "contains": {
+            "@type": "Book",
+            "@explicit": true,
+            "title": {},
+        },
+    });
+    let expected = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@id": "http://example.com/lib",
+        "@type": "Library",
+        "contains": {
+            "@id": "http://example.com/book",
+            "@type": "Book",
+            "title": "JSON-LD",
+        },
+    });
+    assert_eq!(frame(&library(), &frame_doc), expected);
+}
+
+#[test]
+fn embed_never() {
+    let frame_doc = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@type": "Library",
+        "contains": { "@embed": "@never" },
+    });
+    let expected = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@id": "http://example.com/lib",
+        "@type": "Library",
+        "contains": { "@id": "http://example.com/book" },
+    });
+    assert_eq!(frame(&library(), &frame_doc), expected);
+}
+
+#[test]
+fn reverse_property() {
+    let frame_doc = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@type": "Book",
+        "@reverse": { "contains": {} },
+    });
+    // The book is not embedded again in the library, since it is on the embedding path.
+    let expected = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@id": "http://example.com/book",
+        "@type": "Book",
+        "author": "Alice",
+        "title": "JSON-LD",
+        "@reverse": {
+            "contains": {
+                "@id": "http://example.com/lib",
+                "@type": "Library",
+                "contains": { "@id": "http://example.com/book" },
+            },
+        },
+    });
+    assert_eq!(frame(&library(), &frame_doc), expected);
+}
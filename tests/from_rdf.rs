@@ -0,0 +1,40 @@
+//! Serialization of small RDF datasets as JSON-LD.
+
+mod common;
+
+use json_ld::{rdf::nquads::parse_nquads, JsonLdOptions};
+use serde_json::json;
+
+use self::common::processor;
+
+#[test]
+fn owned_quads() {
+    let source = r#"
+<http://example.com/s> <http://example.com/p> "v" .
+<http://example.com/s> <http://example.com/q> _:b0 <http://example.com/g> .
+"#;
+    let expected = json!([
+        {
+            "@id": "http://example.com/g",
+            "@graph": [{
+                "@id": "http://example.com/s",
+                "http://example.com/q": [{ "@id": "_:b0" }],
+            }],
+        },
+        {
+            "@id": "http://example.com/s",
+            "http://example.com/p": [{ "@value": "v" }],
+        },
+    ]);
+    let dataset = parse_nquads(source).expect("valid N-Quads");
+    let options = JsonLdOptions::new();
+
+    let borrowed = processor()
+        .from_rdf(&dataset, &options)
+        .expect("serialization should succeed");
+    assert_eq!(borrowed, expected);
+    let owned = processor()
+        .from_rdf(dataset.into_iter(), &options)
+        .expect("serialization should succeed");
+    assert_eq!(owned, expected);
+}
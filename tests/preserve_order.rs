@@ -0,0 +1,76 @@
+//! Outputs with `preserve_order` feature, where entries of maps keep their insertion order.
+#![cfg(feature = "preserve_order")]
+
+mod common;
+
+use futures_executor::block_on;
+use serde_json::{json, Value};
+
+use self::common::{options, processor};
+
+/// Returns the keys of the map.
+fn keys(v: &Value) -> Vec<&str> {
+    v.as_object()
+        .expect("should be a map")
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+/// Document with entries not sorted by keys.
+fn unsorted_document() -> Value {
+    json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@id": "http://example.com/x",
+        "zeta": { "name": "Zeta" },
+        "alpha": { "name": "Alpha" },
+    })
+}
+
+#[test]
+fn expand_keeps_input_order() {
+    let expanded =
+        block_on(processor().expand(&unsorted_document(), &options())).expect("should expand");
+    assert_eq!(
+        keys(&expanded[0]),
+        ["@id", "http://example.com/zeta", "http://example.com/alpha"]
+    );
+}
+
+#[test]
+fn compact_puts_context_first() {
+    let context = json!({ "@vocab": "http://example.com/" });
+    let compacted = block_on(processor().compact(&unsorted_document(), &context, &options()))
+        .expect("should compact");
+    assert_eq!(keys(&compacted), ["@context", "@id", "zeta", "alpha"]);
+
+    let mut written = Vec::new();
+    block_on(processor().compact_to_writer(
+        &unsorted_document(),
+        &context,
+        &options(),
+        &mut written,
+    ))
+    .expect("should compact");
+    let written: Value = serde_json::from_slice(&written).expect("should be JSON");
+    assert_eq!(keys(&written), ["@context", "@id", "zeta", "alpha"]);
+}
+
+#[test]
+fn blank_node_labels_follow_sorted_properties() {
+    let flattened = block_on(processor().flatten(&unsorted_document(), None, &options()))
+        .expect("should flatten");
+    // Blank nodes are labeled in the order of the sorted properties, as without the feature.
+    assert_eq!(
+        flattened,
+        json!([
+            { "@id": "_:b0", "http://example.com/name": [{ "@value": "Alpha" }] },
+            { "@id": "_:b1", "http://example.com/name": [{ "@value": "Zeta" }] },
+            {
+                "@id": "http://example.com/x",
+                "http://example.com/alpha": [{ "@id": "_:b0" }],
+                "http://example.com/zeta": [{ "@id": "_:b1" }],
+            },
+        ])
+    );
+}
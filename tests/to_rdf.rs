@@ -0,0 +1,98 @@
+//! Deserialization of small documents to RDF, checked against hand-written N-Quads.
+
+mod common;
+
+use futures_executor::block_on;
+use json_ld::{rdf::nquads::to_nquads, JsonLdOptions};
+use serde_json::{json, Value};
+
+use self::common::processor;
+
+/// Converts the document to RDF, and returns the N-Quads statements sorted.
+fn to_sorted_nquads(input: &Value) -> Vec<String> {
+    let dataset = block_on(processor().to_rdf(input, &JsonLdOptions::new()))
+        .expect("conversion should succeed");
+    let mut lines = to_nquads(&dataset)
+        .lines()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn list() {
+    let input = json!({
+        "@context": {
+            "@vocab": "http://example.com/",
+            "items": { "@container": "@list" },
+        },
+        "@id": "http://example.com/s",
+        "@type": "T",
+        "items": ["a", 1, true],
+    });
+    let expected = [
+        r#"<http://example.com/s> <http://example.com/items> _:b0 ."#,
+        r#"<http://example.com/s> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.com/T> ."#,
+        r#"_:b0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> "a" ."#,
+        r#"_:b0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:b1 ."#,
+        r#"_:b1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> "1"^^<http://www.w3.org/2001/XMLSchema#integer> ."#,
+        r#"_:b1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:b2 ."#,
+        r#"_:b2 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> "true"^^<http://www.w3.org/2001/XMLSchema#boolean> ."#,
+        r#"_:b2 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> ."#,
+    ];
+    assert_eq!(to_sorted_nquads(&input), expected);
+}
+
+#[test]
+fn empty_list() {
+    let input = json!({
+        "@id": "http://example.com/s",
+        "http://example.com/items": { "@list": [] },
+    });
+    let expected = [
+        r#"<http://example.com/s> <http://example.com/items> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> ."#,
+    ];
+    assert_eq!(to_sorted_nquads(&input), expected);
+}
+
+#[test]
+fn named_graph() {
+    let input = json!({
+        "@context": { "@vocab": "http://example.com/" },
+        "@id": "http://example.com/g",
+        "@graph": {
+            "label": { "@value": "chat", "@language": "fr" },
+            "size": 1.5,
+        },
+    });
+    let expected = [
+        r#"_:b0 <http://example.com/label> "chat"@fr <http://example.com/g> ."#,
+        r#"_:b0 <http://example.com/size> "1.5E0"^^<http://www.w3.org/2001/XMLSchema#double> <http://example.com/g> ."#,
+    ];
+    assert_eq!(to_sorted_nquads(&input), expected);
+}
+
+#[test]
+fn handler_receives_dataset() {
+    let input = json!({
+        "@context": {
+            "@vocab": "http://example.com/",
+            "items": { "@container": "@list" },
+        },
+        "@id": "http://example.com/g",
+        "items": ["a", { "@id": "_:x" }],
+        "@graph": [
+            { "@id": "_:x", "label": { "@value": "chat", "@language": "fr" } },
+            { "size": 1.5 },
+        ],
+    });
+    let options = JsonLdOptions::new();
+    let dataset =
+        block_on(processor().to_rdf(&input, &options)).expect("conversion should succeed");
+    let mut received = Vec::new();
+    block_on(processor().to_rdf_with_handler(&input, &options, |quad| received.push(quad)))
+        .expect("conversion should succeed");
+    assert_eq!(dataset.len(), 7);
+    assert_eq!(received, dataset);
+}